@@ -0,0 +1,740 @@
+//! Derive macros for the [`cmp`](https://docs.rs/cmp) crate.
+//!
+//! Use them through `cmp` with the `derive` feature enabled, rather than
+//! depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::TokenTree;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, Fields, Ident, LitStr, Member, Type, WhereClause, WherePredicate,
+    parse_macro_input,
+};
+
+/// Derives `cmp::Diffable`, comparing every field of a struct, or the
+/// payload of an enum's variant when both values have the same variant.
+///
+/// For generic structs, every compared field whose type mentions a type
+/// parameter gets a `FieldType: Diffable` bound, which can be replaced with
+/// `#[cmp(bound = "...")]` on the struct or on the field.
+///
+/// See the `cmp::Diffable` documentation for the supported `#[cmp(...)]`
+/// attributes.
+#[proc_macro_derive(Diffable, attributes(cmp))]
+pub fn derive_diffable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `cmp::CompareFields`, comparing every field of a struct with
+/// `PartialEq` and rendering differing values with `Debug`, so
+/// `compare_structs!(a, b)` can compare all fields without `serde`.
+///
+/// `#[cmp(skip)]`, `#[cmp(rename = "...")]`, `#[cmp(ignore = "...")]`,
+/// `#[cmp(include_zst)]` and `#[cmp(bound = "...")]` work like for
+/// `Diffable`; the other attributes are `Diffable`-specific and rejected.
+#[proc_macro_derive(CompareFields, attributes(cmp))]
+pub fn derive_compare_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_compare_fields(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerAttrs {
+    bound: Option<Vec<WherePredicate>>,
+    default_epsilon: Option<Expr>,
+    ignore: Vec<(String, LitStr)>,
+    include_zst: bool,
+    key_matcher: Option<Expr>,
+    unrolled: Option<syn::Path>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    bound: Option<Vec<WherePredicate>>,
+    debug_eq: Option<syn::Path>,
+    epsilon: Option<Expr>,
+    flatten: bool,
+    include_zst: bool,
+    max_render_len: Option<Expr>,
+    rename: Option<String>,
+    skip: bool,
+    unordered: bool,
+    with: Option<syn::ExprPath>,
+}
+
+#[derive(Default)]
+struct VariantAttrs {
+    rename: Option<String>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let container = container_attrs(input)?;
+    let type_params: Vec<&Ident> = input
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect();
+    let mut bounds: Vec<WherePredicate> = Vec::new();
+    let mut table = None;
+
+    let (exact, inherited) = match &input.data {
+        Data::Struct(data) if container.unrolled.is_none() => {
+            let accessors = struct_members(data, &container)?
+                .into_iter()
+                .map(|(member, name)| (quote!(&expected.#member), quote!(&actual.#member), name))
+                .collect();
+            let (exact, inherited) = compare_fields(
+                &data.fields,
+                accessors,
+                &container,
+                &type_params,
+                &mut bounds,
+            )?;
+            let count = exact.len();
+            let fields = exact.iter().zip(&inherited).map(|(exact, inherited)| {
+                quote! {
+                    |expected: &Self,
+                     actual: &Self,
+                     inherited: ::core::option::Option<f64>,
+                     path: &mut ::cmp::DiffPath,
+                     out: &mut ::cmp::Diff| match inherited {
+                        ::core::option::Option::None => { #exact }
+                        ::core::option::Option::Some(epsilon) => { #inherited }
+                    }
+                }
+            });
+            table = Some(quote! {
+                /// The comparison of each field, called in a loop rather
+                /// than inlined, so large structs compile to little code.
+                #[doc(hidden)]
+                #[allow(clippy::type_complexity, unused_variables)]
+                fn __cmp_diff_fields() -> [fn(
+                    &Self,
+                    &Self,
+                    ::core::option::Option<f64>,
+                    &mut ::cmp::DiffPath,
+                    &mut ::cmp::Diff,
+                ); #count] {
+                    [#(#fields),*]
+                }
+            });
+            (
+                vec![quote! {
+                    for diff in Self::__cmp_diff_fields() {
+                        diff(self, other, ::core::option::Option::None, path, out);
+                    }
+                }],
+                vec![quote! {
+                    for diff in Self::__cmp_diff_fields() {
+                        diff(self, other, ::core::option::Option::Some(epsilon), path, out);
+                    }
+                }],
+            )
+        }
+        Data::Struct(data) => {
+            let accessors = struct_members(data, &container)?
+                .into_iter()
+                .map(|(member, name)| (quote!(&self.#member), quote!(&other.#member), name))
+                .collect();
+            compare_fields(
+                &data.fields,
+                accessors,
+                &container,
+                &type_params,
+                &mut bounds,
+            )?
+        }
+        Data::Enum(data) => {
+            if let Some((_, lit)) = container.ignore.first() {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "`ignore` is not supported on enums, use `#[cmp(skip)]` on variant fields",
+                ));
+            }
+            let ident = &input.ident;
+            let mut exact_arms = Vec::new();
+            let mut inherited_arms = Vec::new();
+            for variant in &data.variants {
+                let variant_ident = &variant.ident;
+                let variant_name = variant_attrs(variant)?
+                    .rename
+                    .unwrap_or_else(|| variant_ident.to_string());
+                let members = members(&variant.fields);
+                let expected: Vec<Ident> = (0..members.len())
+                    .map(|index| quote::format_ident!("__expected_{index}"))
+                    .collect();
+                let actual: Vec<Ident> = (0..members.len())
+                    .map(|index| quote::format_ident!("__actual_{index}"))
+                    .collect();
+                let accessors = expected
+                    .iter()
+                    .zip(&actual)
+                    .zip(&members)
+                    .map(|((expected, actual), (_, name))| {
+                        (quote!(#expected), quote!(#actual), name.clone())
+                    })
+                    .collect();
+                let (exact, inherited) = compare_fields(
+                    &variant.fields,
+                    accessors,
+                    &container,
+                    &type_params,
+                    &mut bounds,
+                )?;
+                let fields: Vec<&Member> = members.iter().map(|(member, _)| member).collect();
+                let pattern = quote! {
+                    (
+                        #ident::#variant_ident { #(#fields: #expected),* },
+                        #ident::#variant_ident { #(#fields: #actual),* },
+                    )
+                };
+                exact_arms.push(quote! {
+                    #pattern => {
+                        path.push_variant(#variant_name);
+                        #(#exact)*
+                        path.pop();
+                    }
+                });
+                inherited_arms.push(quote! {
+                    #pattern => {
+                        path.push_variant(#variant_name);
+                        #(#inherited)*
+                        path.pop();
+                    }
+                });
+            }
+
+            let (_, ty_generics, _) = input.generics.split_for_impl();
+            bounds.push(syn::parse_quote!(#ident #ty_generics: ::core::fmt::Debug));
+            // Formatters only apply to `'static` types.
+            let render = if input.generics.params.is_empty() {
+                quote!(::cmp::format_value)
+            } else {
+                quote!(::cmp::__private::render_debug)
+            };
+            let mismatch = quote! {
+                _ => out.push_with(|| ::cmp::FieldDiff::Changed {
+                    path: ::core::clone::Clone::clone(path),
+                    expected: #render(self),
+                    actual: #render(other),
+                }),
+            };
+            let exact = quote! {
+                match (self, other) {
+                    #(#exact_arms)*
+                    #[allow(unreachable_patterns)]
+                    #mismatch
+                }
+            };
+            let inherited = quote! {
+                match (self, other) {
+                    #(#inherited_arms)*
+                    #[allow(unreachable_patterns)]
+                    #mismatch
+                }
+            };
+            (vec![exact], vec![inherited])
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "Diffable can only be derived for structs and enums",
+            ));
+        }
+    };
+
+    let (exact, inherited) = match &container.key_matcher {
+        Some(matcher) => (
+            vec![quote!(::cmp::with_key_matcher(#matcher, || { #(#exact)* });)],
+            vec![quote!(::cmp::with_key_matcher(#matcher, || { #(#inherited)* });)],
+        ),
+        None => (exact, inherited),
+    };
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(container.bound.unwrap_or(bounds));
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let table = table.map(|table| {
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #table
+            }
+        }
+    });
+    Ok(quote! {
+        #table
+
+        impl #impl_generics ::cmp::Diffable for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn diff(&self, other: &Self, path: &mut ::cmp::DiffPath, out: &mut ::cmp::Diff) {
+                #(#exact)*
+            }
+
+            #[allow(unused_variables)]
+            fn diff_with_epsilon(
+                &self,
+                other: &Self,
+                epsilon: f64,
+                path: &mut ::cmp::DiffPath,
+                out: &mut ::cmp::Diff,
+            ) {
+                #(#inherited)*
+            }
+        }
+    })
+}
+
+/// Returns the members of a struct's fields with their names, checking
+/// that the fields named by `#[cmp(ignore = "...")]` exist.
+fn struct_members(
+    data: &syn::DataStruct,
+    container: &ContainerAttrs,
+) -> syn::Result<Vec<(Member, String)>> {
+    let members = members(&data.fields);
+    for (name, lit) in &container.ignore {
+        if !members.iter().any(|(_, member)| member == name) {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!("no field named `{name}` to ignore"),
+            ));
+        }
+    }
+    Ok(members)
+}
+
+fn expand_compare_fields(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "CompareFields can only be derived for structs",
+        ));
+    };
+    let container = container_attrs(input)?;
+    if let Some(attr) = container
+        .default_epsilon
+        .as_ref()
+        .or(container.key_matcher.as_ref())
+    {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "`default_epsilon` and `key_matcher` are not supported by CompareFields, \
+             derive Diffable instead",
+        ));
+    }
+    if let Some(path) = &container.unrolled {
+        return Err(syn::Error::new_spanned(
+            path,
+            "`unrolled` is not supported by CompareFields, derive Diffable instead",
+        ));
+    }
+    let members = struct_members(data, &container)?;
+    let type_params: Vec<&Ident> = input
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect();
+
+    let mut bounds: Vec<WherePredicate> = Vec::new();
+    let mut comparisons = Vec::new();
+    for (field, (member, name)) in data.fields.iter().zip(members) {
+        let attrs = field_attrs(field)?;
+        if attrs.epsilon.is_some()
+            || attrs.flatten
+            || attrs.unordered
+            || attrs.with.is_some()
+            || attrs.debug_eq.is_some()
+            || attrs.max_render_len.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only `skip`, `rename`, `include_zst` and `bound` are supported by CompareFields",
+            ));
+        }
+        if attrs.skip || container.ignore.iter().any(|(ignored, _)| *ignored == name) {
+            continue;
+        }
+        if is_zero_sized(&field.ty) && !attrs.include_zst && !container.include_zst {
+            continue;
+        }
+        let name = attrs.rename.unwrap_or(name);
+        let ty = &field.ty;
+        match attrs.bound {
+            Some(bound) => bounds.extend(bound),
+            None if mentions_any(ty, &type_params) => bounds.push(syn::parse_quote!(
+                #ty: ::core::cmp::PartialEq + ::core::fmt::Debug
+            )),
+            None => {}
+        }
+        let render = if borrows(ty, &type_params) {
+            quote!(::cmp::__private::render_debug)
+        } else {
+            quote!(::cmp::format_value)
+        };
+        comparisons.push(quote! {
+            if self.#member != other.#member {
+                let mut path = ::cmp::DiffPath::new();
+                path.push_field(#name);
+                diffs.push(::cmp::FieldDiff::Changed {
+                    path,
+                    expected: #render(&self.#member),
+                    actual: #render(&other.#member),
+                });
+            }
+        });
+    }
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(container.bound.unwrap_or(bounds));
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::cmp::CompareFields for #ident #ty_generics #where_clause {
+            fn compare_all(&self, other: &Self) -> ::std::vec::Vec<::cmp::FieldDiff> {
+                #[allow(unused_mut)]
+                let mut diffs = ::std::vec::Vec::new();
+                #(#comparisons)*
+                diffs
+            }
+        }
+    })
+}
+
+/// Pairs each field's member with the name used for it in diff paths.
+fn members(fields: &Fields) -> Vec<(Member, String)> {
+    match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = field.ident.clone().expect("named field");
+                let name = ident.to_string();
+                (Member::Named(ident), name)
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|index| (Member::from(index), index.to_string()))
+            .collect(),
+        Fields::Unit => Vec::new(),
+    }
+}
+
+/// Generates the comparisons for a struct's or variant's fields, given the
+/// expressions borrowing each field on the expected and actual side.
+///
+/// Returns the statements for `diff` and for `diff_with_epsilon`, and pushes
+/// the inferred where-clause bounds into `bounds`.
+fn compare_fields(
+    fields: &Fields,
+    accessors: Vec<(TokenStream2, TokenStream2, String)>,
+    container: &ContainerAttrs,
+    type_params: &[&Ident],
+    bounds: &mut Vec<WherePredicate>,
+) -> syn::Result<(Vec<TokenStream2>, Vec<TokenStream2>)> {
+    let mut exact = Vec::new();
+    let mut inherited = Vec::new();
+    for (field, (expected, actual, name)) in fields.iter().zip(accessors) {
+        let attrs = field_attrs(field)?;
+        if attrs.skip || container.ignore.iter().any(|(ignored, _)| *ignored == name) {
+            continue;
+        }
+        if is_zero_sized(&field.ty) && !attrs.include_zst && !container.include_zst {
+            continue;
+        }
+        let name = attrs.rename.unwrap_or(name);
+
+        if let Some(debug_eq) = &attrs.debug_eq {
+            if attrs.with.is_some() || attrs.epsilon.is_some() || attrs.unordered {
+                return Err(syn::Error::new_spanned(
+                    debug_eq,
+                    "`debug_eq` can't be combined with `with`, `epsilon` or `unordered`",
+                ));
+            }
+            match attrs.bound {
+                Some(field_bounds) => bounds.extend(field_bounds),
+                None if mentions_any(&field.ty, type_params) => {
+                    let ty = &field.ty;
+                    bounds.push(syn::parse_quote!(#ty: ::core::fmt::Debug));
+                }
+                None => {}
+            }
+            let compare = in_segment(
+                &name,
+                attrs.flatten,
+                budgeted(
+                    &attrs.max_render_len,
+                    quote!(::cmp::diff_debug(#expected, #actual, path, out);),
+                ),
+            );
+            exact.push(compare.clone());
+            inherited.push(compare);
+            continue;
+        }
+
+        if let Some(with) = &attrs.with {
+            if let Some(bound) = attrs.bound {
+                bounds.extend(bound);
+            }
+            let compare = in_segment(
+                &name,
+                attrs.flatten,
+                budgeted(
+                    &attrs.max_render_len,
+                    quote!(#with(#expected, #actual, path, out);),
+                ),
+            );
+            exact.push(compare.clone());
+            inherited.push(compare);
+            continue;
+        }
+
+        match attrs.bound {
+            Some(field_bounds) => bounds.extend(field_bounds),
+            None if mentions_any(&field.ty, type_params) => {
+                let ty = &field.ty;
+                bounds.push(syn::parse_quote!(#ty: ::cmp::Diffable));
+            }
+            None => {}
+        }
+
+        let epsilon = attrs
+            .epsilon
+            .as_ref()
+            .or(container.default_epsilon.as_ref())
+            .map(|epsilon| quote!(::core::primitive::f64::from(#epsilon)));
+        let mut exact_compare = compare_field(&expected, &actual, epsilon.clone());
+        let mut inherited_compare =
+            compare_field(&expected, &actual, epsilon.or(Some(quote!(epsilon))));
+        if attrs.unordered {
+            exact_compare = unordered(exact_compare);
+            inherited_compare = unordered(inherited_compare);
+        }
+        exact.push(in_segment(
+            &name,
+            attrs.flatten,
+            budgeted(&attrs.max_render_len, exact_compare),
+        ));
+        inherited.push(in_segment(
+            &name,
+            attrs.flatten,
+            budgeted(&attrs.max_render_len, inherited_compare),
+        ));
+    }
+    Ok((exact, inherited))
+}
+
+/// Wraps `compare` so the values it renders are cut to `max_render_len`
+/// bytes, if the field has one.
+fn budgeted(max_render_len: &Option<Expr>, compare: TokenStream2) -> TokenStream2 {
+    match max_render_len {
+        Some(len) => quote! {
+            ::cmp::with_max_render_len(#len, || { #compare });
+        },
+        None => compare,
+    }
+}
+
+/// Wraps `compare` in a `name` path segment, unless the field is flattened
+/// into its parent.
+fn in_segment(name: &str, flatten: bool, compare: TokenStream2) -> TokenStream2 {
+    if flatten {
+        return compare;
+    }
+    quote! {
+        path.push_field(#name);
+        #compare
+        path.pop();
+    }
+}
+
+/// Compares one field, with an absolute tolerance when `epsilon` is given.
+fn compare_field(
+    expected: &TokenStream2,
+    actual: &TokenStream2,
+    epsilon: Option<TokenStream2>,
+) -> TokenStream2 {
+    match epsilon {
+        Some(epsilon) => quote! {
+            ::cmp::Diffable::diff_with_epsilon(#expected, #actual, #epsilon, path, out);
+        },
+        None => quote! {
+            ::cmp::Diffable::diff(#expected, #actual, path, out);
+        },
+    }
+}
+
+/// Wraps `compare` so the field's sequences are compared regardless of order.
+fn unordered(compare: TokenStream2) -> TokenStream2 {
+    quote! {
+        ::cmp::with_unordered_sequences(|| { #compare });
+    }
+}
+
+/// Returns whether `ty` is spelled as a type which is always zero-sized:
+/// `PhantomData`, `PhantomPinned`, `()` or an array of length zero.
+fn is_zero_sized(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => {
+            path.qself.is_none()
+                && path.path.segments.last().is_some_and(|segment| {
+                    segment.ident == "PhantomData" || segment.ident == "PhantomPinned"
+                })
+        }
+        Type::Tuple(tuple) => tuple.elems.is_empty(),
+        Type::Array(array) => matches!(
+            &array.len,
+            Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(len), .. }) if len.base10_digits() == "0"
+        ),
+        Type::Paren(paren) => is_zero_sized(&paren.elem),
+        Type::Group(group) => is_zero_sized(&group.elem),
+        _ => false,
+    }
+}
+
+/// Returns whether `ty` refers to any of the struct's type parameters, in
+/// which case the field needs an inferred `Diffable` bound.
+fn mentions_any(ty: &Type, params: &[&Ident]) -> bool {
+    fn scan(tokens: proc_macro2::TokenStream, params: &[&Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => params.iter().any(|param| **param == ident),
+            TokenTree::Group(group) => scan(group.stream(), params),
+            _ => false,
+        })
+    }
+    !params.is_empty() && scan(quote!(#ty), params)
+}
+
+/// Returns whether `ty` may not be `'static`, mentioning a lifetime other than
+/// `'static` or one of the type parameters `params`, so formatters, which are
+/// looked up for `'static` types only, can't render it.
+fn borrows(ty: &Type, params: &[&Ident]) -> bool {
+    fn scan(tokens: proc_macro2::TokenStream) -> bool {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Punct(punct)
+                    if punct.as_char() == '\''
+                        && !matches!(tokens.peek(), Some(TokenTree::Ident(ident)) if ident == "static") =>
+                {
+                    return true;
+                }
+                TokenTree::Group(group) if scan(group.stream()) => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+    mentions_any(ty, params) || scan(quote!(#ty))
+}
+
+/// Parses `bound = "T: Trait, U: Other"` into where-clause predicates.
+fn parse_bound(lit: &LitStr) -> syn::Result<Vec<WherePredicate>> {
+    if lit.value().trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let clause: WhereClause = syn::parse_str(&format!("where {}", lit.value()))
+        .map_err(|err| syn::Error::new_spanned(lit, err))?;
+    Ok(clause.predicates.into_iter().collect())
+}
+
+fn container_attrs(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut attrs = ContainerAttrs::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cmp"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                attrs.bound = Some(parse_bound(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("default_epsilon") {
+                attrs.default_epsilon = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("include_zst") {
+                attrs.include_zst = true;
+            } else if meta.path.is_ident("key_matcher") {
+                attrs.key_matcher = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("unrolled") {
+                attrs.unrolled = Some(meta.path.clone());
+            } else if meta.path.is_ident("ignore") {
+                let lit: LitStr = meta.value()?.parse()?;
+                for name in lit.value().split(',').map(str::trim) {
+                    if !name.is_empty() {
+                        attrs.ignore.push((name.to_string(), lit.clone()));
+                    }
+                }
+            } else {
+                return Err(meta.error("unknown cmp container attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cmp"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                attrs.bound = Some(parse_bound(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("debug_eq") {
+                attrs.debug_eq = Some(meta.path.clone());
+            } else if meta.path.is_ident("epsilon") {
+                attrs.epsilon = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("flatten") {
+                attrs.flatten = true;
+            } else if meta.path.is_ident("include_zst") {
+                attrs.include_zst = true;
+            } else if meta.path.is_ident("max_render_len") {
+                attrs.max_render_len = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("rename") {
+                attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("skip") {
+                attrs.skip = true;
+            } else if meta.path.is_ident("unordered") {
+                attrs.unordered = true;
+            } else if meta.path.is_ident("with") {
+                attrs.with = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unknown cmp field attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn variant_attrs(variant: &syn::Variant) -> syn::Result<VariantAttrs> {
+    let mut attrs = VariantAttrs::default();
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cmp"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("unknown cmp variant attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
@@ -0,0 +1,34 @@
+/* C API of the cmp diff engine, built with the `capi` feature. */
+
+#ifndef CMP_H
+#define CMP_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Compares two JSON documents and returns the rendered differences, one per
+ * line, or an empty string if they are equal. Returns NULL if either argument
+ * is NULL or not valid UTF-8 JSON. Free the result with cmp_string_free.
+ */
+char *cmp_diff_json(const char *expected_json, const char *actual_json);
+
+/*
+ * Compares two JSON documents and returns the differences as a JSON array of
+ * objects with their "kind", "path", "pointer", "expected" and "actual"
+ * values. options_json may be NULL, or a JSON object such as
+ * {"key": "id", "unordered": true}. Returns NULL for invalid arguments. Free
+ * the result with cmp_string_free.
+ */
+char *cmp_diff_json_entries(const char *expected_json, const char *actual_json,
+                            const char *options_json);
+
+/* Frees a string returned by this API. Does nothing for NULL. */
+void cmp_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CMP_H */
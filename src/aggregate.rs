@@ -0,0 +1,596 @@
+//! Aggregate comparisons of large collections, which can tolerate a share of
+//! differing elements.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+
+use crate::{Diff, DiffPath, Diffable, FieldDiff};
+
+/// Environment variable which sets the seed used to sample the examples of
+/// an aggregate report, when none is set with [`Aggregate::sample_seed`].
+const SEED_VAR: &str = "CMP_SAMPLE_SEED";
+
+/// Options of an aggregate comparison of two slices, element by element.
+///
+/// Elements only present on one side count as differing. Unlike a plain
+/// [`Diffable::compare`], the comparison passes as long as the share of
+/// differing elements is at most the
+/// [tolerated mismatch rate](Aggregate::tolerate_mismatch_rate), which is
+/// useful for noisy outputs such as machine learning or simulation results.
+///
+/// ```edition2024
+/// let expected: Vec<u32> = (0..1000).collect();
+/// let mut actual = expected.clone();
+/// actual[7] = 0;
+///
+/// let report = cmp::Aggregate::new()
+///     .tolerate_mismatch_rate(0.001)
+///     .compare(&expected, &actual);
+///
+/// assert!(report.passed());
+/// assert_eq!(report.mismatched(), 1);
+/// ```
+///
+/// Fields can be given [weights](Aggregate::weight), so the report ranks
+/// differing fields by importance and the comparison can pass or fail on the
+/// [weighted score](AggregateReport::weighted_score) instead, e.g. to score
+/// parity between model versions.
+///
+/// By default, the report shows the first differing elements. With a
+/// [seed](Aggregate::sample_seed), or `CMP_SAMPLE_SEED` in the environment, it
+/// shows a sample which is the same on every run with that seed, so examples
+/// in CI reports are reproducible.
+#[derive(Clone, Debug)]
+pub struct Aggregate {
+    tolerated: f64,
+    max_weighted_score: Option<f64>,
+    weights: Vec<(String, f64)>,
+    max_examples: usize,
+    seed: Option<u64>,
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Self {
+            tolerated: 0.0,
+            max_weighted_score: None,
+            weights: Vec::new(),
+            max_examples: 10,
+            seed: None,
+        }
+    }
+}
+
+impl Aggregate {
+    /// Creates options tolerating no differing elements and showing up to 10
+    /// examples.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the share of elements, between 0 and 1, which may differ for the
+    /// comparison to pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not between 0 and 1.
+    pub fn tolerate_mismatch_rate(mut self, rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "mismatch rate must be between 0 and 1, got {rate}"
+        );
+        self.tolerated = rate;
+        self
+    }
+
+    /// Sets the weight of differences at paths matching `pattern`, a
+    /// [`DiffPath::matches_glob`] pattern such as `[*].score`. Paths matching
+    /// no pattern have a weight of 1, and the first matching pattern wins.
+    pub fn weight(mut self, pattern: &str, weight: f64) -> Self {
+        self.weights.push((pattern.to_string(), weight));
+        self
+    }
+
+    /// Makes the comparison pass only if the
+    /// [weighted score](AggregateReport::weighted_score) is at most `score`,
+    /// instead of checking the mismatch rate.
+    pub fn max_weighted_score(mut self, score: f64) -> Self {
+        self.max_weighted_score = Some(score);
+        self
+    }
+
+    fn weight_of(&self, path: &DiffPath) -> f64 {
+        self.weights
+            .iter()
+            .find(|(pattern, _)| path.matches_glob(pattern))
+            .map_or(1.0, |(_, weight)| *weight)
+    }
+
+    /// Sets the number of differing elements shown in the report.
+    pub fn max_examples(mut self, max_examples: usize) -> Self {
+        self.max_examples = max_examples;
+        self
+    }
+
+    /// Shows a sample of the differing elements, chosen deterministically from
+    /// `seed`, rather than the first ones. This takes precedence over
+    /// `CMP_SAMPLE_SEED`.
+    pub fn sample_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Compares `expected` with `actual` element by element.
+    ///
+    /// The outcome is recorded with the
+    /// [metrics recorder](crate::set_metrics_recorder), if one is set.
+    pub fn compare<T: Diffable>(&self, expected: &[T], actual: &[T]) -> AggregateReport {
+        let mut mismatches = Vec::new();
+        let mut path = DiffPath::new();
+        for index in 0..expected.len().max(actual.len()) {
+            path.push_index(index);
+            let mut diff = Diff::new();
+            match (expected.get(index), actual.get(index)) {
+                (Some(expected), Some(actual)) => expected.diff(actual, &mut path, &mut diff),
+                (Some(_), None) => diff.push(FieldDiff::MissingFromActual {
+                    path: path.clone(),
+                    expected: "<element>".to_string(),
+                }),
+                (None, _) => diff.push(FieldDiff::MissingFromExpected {
+                    path: path.clone(),
+                    actual: "<element>".to_string(),
+                }),
+            }
+            path.pop();
+            if !diff.is_empty() {
+                mismatches.push(diff);
+            }
+        }
+
+        let mut options = self.clone();
+        options.seed = options.seed.or_else(seed_from_env);
+        let report = AggregateReport {
+            elements: expected.len().max(actual.len()),
+            mismatches,
+            options,
+        };
+        crate::metrics::record(&report.diff());
+        report
+    }
+
+    /// Compares `expected` with `actual`, printing a summary to stderr if
+    /// any elements differ.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the summary if more elements differ than tolerated.
+    #[track_caller]
+    pub fn assert<T: Diffable>(&self, expected: &[T], actual: &[T]) {
+        let report = self.compare(expected, actual);
+        if report.mismatched() == 0 {
+            return;
+        }
+        if report.passed() {
+            crate::output::emit(&report);
+            return;
+        }
+        crate::report(&report.diff());
+        let message = report.to_string();
+        panic!("{message}");
+    }
+}
+
+/// Result of an [`Aggregate`] comparison.
+///
+/// `Display` renders a summary: the share of differing elements, the number
+/// of differences per field and the first differing elements.
+#[derive(Clone, Debug)]
+pub struct AggregateReport {
+    elements: usize,
+    mismatches: Vec<Diff>,
+    options: Aggregate,
+}
+
+impl AggregateReport {
+    /// Returns the number of compared elements, i.e. the length of the
+    /// longer slice.
+    pub fn elements(&self) -> usize {
+        self.elements
+    }
+
+    /// Returns the number of differing elements.
+    pub fn mismatched(&self) -> usize {
+        self.mismatches.len()
+    }
+
+    /// Returns the share of differing elements, between 0 and 1.
+    pub fn mismatch_rate(&self) -> f64 {
+        if self.elements == 0 {
+            0.0
+        } else {
+            self.mismatched() as f64 / self.elements as f64
+        }
+    }
+
+    /// Returns the mean weight of the differing fields per element: each
+    /// differing element adds the weights of its differing fields, and the
+    /// sum is divided by the number of elements.
+    pub fn weighted_score(&self) -> f64 {
+        if self.elements == 0 {
+            return 0.0;
+        }
+        let total: f64 = self
+            .field_counts()
+            .iter()
+            .map(|field| field.weight * field.count as f64)
+            .sum();
+        total / self.elements as f64
+    }
+
+    /// Returns whether the weighted score is within
+    /// [`max_weighted_score`](Aggregate::max_weighted_score) if one is set,
+    /// or else whether the mismatch rate is within the tolerated rate.
+    pub fn passed(&self) -> bool {
+        match self.options.max_weighted_score {
+            Some(max) => self.weighted_score() <= max,
+            None => self.mismatch_rate() <= self.options.tolerated,
+        }
+    }
+
+    /// Returns the differences of all elements.
+    pub fn diff(&self) -> Diff {
+        let mut diff = Diff::new();
+        for entry in self.mismatches.iter().flatten() {
+            diff.push(entry.clone());
+        }
+        diff
+    }
+
+    /// Returns the differences of the elements shown as examples: the first
+    /// [`max_examples`](Aggregate::max_examples) differing elements, or a
+    /// sample of them if a seed is set, in their original order.
+    pub fn examples(&self) -> Vec<&Diff> {
+        let max = self.options.max_examples;
+        let Some(seed) = self.options.seed else {
+            return self.mismatches.iter().take(max).collect();
+        };
+        // Partial Fisher-Yates shuffle, keeping the first `max` picks.
+        let mut indices: Vec<usize> = (0..self.mismatches.len()).collect();
+        let mut rng = SplitMix64(seed);
+        let picked = max.min(indices.len());
+        for i in 0..picked {
+            let j = i + (rng.next() % (indices.len() - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices.truncate(picked);
+        indices.sort_unstable();
+        indices.iter().map(|&i| &self.mismatches[i]).collect()
+    }
+
+    /// Returns the number of differing elements per path, ignoring indices,
+    /// ranked by weight times count.
+    pub fn field_counts(&self) -> Vec<FieldCount> {
+        let mut counts: HashMap<String, FieldCount> = HashMap::new();
+        for diff in &self.mismatches {
+            let mut paths: Vec<(String, &DiffPath)> = diff
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.path().with_wildcard_indices().to_string(),
+                        entry.path(),
+                    )
+                })
+                .collect();
+            paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+            paths.dedup_by(|(a, _), (b, _)| a == b);
+            for (key, path) in paths {
+                counts
+                    .entry(key.clone())
+                    .or_insert_with(|| FieldCount {
+                        path: key,
+                        count: 0,
+                        weight: self.options.weight_of(path),
+                    })
+                    .count += 1;
+            }
+        }
+        let mut counts: Vec<_> = counts.into_values().collect();
+        counts.sort_by(|a, b| {
+            (b.weight * b.count as f64)
+                .total_cmp(&(a.weight * a.count as f64))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        counts
+    }
+}
+
+/// Number of differing elements at one path of an [`AggregateReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldCount {
+    /// The path, with indices replaced with `[*]`.
+    pub path: String,
+    pub count: usize,
+    pub weight: f64,
+}
+
+impl fmt::Display for AggregateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} elements differ ({}, tolerated {})",
+            self.mismatched(),
+            self.elements,
+            percent(self.mismatch_rate()),
+            percent(self.options.tolerated)
+        )?;
+        let weighted = !self.options.weights.is_empty();
+        if let Some(max) = self.options.max_weighted_score {
+            writeln!(
+                f,
+                "weighted score {} (tolerated {})",
+                decimal(self.weighted_score()),
+                decimal(max)
+            )?;
+        } else if weighted {
+            writeln!(f, "weighted score {}", decimal(self.weighted_score()))?;
+        }
+        if self.mismatches.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "by field:")?;
+        for FieldCount {
+            path,
+            count,
+            weight,
+        } in self.field_counts()
+        {
+            if weighted {
+                writeln!(f, "  {path}: {count} (weight {})", decimal(weight))?;
+            } else {
+                writeln!(f, "  {path}: {count}")?;
+            }
+        }
+        match self.options.seed {
+            Some(seed) => writeln!(f, "examples (seed {seed}):")?,
+            None => writeln!(f, "examples:")?,
+        }
+        for diff in self.examples() {
+            write!(f, "{diff}")?;
+        }
+        if self.mismatched() > self.options.max_examples {
+            writeln!(
+                f,
+                "and {} more",
+                self.mismatched() - self.options.max_examples
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the seed set with `CMP_SAMPLE_SEED`, if it is a valid `u64`.
+fn seed_from_env() -> Option<u64> {
+    env::var(SEED_VAR).ok()?.trim().parse().ok()
+}
+
+/// The SplitMix64 generator, which is small and good enough to pick
+/// examples reproducibly.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Formats a rate as a percentage with up to three decimals, e.g. `0.1%`.
+fn percent(rate: f64) -> String {
+    format!("{}%", decimal(rate * 100.0))
+}
+
+/// Formats a number with up to three decimals, without trailing zeros.
+fn decimal(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Sets `$config`'s options from `tolerate_mismatch_rate = 0.1%`,
+/// `weight("[*].score") = 5.0` and any other builder method taking one
+/// argument, such as `max_examples = n`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __aggregate_options {
+    ($config:ident;) => {};
+    ($config:ident; tolerate_mismatch_rate = $rate:literal % $(, $($rest:tt)*)?) => {
+        $config = $config.tolerate_mismatch_rate($rate as f64 / 100.0);
+        $crate::__aggregate_options!($config; $($($rest)*)?);
+    };
+    ($config:ident; weight($pattern:expr) = $weight:expr $(, $($rest:tt)*)?) => {
+        $config = $config.weight($pattern, $weight);
+        $crate::__aggregate_options!($config; $($($rest)*)?);
+    };
+    ($config:ident; $option:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $config = $config.$option($value);
+        $crate::__aggregate_options!($config; $($($rest)*)?);
+    };
+}
+
+/// Macro which compares two collections element by element with
+/// [`Diffable`], passing if at most `tolerate_mismatch_rate` of the elements
+/// differ.
+///
+/// The rate is given as a percentage such as `0.1%`, or as a fraction
+/// expression. Fields can be weighted with `weight("[*].score") = 5.0`, and
+/// `max_weighted_score = 0.5` decides on the weighted score instead. When some elements differ but the comparison passes, a summary
+/// of the mismatches is printed to stderr. See [`Aggregate`] for the
+/// non-panicking API.
+///
+/// ```edition2024
+/// use cmp::compare_collections;
+///
+/// let expected: Vec<f64> = (0..100).map(f64::from).collect();
+/// let mut actual = expected.clone();
+/// actual[42] += 1.0;
+///
+/// compare_collections!(expected, actual; tolerate_mismatch_rate = 1%, max_examples = 3);
+/// ```
+///
+/// # Panics
+///
+/// Panics with a summary of the mismatches if more elements differ than
+/// tolerated (by default, any).
+#[macro_export]
+macro_rules! compare_collections {
+    ($expected:expr, $actual:expr $(; $($option:tt)*)?) => {{
+        #[allow(unused_mut)]
+        let mut config = $crate::Aggregate::new();
+        $crate::__aggregate_options!(config; $($($option)*)?);
+        match (&$expected, &$actual) {
+            (expected, actual) => config.assert(expected, actual),
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(cmp_derive::Diffable)]
+    struct Sample {
+        id: u32,
+        score: f64,
+    }
+
+    fn samples(n: u32) -> Vec<Sample> {
+        (0..n)
+            .map(|id| Sample {
+                id,
+                score: f64::from(id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summarizes_mismatches_per_field() {
+        let expected = samples(4);
+        let mut actual = samples(3);
+        actual[1].score = 0.5;
+
+        let report = Aggregate::new().max_examples(1).compare(&expected, &actual);
+
+        assert!(!report.passed());
+        assert_eq!(report.mismatch_rate(), 0.5);
+        assert_eq!(
+            report.to_string(),
+            "2 of 4 elements differ (50%, tolerated 0%)\n\
+             by field:\n  \
+             [*]: 1\n  \
+             [*].score: 1\n\
+             examples:\n\
+             [1].score: 1.0 != 0.5\n\
+             and 1 more\n"
+        );
+    }
+
+    #[test]
+    fn ranks_and_scores_weighted_fields() {
+        let expected = samples(10);
+        let mut actual = samples(10);
+        for sample in &mut actual[..3] {
+            sample.id += 100;
+        }
+        actual[5].score = -1.0;
+
+        let report = Aggregate::new()
+            .weight("[*].score", 5.0)
+            .max_weighted_score(1.0)
+            .max_examples(0)
+            .compare(&expected, &actual);
+
+        assert_eq!(report.weighted_score(), 0.8);
+        assert!(report.passed());
+        assert_eq!(
+            report.to_string(),
+            "4 of 10 elements differ (40%, tolerated 0%)\n\
+             weighted score 0.8 (tolerated 1)\n\
+             by field:\n  \
+             [*].score: 1 (weight 5)\n  \
+             [*].id: 3 (weight 1)\n\
+             examples:\n\
+             and 4 more\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "weighted score 0.5 (tolerated 0.1)")]
+    fn fails_above_the_weighted_score() {
+        let expected = samples(10);
+        let mut actual = samples(10);
+        actual[0].score = -1.0;
+
+        compare_collections!(
+            expected,
+            actual;
+            weight("**.score") = 5.0,
+            max_weighted_score = 0.1
+        );
+    }
+
+    #[test]
+    fn samples_examples_deterministically() {
+        let expected = samples(100);
+        let actual: Vec<Sample> = (0..100).map(|id| Sample { id, score: -1.0 }).collect();
+        let aggregate = Aggregate::new().max_examples(3);
+
+        let sample = |seed| {
+            aggregate
+                .clone()
+                .sample_seed(seed)
+                .compare(&expected, &actual)
+                .examples()
+                .iter()
+                .map(|diff| diff.entries()[0].path().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+        assert_eq!(sample(7).len(), 3);
+        assert!(
+            aggregate
+                .sample_seed(7)
+                .compare(&expected, &actual)
+                .to_string()
+                .contains("examples (seed 7):\n")
+        );
+    }
+
+    #[test]
+    fn tolerates_the_given_rate() {
+        let expected = samples(1000);
+        let mut actual = samples(1000);
+        actual[10].id = 0;
+
+        compare_collections!(expected, actual; tolerate_mismatch_rate = 0.1%);
+        assert_eq!(percent(0.001), "0.1%");
+    }
+
+    #[test]
+    #[should_panic(expected = "2 of 1000 elements differ (0.2%, tolerated 0.1%)")]
+    fn fails_above_the_tolerated_rate() {
+        let expected = samples(1000);
+        let mut actual = samples(1000);
+        actual[10].id = 0;
+        actual[20].id = 0;
+
+        compare_collections!(expected, actual; tolerate_mismatch_rate = 0.001);
+    }
+}
@@ -0,0 +1,100 @@
+//! [`Diffable`] implementations for atomics.
+//!
+//! Atomics are loaded with a process-wide ordering, [`SeqCst`] by default,
+//! and the loaded values are compared like any other primitive.
+
+use std::sync::atomic::Ordering::{self, Acquire, Relaxed, SeqCst};
+use std::sync::atomic::{
+    AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize, AtomicU8, AtomicU16,
+    AtomicU32, AtomicU64, AtomicUsize,
+};
+
+use crate::{Diff, DiffPath, Diffable, diff_eq};
+
+static LOAD_ORDERING: AtomicU8 = AtomicU8::new(2);
+
+/// Sets the ordering used to load atomics when comparing them.
+///
+/// ```edition2024
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use cmp::Diffable;
+///
+/// cmp::set_atomic_load_ordering(Ordering::Acquire);
+///
+/// assert!(AtomicU64::new(1).compare(&AtomicU64::new(1)).is_empty());
+/// ```
+///
+/// # Panics
+///
+/// Panics if `ordering` is [`Release`](Ordering::Release) or
+/// [`AcqRel`](Ordering::AcqRel), which are not valid for loads.
+pub fn set_atomic_load_ordering(ordering: Ordering) {
+    let value = match ordering {
+        Relaxed => 0,
+        Acquire => 1,
+        SeqCst => 2,
+        _ => panic!("{ordering:?} is not a valid ordering for atomic loads"),
+    };
+    LOAD_ORDERING.store(value, Relaxed);
+}
+
+/// Returns the ordering used to load atomics when comparing them.
+pub fn atomic_load_ordering() -> Ordering {
+    match LOAD_ORDERING.load(Relaxed) {
+        0 => Relaxed,
+        1 => Acquire,
+        _ => SeqCst,
+    }
+}
+
+macro_rules! impl_diffable_atomic {
+    ($($ty:ty),+) => {
+        $(
+            impl Diffable for $ty {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    let ordering = atomic_load_ordering();
+                    diff_eq(&self.load(ordering), &other.load(ordering), path, out);
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_atomic!(AtomicBool, AtomicIsize, AtomicUsize);
+impl_diffable_atomic!(AtomicI8, AtomicI16, AtomicI32, AtomicI64);
+impl_diffable_atomic!(AtomicU8, AtomicU16, AtomicU32, AtomicU64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(cmp_derive::Diffable)]
+    struct Stats {
+        running: AtomicBool,
+        requests: AtomicU64,
+    }
+
+    #[test]
+    fn compares_loaded_values() {
+        let expected = Stats {
+            running: AtomicBool::new(true),
+            requests: AtomicU64::new(10),
+        };
+        let actual = Stats {
+            running: AtomicBool::new(true),
+            requests: AtomicU64::new(10),
+        };
+        actual.requests.fetch_add(1, SeqCst);
+
+        assert_eq!(
+            expected.compare(&actual).to_string(),
+            "requests: 10 != 11\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Release is not a valid ordering for atomic loads")]
+    fn rejects_store_only_orderings() {
+        set_atomic_load_ordering(Ordering::Release);
+    }
+}
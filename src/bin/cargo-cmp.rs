@@ -0,0 +1,105 @@
+//! `cargo cmp review`: walks the pending snapshot updates written by
+//! `assert_struct_snapshot!`, showing the field-level diff of each and
+//! asking whether to accept or reject it.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use cmp::review::{self, PendingSnapshot};
+
+const USAGE: &str = "usage: cargo cmp review [DIR]";
+
+fn main() -> ExitCode {
+    // Cargo passes the subcommand name as the first argument.
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "cmp") {
+        args.next();
+    }
+    let (Some(command), root, None) = (args.next(), args.next(), args.next()) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    if command != "review" {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    match run(&root.unwrap_or_else(|| ".".to_string())) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(root: &str) -> io::Result<()> {
+    let pending = review::pending(root)?;
+    if pending.is_empty() {
+        println!("no pending snapshots");
+        return Ok(());
+    }
+
+    let (mut accepted, mut rejected) = (0, 0);
+    let mut stdin = io::stdin().lock();
+    for (position, snapshot) in pending.iter().enumerate() {
+        println!(
+            "[{}/{}] {}",
+            position + 1,
+            pending.len(),
+            snapshot.snapshot.display()
+        );
+        show(snapshot)?;
+        match prompt(&mut stdin)? {
+            Choice::Accept => {
+                snapshot.accept()?;
+                accepted += 1;
+            }
+            Choice::Reject => {
+                snapshot.reject()?;
+                rejected += 1;
+            }
+            Choice::Skip => {}
+            Choice::Quit => break,
+        }
+        println!();
+    }
+    println!("{accepted} accepted, {rejected} rejected");
+    Ok(())
+}
+
+fn show(snapshot: &PendingSnapshot) -> io::Result<()> {
+    if snapshot.is_new() {
+        println!("new snapshot:");
+        print!("{}", std::fs::read_to_string(&snapshot.pending)?);
+    } else {
+        print!("{}", snapshot.diff()?);
+    }
+    Ok(())
+}
+
+enum Choice {
+    Accept,
+    Reject,
+    Skip,
+    Quit,
+}
+
+fn prompt(input: &mut impl BufRead) -> io::Result<Choice> {
+    loop {
+        print!("[a]ccept, [r]eject, [s]kip, [q]uit? ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(Choice::Quit);
+        }
+        match line.trim() {
+            "a" | "accept" => return Ok(Choice::Accept),
+            "r" | "reject" => return Ok(Choice::Reject),
+            "s" | "skip" | "" => return Ok(Choice::Skip),
+            "q" | "quit" => return Ok(Choice::Quit),
+            _ => {}
+        }
+    }
+}
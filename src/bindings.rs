@@ -0,0 +1,50 @@
+//! Comparison of JSON documents with options, shared by the C API and the
+//! Python bindings so both report the same differences.
+
+use serde_json::Value;
+
+use crate::{Diff, DiffPath};
+
+/// The options of a comparison from another language.
+#[derive(Default)]
+pub(crate) struct Options {
+    key: Option<String>,
+    unordered: bool,
+}
+
+impl Options {
+    /// Reads the options from a JSON object, or returns `None` if it has
+    /// unknown or mistyped options.
+    pub(crate) fn from_json(json: &Value) -> Option<Self> {
+        let mut options = Self::default();
+        for (name, value) in json.as_object()? {
+            match name.as_str() {
+                "key" => options.key = Some(value.as_str()?.to_string()),
+                "unordered" => options.unordered = value.as_bool()?,
+                _ => return None,
+            }
+        }
+        Some(options)
+    }
+
+    /// Compares two documents like `compare_structs!` in serde mode with
+    /// these options, and records the comparison's metrics.
+    pub(crate) fn diff(&self, expected: &Value, actual: &Value) -> Diff {
+        let compare = || {
+            let mut diff = Diff::new();
+            crate::value::diff_values(expected, actual, false, &mut DiffPath::new(), &mut diff);
+            diff
+        };
+        let compare = || match &self.key {
+            Some(key) => crate::with_element_key(key, compare),
+            None => compare(),
+        };
+        let diff = if self.unordered {
+            crate::with_unordered_sequences(compare)
+        } else {
+            compare()
+        };
+        crate::metrics::record(&diff);
+        diff
+    }
+}
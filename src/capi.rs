@@ -0,0 +1,216 @@
+//! A C API for the diff engine, enabled by the `capi` feature, so C and C++
+//! test suites get the same differences and output as Rust ones.
+//!
+//! Build the library with `cargo rustc --release --features capi --crate-type
+//! cdylib` (or `staticlib`) and include `include/cmp.h`:
+//!
+//! ```c
+//! char *diff = cmp_diff_json("{\"port\": 8080}", "{\"port\": 8081}");
+//! if (diff != NULL && *diff != '\0') {
+//!     fprintf(stderr, "%s", diff); /* port: 8080 != 8081 */
+//! }
+//! cmp_string_free(diff);
+//! ```
+//!
+//! [`cmp_diff_json_entries`] returns the differences as JSON instead, for
+//! bindings in other languages.
+
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+use serde_json::Value;
+
+use crate::bindings::Options;
+use crate::{Diff, DiffPath};
+
+/// Compares two JSON documents like `compare_structs!` in serde mode and
+/// returns the rendered differences, one per line, as a string to free with
+/// [`cmp_string_free`].
+///
+/// The string is empty if the documents are equal. Returns null if either
+/// pointer is null, or either document isn't valid UTF-8 JSON.
+///
+/// # Safety
+///
+/// `expected_json` and `actual_json` must be null or point to
+/// NUL-terminated strings which stay valid for the duration of the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cmp_diff_json(
+    expected_json: *const c_char,
+    actual_json: *const c_char,
+) -> *mut c_char {
+    // SAFETY: the caller passes null or valid NUL-terminated strings.
+    let (Some(expected), Some(actual)) = (unsafe { parse(expected_json) }, unsafe {
+        parse(actual_json)
+    }) else {
+        return ptr::null_mut();
+    };
+    let mut diff = Diff::new();
+    crate::value::diff_values(&expected, &actual, false, &mut DiffPath::new(), &mut diff);
+    crate::metrics::record(&diff);
+    // Rendered values escape NUL characters, so the rendering has none.
+    CString::new(diff.to_string()).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Compares two JSON documents like [`cmp_diff_json`] and returns the
+/// differences as a JSON array, to free with [`cmp_string_free`].
+///
+/// Each difference is an object with its `kind` (`changed`,
+/// `missing_from_expected` or `missing_from_actual`), `path`, JSON
+/// `pointer`, and rendered `expected` and `actual` values, `null` for the
+/// missing side, as in the output of `CMP_FORMAT=json`.
+///
+/// `options_json` may be null, or a JSON object with any of:
+///
+/// - `"key": "id"`, to pair array elements by their `id` field, like
+///   `compare_structs!(a, b; key = "id")`;
+/// - `"unordered": true`, to compare arrays regardless of order, like
+///   `compare_structs!(a, b; unordered)`.
+///
+/// Returns null if a document pointer is null, a document isn't valid UTF-8
+/// JSON, or the options are invalid.
+///
+/// # Safety
+///
+/// Each argument must be null or point to a NUL-terminated string which
+/// stays valid for the duration of the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cmp_diff_json_entries(
+    expected_json: *const c_char,
+    actual_json: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    // SAFETY: the caller passes null or valid NUL-terminated strings.
+    let (Some(expected), Some(actual)) = (unsafe { parse(expected_json) }, unsafe {
+        parse(actual_json)
+    }) else {
+        return ptr::null_mut();
+    };
+    let options = if options_json.is_null() {
+        Options::default()
+    } else {
+        // SAFETY: as above.
+        match unsafe { parse(options_json) }.and_then(|options| Options::from_json(&options)) {
+            Some(options) => options,
+            None => return ptr::null_mut(),
+        }
+    };
+    let diff = options.diff(&expected, &actual);
+    let entries = Value::Array(crate::format::json_entries(&diff));
+    CString::new(entries.to_string()).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a string returned by [`cmp_diff_json`] or [`cmp_diff_json_entries`].
+/// Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this API which hasn't been
+/// freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cmp_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: `string` was created by `CString::into_raw` and is only
+        // freed once.
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Parses the JSON document `json` points to.
+///
+/// # Safety
+///
+/// `json` must be null or point to a NUL-terminated string.
+unsafe fn parse(json: *const c_char) -> Option<Value> {
+    if json.is_null() {
+        return None;
+    }
+    // SAFETY: `json` is a valid NUL-terminated string.
+    let json = unsafe { CStr::from_ptr(json) }.to_str().ok()?;
+    serde_json::from_str(json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff_json(expected: &CStr, actual: &CStr) -> Option<String> {
+        // SAFETY: both arguments are valid C strings, and the result is freed
+        // once.
+        unsafe {
+            let diff = cmp_diff_json(expected.as_ptr(), actual.as_ptr());
+            let rendered =
+                (!diff.is_null()).then(|| CStr::from_ptr(diff).to_str().unwrap().to_string());
+            cmp_string_free(diff);
+            rendered
+        }
+    }
+
+    #[test]
+    fn renders_differences_like_serde_mode() {
+        assert_eq!(
+            diff_json(
+                c"{\"server\": {\"port\": 8080}, \"name\": \"a\\u0000\"}",
+                c"{\"server\": {\"port\": 8081}, \"name\": \"a\"}"
+            )
+            .unwrap(),
+            "name: \"a\\0\" != \"a\"\nserver.port: 8080 != 8081\n"
+        );
+        assert_eq!(diff_json(c"[1, 2]", c"[1, 2]").unwrap(), "");
+    }
+
+    fn diff_entries(expected: &CStr, actual: &CStr, options: Option<&CStr>) -> Option<Value> {
+        // SAFETY: as in `diff_json`.
+        unsafe {
+            let options = options.map_or(ptr::null(), CStr::as_ptr);
+            let diff = cmp_diff_json_entries(expected.as_ptr(), actual.as_ptr(), options);
+            let entries =
+                (!diff.is_null()).then(|| serde_json::from_slice(CStr::from_ptr(diff).to_bytes()));
+            cmp_string_free(diff);
+            entries.map(Result::unwrap)
+        }
+    }
+
+    #[test]
+    fn returns_entries_as_json_with_options() {
+        let expected = c"{\"items\": [{\"id\": 1, \"n\": 1}, {\"id\": 2, \"n\": 2}]}";
+        let actual = c"{\"items\": [{\"id\": 2, \"n\": 3}, {\"id\": 1, \"n\": 1}]}";
+
+        assert_eq!(
+            diff_entries(expected, actual, Some(c"{\"key\": \"id\"}")).unwrap(),
+            serde_json::json!([{
+                "kind": "changed",
+                "path": "items[1].n",
+                "pointer": "/items/1/n",
+                "expected": "2",
+                "actual": "3",
+            }])
+        );
+        assert_eq!(
+            diff_entries(expected, actual, None)
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+        assert_eq!(
+            diff_entries(expected, actual, Some(c"{\"keys\": \"id\"}")),
+            None
+        );
+        assert_eq!(
+            diff_entries(expected, actual, Some(c"{\"unordered\": 1}")),
+            None
+        );
+    }
+
+    #[test]
+    fn returns_null_for_invalid_input() {
+        assert_eq!(diff_json(c"{\"a\": ", c"{}"), None);
+        // SAFETY: null pointers are accepted.
+        unsafe {
+            assert!(cmp_diff_json(ptr::null(), c"{}".as_ptr()).is_null());
+            cmp_string_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Expression capture for failure messages, enabled with `CMP_CAPTURE=1`.
+//!
+//! Like the `assert2` crate, the captured header shows the source text of
+//! both compared expressions and, when their types implement `Debug`, the
+//! values they evaluated to. This is used by the macros through
+//! `cmp::__private` and is not part of the public API.
+
+use std::env;
+use std::fmt::{Debug, Write};
+
+/// Environment variable which enables expression capture.
+const CAPTURE_VAR: &str = "CMP_CAPTURE";
+
+/// Returns whether `CMP_CAPTURE` is set to anything other than `0` or an
+/// empty string.
+pub fn enabled() -> bool {
+    env::var_os(CAPTURE_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// A compared value, rendered with `Debug` if its type implements it.
+///
+/// Call `(&Capture(value)).render()` with both [`CaptureDebug`] and
+/// [`CaptureFallback`] in scope: method resolution prefers the `Debug`
+/// implementation and falls back to `None` for other types.
+pub struct Capture<'a, T: ?Sized>(pub &'a T);
+
+pub trait CaptureDebug {
+    fn render(&self) -> Option<String>;
+}
+
+impl<T: Debug + ?Sized> CaptureDebug for Capture<'_, T> {
+    fn render(&self) -> Option<String> {
+        Some(format!("{:#?}", self.0))
+    }
+}
+
+pub trait CaptureFallback {
+    fn render(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<T: ?Sized> CaptureFallback for &Capture<'_, T> {}
+
+/// Renders the header printed above the field diffs, listing each side's
+/// source text and value.
+pub fn header(
+    expected_source: &str,
+    expected: Option<String>,
+    actual_source: &str,
+    actual: Option<String>,
+) -> String {
+    // Captured values aren't redacted, so they could reveal secrets.
+    #[cfg(feature = "serde")]
+    let (expected, actual) = match crate::redact::redactions() {
+        Some(_) => (None, None),
+        None => (expected, actual),
+    };
+    let messages = crate::messages::messages();
+    let mut header = String::new();
+    for (side, source, value) in [
+        (messages.expected(), expected_source, expected),
+        (messages.actual(), actual_source, actual),
+    ] {
+        let _ = writeln!(header, "{side}: {source}");
+        if let Some(value) = value {
+            for line in value.lines() {
+                let _ = writeln!(header, "  {line}");
+            }
+        }
+    }
+    header.push('\n');
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Opaque;
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn renders_debug_values_when_available() {
+        assert_eq!(
+            (&Capture(&(1, "a"))).render().as_deref(),
+            Some("(\n    1,\n    \"a\",\n)")
+        );
+        assert_eq!((&Capture(&Opaque)).render(), None);
+    }
+
+    #[test]
+    fn header_lists_source_and_indented_values() {
+        let header = header(
+            "user.with_name(\"a\")",
+            Some("User {\n    name: \"a\",\n}".to_string()),
+            "fetch()",
+            None,
+        );
+
+        assert_eq!(
+            header,
+            "expected: user.with_name(\"a\")\n  User {\n      name: \"a\",\n  }\nactual: fetch()\n\n"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Incremental comparison of sequences too large to hold in memory, fed one
+//! chunk at a time as they are streamed in.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use crate::{Diff, DiffPath, Diffable, FieldDiff};
+
+/// A comparison of two sequences which accepts corresponding chunks of the
+/// expected and actual elements as they arrive, so the comparison can
+/// overlap with reading them, and produces the combined [`Diff`] at the end.
+///
+/// Elements are compared by position across chunks, and reported at their
+/// index in the whole sequence, like a [`Diffable::compare`] of the two
+/// concatenated sequences. Chunks of the two sides need not have the same
+/// length: elements without a counterpart yet are kept until the other side
+/// catches up, and reported as missing by [`finish`](ChunkedComparer::finish)
+/// if it never does. Only those elements are held, so memory stays bounded
+/// by the chunk sizes and the differences found.
+///
+/// ```edition2024
+/// let mut comparer = cmp::ChunkedComparer::new();
+/// comparer.push(vec![1, 2, 3], vec![1, 2]);
+/// comparer.push(vec![4, 5], vec![3, 9, 5, 6]);
+///
+/// assert_eq!(comparer.compared(), 5);
+/// assert_eq!(
+///     comparer.finish().to_string(),
+///     "[3]: 4 != 9\n[5]: field missing from expected: 6\n"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ChunkedComparer<T> {
+    expected: VecDeque<T>,
+    actual: VecDeque<T>,
+    compared: usize,
+    epsilon: Option<f64>,
+    path: DiffPath,
+    diff: Diff,
+}
+
+impl<T: Diffable + Debug> Default for ChunkedComparer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Diffable + Debug> ChunkedComparer<T> {
+    /// Creates a comparison which has seen no elements.
+    pub fn new() -> Self {
+        Self {
+            expected: VecDeque::new(),
+            actual: VecDeque::new(),
+            compared: 0,
+            epsilon: None,
+            path: DiffPath::new(),
+            diff: Diff::new(),
+        }
+    }
+
+    /// Compares elements with [`Diffable::diff_with_epsilon`], so floats
+    /// within `epsilon` of each other are equal.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = Some(epsilon);
+        self
+    }
+
+    /// Compares the next chunk of expected elements with the next chunk of
+    /// actual elements.
+    ///
+    /// Either chunk may be empty, e.g. when one stream is ahead of the
+    /// other.
+    pub fn push(
+        &mut self,
+        expected: impl IntoIterator<Item = T>,
+        actual: impl IntoIterator<Item = T>,
+    ) {
+        self.expected.extend(expected);
+        self.actual.extend(actual);
+        let paired = self.expected.len().min(self.actual.len());
+        for (expected, actual) in self
+            .expected
+            .drain(..paired)
+            .zip(self.actual.drain(..paired))
+        {
+            self.path.push_index(self.compared);
+            crate::impls::diff_value(
+                &expected,
+                &actual,
+                self.epsilon,
+                &mut self.path,
+                &mut self.diff,
+            );
+            self.path.pop();
+            self.compared += 1;
+        }
+    }
+
+    /// Returns the number of elements compared with a counterpart so far.
+    pub fn compared(&self) -> usize {
+        self.compared
+    }
+
+    /// Returns the differences found so far, excluding elements still
+    /// waiting for their counterpart.
+    pub fn diff(&self) -> &Diff {
+        &self.diff
+    }
+
+    /// Ends the comparison, reporting the elements left without a
+    /// counterpart as missing, and returns all differences.
+    ///
+    /// The outcome is recorded with the
+    /// [metrics recorder](crate::set_metrics_recorder), if one is set.
+    pub fn finish(mut self) -> Diff {
+        for (offset, expected) in self.expected.iter().enumerate() {
+            self.path.push_index(self.compared + offset);
+            self.diff.push_with(|| FieldDiff::MissingFromActual {
+                path: self.path.clone(),
+                expected: crate::formatters::render_debug(expected),
+            });
+            self.path.pop();
+        }
+        for (offset, actual) in self.actual.iter().enumerate() {
+            self.path.push_index(self.compared + offset);
+            self.diff.push_with(|| FieldDiff::MissingFromExpected {
+                path: self.path.clone(),
+                actual: crate::formatters::render_debug(actual),
+            });
+            self.path.pop();
+        }
+        crate::metrics::record(&self.diff);
+        self.diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_comparing_the_whole_sequences() {
+        let expected: Vec<u32> = (0..100).collect();
+        let mut actual = expected.clone();
+        actual[3] = 0;
+        actual[64] = 0;
+        actual.truncate(90);
+
+        let mut comparer = ChunkedComparer::new();
+        for (index, expected) in expected.chunks(7).enumerate() {
+            let actual = actual.get(index * 11..).unwrap_or_default();
+            comparer.push(
+                expected.to_vec(),
+                actual.iter().take(11).copied().collect::<Vec<_>>(),
+            );
+        }
+
+        assert_eq!(comparer.finish(), expected.compare(&actual));
+    }
+
+    #[test]
+    fn forwards_the_epsilon() {
+        let mut comparer = ChunkedComparer::new().epsilon(0.1);
+        comparer.push([1.0, 2.0], [1.05]);
+        assert!(comparer.diff().is_empty());
+        comparer.push([], [2.5]);
+
+        assert_eq!(comparer.finish().to_string(), "[1]: 2.0 != 2.5\n");
+    }
+}
@@ -0,0 +1,81 @@
+//! Colored failure messages, enabled by the `color` feature, with expected
+//! values in green and actual values in red.
+//!
+//! Colors are only used when the output is a terminal and `NO_COLOR` isn't
+//! set to a non-empty value, following <https://no-color.org>.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::IsTerminal;
+
+use crate::Diff;
+use crate::diff::Palette;
+
+/// Environment variable which disables colors when set to a non-empty value.
+const NO_COLOR_VAR: &str = "NO_COLOR";
+
+/// Green expected values and red actual values.
+const PALETTE: Palette = Palette {
+    expected: "\x1b[32m",
+    actual: "\x1b[31m",
+    reset: "\x1b[0m",
+};
+
+/// Returns whether output written to `stream` should be colored.
+pub(crate) fn enabled(stream: &impl IsTerminal) -> bool {
+    allowed(env::var_os(NO_COLOR_VAR).as_deref(), stream.is_terminal())
+}
+
+fn allowed(no_color: Option<&OsStr>, terminal: bool) -> bool {
+    terminal && no_color.is_none_or(OsStr::is_empty)
+}
+
+/// Renders `diff` like its `Display` implementation, with colored values.
+pub(crate) fn paint(diff: &Diff) -> String {
+    struct Colored<'a>(&'a Diff);
+
+    impl fmt::Display for Colored<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.write(f, PALETTE)
+        }
+    }
+
+    Colored(diff).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiffPath, FieldDiff};
+
+    #[test]
+    fn colors_expected_green_and_actual_red() {
+        let mut path = DiffPath::new();
+        path.push_field("tags");
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::Changed {
+            path: path.clone(),
+            expected: "[\n    1,\n]".into(),
+            actual: "[]".into(),
+        });
+        diff.push(FieldDiff::MissingFromExpected {
+            path,
+            actual: "2".into(),
+        });
+
+        assert_eq!(
+            paint(&diff),
+            "tags: \x1b[32m[\x1b[0m\n\x1b[32m    1,\x1b[0m\n\x1b[32m]\x1b[0m != \x1b[31m[]\x1b[0m\n\
+             tags: field missing from expected: \x1b[31m2\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn respects_no_color_and_terminals() {
+        assert!(allowed(None, true));
+        assert!(allowed(Some(OsStr::new("")), true));
+        assert!(!allowed(Some(OsStr::new("1")), true));
+        assert!(!allowed(None, false));
+    }
+}
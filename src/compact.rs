@@ -0,0 +1,403 @@
+//! A compact binary encoding of [`Diff`]s, for services which ship diffs
+//! over the network, such as shadow comparisons of production traffic.
+//!
+//! Integers are LEB128 varints, and each distinct string (field names, keys
+//! and rendered values) is written once and then referred to by its index, so
+//! the many entries sharing a field name or value cost a byte or two each.
+//! With the `compression` feature, the encoding can also be deflated.
+
+use std::io;
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+use crate::{Diff, DiffPath, FieldDiff, Segment};
+
+/// Leading bytes of an encoded diff, with the version of the encoding.
+const MAGIC: &[u8; 5] = b"cmpd\x02";
+
+impl Diff {
+    /// Encodes the diff, with its context lines, count of
+    /// [capped](Diff::capped) differences and labels of differences
+    /// [found by comparing `Debug` output](Diff::is_debug_compared), in a
+    /// compact binary form which
+    /// [`from_compact_bytes`](Diff::from_compact_bytes) decodes.
+    ///
+    /// Repeated strings are only stored once, so the encoding is typically
+    /// much smaller than the rendered diff. With the `compression` feature,
+    /// [`to_compressed_bytes`](Diff::to_compressed_bytes) deflates it too, if
+    /// bandwidth matters more than CPU.
+    ///
+    /// ```edition2024
+    /// use cmp::{Diff, Diffable};
+    ///
+    /// let diff = vec![("pending", 1); 100].compare(&vec![("failed", 1); 100]);
+    /// let bytes = diff.to_compact_bytes();
+    ///
+    /// assert!(bytes.len() < diff.to_string().len() / 3);
+    /// assert_eq!(Diff::from_compact_bytes(&bytes).unwrap(), diff);
+    /// ```
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        encoder.out.extend_from_slice(MAGIC);
+        encoder.int(self.len());
+        for entry in self {
+            match entry {
+                FieldDiff::Changed {
+                    path,
+                    expected,
+                    actual,
+                } => {
+                    encoder.out.push(0);
+                    encoder.path(path);
+                    encoder.string(expected);
+                    encoder.string(actual);
+                }
+                FieldDiff::MissingFromExpected { path, actual } => {
+                    encoder.out.push(1);
+                    encoder.path(path);
+                    encoder.string(actual);
+                }
+                FieldDiff::MissingFromActual { path, expected } => {
+                    encoder.out.push(2);
+                    encoder.path(path);
+                    encoder.string(expected);
+                }
+            }
+        }
+        encoder.int(self.context_lines().count());
+        for (before, path, value) in self.context_lines() {
+            encoder.int(before);
+            encoder.path(path);
+            encoder.string(value);
+        }
+        match self.overflow_counts() {
+            Some((limit, count)) => {
+                encoder.out.push(1);
+                encoder.int(limit);
+                encoder.int(count);
+            }
+            None => encoder.out.push(0),
+        }
+        encoder.int(self.debug_compared().len());
+        for path in self.debug_compared() {
+            encoder.path(path);
+        }
+        encoder.out
+    }
+
+    /// Decodes a diff encoded by [`to_compact_bytes`](Diff::to_compact_bytes).
+    ///
+    /// Repeated strings are copied into each entry, so the decoded strings
+    /// may take much more memory than `bytes`; they may take up to the
+    /// [memory limit](crate::set_diff_memory_limit) of a diff in all.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error if `bytes` is not a
+    /// valid encoding, e.g. because it was truncated, or if its strings
+    /// would take more than the memory limit.
+    pub fn from_compact_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let rest = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| invalid("not a compact diff"))?;
+        let mut decoder = Decoder {
+            bytes: rest,
+            strings: Vec::new(),
+            remaining: crate::limit::diff_memory_limit(),
+        };
+        let count = decoder.int()?;
+        let mut entries = Vec::with_capacity(count.min(rest.len()));
+        for _ in 0..count {
+            let tag = decoder.byte()?;
+            let path = decoder.path()?;
+            entries.push(match tag {
+                0 => FieldDiff::Changed {
+                    path,
+                    expected: decoder.string()?,
+                    actual: decoder.string()?,
+                },
+                1 => FieldDiff::MissingFromExpected {
+                    path,
+                    actual: decoder.string()?,
+                },
+                2 => FieldDiff::MissingFromActual {
+                    path,
+                    expected: decoder.string()?,
+                },
+                _ => return Err(invalid("unknown entry kind")),
+            });
+        }
+        let count = decoder.int()?;
+        let mut context = Vec::with_capacity(count.min(decoder.bytes.len()));
+        for _ in 0..count {
+            context.push((decoder.int()?, decoder.path()?, decoder.string()?));
+        }
+        let overflow = match decoder.byte()? {
+            0 => None,
+            1 => Some((decoder.int()?, decoder.int()?)),
+            _ => return Err(invalid("unknown overflow marker")),
+        };
+        let count = decoder.int()?;
+        let mut debug_compared = Vec::with_capacity(count.min(decoder.bytes.len()));
+        for _ in 0..count {
+            debug_compared.push(decoder.path()?);
+        }
+        if !decoder.bytes.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(Diff::from_parts(entries, context, overflow, debug_compared))
+    }
+
+    /// Encodes the diff like [`to_compact_bytes`](Diff::to_compact_bytes),
+    /// then deflates it, for [`from_compressed_bytes`](Diff::from_compressed_bytes)
+    /// to decode.
+    ///
+    /// ```edition2024
+    /// use cmp::{Diff, Diffable};
+    ///
+    /// let diff = (0..100).map(|n| n * 2).collect::<Vec<u32>>().compare(&(0..100).collect());
+    /// let bytes = diff.to_compressed_bytes();
+    ///
+    /// assert!(bytes.len() < diff.to_compact_bytes().len());
+    /// assert_eq!(Diff::from_compressed_bytes(&bytes).unwrap(), diff);
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        // Writing to a `Vec` can't fail.
+        let _ = encoder.write_all(&self.to_compact_bytes());
+        encoder.finish().unwrap_or_default()
+    }
+
+    /// Decodes a diff encoded by
+    /// [`to_compressed_bytes`](Diff::to_compressed_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error if `bytes` is not a
+    /// valid encoding, or if it inflates to more than the
+    /// [memory limit](crate::set_diff_memory_limit) of a diff, like
+    /// [`from_compact_bytes`](Diff::from_compact_bytes).
+    #[cfg(feature = "compression")]
+    pub fn from_compressed_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let limit = crate::limit::diff_memory_limit();
+        let mut compact = Vec::new();
+        flate2::read::DeflateDecoder::new(bytes)
+            .take(limit.saturating_add(1) as u64)
+            .read_to_end(&mut compact)
+            .map_err(|_| invalid("invalid deflate stream"))?;
+        if compact.len() > limit {
+            return Err(invalid("larger than the memory limit"));
+        }
+        Self::from_compact_bytes(&compact)
+    }
+}
+
+#[derive(Default)]
+struct Encoder<'a> {
+    out: Vec<u8>,
+    /// Index of each string written so far.
+    strings: std::collections::HashMap<&'a str, usize>,
+}
+
+impl<'a> Encoder<'a> {
+    fn int(&mut self, mut value: usize) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    /// Writes the index of `value` plus one if it was written before, or else
+    /// 0 and the string.
+    fn string(&mut self, value: &'a str) {
+        if let Some(&index) = self.strings.get(value) {
+            self.int(index + 1);
+            return;
+        }
+        self.strings.insert(value, self.strings.len());
+        self.int(0);
+        self.int(value.len());
+        self.out.extend_from_slice(value.as_bytes());
+    }
+
+    fn path(&mut self, path: &'a DiffPath) {
+        self.int(path.segments().len());
+        for segment in path.segments() {
+            match segment {
+                Segment::Field(name) => {
+                    self.out.push(0);
+                    self.string(name);
+                }
+                Segment::Index(index) => {
+                    self.out.push(1);
+                    self.int(*index);
+                }
+                Segment::Key(key) => {
+                    self.out.push(2);
+                    self.string(key);
+                }
+                Segment::Variant(name) => {
+                    self.out.push(3);
+                    self.string(name);
+                }
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    strings: Vec<&'a str>,
+    /// Bytes the strings decoded from now on may take.
+    remaining: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let (&byte, rest) = self
+            .bytes
+            .split_first()
+            .ok_or_else(|| invalid("unexpected end"))?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn int(&mut self) -> io::Result<usize> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            value |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .ok_or_else(|| invalid("integer overflow"))?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("integer overflow"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let index = self.int()?;
+        let string = if index > 0 {
+            *self
+                .strings
+                .get(index - 1)
+                .ok_or_else(|| invalid("unknown string"))?
+        } else {
+            let len = self.int()?;
+            if len > self.bytes.len() {
+                return Err(invalid("unexpected end"));
+            }
+            let (string, rest) = self.bytes.split_at(len);
+            let string = std::str::from_utf8(string).map_err(|_| invalid("invalid UTF-8"))?;
+            self.bytes = rest;
+            self.strings.push(string);
+            string
+        };
+        // Back-references copy strings, so a small encoding could otherwise
+        // decode to any size.
+        self.remaining = self
+            .remaining
+            .checked_sub(string.len())
+            .ok_or_else(|| invalid("strings larger than the memory limit"))?;
+        Ok(string.to_string())
+    }
+
+    fn path(&mut self) -> io::Result<DiffPath> {
+        let count = self.int()?;
+        let mut segments = Vec::with_capacity(count.min(self.bytes.len()));
+        for _ in 0..count {
+            segments.push(match self.byte()? {
+                0 => Segment::Field(self.string()?),
+                1 => Segment::Index(self.int()?),
+                2 => Segment::Key(self.string()?),
+                3 => Segment::Variant(self.string()?),
+                _ => return Err(invalid("unknown path segment")),
+            });
+        }
+        Ok(segments.into_iter().collect())
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid compact diff: {reason}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn round_trips_entries_context_overflow_and_labels() {
+        let mut diff = Diff::with_memory_limit(200);
+        let mut handle = DiffPath::new();
+        handle.push_field("handle");
+        crate::diff_debug(&1, &2, &handle, &mut diff);
+        let expected = BTreeMap::from([("a\u{e9}", vec![1, 2]), ("b", vec![3])]);
+        let actual = BTreeMap::from([("a\u{e9}", vec![1, 5, 6]), ("c", vec![3])]);
+        expected.diff(&actual, &mut DiffPath::new(), &mut diff);
+        diff.push_context(DiffPath::new(), "ctx".into());
+        (0..300)
+            .collect::<Vec<u32>>()
+            .diff(&(1..301).collect(), &mut DiffPath::new(), &mut diff);
+        assert!(diff.capped() > 0);
+
+        let decoded = Diff::from_compact_bytes(&diff.to_compact_bytes()).unwrap();
+        assert_eq!(decoded, diff);
+        assert_eq!(decoded.to_string(), diff.to_string());
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        let bytes = (vec![1], vec![2])
+            .compare(&(vec![3], vec![]))
+            .to_compact_bytes();
+
+        for len in 0..bytes.len() {
+            let err = Diff::from_compact_bytes(&bytes[..len]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(Diff::from_compact_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(Diff::from_compact_bytes(b"{}").is_err());
+    }
+
+    #[test]
+    fn rejects_strings_past_the_memory_limit() {
+        let value = "x".repeat(1000);
+        let mut bytes = MAGIC.to_vec();
+        // 10,000 entries all referring to the first value.
+        bytes.extend([0x90, 0x4e, 1, 0, 0, 0xe8, 0x07]);
+        bytes.extend(value.as_bytes());
+        for _ in 1..10_000 {
+            bytes.extend([1, 0, 1]);
+        }
+        bytes.extend([0, 0, 0]);
+
+        let err = Diff::from_compact_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("memory limit"));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn rejects_compressed_bytes_inflating_past_the_memory_limit() {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(MAGIC).unwrap();
+        encoder.write_all(&vec![0; 4 << 20]).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let err = Diff::from_compressed_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("memory limit"));
+    }
+}
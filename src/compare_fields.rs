@@ -0,0 +1,111 @@
+//! All-field comparisons of structs which can't implement `Serialize`.
+
+use crate::{Diff, FieldDiff};
+
+/// Compares every field of a struct with `PartialEq`, for
+/// `compare_structs!(a, b)` without the `serde` feature.
+///
+/// Derive it with `#[derive(CompareFields)]` and the `derive` feature. Unlike
+/// [`Diffable`](crate::Diffable), fields only need `PartialEq` and `Debug`,
+/// and differing fields are reported as a whole.
+///
+/// ```edition2024
+/// # #[cfg(all(feature = "derive", not(feature = "serde")))]
+/// # {
+/// use cmp::{CompareFields, compare_structs};
+///
+/// #[derive(CompareFields)]
+/// struct Job { id: u32, command: String, #[cmp(skip)] handle: Option<std::fs::File> }
+///
+/// let expected = Job { id: 1, command: "make".into(), handle: None };
+/// let actual = Job { id: 1, command: "make".into(), handle: None };
+///
+/// assert!(expected.compare_all(&actual).is_empty());
+/// compare_structs!(expected, actual);
+/// # }
+/// ```
+pub trait CompareFields {
+    /// Returns the differences between each field of `self` and of `other`.
+    fn compare_all(&self, other: &Self) -> Vec<FieldDiff>;
+}
+
+/// Compares all fields of two values for `compare_structs!(a, b)` without
+/// the `serde` feature.
+pub fn compare_all<T: CompareFields + ?Sized>(expected: &T, actual: &T) -> Diff {
+    let mut diff = Diff::new();
+    for entry in expected.compare_all(actual) {
+        diff.push(entry);
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(cmp_derive::CompareFields)]
+    #[cmp(ignore = "updated_at")]
+    struct Task<T> {
+        id: u32,
+        #[cmp(rename = "taskName")]
+        name: String,
+        payload: T,
+        updated_at: u64,
+        #[cmp(skip)]
+        callback: Option<fn()>,
+        marker: PhantomData<T>,
+    }
+
+    fn task(name: &str, payload: Vec<u8>, updated_at: u64) -> Task<Vec<u8>> {
+        Task {
+            id: 1,
+            name: name.into(),
+            payload,
+            updated_at,
+            callback: None,
+            marker: PhantomData,
+        }
+    }
+
+    #[test]
+    fn compares_every_compared_field() {
+        let diff = compare_all(&task("a", vec![1], 1), &task("b", vec![1, 2], 2));
+
+        assert_eq!(
+            diff.to_string(),
+            "taskName: \"a\" != \"b\"\npayload: [\n    1,\n] != [\n    1,\n    2,\n]\n"
+        );
+        assert!(compare_all(&task("a", vec![1], 1), &task("a", vec![1], 2)).is_empty());
+    }
+
+    #[test]
+    fn formats_only_fields_which_do_not_borrow() {
+        #[derive(cmp_derive::CompareFields)]
+        struct Login<'a> {
+            user: &'a str,
+            retries: u32,
+        }
+
+        let user = String::from("ada");
+        let formatters = crate::ValueFormatters::new().with(|retries: &u32| format!("{retries}x"));
+        let diff = crate::with_value_formatters(formatters, || {
+            compare_all(
+                &Login {
+                    user: &user,
+                    retries: 1,
+                },
+                &Login {
+                    user: "bob",
+                    retries: 2,
+                },
+            )
+        });
+        assert_eq!(
+            diff.to_string(),
+            "user: \"ada\" != \"bob\"\nretries: 1x != 2x\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Soft assertions: comparisons recorded by a [`Comparer`] without
+//! panicking, which panics once with every failure when it finishes, so a
+//! table-driven test reports all mismatched rows rather than the first.
+
+use std::fmt::Write;
+use std::panic::Location;
+use std::thread;
+
+use crate::{Diffable, StructDiff};
+
+/// A session of comparisons whose failures are collected and reported
+/// together by [`finish`](Comparer::finish).
+///
+/// Record struct comparisons of named fields with
+/// [`check`](Comparer::check) and
+/// [`try_compare_structs!`](crate::try_compare_structs), and whole
+/// [`Diffable`] values with [`compare`](Comparer::compare). Each failure is
+/// reported with the location of its call.
+///
+/// A comparer dropped with failures, because `finish` wasn't called, panics
+/// like `finish`, unless the thread is already panicking.
+///
+/// ```edition2024,should_panic
+/// use cmp::{Comparer, try_compare_structs};
+///
+/// #[derive(Debug)]
+/// struct Row { input: &'static str, output: i32 }
+///
+/// let expected = [Row { input: "1", output: 1 }, Row { input: "2", output: 2 }];
+/// let actual = [Row { input: "1", output: 0 }, Row { input: "2", output: 3 }];
+///
+/// let mut comparer = Comparer::new();
+/// for (expected, actual) in expected.iter().zip(&actual) {
+///     comparer.check(try_compare_structs!(expected, actual, input, output));
+/// }
+/// // Panics with both mismatched rows.
+/// comparer.finish();
+/// ```
+#[derive(Debug, Default)]
+pub struct Comparer {
+    comparisons: usize,
+    failures: Vec<(&'static Location<'static>, StructDiff)>,
+}
+
+impl Comparer {
+    /// Creates a comparer without any comparisons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the outcome of a comparison, such as that of
+    /// [`try_compare_structs!`](crate::try_compare_structs), returning
+    /// whether it passed.
+    #[track_caller]
+    pub fn check(&mut self, result: Result<(), StructDiff>) -> bool {
+        self.comparisons += 1;
+        match result {
+            Ok(()) => true,
+            Err(failure) => {
+                self.failures.push((Location::caller(), failure));
+                false
+            }
+        }
+    }
+
+    /// Compares two values with [`Diffable`], recording the differences if
+    /// there are any, and returns whether they were equal.
+    #[track_caller]
+    pub fn compare<T: Diffable + ?Sized>(&mut self, expected: &T, actual: &T) -> bool {
+        let diff = expected.compare(actual);
+        crate::metrics::record(&diff);
+        self.check(StructDiff::check(diff, "expected", "actual"))
+    }
+
+    /// Returns the failed comparisons so far, in the order they were
+    /// recorded.
+    pub fn failures(&self) -> impl Iterator<Item = &StructDiff> {
+        self.failures.iter().map(|(_, failure)| failure)
+    }
+
+    /// Reports every failed comparison and panics with all of their
+    /// differences, or returns if all comparisons passed.
+    #[track_caller]
+    pub fn finish(mut self) {
+        self.fail();
+    }
+
+    #[track_caller]
+    fn fail(&mut self) {
+        if self.failures.is_empty() {
+            return;
+        }
+        let failures = std::mem::take(&mut self.failures);
+        let mut message =
+            crate::messages::messages().comparisons_failed(failures.len(), self.comparisons);
+        message.push('\n');
+        for (location, failure) in &failures {
+            crate::report(failure.diff());
+            let _ = write!(message, "\n{location}: {failure}");
+        }
+        panic!("{message}");
+    }
+}
+
+impl Drop for Comparer {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Row {
+        id: u32,
+        total: i64,
+    }
+
+    fn panic_message(run: impl FnOnce()) -> String {
+        let payload = panic::catch_unwind(AssertUnwindSafe(run)).unwrap_err();
+        payload.downcast_ref::<String>().unwrap().clone()
+    }
+
+    #[test]
+    fn panics_once_with_every_failure() {
+        let mut comparer = Comparer::new();
+        let (a, b) = (Row { id: 1, total: 5 }, Row { id: 1, total: 6 });
+        let first = line!() + 1;
+        assert!(!comparer.check(crate::try_compare_structs!(a, b, id, total)));
+        assert!(comparer.check(crate::try_compare_structs!(a, b, id)));
+        assert!(!comparer.compare(&vec![1, 2], &vec![1, 3]));
+        assert_eq!(comparer.failures().count(), 2);
+
+        let message = panic_message(|| comparer.finish());
+        let (header, failures) = message.split_once("\n\n").unwrap();
+        assert_eq!(header, "2 of 3 comparisons failed");
+        let failures: Vec<_> = failures.split("\n\n").collect();
+        assert!(failures[0].starts_with(&format!("{}:{first}:", file!())));
+        assert!(failures[0].ends_with(": `a` and `b` differ in 1 field\ntotal: 5 != 6"));
+        assert!(failures[1].starts_with(&format!("{}:{}:", file!(), first + 2)));
+        assert!(
+            failures[1].ends_with(": `expected` and `actual` differ in 1 field\n[1]: 2 != 3\n")
+        );
+    }
+
+    #[test]
+    fn passes_without_failures_and_panics_when_dropped_with_some() {
+        let mut comparer = Comparer::new();
+        comparer.compare(&1, &1);
+        comparer.finish();
+
+        let message = panic_message(|| {
+            let mut comparer = Comparer::new();
+            comparer.compare("a", "b");
+        });
+        assert!(message.starts_with("1 of 1 comparison failed\n"));
+    }
+}
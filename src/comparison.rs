@@ -0,0 +1,569 @@
+//! The [`Comparison`] builder, the per-path [`PathRule`]s it applies and
+//! the [`ComparePlugin`] hooks it runs.
+
+use std::any;
+use std::env;
+
+use crate::{Diff, DiffPath, Diffable, FieldDiff};
+
+/// Environment variable which makes [`Comparison::run`] explain the outcome
+/// of each difference it finds.
+const EXPLAIN_VAR: &str = "CMP_EXPLAIN";
+
+/// Returns whether `CMP_EXPLAIN` is set to anything other than `0` or an
+/// empty string.
+fn explain_enabled() -> bool {
+    env::var_os(EXPLAIN_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Hooks run by a [`Comparison`] on the differences it finds, so redaction,
+/// normalization or custom reporting can be composed without upstream
+/// support. Every method does nothing by default.
+///
+/// Plugins run in the order they were registered: each difference must be
+/// kept by every plugin's [`before_field`](ComparePlugin::before_field), then
+/// is rewritten by every [`transform_value`](ComparePlugin::transform_value),
+/// and finally passed to every [`on_diff`](ComparePlugin::on_diff).
+pub trait ComparePlugin {
+    /// Called with the path of every difference before it is recorded.
+    /// Returning `false` drops it.
+    fn before_field(&self, path: &DiffPath) -> bool {
+        let _ = path;
+        true
+    }
+
+    /// Rewrites the rendered value of one side at `path`. A
+    /// [`FieldDiff::Changed`] whose sides become equal is dropped, so
+    /// normalizing plugins can hide irrelevant differences.
+    fn transform_value(&self, path: &DiffPath, value: &mut String) {
+        let _ = (path, value);
+    }
+
+    /// Called with every difference which is recorded.
+    fn on_diff(&self, diff: &FieldDiff) {
+        let _ = diff;
+    }
+}
+
+/// How a [`Comparison`] treats the differences at a path, set with
+/// [`Comparison::override_path`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathRule {
+    /// Drop every difference.
+    Ignore,
+    /// Drop differences between floats at most this far apart.
+    Epsilon(f64),
+    /// Keep every difference, even if a default epsilon is set.
+    Exact,
+}
+
+/// Returns [`PathRule::Ignore`].
+pub fn ignore() -> PathRule {
+    PathRule::Ignore
+}
+
+/// Returns [`PathRule::Epsilon`] with the given absolute tolerance.
+pub fn epsilon(epsilon: f64) -> PathRule {
+    PathRule::Epsilon(epsilon)
+}
+
+/// Returns [`PathRule::Exact`].
+pub fn exact() -> PathRule {
+    PathRule::Exact
+}
+
+impl PathRule {
+    /// Returns whether `entry` is still a difference under this rule.
+    fn keeps(self, entry: &FieldDiff) -> bool {
+        match (self, entry) {
+            (PathRule::Ignore, _) => false,
+            (
+                PathRule::Epsilon(epsilon),
+                FieldDiff::Changed {
+                    expected, actual, ..
+                },
+            ) => match (parse_float(expected), parse_float(actual)) {
+                (Some(expected), Some(actual)) => (expected - actual).abs() > epsilon,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// Parses a `Debug`-rendered float, but not an integer, so tolerances only
+/// apply to float fields like with [`Diffable::diff_with_epsilon`].
+fn parse_float(value: &str) -> Option<f64> {
+    if value.parse::<i128>().is_ok() {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Builder comparing two [`Diffable`] values with additional options.
+///
+/// ```edition2024
+/// use cmp::{ComparePlugin, Comparison, DiffPath};
+///
+/// struct Redact;
+///
+/// impl ComparePlugin for Redact {
+///     fn transform_value(&self, _: &DiffPath, value: &mut String) {
+///         if value.contains("secret") {
+///             *value = "<redacted>".to_string();
+///         }
+///     }
+/// }
+///
+/// let diff = Comparison::new("secret-1", "secret-2").plugin(Redact).run();
+///
+/// assert!(diff.is_empty());
+/// ```
+///
+/// Rules for paths matching a [glob](DiffPath::matches_glob) pattern
+/// override the [default epsilon](Comparison::epsilon), for payloads whose
+/// parts need different tolerances. A rule also applies below the paths it
+/// matches, and when several match, the one added last wins:
+///
+/// ```edition2024
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use cmp::{Comparison, epsilon, ignore};
+///
+/// #[derive(Debug, cmp::Diffable)]
+/// struct Item { id: u64, price: f64, weight: f64 }
+///
+/// let expected = vec![Item { id: 1, price: 9.99, weight: 1.0 }];
+/// let actual = vec![Item { id: 7, price: 10.0, weight: 1.2 }];
+///
+/// let diff = Comparison::new(&expected, &actual)
+///     .override_path("[*].price", epsilon(0.01))
+///     .override_path("[*].id", ignore())
+///     .run();
+///
+/// assert_eq!(diff.to_string(), "[0].weight: 1.0 != 1.2\n");
+/// # }
+/// ```
+///
+/// Run with `CMP_EXPLAIN=1` to print which rule or plugin decided the
+/// outcome of each difference found, and which overrides it shadowed, e.g.
+/// `items[0].price: passed within epsilon 0.01 of override "items[*].price"
+/// (shadowing "items[*]")`.
+pub struct Comparison<'a, T: ?Sized> {
+    expected: &'a T,
+    actual: &'a T,
+    epsilon: Option<f64>,
+    overrides: Vec<(String, PathRule)>,
+    plugins: Vec<(&'static str, Box<dyn ComparePlugin + 'a>)>,
+}
+
+impl<'a, T: Diffable + ?Sized> Comparison<'a, T> {
+    /// Creates a comparison of `expected` with `actual`.
+    pub fn new(expected: &'a T, actual: &'a T) -> Self {
+        Self {
+            expected,
+            actual,
+            epsilon: None,
+            overrides: Vec::new(),
+            plugins: Vec::new(),
+        }
+    }
+
+    /// Compares floats with an absolute tolerance at paths without an
+    /// override.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = Some(epsilon);
+        self
+    }
+
+    /// Applies `rule` to the differences at paths matching the glob
+    /// `pattern`, and below them, overriding rules added before it and the
+    /// default epsilon.
+    pub fn override_path(mut self, pattern: &str, rule: PathRule) -> Self {
+        self.overrides.push((pattern.to_string(), rule));
+        self
+    }
+
+    /// Registers a plugin, run after those registered before it.
+    pub fn plugin<P: ComparePlugin + 'a>(mut self, plugin: P) -> Self {
+        self.plugins.push((any::type_name::<P>(), Box::new(plugin)));
+        self
+    }
+
+    /// Compares the values, returning the differences kept by the path
+    /// rules and the plugins.
+    ///
+    /// The outcome is recorded with the
+    /// [metrics recorder](crate::set_metrics_recorder), if one is set, and
+    /// explained on standard error with `CMP_EXPLAIN=1`.
+    pub fn run(&self) -> Diff {
+        let explain = explain_enabled();
+        let (out, explanations) = self.run_explained(explain);
+        if explain && !explanations.is_empty() {
+            crate::output::emit(explanations.join("\n"));
+        }
+        out
+    }
+
+    /// Compares the values, returning the differences and, if `explain`, a
+    /// line for each difference found saying what decided its outcome.
+    fn run_explained(&self, explain: bool) -> (Diff, Vec<String>) {
+        let mut found = Diff::new();
+        self.expected
+            .diff(self.actual, &mut DiffPath::new(), &mut found);
+
+        let mut out = Diff::new();
+        let mut explanations = Vec::new();
+        let mut note = |path: &DiffPath, outcome: String| {
+            if explain {
+                explanations.push(format!("{path}: {outcome}"));
+            }
+        };
+        for mut entry in found.into_entries() {
+            let (rule, matched) = self.rule(entry.path());
+            let kept = rule.keeps(&entry);
+            if !kept {
+                note(entry.path(), self.describe(rule, &matched, kept));
+                continue;
+            }
+            if let Some((name, _)) = self
+                .plugins
+                .iter()
+                .find(|(_, plugin)| !plugin.before_field(entry.path()))
+            {
+                note(entry.path(), format!("dropped by plugin {name}"));
+                continue;
+            }
+            if !self.transform(&mut entry) {
+                note(
+                    entry.path(),
+                    "passed after plugins made the values equal".to_string(),
+                );
+                continue;
+            }
+            for (_, plugin) in &self.plugins {
+                plugin.on_diff(&entry);
+            }
+            note(entry.path(), self.describe(rule, &matched, kept));
+            out.push(entry);
+        }
+        crate::metrics::record(&out);
+        (out, explanations)
+    }
+
+    /// Returns the rule of the last override matching `path` or one of its
+    /// ancestors, or the default, with the patterns of every matching
+    /// override, last first.
+    fn rule(&self, path: &DiffPath) -> (PathRule, Vec<&str>) {
+        let segments = path.segments();
+        let matched: Vec<(&str, PathRule)> = self
+            .overrides
+            .iter()
+            .rev()
+            .filter(|(pattern, _)| {
+                (1..=segments.len()).any(|len| {
+                    segments[..len]
+                        .iter()
+                        .cloned()
+                        .collect::<DiffPath>()
+                        .matches_glob(pattern)
+                })
+            })
+            .map(|(pattern, rule)| (pattern.as_str(), *rule))
+            .collect();
+        let rule = matched
+            .first()
+            .map(|&(_, rule)| rule)
+            .unwrap_or(self.epsilon.map_or(PathRule::Exact, PathRule::Epsilon));
+        (
+            rule,
+            matched.into_iter().map(|(pattern, _)| pattern).collect(),
+        )
+    }
+
+    /// Explains how `rule`, from the first of the `matched` overrides or the
+    /// default, decided whether a difference was `kept`.
+    fn describe(&self, rule: PathRule, matched: &[&str], kept: bool) -> String {
+        let source = match matched.first() {
+            Some(pattern) => format!("override {pattern:?}"),
+            None => "the default epsilon".to_string(),
+        };
+        let outcome = if kept { "failed" } else { "passed" };
+        let mut description = match rule {
+            PathRule::Ignore => format!("ignored by {source}"),
+            PathRule::Epsilon(epsilon) if kept => format!(
+                "{outcome} outside epsilon {} of {source}",
+                crate::float::tolerance(epsilon)
+            ),
+            PathRule::Epsilon(epsilon) => format!(
+                "{outcome} within epsilon {} of {source}",
+                crate::float::tolerance(epsilon)
+            ),
+            PathRule::Exact if matched.is_empty() => format!("{outcome}, compared exactly"),
+            PathRule::Exact => format!("{outcome}, compared exactly by {source}"),
+        };
+        let mut shadowed: Vec<String> = matched
+            .iter()
+            .skip(1)
+            .map(|pattern| format!("{pattern:?}"))
+            .collect();
+        if let (Some(epsilon), false) = (self.epsilon, matched.is_empty()) {
+            shadowed.push(format!(
+                "the default epsilon {}",
+                crate::float::tolerance(epsilon)
+            ));
+        }
+        if !shadowed.is_empty() {
+            description += &format!(" (shadowing {})", shadowed.join(", "));
+        }
+        description
+    }
+
+    /// Runs every plugin's `transform_value` on the values of `entry`,
+    /// returning whether it still differs.
+    fn transform(&self, entry: &mut FieldDiff) -> bool {
+        let (path, values) = match entry {
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => (&*path, vec![expected, actual]),
+            FieldDiff::MissingFromExpected { path, actual } => (&*path, vec![actual]),
+            FieldDiff::MissingFromActual { path, expected } => (&*path, vec![expected]),
+        };
+        for value in values {
+            for (_, plugin) in &self.plugins {
+                plugin.transform_value(path, value);
+            }
+        }
+        match entry {
+            FieldDiff::Changed {
+                expected, actual, ..
+            } => expected != actual,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Diffable + serde::Serialize + ?Sized> Comparison<'_, T> {
+    /// Returns the paths which [`run`](Comparison::run) would compare, with
+    /// the rule each would use, without comparing the values.
+    ///
+    /// The paths are those of the serialized values, useful when authoring
+    /// overrides for large payloads:
+    ///
+    /// ```edition2024
+    /// use cmp::{Comparison, ignore};
+    ///
+    /// let expected = vec![("id", 1.5)];
+    /// let actual = vec![("id", 2.5)];
+    ///
+    /// let plan = Comparison::new(&expected, &actual)
+    ///     .override_path("[*][1]", ignore())
+    ///     .plan();
+    ///
+    /// assert_eq!(plan.to_string(), "[0][0]: exact\n[0][1]: ignore (override \"[*][1]\")\n");
+    /// ```
+    pub fn plan(&self) -> crate::Plan {
+        let paths = crate::plan::leaf_paths(
+            &crate::serialize::expect_serialized(self.expected, "expected").value,
+            &crate::serialize::expect_serialized(self.actual, "actual").value,
+        );
+        crate::Plan::new(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let (rule, matched) = self.rule(&path);
+                    crate::PlannedPath {
+                        rule,
+                        pattern: matched.first().map(|pattern| pattern.to_string()),
+                        path,
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[derive(cmp_derive::Diffable)]
+    struct User {
+        name: String,
+        email: String,
+        token: String,
+    }
+
+    fn users() -> (User, User) {
+        let expected = User {
+            name: "Ada".into(),
+            email: "ADA@example.com".into(),
+            token: "t-1".into(),
+        };
+        let actual = User {
+            name: "Grace".into(),
+            email: "ada@example.com".into(),
+            token: "t-2".into(),
+        };
+        (expected, actual)
+    }
+
+    struct Lowercase;
+
+    impl ComparePlugin for Lowercase {
+        fn transform_value(&self, _: &DiffPath, value: &mut String) {
+            *value = value.to_lowercase();
+        }
+    }
+
+    struct SkipTokens;
+
+    impl ComparePlugin for SkipTokens {
+        fn before_field(&self, path: &DiffPath) -> bool {
+            !path.matches_glob("token")
+        }
+    }
+
+    struct Collect<'a>(&'a RefCell<Vec<String>>);
+
+    impl ComparePlugin for Collect<'_> {
+        fn on_diff(&self, diff: &FieldDiff) {
+            self.0.borrow_mut().push(diff.to_string());
+        }
+    }
+
+    #[test]
+    fn runs_plugins_in_order() {
+        let (expected, actual) = users();
+        let seen = RefCell::new(Vec::new());
+
+        let diff = Comparison::new(&expected, &actual)
+            .plugin(SkipTokens)
+            .plugin(Lowercase)
+            .plugin(Collect(&seen))
+            .run();
+
+        assert_eq!(diff.to_string(), "name: \"ada\" != \"grace\"\n");
+        assert_eq!(*seen.borrow(), ["name: \"ada\" != \"grace\""]);
+    }
+
+    #[test]
+    fn without_plugins_reports_every_difference() {
+        let (expected, actual) = users();
+
+        assert_eq!(Comparison::new(&expected, &actual).run().len(), 3);
+    }
+
+    #[derive(Debug, cmp_derive::Diffable)]
+    struct Line {
+        sku: String,
+        price: f64,
+        quantity: u32,
+    }
+
+    #[derive(cmp_derive::Diffable)]
+    struct Order {
+        id: u64,
+        total: f64,
+        items: Vec<Line>,
+    }
+
+    fn orders() -> (Order, Order) {
+        let expected = Order {
+            id: 1,
+            total: 10.0,
+            items: vec![Line {
+                sku: "a".into(),
+                price: 5.0,
+                quantity: 2,
+            }],
+        };
+        let actual = Order {
+            id: 2,
+            total: 10.5,
+            items: vec![Line {
+                sku: "b".into(),
+                price: 5.004,
+                quantity: 3,
+            }],
+        };
+        (expected, actual)
+    }
+
+    #[test]
+    fn path_overrides_compose_with_the_default_epsilon() {
+        let (expected, actual) = orders();
+
+        let diff = Comparison::new(&expected, &actual)
+            .epsilon(1.0)
+            .override_path("items[*]", ignore())
+            .override_path("items[*].price", epsilon(0.01))
+            .override_path("total", exact())
+            .override_path("id", ignore())
+            .run();
+
+        assert_eq!(diff.to_string(), "total: 10.0 != 10.5\n");
+    }
+
+    #[test]
+    fn later_overrides_win_and_tolerances_skip_integers() {
+        let (expected, actual) = orders();
+
+        let diff = Comparison::new(&expected, &actual)
+            .override_path("**", epsilon(5.0))
+            .override_path("items[*].sku", ignore())
+            .run();
+
+        assert_eq!(diff.to_string(), "id: 1 != 2\nitems[0].quantity: 2 != 3\n");
+    }
+
+    #[test]
+    fn explains_which_rule_decided_each_difference() {
+        let (expected, actual) = orders();
+
+        let (_, explanations) = Comparison::new(&expected, &actual)
+            .epsilon(1.0)
+            .override_path("items[*]", ignore())
+            .override_path("items[*].price", epsilon(0.01))
+            .override_path("id", exact())
+            .plugin(SkipTokens)
+            .run_explained(true);
+
+        assert_eq!(
+            explanations,
+            [
+                "id: failed, compared exactly by override \"id\" (shadowing the default epsilon 1)",
+                "total: passed within epsilon 1 of the default epsilon",
+                "items[0].sku: ignored by override \"items[*]\" (shadowing the default epsilon 1)",
+                "items[0].price: passed within epsilon 0.01 of override \"items[*].price\" \
+                 (shadowing \"items[*]\", the default epsilon 1)",
+                "items[0].quantity: ignored by override \"items[*]\" (shadowing the default epsilon 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn explains_plugin_decisions() {
+        let (expected, actual) = users();
+
+        let (diff, explanations) = Comparison::new(&expected, &actual)
+            .plugin(SkipTokens)
+            .plugin(Lowercase)
+            .run_explained(true);
+
+        assert_eq!(diff.len(), 1);
+        assert_eq!(
+            explanations,
+            [
+                "name: failed, compared exactly",
+                "email: passed after plugins made the values equal",
+                "token: dropped by plugin cmp::comparison::tests::SkipTokens",
+            ]
+        );
+    }
+}
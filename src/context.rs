@@ -0,0 +1,86 @@
+//! Context lines around differing sequence elements.
+//!
+//! Like the context lines of a text diff, equal elements next to a
+//! mismatch are recorded with [`Diff::push_context`](crate::Diff::push_context)
+//! so the reader can see where in the sequence the problem is without the
+//! whole collection being printed.
+
+use std::env;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable which sets the number of context elements when
+/// [`set_sequence_context`] hasn't been called.
+const CONTEXT_VAR: &str = "CMP_CONTEXT";
+
+/// Context set with [`set_sequence_context`], or `usize::MAX` if unset.
+static CONTEXT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Sets the number of equal elements shown before and after each differing
+/// element of a sequence, overriding `CMP_CONTEXT`.
+///
+/// ```edition2024
+/// use cmp::Diffable;
+///
+/// cmp::set_sequence_context(1);
+///
+/// let diff = vec![1, 2, 3, 4, 5].compare(&vec![1, 2, 0, 4, 5]);
+/// assert_eq!(diff.to_string(), "[1]: 2 (equal)\n[2]: 3 != 0\n[3]: 4 (equal)\n");
+/// assert_eq!(diff.len(), 1);
+/// # cmp::set_sequence_context(0);
+/// ```
+pub fn set_sequence_context(elements: usize) {
+    CONTEXT.store(elements, Ordering::Relaxed);
+}
+
+/// Returns the number of context elements to record around each differing
+/// sequence element.
+pub(crate) fn sequence_context() -> usize {
+    match CONTEXT.load(Ordering::Relaxed) {
+        usize::MAX => from_env(),
+        elements => elements,
+    }
+}
+
+fn from_env() -> usize {
+    static FROM_ENV: OnceLock<usize> = OnceLock::new();
+    *FROM_ENV.get_or_init(|| {
+        env::var(CONTEXT_VAR)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0)
+    })
+}
+
+/// Returns which elements to show as context, given which differ.
+pub(crate) fn context_mask(differs: &[bool], elements: usize) -> Vec<bool> {
+    let mut shown = vec![false; differs.len()];
+    for (index, _) in differs.iter().enumerate().filter(|(_, differs)| **differs) {
+        let start = index.saturating_sub(elements);
+        let end = (index + elements + 1).min(differs.len());
+        for (at, show) in shown.iter_mut().enumerate().take(end).skip(start) {
+            *show = !differs[at];
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_equal_neighbours_of_differing_elements() {
+        let differs = [false, false, true, false, false, false, true, false];
+
+        assert_eq!(
+            context_mask(&differs, 1),
+            [false, true, false, true, false, true, false, true]
+        );
+        assert_eq!(context_mask(&differs, 0), [false; 8]);
+        assert_eq!(
+            context_mask(&differs, 5),
+            [true, true, false, true, true, true, false, true]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! [`assert_comparison_covers!`](crate::assert_comparison_covers): checking
+//! that a [`Diffable`](crate::Diffable) comparison detects changes to each
+//! field, and
+//! [`assert_compares_all_fields!`](crate::assert_compares_all_fields):
+//! checking that a list of fields covers every field of a struct.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Types which can be changed to a different value, to check that a
+/// comparison notices.
+///
+/// It is implemented for primitives, `String`, `Option`, `Vec` and maps.
+/// Implement it for other field types to use them with
+/// [`assert_comparison_covers!`](crate::assert_comparison_covers).
+pub trait Mutate {
+    /// Changes `self` to a value which is not equal to the current one.
+    fn mutate(&mut self);
+}
+
+macro_rules! mutate_integers {
+    ($($ty:ty),*) => {
+        $(
+            impl Mutate for $ty {
+                fn mutate(&mut self) {
+                    *self = self.wrapping_add(1);
+                }
+            }
+        )*
+    };
+}
+
+mutate_integers!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+macro_rules! mutate_floats {
+    ($($ty:ty),*) => {
+        $(
+            impl Mutate for $ty {
+                fn mutate(&mut self) {
+                    // Far enough to exceed any sensible epsilon.
+                    *self = if self.abs() < 1.0 { *self + 1.0 } else { *self * 2.0 };
+                }
+            }
+        )*
+    };
+}
+
+mutate_floats!(f32, f64);
+
+impl Mutate for bool {
+    fn mutate(&mut self) {
+        *self = !*self;
+    }
+}
+
+impl Mutate for char {
+    fn mutate(&mut self) {
+        *self = if *self == 'a' { 'b' } else { 'a' };
+    }
+}
+
+impl Mutate for String {
+    fn mutate(&mut self) {
+        self.push('~');
+    }
+}
+
+impl<T: Default> Mutate for Option<T> {
+    fn mutate(&mut self) {
+        *self = match self {
+            Some(_) => None,
+            None => Some(T::default()),
+        };
+    }
+}
+
+impl<T: Default> Mutate for Vec<T> {
+    fn mutate(&mut self) {
+        self.push(T::default());
+    }
+}
+
+impl<K: Mutate + Default + Eq + Hash, V: Default> Mutate for HashMap<K, V> {
+    fn mutate(&mut self) {
+        let mut key = K::default();
+        while self.contains_key(&key) {
+            key.mutate();
+        }
+        self.insert(key, V::default());
+    }
+}
+
+impl<K: Mutate + Default + Ord, V: Default> Mutate for BTreeMap<K, V> {
+    fn mutate(&mut self) {
+        let mut key = K::default();
+        while self.contains_key(&key) {
+            key.mutate();
+        }
+        self.insert(key, V::default());
+    }
+}
+
+impl<T: Mutate + ?Sized> Mutate for Box<T> {
+    fn mutate(&mut self) {
+        (**self).mutate();
+    }
+}
+
+/// Macro which checks that the [`Diffable`](crate::Diffable) comparison of a
+/// `Default + Clone` type detects a change to each of the listed fields,
+/// guarding against `ignore` lists or custom comparators which silently mask
+/// regressions.
+///
+/// For each field, a default value is cloned, the field of the clone is
+/// changed with [`Mutate`], and the two values are compared.
+///
+/// ```edition2024
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use cmp::{Diffable, assert_comparison_covers};
+///
+/// #[derive(Clone, Default, Diffable)]
+/// #[cmp(ignore = "updated_at")]
+/// struct Order { id: u32, total: f64, updated_at: u64 }
+///
+/// assert_comparison_covers!(Order, [id, total]);
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics listing the fields whose changes the comparison doesn't detect.
+#[macro_export]
+macro_rules! assert_comparison_covers {
+    ($ty:ty, [$($field:ident),+ $(,)?]) => {{
+        let base: $ty = ::core::default::Default::default();
+        let mut uncovered: ::std::vec::Vec<&str> = ::std::vec::Vec::new();
+        $(
+            let mut mutated = ::core::clone::Clone::clone(&base);
+            $crate::Mutate::mutate(&mut mutated.$field);
+            let mut diff = $crate::Diff::new();
+            $crate::Diffable::diff(&base, &mutated, &mut $crate::DiffPath::new(), &mut diff);
+            if diff.is_empty() {
+                uncovered.push(stringify!($field));
+            }
+        )+
+        if !uncovered.is_empty() {
+            panic!(
+                "comparison of `{}` doesn't detect changes to: {}",
+                stringify!($ty),
+                uncovered.join(", ")
+            );
+        }
+    }};
+}
+
+/// Macro which fails to compile unless the listed fields, compared and
+/// ignored, are exactly the fields of a struct, so adding a field forces the
+/// tests to decide whether to compare or ignore it.
+///
+/// Use it next to `compare_structs!` calls listing fields, or
+/// [`assert_comparison_covers!`](crate::assert_comparison_covers) for
+/// derived comparisons. It does nothing at run time.
+///
+/// ```edition2024
+/// use cmp::{assert_compares_all_fields, compare_structs};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Order { id: u32, total: f64, updated_at: u64 }
+///
+/// let order = Order { id: 1, total: 9.5, updated_at: 10 };
+///
+/// assert_compares_all_fields!(Order, [id, total], ignore: [updated_at]);
+/// compare_structs!(order, order, id, total);
+/// ```
+///
+/// A field which is neither compared nor ignored doesn't compile:
+///
+/// ```edition2024,compile_fail
+/// # use cmp::assert_compares_all_fields;
+/// struct Order { id: u32, total: f64, updated_at: u64 }
+///
+/// // error[E0063]: missing field `updated_at` in initializer of `Order`
+/// assert_compares_all_fields!(Order, [id, total]);
+/// ```
+#[macro_export]
+macro_rules! assert_compares_all_fields {
+    ($ty:path, [$($field:ident),* $(,)?] $(, ignore: [$($ignored:ident),* $(,)?])? $(,)?) => {{
+        // Struct expressions must initialize every field, and name the
+        // struct's fields in their errors.
+        #[allow(dead_code)]
+        fn assert_compares_all_fields(value: $ty) -> $ty {
+            $ty {
+                $($field: value.$field,)*
+                $($($ignored: value.$ignored,)*)?
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default, cmp_derive::Diffable)]
+    #[cmp(ignore = "revision")]
+    struct Config {
+        name: String,
+        #[cmp(epsilon = 0.5)]
+        ratio: f64,
+        tags: Vec<String>,
+        limits: BTreeMap<String, u32>,
+        retries: Option<u8>,
+        revision: u64,
+        #[cmp(skip)]
+        cache: Vec<u8>,
+    }
+
+    #[test]
+    fn mutations_change_the_value() {
+        let mut value = (0u8, -0.25f64, 'a', Some(1), u32::MAX);
+        value.0.mutate();
+        value.1.mutate();
+        value.2.mutate();
+        value.3.mutate();
+        value.4.mutate();
+
+        assert_eq!(value, (1, 0.75, 'b', None, 0));
+    }
+
+    #[test]
+    fn passes_when_every_field_is_compared() {
+        assert_comparison_covers!(Config, [name, ratio, tags, limits, retries]);
+    }
+
+    #[test]
+    #[should_panic(expected = "comparison of `Config` doesn't detect changes to: revision, cache")]
+    fn lists_ignored_and_skipped_fields() {
+        assert_comparison_covers!(Config, [name, revision, cache]);
+    }
+
+    #[test]
+    fn lists_of_every_field_compile() {
+        assert_compares_all_fields!(Config, [name, ratio, tags, limits, retries], ignore: [revision, cache]);
+    }
+}
@@ -0,0 +1,643 @@
+//! Structured results of a comparison.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use crate::lines::Line;
+use crate::{DiffPath, Segment};
+
+/// A single difference between the expected and actual values.
+///
+/// Values are stored as their pretty-printed `Debug` representation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum FieldDiff {
+    /// The value at `path` differs.
+    Changed {
+        path: DiffPath,
+        expected: String,
+        actual: String,
+    },
+    /// The value at `path` only exists on the actual side.
+    MissingFromExpected { path: DiffPath, actual: String },
+    /// The value at `path` only exists on the expected side.
+    MissingFromActual { path: DiffPath, expected: String },
+}
+
+impl FieldDiff {
+    /// Returns the path of the differing value.
+    pub fn path(&self) -> &DiffPath {
+        match self {
+            FieldDiff::Changed { path, .. }
+            | FieldDiff::MissingFromExpected { path, .. }
+            | FieldDiff::MissingFromActual { path, .. } => path,
+        }
+    }
+
+    /// Returns the difference with the expected and actual sides swapped.
+    pub fn inverted(&self) -> FieldDiff {
+        match self.clone() {
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => FieldDiff::Changed {
+                path,
+                expected: actual,
+                actual: expected,
+            },
+            FieldDiff::MissingFromExpected { path, actual } => FieldDiff::MissingFromActual {
+                path,
+                expected: actual,
+            },
+            FieldDiff::MissingFromActual { path, expected } => FieldDiff::MissingFromExpected {
+                path,
+                actual: expected,
+            },
+        }
+    }
+}
+
+/// Escape sequences written around each line of expected and actual values,
+/// empty when rendering plain text.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Palette {
+    pub(crate) expected: &'static str,
+    pub(crate) actual: &'static str,
+    pub(crate) reset: &'static str,
+}
+
+/// A value with each of its lines wrapped in `start` and `reset`, so colors
+/// survive terminals and CI logs which reset them at line ends.
+struct Painted<'a> {
+    value: &'a str,
+    start: &'static str,
+    reset: &'static str,
+}
+
+impl fmt::Display for Painted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start.is_empty() {
+            return f.write_str(self.value);
+        }
+        for (index, line) in self.value.split('\n').enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}{line}{}", self.start, self.reset)?;
+        }
+        Ok(())
+    }
+}
+
+impl Palette {
+    fn expected<'a>(&self, value: &'a str) -> Painted<'a> {
+        Painted {
+            value,
+            start: self.expected,
+            reset: self.reset,
+        }
+    }
+
+    fn actual<'a>(&self, value: &'a str) -> Painted<'a> {
+        Painted {
+            value,
+            start: self.actual,
+            reset: self.reset,
+        }
+    }
+}
+
+impl FieldDiff {
+    /// Writes the difference with its values in the colors of `palette`.
+    pub(crate) fn write(&self, f: &mut fmt::Formatter<'_>, palette: Palette) -> fmt::Result {
+        let messages = crate::messages::messages();
+        match self {
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => {
+                let note = crate::invisible::note(expected, actual);
+                if let Some(lines) = crate::lines::diff(expected, actual) {
+                    let all = crate::invisible::only_whitespace_differs(expected, actual);
+                    write!(f, "{path}: {}:", messages.lines_differ())?;
+                    for line in lines {
+                        match line {
+                            Line::Hunk(header) => write!(f, "\n{header}")?,
+                            Line::Equal(line) => write!(f, "\n {line}")?,
+                            Line::Removed(line) => {
+                                let line = crate::invisible::mark_line(line, all);
+                                write!(f, "\n{}", palette.expected(&format!("-{line}")))?
+                            }
+                            Line::Added(line) => {
+                                let line = crate::invisible::mark_line(line, all);
+                                write!(f, "\n{}", palette.actual(&format!("+{line}")))?
+                            }
+                        }
+                    }
+                    return match note {
+                        Some(note) => write!(f, "\n({note})"),
+                        None => Ok(()),
+                    };
+                }
+                let (expected, actual) = crate::invisible::mark(expected, actual);
+                write!(
+                    f,
+                    "{path}: {} {} {}",
+                    palette.expected(&expected),
+                    messages.not_equal(),
+                    palette.actual(&actual)
+                )?;
+                match note {
+                    Some(note) => write!(f, " ({note})"),
+                    None => Ok(()),
+                }
+            }
+            FieldDiff::MissingFromExpected { path, actual } => {
+                write!(
+                    f,
+                    "{path}: {}: {}",
+                    messages.missing_from_expected(),
+                    palette.actual(actual)
+                )
+            }
+            FieldDiff::MissingFromActual { path, expected } => {
+                write!(
+                    f,
+                    "{path}: {}: {}",
+                    messages.missing_from_actual(),
+                    palette.expected(expected)
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, Palette::default())
+    }
+}
+
+impl FieldDiff {
+    /// Approximate number of bytes the entry uses.
+    fn size(&self) -> usize {
+        let (path, values) = match self {
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => (path, expected.len() + actual.len()),
+            FieldDiff::MissingFromExpected {
+                path,
+                actual: value,
+            }
+            | FieldDiff::MissingFromActual {
+                path,
+                expected: value,
+            } => (path, value.len()),
+        };
+        let segments: usize = path
+            .segments()
+            .iter()
+            .map(|segment| {
+                size_of::<Segment>()
+                    + match segment {
+                        Segment::Field(name) | Segment::Key(name) | Segment::Variant(name) => {
+                            name.len()
+                        }
+                        Segment::Index(_) => 0,
+                    }
+            })
+            .sum();
+        size_of::<FieldDiff>() + segments + values
+    }
+}
+
+/// All differences found by a comparison, in the order they were found.
+///
+/// `Display` renders one line per entry, in the same format as the panic
+/// message of [`compare_structs!`](crate::compare_structs), with any
+/// [context](Diff::push_context) lines in between.
+///
+/// The entries are capped at a [memory limit](crate::set_diff_memory_limit),
+/// past which differences are only [counted](Diff::capped).
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diff {
+    entries: Vec<FieldDiff>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    context: Vec<Context>,
+    /// Allocated with the first entry, so empty diffs stay small.
+    #[cfg_attr(feature = "serde", serde(default, flatten))]
+    budget: Option<Box<Budget>>,
+}
+
+/// The memory accounting and labels of the entries of a [`Diff`].
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Budget {
+    /// Approximate number of bytes used by the entries.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bytes: usize,
+    /// The memory limit of the diff, or 0 for the process-wide one.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    limit: usize,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    overflow: Option<Overflow>,
+    /// Paths of the entries found by comparing `Debug` output.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    debug_compared: Vec<DiffPath>,
+}
+
+/// Differences counted but not recorded once a diff reached its limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Overflow {
+    /// The memory limit in bytes which was reached.
+    limit: usize,
+    count: usize,
+}
+
+impl PartialEq for Diff {
+    fn eq(&self, other: &Self) -> bool {
+        let overflow = |diff: &Diff| diff.budget.as_ref().and_then(|budget| budget.overflow);
+        self.entries == other.entries
+            && self.context == other.context
+            && self.debug_compared() == other.debug_compared()
+            && overflow(self) == overflow(other)
+    }
+}
+
+impl Eq for Diff {}
+
+/// An equal value shown between the differences for context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Context {
+    /// Number of entries recorded before this line.
+    before: usize,
+    path: DiffPath,
+    value: String,
+}
+
+impl Diff {
+    /// Creates an empty diff.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty diff whose entries may use about `bytes` bytes,
+    /// rather than the [process-wide limit](crate::set_diff_memory_limit),
+    /// before further differences are only counted.
+    ///
+    /// ```edition2024
+    /// use cmp::{Diff, DiffPath, Diffable};
+    ///
+    /// let mut diff = Diff::with_memory_limit(1);
+    /// vec![0u64; 100].diff(&vec![1; 100], &mut DiffPath::new(), &mut diff);
+    ///
+    /// assert_eq!((diff.len(), diff.capped()), (1, 99));
+    /// assert_eq!(
+    ///     diff.to_string(),
+    ///     "[0]: 0 != 1\noutput capped at 1 byte; 99 additional differences counted\n"
+    /// );
+    /// ```
+    pub fn with_memory_limit(bytes: usize) -> Self {
+        Self {
+            budget: Some(Box::new(Budget {
+                limit: bytes.max(1),
+                ..Budget::default()
+            })),
+            ..Self::default()
+        }
+    }
+
+    /// Records a difference, or only counts it if recording it would take
+    /// the entries past the [memory limit](crate::set_diff_memory_limit).
+    pub fn push(&mut self, mut entry: FieldDiff) {
+        let budget = self.budget.get_or_insert_default();
+        if let Some(overflow) = &mut budget.overflow {
+            overflow.count += 1;
+            return;
+        }
+        crate::truncate::truncate(&mut entry);
+        let size = entry.size();
+        let limit = match budget.limit {
+            0 => crate::limit::diff_memory_limit(),
+            limit => limit,
+        };
+        if self.entries.is_empty() || budget.bytes.saturating_add(size) <= limit {
+            budget.bytes += size;
+            self.entries.push(entry);
+        } else {
+            budget.overflow = Some(Overflow { limit, count: 1 });
+        }
+    }
+
+    /// Records the difference built by `entry`, or only counts it, without
+    /// building it, if the diff reached its memory limit.
+    ///
+    /// Prefer it to [`push`](Diff::push) in [`Diffable`](crate::Diffable)
+    /// implementations, so capped diffs don't render values which are
+    /// dropped.
+    pub fn push_with(&mut self, entry: impl FnOnce() -> FieldDiff) {
+        match self
+            .budget
+            .as_mut()
+            .and_then(|budget| budget.overflow.as_mut())
+        {
+            Some(overflow) => overflow.count += 1,
+            None => self.push(entry()),
+        }
+    }
+
+    /// Creates an empty diff which only counts differences, without
+    /// rendering them, for comparisons which only need to know whether
+    /// values differ.
+    pub(crate) fn counting() -> Self {
+        Self {
+            budget: Some(Box::new(Budget {
+                overflow: Some(Overflow { limit: 0, count: 0 }),
+                ..Budget::default()
+            })),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the differences counted past the limit, if the diff is capped.
+    fn overflow(&self) -> Option<Overflow> {
+        self.budget.as_ref()?.overflow
+    }
+
+    /// Returns the number of differences which were only counted, because
+    /// the diff had reached its [memory limit](crate::set_diff_memory_limit).
+    pub fn capped(&self) -> usize {
+        self.overflow().map_or(0, |overflow| overflow.count)
+    }
+
+    /// Records an equal value at `path`, rendered after the differences
+    /// recorded so far, such as a sequence element next to a differing one.
+    ///
+    /// Context lines don't count as differences.
+    pub fn push_context(&mut self, path: DiffPath, value: String) {
+        self.context.push(Context {
+            before: self.entries.len(),
+            path,
+            value,
+        });
+    }
+
+    /// Marks the entry at `path` as found by comparing `Debug` output, so it
+    /// is labeled as such.
+    pub(crate) fn mark_debug_compared(&mut self, path: DiffPath) {
+        let budget = self.budget.get_or_insert_default();
+        budget.debug_compared.push(path);
+    }
+
+    /// Returns the paths of the entries found by comparing `Debug` output.
+    pub(crate) fn debug_compared(&self) -> &[DiffPath] {
+        self.budget
+            .as_ref()
+            .map_or(&[], |budget| budget.debug_compared.as_slice())
+    }
+
+    /// Returns whether `entry` was found by comparing `Debug` output, such as
+    /// for a field marked `#[cmp(debug_eq)]`, rather than the values.
+    pub fn is_debug_compared(&self, entry: &FieldDiff) -> bool {
+        self.debug_compared().contains(entry.path())
+    }
+
+    /// Appends the entries and context lines of `other`.
+    pub(crate) fn append(&mut self, other: Diff) {
+        let offset = self.entries.len();
+        let overflow = other.overflow();
+        if let Some(budget) = &other.budget
+            && !budget.debug_compared.is_empty()
+        {
+            let debug_compared = budget.debug_compared.clone();
+            self.budget
+                .get_or_insert_default()
+                .debug_compared
+                .extend(debug_compared);
+        }
+        self.context
+            .extend(other.context.into_iter().map(|line| Context {
+                before: line.before + offset,
+                ..line
+            }));
+        for entry in other.entries {
+            self.push(entry);
+        }
+        if let Some(other) = overflow {
+            let budget = self.budget.get_or_insert_default();
+            budget
+                .overflow
+                .get_or_insert(Overflow { count: 0, ..other })
+                .count += other.count;
+        }
+    }
+
+    /// Returns the context lines with the number of entries recorded before
+    /// each.
+    pub(crate) fn context_lines(&self) -> impl Iterator<Item = (usize, &DiffPath, &str)> {
+        self.context
+            .iter()
+            .map(|line| (line.before, &line.path, line.value.as_str()))
+    }
+
+    /// Returns the memory limit which was reached and the number of
+    /// differences counted past it, if the diff is capped.
+    pub(crate) fn overflow_counts(&self) -> Option<(usize, usize)> {
+        self.overflow()
+            .map(|overflow| (overflow.limit, overflow.count))
+    }
+
+    /// Rebuilds a diff from its entries, context lines, overflow counts and
+    /// the paths of its entries found by comparing `Debug` output, like one
+    /// loaded from JSON.
+    ///
+    /// The entries count towards the memory limit of differences pushed
+    /// later.
+    pub(crate) fn from_parts(
+        entries: Vec<FieldDiff>,
+        context: Vec<(usize, DiffPath, String)>,
+        overflow: Option<(usize, usize)>,
+        debug_compared: Vec<DiffPath>,
+    ) -> Self {
+        let budget = (!entries.is_empty() || overflow.is_some() || !debug_compared.is_empty())
+            .then(|| {
+                Box::new(Budget {
+                    bytes: entries.iter().map(FieldDiff::size).sum(),
+                    overflow: overflow.map(|(limit, count)| Overflow { limit, count }),
+                    debug_compared,
+                    ..Budget::default()
+                })
+            });
+        Self {
+            entries,
+            context: context
+                .into_iter()
+                .map(|(before, path, value)| Context {
+                    before,
+                    path,
+                    value,
+                })
+                .collect(),
+            budget,
+        }
+    }
+
+    /// Returns the paths and values of the context lines.
+    pub fn context(&self) -> impl Iterator<Item = (&DiffPath, &str)> {
+        self.context
+            .iter()
+            .map(|line| (&line.path, line.value.as_str()))
+    }
+
+    /// Returns `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of differences found.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the differences found.
+    pub fn entries(&self) -> &[FieldDiff] {
+        &self.entries
+    }
+
+    /// Returns an iterator over the differences found.
+    pub fn iter(&self) -> std::slice::Iter<'_, FieldDiff> {
+        self.entries.iter()
+    }
+
+    /// Returns the diff with the expected and actual sides of every entry
+    /// swapped, which is what comparing the values the other way round
+    /// should find.
+    pub fn inverted(&self) -> Diff {
+        self.map_entries(FieldDiff::inverted)
+    }
+
+    /// Returns the diff with every entry replaced by `map(entry)`, keeping
+    /// the context lines.
+    pub(crate) fn map_entries(&self, map: impl FnMut(&FieldDiff) -> FieldDiff) -> Diff {
+        Diff {
+            entries: self.entries.iter().map(map).collect(),
+            context: self.context.clone(),
+            budget: self.budget.clone(),
+        }
+    }
+
+    /// Consumes the diff, returning its entries.
+    pub fn into_entries(self) -> Vec<FieldDiff> {
+        self.entries
+    }
+
+    /// Consumes the diff, returning the exit status of a verification
+    /// binary: success if no differences were found, and `1` otherwise.
+    ///
+    /// Use [`report_and_exit`](crate::report_and_exit) to also print the
+    /// differences.
+    pub fn into_exit_code(self) -> ExitCode {
+        if self.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl Diff {
+    /// Returns the name of a field reported missing from expected next to
+    /// `entry`, a field missing from actual, if it looks like a rename.
+    fn near_miss(&self, entry: &FieldDiff) -> Option<&str> {
+        let FieldDiff::MissingFromActual { path, .. } = entry else {
+            return None;
+        };
+        let (Segment::Field(name), parent) = path.segments().split_last()? else {
+            return None;
+        };
+        let extra = self.entries.iter().filter_map(|other| match other {
+            FieldDiff::MissingFromExpected { path, .. } => match path.segments().split_last() {
+                Some((Segment::Field(other), other_parent)) if other_parent == parent => {
+                    Some(other.as_str())
+                }
+                _ => None,
+            },
+            _ => None,
+        });
+        crate::similarity::near_miss(name, extra)
+    }
+}
+
+impl Diff {
+    /// Writes one line per entry with values in the colors of `palette`, and
+    /// the context lines in between.
+    pub(crate) fn write(&self, f: &mut fmt::Formatter<'_>, palette: Palette) -> fmt::Result {
+        let messages = crate::messages::messages();
+        let equal = messages.equal();
+        let mut context = self.context.iter().peekable();
+        for (index, entry) in self.entries.iter().enumerate() {
+            while let Some(line) = context.next_if(|line| line.before <= index) {
+                writeln!(f, "{}: {} ({equal})", line.path, line.value)?;
+            }
+            entry.write(f, palette)?;
+            if self.is_debug_compared(entry) {
+                write!(f, " ({})", messages.compared_by_debug())?;
+            }
+            match self.near_miss(entry) {
+                Some(name) => writeln!(f, " ({})", messages.did_you_mean(name))?,
+                None => writeln!(f)?,
+            }
+        }
+        for line in context {
+            writeln!(f, "{}: {} ({equal})", line.path, line.value)?;
+        }
+        if let Some(overflow) = self.overflow() {
+            writeln!(f, "{}", messages.capped(overflow.limit, overflow.count))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, Palette::default())
+    }
+}
+
+impl<'a> IntoIterator for &'a Diff {
+    type Item = &'a FieldDiff;
+    type IntoIter = std::slice::Iter<'a, FieldDiff>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Diff {
+    type Item = FieldDiff;
+    type IntoIter = std::vec::IntoIter<FieldDiff>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
@@ -0,0 +1,669 @@
+//! Field-by-field comparison without `serde`.
+
+use std::fmt::Debug;
+
+use crate::{Diff, DiffPath, FieldDiff};
+
+/// Types which can be compared field by field, recording every difference
+/// into a [`Diff`].
+///
+/// It is implemented for primitives, strings, `Option`, `Result`, tuples,
+/// arrays, slices, `Vec`, `VecDeque`, `HashMap`, `BTreeMap`, `HashSet` and
+/// `BTreeSet`, and through `Box`, `Rc`, `Arc`, `Mutex`, `RwLock`, `Cell`,
+/// `RefCell` and `Wrapping`. Atomics are loaded with
+/// [`atomic_load_ordering`](crate::atomic_load_ordering) and compared by
+/// value. Nested types compose: a difference inside a
+/// `Vec<Option<User>>` is reported at its own path, such as `users[3].name`.
+///
+/// Implement it with `#[derive(Diffable)]` (requires the `derive` feature).
+/// For enums, values with the same variant have their payloads compared
+/// under a `::Variant` path segment, and values with different variants are
+/// reported as a whole. The derive accepts `#[cmp(...)]` attributes on the
+/// type, which set defaults for every field, and on individual struct or
+/// variant fields, which override them:
+///
+/// | Attribute | Where | Effect |
+/// |-----------|-------|--------|
+/// | `default_epsilon = 1e-9` | struct | Compare float fields with an absolute tolerance |
+/// | `ignore = "a, b"` | struct | Skip the named fields |
+/// | `bound = "T: Diffable"` | struct | Replace all inferred where-clause bounds |
+/// | `include_zst` | struct, field | Compare zero-sized fields too |
+/// | `key_matcher = KeyMatcher::CaseInsensitive` | struct | Match map keys with a [`KeyMatcher`](crate::KeyMatcher) |
+/// | `unrolled` | struct | Inline every field's comparison instead of looping over a table |
+/// | `epsilon = 1e-3` | field | Tolerance for this field, overriding `default_epsilon` |
+/// | `skip` | field | Skip this field |
+/// | `flatten` | field | Report this field's differences without its own path segment |
+/// | `unordered` | field | Compare this field's sequences regardless of order, as multisets |
+/// | `rename = "userName"` | field, variant | Name used in diff paths, e.g. to match a serde rename |
+/// | `with = path::to::fn` | field | Compare with `fn(&T, &T, &mut DiffPath, &mut Diff)` instead of `Diffable` |
+/// | `debug_eq` | field | Compare the `Debug` output with [`diff_debug`], for types without `Diffable` or `PartialEq` |
+/// | `max_render_len = 200` | field | Cut each value rendered for this field to fewer than that many bytes, with [`with_max_render_len`](crate::with_max_render_len) |
+/// | `bound = "T: Diffable"` | field | Replace the bound inferred for this field |
+///
+/// A struct's fields are compared by looping over a generated table of one
+/// small function per field, so structs with hundreds of fields don't
+/// compile to one huge function. `#[cmp(unrolled)]` generates the
+/// comparisons inline instead, which can be faster for small, hot structs;
+/// the differences reported are the same.
+///
+/// Fields of types which are always zero-sized (`PhantomData`,
+/// `PhantomPinned`, `()` and `[T; 0]`) are skipped, so they need no bounds.
+///
+/// For generic structs, the derive adds a `FieldType: Diffable` bound for
+/// every compared field whose type mentions a type parameter, so
+/// `struct Window<T, const N: usize> { samples: [T; N] }` gets
+/// `[T; N]: Diffable` without any annotation.
+///
+/// ```edition2024
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use cmp::Diffable;
+///
+/// #[derive(Diffable)]
+/// #[cmp(default_epsilon = 1e-9, ignore = "updated_at")]
+/// struct Reading {
+///     value: f64,
+///     #[cmp(epsilon = 0.5)]
+///     temperature: f64,
+///     updated_at: u64,
+/// }
+///
+/// let expected = Reading { value: 0.3, temperature: 20.0, updated_at: 1 };
+/// let actual = Reading { value: 0.1 + 0.2, temperature: 20.4, updated_at: 2 };
+///
+/// assert!(expected.compare(&actual).is_empty());
+/// # }
+/// ```
+pub trait Diffable {
+    /// Compares `self` (the expected value) with `other` (the actual value),
+    /// pushing a [`FieldDiff`] into `out` for every difference found under
+    /// `path`.
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff);
+
+    /// Like [`diff`](Diffable::diff), but floating point values are equal
+    /// when they are within `epsilon` of each other.
+    ///
+    /// The default implementation ignores `epsilon`.
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        let _ = epsilon;
+        self.diff(other, path, out);
+    }
+
+    /// Returns `true` if `expected` and `actual` are known to be equal
+    /// element for element, checked before comparing slices, arrays and
+    /// `Vec`s of `Self` element by element.
+    ///
+    /// Primitive numbers, `bool` and `char` compare the whole slices at once,
+    /// which for integers is a `memcmp`, so equal buffers cost no per-element
+    /// work. The default implementation returns `false`, so every element is
+    /// compared.
+    fn slices_equal(expected: &[Self], actual: &[Self]) -> bool
+    where
+        Self: Sized,
+    {
+        let _ = (expected, actual);
+        false
+    }
+
+    /// Compares `self` with `other`, returning all differences.
+    ///
+    /// The outcome is recorded with the
+    /// [metrics recorder](crate::set_metrics_recorder), if one is set. With
+    /// `CMP_SELF_CHECK=1`, the comparison is also
+    /// [checked](crate::self_check) for reflexivity and symmetry.
+    fn compare(&self, other: &Self) -> Diff {
+        let mut out = Diff::new();
+        self.diff(other, &mut DiffPath::new(), &mut out);
+        if crate::self_check::enabled()
+            && let Err(failure) = crate::self_check::verify(self, other, &out)
+        {
+            panic!("{failure}");
+        }
+        crate::metrics::record(&out);
+        out
+    }
+}
+
+/// Compares two values with `PartialEq`, recording a
+/// [`FieldDiff::Changed`] at `path` when they differ.
+///
+/// This is the building block for leaf values in [`Diffable`]
+/// implementations.
+pub fn diff_eq<T: PartialEq + Debug + ?Sized + 'static>(
+    expected: &T,
+    actual: &T,
+    path: &DiffPath,
+    out: &mut Diff,
+) {
+    if expected != actual {
+        out.push_with(|| FieldDiff::Changed {
+            path: path.clone(),
+            expected: crate::format_value(expected),
+            actual: crate::format_value(actual),
+        });
+    }
+}
+
+/// Compares two values by their `Debug` output, recording a
+/// [`FieldDiff::Changed`] at `path`, labeled as compared by `Debug`, when
+/// the renderings differ.
+///
+/// This is the fallback for third-party types with neither `PartialEq` nor
+/// `Serialize`, used by `#[cmp(debug_eq)]` and `compare_structs!(a, b, field
+/// via debug)`. Values which render alike compare equal even if they differ,
+/// e.g. in fields their `Debug` implementation leaves out, and values with
+/// nondeterministic output, such as a `HashMap`'s, may differ when equal.
+///
+/// ```edition2024
+/// use cmp::{Diff, DiffPath};
+///
+/// #[derive(Debug)]
+/// struct Handle(u32);
+///
+/// let mut diff = Diff::new();
+/// cmp::diff_debug(&Handle(1), &Handle(2), &DiffPath::new(), &mut diff);
+///
+/// assert_eq!(diff.to_string(), ": Handle(\n    1,\n) != Handle(\n    2,\n) (compared by Debug output)\n");
+/// ```
+pub fn diff_debug<T: Debug + ?Sized>(expected: &T, actual: &T, path: &DiffPath, out: &mut Diff) {
+    let expected = format!("{expected:#?}");
+    let actual = format!("{actual:#?}");
+    if expected != actual {
+        let recorded = out.len();
+        out.push(FieldDiff::Changed {
+            path: path.clone(),
+            expected,
+            actual,
+        });
+        // Differences past the memory limit are only counted.
+        if out.len() > recorded {
+            out.mark_debug_compared(path.clone());
+        }
+    }
+}
+
+/// Returns whether `expected` and `actual` compare equal, like an empty
+/// [`Diffable::compare`], without rendering differences, recording metrics,
+/// self-checking or panicking.
+///
+/// Differences are only counted, so the cost is that of walking the values,
+/// for Criterion benchmarks and property-test generators where assertion
+/// overhead must not dominate.
+///
+/// ```edition2024
+/// use std::hint::black_box;
+///
+/// let (expected, actual) = (vec![1.0, 2.0], vec![1.0, 2.5]);
+///
+/// assert!(cmp::compare_quiet(black_box(&expected), black_box(&expected)));
+/// assert!(!cmp::compare_quiet(black_box(&expected), black_box(&actual)));
+/// ```
+#[inline]
+pub fn compare_quiet<T: Diffable + ?Sized>(expected: &T, actual: &T) -> bool {
+    let mut out = Diff::counting();
+    expected.diff(actual, &mut DiffPath::new(), &mut out);
+    out.capped() == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Diff, DiffPath, Diffable, FieldDiff};
+
+    #[allow(dead_code)]
+    #[derive(cmp_derive::Diffable)]
+    #[cmp(default_epsilon = 1e-6, ignore = "id, updated_at")]
+    struct Sample {
+        id: u32,
+        name: String,
+        ratio: f64,
+        #[cmp(epsilon = 0.5)]
+        score: f32,
+        updated_at: u64,
+        #[cmp(skip)]
+        cache: Vec<u8>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            id: 1,
+            name: "a".to_string(),
+            ratio: 0.3,
+            score: 10.0,
+            updated_at: 100,
+            cache: vec![1],
+        }
+    }
+
+    #[test]
+    fn applies_container_defaults_and_field_overrides() {
+        let actual = Sample {
+            id: 2,
+            ratio: 0.1 + 0.2,
+            score: 10.4,
+            updated_at: 200,
+            cache: vec![],
+            ..sample()
+        };
+
+        assert!(sample().compare(&actual).is_empty());
+    }
+
+    #[test]
+    fn reports_fields_outside_tolerance() {
+        let actual = Sample {
+            name: "b".to_string(),
+            ratio: 0.31,
+            score: 11.0,
+            ..sample()
+        };
+
+        let diff = sample().compare(&actual);
+        let paths: Vec<String> = diff.iter().map(|entry| entry.path().to_string()).collect();
+
+        assert_eq!(paths, ["name", "ratio", "score"]);
+    }
+
+    #[test]
+    fn nan_is_never_within_epsilon() {
+        let actual = Sample {
+            ratio: f64::NAN,
+            ..sample()
+        };
+
+        assert_eq!(sample().compare(&actual).len(), 1);
+    }
+
+    #[test]
+    fn generic_structs_infer_bounds() {
+        #[derive(cmp_derive::Diffable)]
+        struct Window<T, const N: usize> {
+            samples: [T; N],
+            label: &'static str,
+        }
+
+        let expected = Window {
+            samples: [1.0, 2.0],
+            label: "w",
+        };
+        let actual = Window {
+            samples: [1.0, 2.5],
+            label: "w",
+        };
+
+        assert_eq!(
+            expected.compare(&actual).to_string(),
+            "samples[1]: 2.0 != 2.5\n"
+        );
+    }
+
+    #[test]
+    fn zero_sized_fields_are_skipped_without_bounds() {
+        use std::marker::PhantomData;
+
+        struct NotDiffable;
+
+        #[allow(dead_code)]
+        #[derive(cmp_derive::Diffable)]
+        struct Id<T> {
+            value: u64,
+            kind: PhantomData<T>,
+            unit: (),
+            #[cmp(include_zst)]
+            empty: [u8; 0],
+        }
+
+        let id = |value| Id::<NotDiffable> {
+            value,
+            kind: PhantomData,
+            unit: (),
+            empty: [],
+        };
+
+        assert_eq!(id(1).compare(&id(2)).len(), 1);
+    }
+
+    #[test]
+    fn explicit_bounds_replace_inferred_ones() {
+        use std::fmt::Debug;
+        use std::marker::PhantomData;
+
+        #[derive(cmp_derive::Diffable)]
+        #[cmp(bound = "T: Diffable + Debug")]
+        struct Tagged<T, Unit> {
+            values: Vec<T>,
+            #[cmp(skip)]
+            unit: PhantomData<Unit>,
+        }
+
+        struct Meters;
+
+        let expected: Tagged<u8, Meters> = Tagged {
+            values: vec![1],
+            unit: PhantomData,
+        };
+
+        assert!(expected.compare(&expected).is_empty());
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, cmp_derive::Diffable)]
+    #[cmp(default_epsilon = 1e-6)]
+    enum Shape {
+        Circle {
+            radius: f64,
+            #[cmp(skip)]
+            label: String,
+        },
+        Polygon(
+            #[cmp(with = same_len)] Vec<(f64, f64)>,
+            #[cmp(epsilon = 0.5)] f32,
+        ),
+        Empty,
+    }
+
+    fn same_len(
+        expected: &[(f64, f64)],
+        actual: &[(f64, f64)],
+        path: &mut DiffPath,
+        out: &mut Diff,
+    ) {
+        crate::diff_eq(&expected.len(), &actual.len(), path, out);
+    }
+
+    #[test]
+    fn max_render_len_fields_cut_their_values() {
+        #[derive(cmp_derive::Diffable)]
+        struct Upload {
+            #[cmp(max_render_len = 24)]
+            body: Vec<String>,
+            name: String,
+        }
+
+        let upload = |body: &str, name: &str| Upload {
+            body: vec![body.repeat(40)],
+            name: name.repeat(20),
+        };
+        assert_eq!(
+            upload("a", "x").compare(&upload("b", "y")).to_string(),
+            format!(
+                "body[0]: \"aaaa…(37 more bytes) != \"bbbb…(37 more bytes)\n\
+                 name: {:?} != {:?}\n",
+                "x".repeat(20),
+                "y".repeat(20)
+            )
+        );
+    }
+
+    #[test]
+    fn debug_eq_fields_compare_debug_output() {
+        /// A third-party type with neither `PartialEq` nor `Diffable`.
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Handle {
+            id: u32,
+        }
+
+        #[derive(cmp_derive::Diffable)]
+        struct Connection<H> {
+            #[cmp(debug_eq)]
+            handle: H,
+            port: u16,
+        }
+
+        let connection = |id, port| Connection {
+            handle: Handle { id },
+            port,
+        };
+        assert!(connection(1, 80).compare(&connection(1, 80)).is_empty());
+
+        let diff = connection(1, 80).compare(&connection(2, 81));
+        assert!(diff.is_debug_compared(&diff.entries()[0]));
+        assert!(!diff.is_debug_compared(&diff.entries()[1]));
+        assert_eq!(
+            diff.to_string(),
+            "handle: Handle {\n    id: 1,\n} != Handle {\n    id: 2,\n} (compared by Debug output)\n\
+             port: 80 != 81\n"
+        );
+    }
+
+    #[test]
+    fn only_labels_recorded_debug_differences() {
+        let mut diff = Diff::with_memory_limit(1);
+        let mut path = DiffPath::new();
+        path.push_field("a");
+        crate::diff_eq(&1, &2, &path, &mut diff);
+        path.pop();
+        path.push_field("b");
+        crate::diff_debug(&1, &2, &path, &mut diff);
+
+        assert_eq!((diff.len(), diff.capped()), (1, 1));
+        assert!(diff.debug_compared().is_empty());
+    }
+
+    #[test]
+    fn enums_compare_payloads_of_the_same_variant() {
+        let circle = |radius, label: &str| Shape::Circle {
+            radius,
+            label: label.into(),
+        };
+
+        assert!(
+            circle(1.0, "a")
+                .compare(&circle(1.0 + 1e-9, "b"))
+                .is_empty()
+        );
+        assert_eq!(
+            Shape::Polygon(vec![(0.0, 0.0)], 1.0)
+                .compare(&Shape::Polygon(vec![(1.0, 1.0), (2.0, 2.0)], 1.4))
+                .to_string(),
+            "Polygon.0: 1 != 2\n"
+        );
+        assert_eq!(
+            circle(1.0, "a").compare(&Shape::Empty).entries(),
+            [FieldDiff::Changed {
+                path: DiffPath::new(),
+                expected: format!("{:#?}", circle(1.0, "a")),
+                actual: "Empty".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn renamed_fields_and_variants_use_the_new_name() {
+        #[derive(Debug, cmp_derive::Diffable)]
+        enum Event {
+            #[cmp(rename = "login")]
+            LoggedIn {
+                #[cmp(rename = "userName")]
+                user_name: String,
+            },
+        }
+
+        #[derive(cmp_derive::Diffable)]
+        struct Envelope {
+            #[cmp(rename = "ID")]
+            id: u32,
+            #[cmp(rename = "evt")]
+            event: Event,
+        }
+
+        let envelope = |id, user_name: &str| Envelope {
+            id,
+            event: Event::LoggedIn {
+                user_name: user_name.into(),
+            },
+        };
+
+        assert_eq!(
+            envelope(1, "ada").compare(&envelope(2, "bob")).to_string(),
+            "ID: 1 != 2\nevt::login.userName: \"ada\" != \"bob\"\n"
+        );
+    }
+
+    #[test]
+    fn flattened_fields_have_no_path_segment() {
+        #[derive(cmp_derive::Diffable)]
+        struct Address {
+            city: String,
+        }
+
+        #[derive(cmp_derive::Diffable)]
+        struct Customer {
+            #[cmp(flatten)]
+            address: Address,
+            tags: Vec<String>,
+        }
+
+        let customer = |city: &str, tag: &str| Customer {
+            address: Address { city: city.into() },
+            tags: vec![tag.into()],
+        };
+
+        assert_eq!(
+            customer("Oslo", "a")
+                .compare(&customer("Rome", "b"))
+                .to_string(),
+            "city: \"Oslo\" != \"Rome\"\ntags[0]: \"a\" != \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn key_matcher_applies_to_nested_maps() {
+        use std::collections::BTreeMap;
+
+        #[derive(cmp_derive::Diffable)]
+        #[cmp(key_matcher = crate::KeyMatcher::CaseInsensitive)]
+        struct Response {
+            headers: BTreeMap<&'static str, &'static str>,
+        }
+
+        let response = |key, value| Response {
+            headers: BTreeMap::from([(key, value)]),
+        };
+
+        assert!(
+            response("ETag", "1")
+                .compare(&response("etag", "1"))
+                .is_empty()
+        );
+        assert_eq!(
+            response("ETag", "1")
+                .compare(&response("etag", "2"))
+                .to_string(),
+            "headers[\"ETag\"]: \"1\" != \"2\"\n"
+        );
+    }
+
+    #[test]
+    fn compares_unordered_fields_as_multisets() {
+        #[derive(cmp_derive::Diffable)]
+        struct Role {
+            #[cmp(unordered)]
+            permissions: Vec<&'static str>,
+            steps: Vec<&'static str>,
+        }
+
+        let role = |permissions, steps| Role { permissions, steps };
+        let diff = role(vec!["read", "write"], vec!["a", "b"])
+            .compare(&role(vec!["write", "read", "admin"], vec!["b", "a"]));
+
+        assert_eq!(
+            diff.to_string(),
+            "permissions[2]: field missing from expected: \"admin\"\n\
+             steps[0]: \"a\" != \"b\"\n\
+             steps[1]: \"b\" != \"a\"\n"
+        );
+    }
+
+    #[test]
+    fn unrolled_structs_report_the_same_differences() {
+        #[derive(cmp_derive::Diffable)]
+        #[cmp(default_epsilon = 0.1)]
+        struct Table {
+            a: f64,
+            #[cmp(flatten)]
+            inner: (u8, u8),
+            #[cmp(epsilon = 1.0)]
+            b: f64,
+        }
+
+        #[derive(cmp_derive::Diffable)]
+        #[cmp(unrolled, default_epsilon = 0.1)]
+        struct Unrolled {
+            a: f64,
+            #[cmp(flatten)]
+            inner: (u8, u8),
+            #[cmp(epsilon = 1.0)]
+            b: f64,
+        }
+
+        let table = |a, inner, b| Table { a, inner, b };
+        let unrolled = |a, inner, b| Unrolled { a, inner, b };
+        let expected = table(1.0, (1, 2), 1.0).compare(&table(1.05, (1, 3), 3.0));
+
+        assert_eq!(expected.to_string(), "1: 2 != 3\nb: 1.0 != 3.0\n");
+        assert_eq!(
+            unrolled(1.0, (1, 2), 1.0).compare(&unrolled(1.05, (1, 3), 3.0)),
+            expected
+        );
+        let (mut table_out, mut unrolled_out) = (Diff::new(), Diff::new());
+        table(1.0, (1, 2), 1.0).diff_with_epsilon(
+            &table(3.0, (1, 2), 2.5),
+            10.0,
+            &mut DiffPath::new(),
+            &mut table_out,
+        );
+        unrolled(1.0, (1, 2), 1.0).diff_with_epsilon(
+            &unrolled(3.0, (1, 2), 2.5),
+            10.0,
+            &mut DiffPath::new(),
+            &mut unrolled_out,
+        );
+        assert_eq!(table_out.to_string(), "a: 1.0 != 3.0\nb: 1.0 != 2.5\n");
+        assert_eq!(table_out, unrolled_out);
+    }
+
+    #[test]
+    fn tuple_structs_use_index_names() {
+        #[derive(cmp_derive::Diffable)]
+        struct Pair(i32, String);
+
+        let mut path = DiffPath::new();
+        path.push_field("1");
+
+        assert_eq!(
+            Pair(1, "a".into()).compare(&Pair(1, "b".into())).entries(),
+            [FieldDiff::Changed {
+                path,
+                expected: "\"a\"".to_string(),
+                actual: "\"b\"".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn quiet_comparisons_render_nothing() {
+        #[derive(PartialEq)]
+        struct Opaque(u8);
+
+        impl std::fmt::Debug for Opaque {
+            fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                panic!("rendered a quiet comparison");
+            }
+        }
+
+        impl Diffable for Opaque {
+            fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                crate::diff_eq(self, other, path, out);
+            }
+        }
+
+        let expected = vec![Opaque(1), Opaque(2)];
+        assert!(crate::compare_quiet(&expected, &vec![Opaque(1), Opaque(2)]));
+        assert!(!crate::compare_quiet(
+            &expected,
+            &vec![Opaque(1), Opaque(3)]
+        ));
+        assert!(!crate::compare_quiet(&expected, &vec![Opaque(1)]));
+    }
+}
@@ -0,0 +1,190 @@
+//! Differential testing of two implementations, e.g. as a fuzzing oracle.
+
+use std::fmt;
+
+use crate::{Diff, Diffable, FieldDiff};
+
+/// Runs `impl_a` and `impl_b` on `input` and compares their outputs, for
+/// differential testing with cargo-fuzz or libFuzzer.
+///
+/// On divergence, the returned [`Divergence`] has a compact
+/// [signature](Divergence::signature) of where the outputs differ, which is
+/// the same for every input triggering the same divergence, so crashes can be
+/// deduplicated. To compare only some fields of outputs which aren't
+/// [`Diffable`], use the [`differential_check!`](crate::differential_check!)
+/// macro.
+///
+/// ```edition2024
+/// fn sum_loop(values: &[u8]) -> u32 {
+///     let mut sum = 0;
+///     for value in values {
+///         sum += u32::from(*value);
+///     }
+///     sum
+/// }
+///
+/// fn sum_iter(values: &[u8]) -> u32 {
+///     values.iter().map(|&value| u32::from(value)).sum()
+/// }
+///
+/// // In a fuzz target: `if let Err(divergence) = ... { panic!("{divergence}") }`
+/// assert!(cmp::differential_check(&[1, 2, 255][..], sum_loop, sum_iter).is_ok());
+/// ```
+pub fn differential_check<I: ?Sized, O: Diffable>(
+    input: &I,
+    impl_a: impl FnOnce(&I) -> O,
+    impl_b: impl FnOnce(&I) -> O,
+) -> Result<(), Divergence> {
+    Divergence::check(impl_a(input).compare(&impl_b(input)))
+}
+
+/// Differences between the outputs of two implementations, returned by
+/// [`differential_check`].
+///
+/// `Display` renders the signature on the first line, followed by the
+/// differences.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    signature: String,
+    diff: Diff,
+}
+
+impl Divergence {
+    /// Returns `Err` with the divergence if `diff` is not empty.
+    pub fn check(diff: Diff) -> Result<(), Self> {
+        if diff.is_empty() {
+            return Ok(());
+        }
+        let mut kinds: Vec<String> = diff
+            .iter()
+            .map(|entry| {
+                let kind = match entry {
+                    FieldDiff::Changed { .. } => "changed",
+                    FieldDiff::MissingFromExpected { .. } => "extra",
+                    FieldDiff::MissingFromActual { .. } => "missing",
+                };
+                format!("{kind}:{}", entry.path().with_wildcard_indices())
+            })
+            .collect();
+        kinds.sort();
+        kinds.dedup();
+        Err(Self {
+            signature: kinds.join(","),
+            diff,
+        })
+    }
+
+    /// Returns the sorted, deduplicated kinds and paths of the differences,
+    /// with indices replaced with `[*]` and without values, e.g.
+    /// `changed:items[*].price,missing:total`.
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the signature, stable across runs and
+    /// platforms, to bucket divergences.
+    pub fn signature_hash(&self) -> u64 {
+        self.signature
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// Returns the differences, with values, from the first implementation's
+    /// output to the second's.
+    pub fn diff(&self) -> &Diff {
+        &self.diff
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "implementations diverge: {}", self.signature)?;
+        write!(f, "{}", self.diff)
+    }
+}
+
+/// Macro which runs two implementations on an input and compares the named
+/// fields of their outputs with `PartialEq`, returning
+/// `Result<(), Divergence>` like [`differential_check`].
+///
+/// ```edition2024
+/// use cmp::differential_check;
+///
+/// struct Parsed { value: i64, rest: String }
+///
+/// fn parse_a(input: &str) -> Parsed { Parsed { value: input.len() as i64, rest: String::new() } }
+/// fn parse_b(input: &str) -> Parsed { Parsed { value: input.chars().count() as i64, rest: String::new() } }
+///
+/// let divergence = differential_check!("é", parse_a, parse_b, value, rest).unwrap_err();
+/// assert_eq!(divergence.signature(), "changed:value");
+/// ```
+#[macro_export]
+macro_rules! differential_check {
+    ($input:expr, $impl_a:expr, $impl_b:expr, $($field:ident),+ $(,)?) => {{
+        let input = &$input;
+        let (a, b) = (($impl_a)(input), ($impl_b)(input));
+        $crate::Divergence::check($crate::__diff_fields!(&a, &b, $($field),+))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_a(input: &str) -> Vec<String> {
+        input.split(',').map(str::to_string).collect()
+    }
+
+    fn split_b(input: &str) -> Vec<String> {
+        input
+            .split(',')
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn signatures_ignore_values_and_indices() {
+        assert!(differential_check("a,b", split_a, split_b).is_ok());
+
+        let first = differential_check("a,,b", split_a, split_b).unwrap_err();
+        let second = differential_check("x,y,,z,", split_a, split_b).unwrap_err();
+
+        assert_eq!(first.signature(), "changed:[*],missing:[*]");
+        assert_eq!(first.signature(), second.signature());
+        assert_eq!(first.signature_hash(), second.signature_hash());
+        assert!(
+            first
+                .to_string()
+                .starts_with("implementations diverge: changed:[*],missing:[*]\n[1]: ")
+        );
+    }
+
+    #[test]
+    fn compares_named_fields() {
+        struct Stats {
+            count: usize,
+            first: Option<char>,
+        }
+
+        let stats = |input: &&str| Stats {
+            count: input.len(),
+            first: input.chars().next(),
+        };
+        let char_stats = |input: &&str| Stats {
+            count: input.chars().count(),
+            first: input.chars().next(),
+        };
+
+        assert!(differential_check!("abc", stats, char_stats, count, first).is_ok());
+        assert_eq!(
+            differential_check!("é", stats, char_stats, count, first)
+                .unwrap_err()
+                .diff()
+                .to_string(),
+            "count: 2 != 1\n"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Elision of equal subtrees in the values of differing entries, enabled
+//! with `CMP_ELIDE=1`.
+//!
+//! Values are stored as pretty-printed `Debug` text, which nests one item
+//! per line with a closing line at the same indentation as its opening one.
+//! Both sides are parsed back into trees along those lines, and matching
+//! items are compared pairwise, so the path to each differing leaf stays
+//! visible while everything equal around it doesn't.
+
+use std::env;
+
+use crate::{Diff, FieldDiff};
+
+/// Environment variable which enables elision in the text output format.
+const ELIDE_VAR: &str = "CMP_ELIDE";
+
+/// Stands in for elided items.
+const ELLIPSIS: &str = "…";
+
+/// Returns whether `CMP_ELIDE` is set to anything other than `0` or an
+/// empty string.
+pub(crate) fn enabled() -> bool {
+    env::var_os(ELIDE_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+impl Diff {
+    /// Returns the diff with equal subtrees of the values of each changed
+    /// entry collapsed to `…`, keeping struct field and map key names.
+    ///
+    /// Runs of equal sequence elements collapse to a single `…`. Equal leaf
+    /// fields are kept, as they are short and show where in the value the
+    /// differences are. `compare_structs!` does this for its failure messages
+    /// with `CMP_ELIDE=1`.
+    ///
+    /// ```edition2024
+    /// #[derive(Debug, PartialEq)]
+    /// struct Server { name: &'static str, ports: Vec<u16>, limits: (u32, u32) }
+    ///
+    /// let expected = Server { name: "web", ports: vec![80, 443], limits: (1, 2) };
+    /// let actual = Server { name: "web", ports: vec![80, 443], limits: (1, 3) };
+    ///
+    /// let mut diff = cmp::Diff::new();
+    /// cmp::diff_eq(&expected, &actual, &mut cmp::DiffPath::new(), &mut diff);
+    ///
+    /// assert_eq!(
+    ///     diff.elided().entries()[0],
+    ///     cmp::FieldDiff::Changed {
+    ///         path: cmp::DiffPath::new(),
+    ///         expected: "Server {\n    name: \"web\",\n    ports: …,\n    limits: (\n        …,\n        2,\n    ),\n}".into(),
+    ///         actual: "Server {\n    name: \"web\",\n    ports: …,\n    limits: (\n        …,\n        3,\n    ),\n}".into(),
+    ///     }
+    /// );
+    /// ```
+    pub fn elided(&self) -> Diff {
+        self.map_entries(|entry| match entry {
+            FieldDiff::Changed {
+                path,
+                expected,
+                actual,
+            } => {
+                let (expected, actual) = elide(expected, actual);
+                FieldDiff::Changed {
+                    path: path.clone(),
+                    expected,
+                    actual,
+                }
+            }
+            other => other.clone(),
+        })
+    }
+}
+
+/// An item of pretty-printed `Debug` output: its first line and, for
+/// containers, the items inside and the closing line.
+#[derive(PartialEq)]
+struct Node<'a> {
+    line: &'a str,
+    children: Vec<Node<'a>>,
+    close: Option<&'a str>,
+}
+
+impl<'a> Node<'a> {
+    fn parse(lines: &[&'a str], at: &mut usize) -> Self {
+        let line = lines[*at];
+        *at += 1;
+        let mut children = Vec::new();
+        let mut close = None;
+        if line.trim_end().ends_with(['{', '[', '(']) {
+            let indent = indentation(line);
+            while *at < lines.len() {
+                let next = lines[*at];
+                if indentation(next) == indent && next.trim_start().starts_with(['}', ']', ')']) {
+                    close = Some(next);
+                    *at += 1;
+                    break;
+                }
+                children.push(Node::parse(lines, at));
+            }
+        }
+        Node {
+            line,
+            children,
+            close,
+        }
+    }
+
+    fn render(&self, out: &mut Vec<String>) {
+        out.push(self.line.to_string());
+        for child in &self.children {
+            child.render(out);
+        }
+        out.extend(self.close.map(str::to_string));
+    }
+}
+
+/// Collapses the subtrees which are equal on both sides, or returns the
+/// values unchanged if either isn't a single pretty-printed item.
+fn elide(expected: &str, actual: &str) -> (String, String) {
+    let (expected_lines, actual_lines): (Vec<_>, Vec<_>) =
+        (expected.lines().collect(), actual.lines().collect());
+    let (Some(expected_root), Some(actual_root)) =
+        (single_node(&expected_lines), single_node(&actual_lines))
+    else {
+        return (expected.to_string(), actual.to_string());
+    };
+
+    let (mut expected_out, mut actual_out) = (Vec::new(), Vec::new());
+    elide_pair(
+        &expected_root,
+        &actual_root,
+        &mut expected_out,
+        &mut actual_out,
+    );
+    (expected_out.join("\n"), actual_out.join("\n"))
+}
+
+fn single_node<'a>(lines: &[&'a str]) -> Option<Node<'a>> {
+    if lines.is_empty() {
+        return None;
+    }
+    let mut at = 0;
+    let node = Node::parse(lines, &mut at);
+    (at == lines.len()).then_some(node)
+}
+
+fn elide_pair(
+    expected: &Node,
+    actual: &Node,
+    expected_out: &mut Vec<String>,
+    actual_out: &mut Vec<String>,
+) {
+    let same_shape =
+        expected.line == actual.line && expected.close == actual.close && expected.close.is_some();
+    if !same_shape {
+        expected.render(expected_out);
+        actual.render(actual_out);
+        return;
+    }
+
+    expected_out.push(expected.line.to_string());
+    actual_out.push(actual.line.to_string());
+    // Indentation of a pending run of equal unnamed items.
+    let mut run: Option<usize> = None;
+    for index in 0..expected.children.len().max(actual.children.len()) {
+        match (expected.children.get(index), actual.children.get(index)) {
+            (Some(e), Some(a)) if e == a => match key(e.line) {
+                Some(key) if e.close.is_some() => {
+                    flush(&mut run, expected_out, actual_out);
+                    let indent = indentation(e.line);
+                    push_both(
+                        format!("{:indent$}{key}: {ELLIPSIS},", ""),
+                        expected_out,
+                        actual_out,
+                    );
+                }
+                Some(_) => {
+                    flush(&mut run, expected_out, actual_out);
+                    e.render(expected_out);
+                    a.render(actual_out);
+                }
+                None => {
+                    run.get_or_insert(indentation(e.line));
+                }
+            },
+            (e, a) => {
+                flush(&mut run, expected_out, actual_out);
+                match (e, a) {
+                    (Some(e), Some(a)) => elide_pair(e, a, expected_out, actual_out),
+                    (Some(e), None) => e.render(expected_out),
+                    (None, Some(a)) => a.render(actual_out),
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+    flush(&mut run, expected_out, actual_out);
+    expected_out.extend(expected.close.map(str::to_string));
+    actual_out.extend(actual.close.map(str::to_string));
+}
+
+/// Ends a run of equal unnamed items with a single `…`.
+fn flush(run: &mut Option<usize>, expected_out: &mut Vec<String>, actual_out: &mut Vec<String>) {
+    if let Some(indent) = run.take() {
+        push_both(
+            format!("{:indent$}{ELLIPSIS},", ""),
+            expected_out,
+            actual_out,
+        );
+    }
+}
+
+fn push_both(line: String, expected_out: &mut Vec<String>, actual_out: &mut Vec<String>) {
+    expected_out.push(line.clone());
+    actual_out.push(line);
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Returns the struct field name or map key an item starts with, if any.
+fn key(line: &str) -> Option<&str> {
+    let item = line.trim_start();
+    let end = if let Some(string) = item.strip_prefix('"') {
+        let mut escaped = false;
+        let close = string.find(|c| {
+            let found = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            found
+        })?;
+        close + 2
+    } else {
+        item.find(|c: char| !(c.is_alphanumeric() || c == '_'))?
+    };
+    (end > 0 && item[end..].starts_with(": ")).then(|| &item[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pretty(value: &impl std::fmt::Debug) -> String {
+        format!("{value:#?}")
+    }
+
+    #[test]
+    fn collapses_equal_subtrees_and_runs_of_elements() {
+        let expected = (vec![1, 2, 3, 4], vec![(1, 1)], "name");
+        let actual = (vec![1, 2, 3, 5], vec![(1, 1)], "name");
+
+        assert_eq!(
+            elide(&pretty(&expected), &pretty(&actual)),
+            (
+                "(\n    [\n        …,\n        4,\n    ],\n    …,\n)".to_string(),
+                "(\n    [\n        …,\n        5,\n    ],\n    …,\n)".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_map_keys_and_extra_elements() {
+        use std::collections::BTreeMap;
+
+        let expected = BTreeMap::from([("a: b", vec![1]), ("c", vec![2])]);
+        let actual = BTreeMap::from([("a: b", vec![1]), ("c", vec![2, 3])]);
+
+        assert_eq!(
+            elide(&pretty(&expected), &pretty(&actual)),
+            (
+                "{\n    \"a: b\": …,\n    \"c\": [\n        …,\n    ],\n}".to_string(),
+                "{\n    \"a: b\": …,\n    \"c\": [\n        …,\n        3,\n    ],\n}".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn leaves_values_which_are_not_containers_unchanged() {
+        assert_eq!(
+            elide("\"a\"", "\"b\""),
+            ("\"a\"".to_string(), "\"b\"".to_string())
+        );
+        assert_eq!(key("    user_name: 1,"), Some("user_name"));
+        assert_eq!(key(r#"    "a\"b": 1,"#), Some(r#""a\"b""#));
+        assert_eq!(key("    1,"), None);
+    }
+}
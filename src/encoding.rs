@@ -0,0 +1,495 @@
+//! Byte-for-byte comparison of serialized values, enabled by the `serde`
+//! feature, for signing and hashing pipelines where equal values must also
+//! serialize identically.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Number of bytes shown on each side of the first differing byte.
+const CONTEXT: usize = 16;
+
+/// A serialization backend compared byte for byte by
+/// [`compare_bytes`] and `compare_structs!(a, b; bytes = ...)`.
+///
+/// Only [`Json`](Encoding::Json) is the output of a real serializer.
+/// The other encodings are this crate's own, written from the
+/// `serde_json::Value` a value serializes to: they follow the JSON data
+/// model, so byte strings are arrays of integers, map keys must be strings
+/// and integers must fit in 64 bits, and they may differ from the output of
+/// a canonical JSON (RFC 8785) or CBOR library. Use them to check that values
+/// serialize identically, not to reproduce the bytes another encoder signs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Compact JSON as written by `serde_json`, with fields in the order
+    /// they are serialized.
+    Json,
+    /// Compact JSON with the keys of every object sorted by their UTF-8
+    /// bytes, so field order doesn't matter but every other byte does.
+    /// Numbers are written as `serde_json` writes them.
+    SortedKeyJson,
+    /// CBOR encoded from the JSON data model with the rules of deterministic
+    /// encoding (RFC 8949, section 4.2.1): definite lengths, the shortest
+    /// form of each integer and float, and map keys sorted by their encoded
+    /// bytes.
+    JsonModelCbor,
+}
+
+impl Encoding {
+    /// Serializes `value` with this encoding.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` can't be serialized, or, except for
+    /// [`Json`](Encoding::Json), isn't in the JSON data model, e.g. if it is
+    /// a map with non-string keys.
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> serde_json::Result<Vec<u8>> {
+        if self == Encoding::Json {
+            return serde_json::to_vec(value);
+        }
+        let value = serde_json::to_value(value)?;
+        let mut out = Vec::new();
+        match self {
+            Encoding::Json => unreachable!("serialized directly"),
+            Encoding::SortedKeyJson => sorted_key_json(&value, &mut out)?,
+            Encoding::JsonModelCbor => cbor(&value, &mut out),
+        }
+        Ok(out)
+    }
+
+    /// Returns whether the encoding is text, shown as such in mismatches.
+    fn is_text(self) -> bool {
+        self != Encoding::JsonModelCbor
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Json => "JSON",
+            Encoding::SortedKeyJson => "sorted-key JSON",
+            Encoding::JsonModelCbor => "JSON-model CBOR",
+        })
+    }
+}
+
+/// Two values whose serializations differ, as returned by
+/// [`compare_bytes`].
+///
+/// `Display` renders the offset of the first differing byte, and the bytes
+/// around it in both serializations, as text for JSON and as hex for CBOR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteMismatch {
+    encoding: Encoding,
+    offset: usize,
+    expected: Vec<u8>,
+    actual: Vec<u8>,
+}
+
+impl ByteMismatch {
+    /// Returns the encoding the values were serialized with.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the offset of the first differing byte, which is the length
+    /// of the shorter serialization if it is a prefix of the other.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the serialized expected value.
+    pub fn expected(&self) -> &[u8] {
+        &self.expected
+    }
+
+    /// Returns the serialized actual value.
+    pub fn actual(&self) -> &[u8] {
+        &self.actual
+    }
+
+    /// Renders the bytes of `bytes` around the offset, returning them with
+    /// the column of the offset.
+    fn window(&self, bytes: &[u8]) -> (String, usize) {
+        let start = self.offset.saturating_sub(CONTEXT);
+        let end = bytes.len().min(self.offset + CONTEXT);
+        let before = &bytes[start.min(bytes.len())..self.offset.min(bytes.len())];
+        let after = &bytes[self.offset.min(bytes.len())..end];
+        let ellipsis = if start > 0 { "..." } else { "" };
+        let trailing = if end < bytes.len() { "..." } else { "" };
+        let render = |bytes: &[u8]| {
+            if self.encoding.is_text() {
+                String::from_utf8_lossy(bytes).into_owned()
+            } else {
+                bytes.iter().map(|byte| format!("{byte:02x} ")).collect()
+            }
+        };
+        let before = format!("{ellipsis}{}", render(before));
+        let column = before.chars().count();
+        (format!("{before}{}{trailing}", render(after)), column)
+    }
+}
+
+impl fmt::Display for ByteMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} differs from byte {} ({} and {} bytes)",
+            self.encoding,
+            self.offset,
+            self.expected.len(),
+            self.actual.len()
+        )?;
+        let (expected, column) = self.window(&self.expected);
+        let (actual, _) = self.window(&self.actual);
+        writeln!(f, "expected: {}", expected.trim_end())?;
+        writeln!(f, "actual:   {}", actual.trim_end())?;
+        writeln!(f, "          {}^", " ".repeat(column))
+    }
+}
+
+/// Serializes both values with `encoding` and returns the first differing
+/// byte if the serializations aren't identical.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::Encoding;
+///
+/// assert!(cmp::compare_bytes(&[1.5], &[1.5], Encoding::JsonModelCbor).is_ok());
+///
+/// let mismatch = cmp::compare_bytes(&[0.0], &[-0.0], Encoding::SortedKeyJson).unwrap_err();
+/// assert_eq!(mismatch.offset(), 1);
+/// assert_eq!(
+///     mismatch.to_string(),
+///     "sorted-key JSON differs from byte 1 (5 and 6 bytes)\n\
+///      expected: [0.0]\n\
+///      actual:   [-0.0]\n\
+///      \x20          ^\n"
+/// );
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if either value can't be serialized.
+pub fn compare_bytes<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+    encoding: Encoding,
+) -> Result<(), ByteMismatch> {
+    let serialize = |value: Result<Vec<u8>, serde_json::Error>| {
+        value.unwrap_or_else(|err| panic!("Could not serialize value as {encoding}: {err}"))
+    };
+    let expected = serialize(encoding.encode(expected));
+    let actual = serialize(encoding.encode(actual));
+    if expected == actual {
+        return Ok(());
+    }
+    let offset = expected
+        .iter()
+        .zip(&actual)
+        .position(|(expected, actual)| expected != actual)
+        .unwrap_or(expected.len().min(actual.len()));
+    Err(ByteMismatch {
+        encoding,
+        offset,
+        expected,
+        actual,
+    })
+}
+
+/// Panics if the serializations of two values compared equal by
+/// `compare_structs!(a, b; bytes = ...)` differ.
+#[track_caller]
+pub fn assert_identical_bytes<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+    encoding: Encoding,
+    expected_expr: &str,
+    actual_expr: &str,
+) {
+    if let Err(mismatch) = compare_bytes(expected, actual, encoding) {
+        let header = crate::messages::messages().serialized_differently(
+            expected_expr,
+            actual_expr,
+            &encoding.to_string(),
+        );
+        panic!("{header}\n{mismatch}");
+    }
+}
+
+fn sorted_key_json(value: &Value, out: &mut Vec<u8>) -> serde_json::Result<()> {
+    match value {
+        Value::Array(elements) => {
+            out.push(b'[');
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    out.push(b',');
+                }
+                sorted_key_json(element, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_unstable_by_key(|(key, _)| key.as_str());
+            out.push(b'{');
+            for (index, (key, value)) in fields.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                sorted_key_json(value, out)?;
+            }
+            out.push(b'}');
+        }
+        scalar => serde_json::to_writer(out, scalar)?,
+    }
+    Ok(())
+}
+
+fn cbor(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(number) => {
+            if let Some(unsigned) = number.as_u64() {
+                cbor_head(0, unsigned, out);
+            } else if let Some(signed) = number.as_i64() {
+                cbor_head(1, !(signed as u64), out);
+            } else {
+                cbor_float(number.as_f64().unwrap_or(f64::NAN), out);
+            }
+        }
+        Value::String(string) => {
+            cbor_head(3, string.len() as u64, out);
+            out.extend_from_slice(string.as_bytes());
+        }
+        Value::Array(elements) => {
+            cbor_head(4, elements.len() as u64, out);
+            for element in elements {
+                cbor(element, out);
+            }
+        }
+        Value::Object(fields) => {
+            let mut entries: Vec<(Vec<u8>, &Value)> = fields
+                .iter()
+                .map(|(key, value)| {
+                    let mut encoded = Vec::new();
+                    cbor_head(3, key.len() as u64, &mut encoded);
+                    encoded.extend_from_slice(key.as_bytes());
+                    (encoded, value)
+                })
+                .collect();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            cbor_head(5, entries.len() as u64, out);
+            for (key, value) in entries {
+                out.extend_from_slice(&key);
+                cbor(value, out);
+            }
+        }
+    }
+}
+
+/// Writes a CBOR head of major type `major` with argument `value` in its
+/// shortest form.
+fn cbor_head(major: u8, value: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match value {
+        0..24 => out.push(major | value as u8),
+        24..0x100 => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..0x1_0000 => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..0x1_0000_0000 => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Writes a float as the shortest of half, single and double precision
+/// which represents it exactly.
+fn cbor_float(value: f64, out: &mut Vec<u8>) {
+    if value.is_nan() {
+        out.extend_from_slice(&[0xf9, 0x7e, 0x00]);
+    } else if let Some(half) = half_bits(value) {
+        out.push(0xf9);
+        out.extend_from_slice(&half.to_be_bytes());
+    } else if f64::from(value as f32) == value {
+        out.push(0xfa);
+        out.extend_from_slice(&(value as f32).to_be_bytes());
+    } else {
+        out.push(0xfb);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Returns the bits of the half-precision float equal to `value`, if any.
+fn half_bits(value: f64) -> Option<u16> {
+    let single = value as f32;
+    if f64::from(single) != value {
+        return None;
+    }
+    let bits = single.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0 && mantissa == 0 {
+        return Some(sign);
+    }
+    if exponent == 0xff {
+        return Some(sign | 0x7c00);
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 31 {
+        return None;
+    }
+    if half_exponent >= 1 {
+        return (mantissa & 0x1fff == 0)
+            .then_some(sign | ((half_exponent as u16) << 10) | (mantissa >> 13) as u16);
+    }
+    // A subnormal half: a multiple of 2^-24 below 2^-14.
+    let full = mantissa | 0x80_0000;
+    let shift = 126 - exponent;
+    if exponent == 0 || shift >= 32 || full & ((1 << shift) - 1) != 0 {
+        return None;
+    }
+    Some(sign | (full >> shift) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn hex(value: Value) -> String {
+        Encoding::JsonModelCbor
+            .encode(&value)
+            .unwrap()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn encodes_deterministic_cbor() {
+        // Examples from RFC 8949, appendix A.
+        for (value, encoded) in [
+            (json!(0), "00"),
+            (json!(23), "17"),
+            (json!(1000000), "1a000f4240"),
+            (json!(u64::MAX), "1bffffffffffffffff"),
+            (json!(-1000), "3903e7"),
+            (json!(0.0), "f90000"),
+            (json!(-0.0), "f98000"),
+            (json!(1.5), "f93e00"),
+            (json!(65504.0), "f97bff"),
+            (json!(100000.0), "fa47c35000"),
+            (json!(5.960464477539063e-8), "f90001"),
+            (json!(1.1), "fb3ff199999999999a"),
+            (json!("IETF"), "6449455446"),
+            (json!([1, [2, 3]]), "8201820203"),
+            (json!({"b": [2, 3], "a": 1}), "a26161016162820203"),
+            (json!([null, true, false]), "83f6f5f4"),
+        ] {
+            assert_eq!(hex(value.clone()), encoded, "{value}");
+        }
+    }
+
+    #[test]
+    fn sorts_json_keys() {
+        #[derive(Serialize)]
+        struct Payload {
+            z: u8,
+            a: Vec<&'static str>,
+        }
+
+        let payload = Payload { z: 1, a: vec!["x"] };
+        assert_eq!(
+            Encoding::SortedKeyJson.encode(&payload).unwrap(),
+            br#"{"a":["x"],"z":1}"#
+        );
+        assert_eq!(
+            Encoding::Json.encode(&payload).unwrap(),
+            br#"{"z":1,"a":["x"]}"#
+        );
+    }
+
+    #[test]
+    fn shows_the_bytes_around_the_first_difference() {
+        let mismatch = compare_bytes(
+            &json!({"items": (0..20).collect::<Vec<_>>()}),
+            &json!({"items": (0..20).map(|n| if n == 12 { 99 } else { n }).collect::<Vec<_>>()}),
+            Encoding::SortedKeyJson,
+        )
+        .unwrap_err();
+        assert_eq!(
+            mismatch.to_string(),
+            "sorted-key JSON differs from byte 36 (61 and 61 bytes)\n\
+             expected: ...5,6,7,8,9,10,11,12,13,14,15,16,1...\n\
+             actual:   ...5,6,7,8,9,10,11,99,13,14,15,16,1...\n\
+             \x20                            ^\n"
+        );
+
+        let mismatch = compare_bytes(&[1, 2], &[1, 2, 3], Encoding::JsonModelCbor).unwrap_err();
+        assert_eq!(mismatch.offset(), 0);
+        assert_eq!(
+            mismatch.to_string(),
+            "JSON-model CBOR differs from byte 0 (3 and 4 bytes)\n\
+             expected: 82 01 02\n\
+             actual:   83 01 02 03\n\
+             \x20         ^\n"
+        );
+    }
+
+    #[test]
+    fn compare_structs_checks_bytes_after_values() {
+        #[derive(Serialize)]
+        struct Signed {
+            id: u32,
+            amount: f64,
+        }
+
+        #[derive(Serialize)]
+        struct Reordered {
+            amount: f64,
+            id: u32,
+        }
+
+        let signed = Signed { id: 1, amount: 0.0 };
+        crate::compare_structs!(signed, Reordered { amount: 0.0, id: 1 }; bytes = Encoding::SortedKeyJson);
+
+        let failure = std::panic::catch_unwind(|| {
+            crate::compare_structs!(signed, Reordered { amount: 0.0, id: 1 }; bytes = Encoding::Json)
+        })
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+        assert!(failure.starts_with(
+            "`signed` and `Reordered { amount: 0.0, id: 1 }` are equal, but serialize to different JSON\n\
+             JSON differs from byte 2 (21 and 21 bytes)\n"
+        ), "{failure}");
+
+        let failure = std::panic::catch_unwind(|| {
+            crate::compare_structs!(signed, Signed { id: 1, amount: -0.0 }; bytes = Encoding::JsonModelCbor)
+        })
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+        assert!(
+            failure.contains("JSON-model CBOR differs from byte 13"),
+            "{failure}"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Comparison of environment-style string maps with secret masking.
+//!
+//! Keys are compared in sorted order. Values of keys matching one of the
+//! secret patterns are still compared, but are never printed: the diff shows
+//! `<redacted>` in their place, without their length, and a changed secret
+//! is reported as `KEY: <redacted> != <redacted> (secret values differ)`.
+
+use std::collections::BTreeMap;
+
+use crate::glob;
+
+/// Key patterns treated as secrets by [`compare_env!`](crate::compare_env) when none are given.
+pub const DEFAULT_SECRET_PATTERNS: &[&str] = &["*_TOKEN", "*_SECRET", "*_PASSWORD"];
+
+/// Compares two maps of keys to values, returning one line per added,
+/// removed or changed key.
+///
+/// Values of keys matching any of `secret_patterns` (globs where `*` matches
+/// any run of characters, case-insensitively) are masked in the output.
+///
+/// ```edition2024
+/// use std::collections::HashMap;
+///
+/// let expected = HashMap::from([("API_TOKEN", "abc"), ("PORT", "8080")]);
+/// let actual = HashMap::from([("API_TOKEN", "abd"), ("PORT", "8080")]);
+///
+/// assert_eq!(
+///     cmp::env::diff(&expected, &actual, cmp::env::DEFAULT_SECRET_PATTERNS),
+///     ["API_TOKEN: <redacted> != <redacted> (secret values differ)"]
+/// );
+/// ```
+pub fn diff<K, V>(
+    expected: impl IntoIterator<Item = (K, V)>,
+    actual: impl IntoIterator<Item = (K, V)>,
+    secret_patterns: &[&str],
+) -> Vec<String>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let expected = collect(expected);
+    let actual = collect(actual);
+    let secret = |key: &str| {
+        secret_patterns
+            .iter()
+            .any(|pattern| glob::matches(pattern, key))
+    };
+    let render = |key: &str, value: &str| {
+        if secret(key) {
+            "<redacted>".to_string()
+        } else {
+            format!("{value:?}")
+        }
+    };
+
+    let mut diffs = Vec::new();
+    for (key, expected_value) in &expected {
+        match actual.get(key) {
+            // Both sides render alike, so say that the hidden values differ.
+            Some(actual_value) if actual_value != expected_value && secret(key) => diffs.push(
+                format!("{key}: <redacted> != <redacted> (secret values differ)"),
+            ),
+            Some(actual_value) if actual_value != expected_value => diffs.push(format!(
+                "{key}: {} != {}",
+                render(key, expected_value),
+                render(key, actual_value)
+            )),
+            Some(_) => {}
+            None => diffs.push(format!(
+                "{key}: key missing from actual: {}",
+                render(key, expected_value)
+            )),
+        }
+    }
+    for (key, actual_value) in &actual {
+        if !expected.contains_key(key) {
+            diffs.push(format!(
+                "{key}: key missing from expected: {}",
+                render(key, actual_value)
+            ));
+        }
+    }
+    diffs
+}
+
+fn collect<K, V>(map: impl IntoIterator<Item = (K, V)>) -> BTreeMap<String, String>
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    map.into_iter()
+        .map(|(key, value)| (key.as_ref().to_string(), value.as_ref().to_string()))
+        .collect()
+}
+
+/// Macro which compares environment/config maps, masking secret values in
+/// the output.
+///
+/// Both arguments can be anything iterable as string key/value pairs, such
+/// as `&HashMap<String, String>`, `&BTreeMap<&str, &str>` or
+/// `std::env::vars()`. By default, keys matching
+/// [`DEFAULT_SECRET_PATTERNS`](crate::env::DEFAULT_SECRET_PATTERNS) are
+/// masked; pass `secrets = [...]` to use different patterns.
+///
+/// ```edition2024
+/// use cmp::compare_env;
+/// use std::collections::BTreeMap;
+///
+/// let expected = BTreeMap::from([("DATABASE_URL", "postgres://db"), ("GITHUB_TOKEN", "ghp_1")]);
+/// let actual = expected.clone();
+///
+/// compare_env!(&expected, &actual);
+/// compare_env!(&expected, &actual; secrets = ["*_TOKEN", "DATABASE_URL"]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if any key is added, removed or changed.
+#[macro_export]
+macro_rules! compare_env {
+    ($expected:expr, $actual:expr) => {
+        $crate::compare_env!($expected, $actual; secrets = $crate::env::DEFAULT_SECRET_PATTERNS)
+    };
+    ($expected:expr, $actual:expr; secrets = [$($pattern:expr),* $(,)?]) => {
+        $crate::compare_env!($expected, $actual; secrets = &[$($pattern),*])
+    };
+    ($expected:expr, $actual:expr; secrets = $patterns:expr) => {{
+        let diffs = $crate::env::diff($expected, $actual, $patterns);
+
+        assert!(diffs.is_empty(), "{}\n", diffs.join("\n"));
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn reports_added_removed_and_changed_keys() {
+        let expected = HashMap::from([("A", "1"), ("B", "2"), ("C", "3")]);
+        let actual = HashMap::from([("A", "1"), ("B", "20"), ("D", "4")]);
+
+        assert_eq!(
+            diff(&expected, &actual, DEFAULT_SECRET_PATTERNS),
+            [
+                r#"B: "2" != "20""#,
+                r#"C: key missing from actual: "3""#,
+                r#"D: key missing from expected: "4""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn masks_secret_values() {
+        let expected = HashMap::from([("db_password", "hunter2"), ("SESSION_KEY", "a")]);
+        let actual = HashMap::from([("db_password", "hunter3")]);
+
+        assert_eq!(
+            diff(&expected, &actual, &["*_PASSWORD", "SESSION_*"]),
+            [
+                "SESSION_KEY: key missing from actual: <redacted>",
+                "db_password: <redacted> != <redacted> (secret values differ)",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "GITHUB_TOKEN: <redacted> != <redacted> (secret values differ)")]
+    fn compare_env_panic_masks_secrets() {
+        let expected = HashMap::from([("GITHUB_TOKEN".to_string(), "ghp_1".to_string())]);
+        let actual = HashMap::from([("GITHUB_TOKEN".to_string(), "ghp_2".to_string())]);
+
+        compare_env!(&expected, &actual);
+    }
+}
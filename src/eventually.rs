@@ -0,0 +1,379 @@
+//! Runtime support for
+//! [`assert_eventually_eq!`](crate::assert_eventually_eq): retrying a
+//! comparison until it passes or a timeout elapses.
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Diff, FieldDiff};
+
+/// Timeout and retry interval of an eventual comparison.
+#[derive(Clone, Copy, Debug)]
+pub struct Eventually {
+    pub timeout: Duration,
+    pub interval: Duration,
+}
+
+impl Default for Eventually {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl Eventually {
+    /// Calls `attempt` every `interval` until it returns an empty diff,
+    /// returning the number of attempts made, or a [`Timeout`] describing the
+    /// attempts if `timeout` elapses first.
+    pub fn run(&self, mut attempt: impl FnMut() -> Diff) -> Result<usize, Timeout> {
+        let start = Instant::now();
+        let mut convergence = Convergence::default();
+        loop {
+            let diff = attempt();
+            convergence.observe(&diff);
+            if diff.is_empty() {
+                return Ok(convergence.attempts);
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= self.timeout {
+                return Err(Timeout {
+                    after: self.timeout,
+                    diff,
+                    convergence,
+                });
+            }
+            thread::sleep(self.interval.min(self.timeout - elapsed));
+        }
+    }
+}
+
+/// The outcome of an eventual comparison which timed out.
+#[derive(Debug)]
+pub struct Timeout {
+    pub after: Duration,
+    /// Differences of the last attempt.
+    pub diff: Diff,
+    pub convergence: Convergence,
+}
+
+impl Timeout {
+    /// Returns the header printed above the last diff: how long and how many
+    /// attempts were made, and the convergence report.
+    pub fn header(&self) -> String {
+        format!(
+            "still differs after {:?} ({} attempts)\n{}last differences:\n",
+            self.after, self.convergence.attempts, self.convergence
+        )
+    }
+}
+
+/// How each differing path evolved over the attempts of an eventual
+/// comparison, to tell whether the system was converging or stuck.
+#[derive(Debug, Default)]
+pub struct Convergence {
+    attempts: usize,
+    paths: Vec<PathHistory>,
+}
+
+#[derive(Debug)]
+struct PathHistory {
+    path: String,
+    first_actual: String,
+    last_actual: String,
+    /// Number of attempts in which the path differed.
+    failed: usize,
+    /// Whether the path differed in the latest attempt.
+    failing: bool,
+}
+
+impl Convergence {
+    fn observe(&mut self, diff: &Diff) {
+        self.attempts += 1;
+        for history in &mut self.paths {
+            history.failing = false;
+        }
+        for entry in diff {
+            let path = entry.path().to_string();
+            let actual = match entry {
+                FieldDiff::Changed { actual, .. }
+                | FieldDiff::MissingFromExpected { actual, .. } => actual.clone(),
+                FieldDiff::MissingFromActual { .. } => "<missing>".to_string(),
+            };
+            match self.paths.iter_mut().find(|history| history.path == path) {
+                Some(history) => {
+                    history.last_actual = actual;
+                    history.failed += 1;
+                    history.failing = true;
+                }
+                None => self.paths.push(PathHistory {
+                    path,
+                    first_actual: actual.clone(),
+                    last_actual: actual,
+                    failed: 1,
+                    failing: true,
+                }),
+            }
+        }
+    }
+}
+
+impl fmt::Display for Convergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        type Include = fn(&PathHistory, usize) -> bool;
+        let sections: [(&str, Include); 3] = [
+            ("converged", |history, _| !history.failing),
+            ("never matched", |history, attempts| {
+                history.failed == attempts
+            }),
+            ("matched at times", |history, attempts| {
+                history.failing && history.failed < attempts
+            }),
+        ];
+        for (title, include) in sections {
+            let mut histories = self
+                .paths
+                .iter()
+                .filter(|history| include(history, self.attempts))
+                .peekable();
+            if histories.peek().is_none() {
+                continue;
+            }
+            writeln!(f, "{title}:")?;
+            for history in histories {
+                let PathHistory {
+                    path,
+                    first_actual,
+                    last_actual,
+                    failed,
+                    ..
+                } = history;
+                if history.failing {
+                    writeln!(
+                        f,
+                        "  {path}: first seen {first_actual}, last seen {last_actual}"
+                    )?;
+                } else {
+                    writeln!(
+                        f,
+                        "  {path}: differed in {failed} attempts, first seen {first_actual}"
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a duration literal such as `5s`, `100ms`, `1.5s`, `2m` or `250us`.
+///
+/// # Panics
+///
+/// Panics if `literal` has no number or an unknown unit, since it comes from
+/// the macro invocation.
+pub fn parse_duration(literal: &str) -> Duration {
+    let split = literal
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(literal.len());
+    let (number, unit) = literal.split_at(split);
+    let number: f64 = number
+        .replace('_', "")
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid duration `{literal}`"));
+    let seconds = match unit {
+        "h" => 3600.0,
+        "m" => 60.0,
+        "s" => 1.0,
+        "ms" => 1e-3,
+        "us" => 1e-6,
+        "ns" => 1e-9,
+        _ => panic!("invalid duration `{literal}`, expected a unit of h, m, s, ms, us or ns"),
+    };
+    Duration::from_secs_f64(number * seconds)
+}
+
+/// Sets `$config`'s fields from `timeout = ..., interval = ...` options,
+/// given either as duration literals (`5s`) or `Duration` expressions.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __eventually_options {
+    ($config:ident;) => {};
+    ($config:ident; $option:ident = $value:literal $(, $($rest:tt)*)?) => {
+        $config.$option = $crate::__private::parse_duration(stringify!($value));
+        $crate::__eventually_options!($config; $($($rest)*)?);
+    };
+    ($config:ident; $option:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $config.$option = $value;
+        $crate::__eventually_options!($config; $($($rest)*)?);
+    };
+}
+
+/// Macro which repeatedly evaluates `actual` until its fields match those of
+/// `expected`, for tests against eventually consistent systems.
+///
+/// `actual` is re-evaluated every `interval` (100ms by default) until the
+/// comparison passes, or `timeout` (5s by default) elapses, in which case the
+/// macro panics with the differences of the last attempt, preceded by which
+/// fields converged over time and which never matched (with the values first
+/// and last seen). Both options accept
+/// a duration literal such as `250ms` or a `Duration`. Note that, unlike
+/// [`compare_structs!`](crate::compare_structs), the actual value comes
+/// first.
+///
+/// ```edition2024
+/// use std::cell::Cell;
+/// use std::time::Duration;
+///
+/// use cmp::assert_eventually_eq;
+///
+/// struct State { replicas: u32, ready: bool }
+///
+/// let polls = Cell::new(0);
+/// let fetch_state = || {
+///     polls.set(polls.get() + 1);
+///     State { replicas: 3, ready: polls.get() >= 3 }
+/// };
+/// let expected = State { replicas: 3, ready: true };
+///
+/// assert_eventually_eq!(fetch_state(), expected, replicas, ready; timeout = 1s, interval = 10ms);
+/// assert_eventually_eq!(fetch_state(), expected, ready; interval = Duration::from_millis(5));
+/// ```
+///
+/// # Panics
+///
+/// Panics if the fields still differ when the timeout elapses.
+#[macro_export]
+macro_rules! assert_eventually_eq {
+    ($actual:expr, $expected:expr, $($field:ident),+ $(; $($option:tt)*)?) => {
+        $crate::__eventually!(
+            $actual,
+            $expected,
+            |expected, actual| $crate::__diff_fields!(expected, actual, $($field),+),
+            $($($option)*)?
+        )
+    };
+    ($actual:expr, $expected:expr $(; $($option:tt)*)?) => {
+        $crate::__eventually!(
+            $actual,
+            $expected,
+            |expected, actual| $crate::__diff_all!(expected, actual, true),
+            $($($option)*)?
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __eventually {
+    ($actual:expr, $expected:expr, |$e:ident, $a:ident| $diff:expr, $($option:tt)*) => {{
+        let mut config = $crate::__private::Eventually::default();
+        $crate::__eventually_options!(config; $($option)*);
+
+        let $e = &$expected;
+        let outcome = config.run(|| {
+            let $a = &$actual;
+            $diff
+        });
+        match outcome {
+            Ok(_) => $crate::__private::record(&$crate::Diff::new()),
+            Err(timeout) => {
+                $crate::__private::record(&timeout.diff);
+                $crate::__private::fail(&timeout.header(), &timeout.diff);
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    struct Job {
+        id: u32,
+        status: &'static str,
+    }
+
+    #[test]
+    fn parses_duration_literals() {
+        assert_eq!(parse_duration("5s"), Duration::from_secs(5));
+        assert_eq!(parse_duration("100ms"), Duration::from_millis(100));
+        assert_eq!(parse_duration("1.5s"), Duration::from_millis(1500));
+        assert_eq!(parse_duration("2m"), Duration::from_secs(120));
+        assert_eq!(parse_duration("1_000us"), Duration::from_millis(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid duration `5`")]
+    fn rejects_durations_without_unit() {
+        parse_duration("5");
+    }
+
+    #[test]
+    fn retries_until_equal() {
+        let polls = Cell::new(0);
+        let fetch = || {
+            polls.set(polls.get() + 1);
+            Job {
+                id: 1,
+                status: if polls.get() < 4 { "running" } else { "done" },
+            }
+        };
+
+        assert_eventually_eq!(fetch(), Job { id: 1, status: "done" }, id, status; interval = 1ms);
+        assert_eq!(polls.get(), 4);
+    }
+
+    #[test]
+    fn reports_convergence_per_path() {
+        let mut convergence = Convergence::default();
+        for (id, status, owner) in [(0, "queued", "a"), (0, "running", "b"), (1, "running", "b")] {
+            let actual = Job { id, status };
+            let expected = Job {
+                id: 1,
+                status: "done",
+            };
+            let mut diff = crate::__diff_fields!(&expected, &actual, id, status);
+            if owner == "b" {
+                let mut path = crate::DiffPath::new();
+                path.push_field("owner");
+                diff.push(FieldDiff::MissingFromActual {
+                    path,
+                    expected: "\"a\"".into(),
+                });
+            }
+            convergence.observe(&diff);
+        }
+
+        assert_eq!(
+            convergence.to_string(),
+            "converged:\n  \
+             id: differed in 2 attempts, first seen 0\n\
+             never matched:\n  \
+             status: first seen \"queued\", last seen \"running\"\n\
+             matched at times:\n  \
+             owner: first seen <missing>, last seen <missing>\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "still differs after 30ms")]
+    fn reports_the_last_diff_on_timeout() {
+        let expected = Job {
+            id: 1,
+            status: "done",
+        };
+
+        assert_eventually_eq!(
+            Job { id: 1, status: "running" },
+            expected,
+            id,
+            status;
+            timeout = 30ms,
+            interval = Duration::from_millis(10)
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Comparisons of every field of two structs except some, for
+//! `compare_structs!(a, b, except: ...)`.
+//!
+//! With the `serde` feature, the named fields are removed from both
+//! serialized values before they are compared. Without it, the structs are
+//! compared with [`Diffable`] and differences inside the named fields are
+//! dropped.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[cfg(feature = "serde")]
+use crate::serialize::Serialized;
+use crate::{Diff, Segment};
+#[cfg(not(feature = "serde"))]
+use crate::{DiffPath, Diffable};
+
+/// Compares all serialized fields of two structs but those named `except`.
+#[cfg(feature = "serde")]
+pub fn diff_except<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+    except: &[&str],
+) -> Diff {
+    let expected = without(
+        crate::serialize::expect_serialized(expected, "expected"),
+        except,
+        "expected",
+    );
+    let actual = without(
+        crate::serialize::expect_serialized(actual, "actual"),
+        except,
+        "actual",
+    );
+
+    let mut diff = Diff::new();
+    crate::value::diff_serialized(&expected, &actual, true, &mut diff);
+    diff
+}
+
+/// Removes the fields named `except` from a serialized `side` value, along
+/// with those of them left out because they couldn't be serialized.
+#[cfg(feature = "serde")]
+fn without(mut serialized: Serialized, except: &[&str], side: &str) -> Serialized {
+    let fields = serialized
+        .value
+        .as_object_mut()
+        .unwrap_or_else(|| panic!("{side} value is not an object"));
+    for name in except {
+        fields.remove(*name);
+    }
+    serialized.skipped.retain(|(path, _)| {
+        !matches!(
+            path.segments().first(),
+            Some(Segment::Field(name)) if except.contains(&name.as_str())
+        )
+    });
+    serialized
+}
+
+/// Compares two structs with [`Diffable`], dropping the differences inside
+/// the fields named `except`.
+#[cfg(not(feature = "serde"))]
+pub fn diff_except<T: Diffable + ?Sized>(expected: &T, actual: &T, except: &[&str]) -> Diff {
+    let mut found = Diff::new();
+    expected.diff(actual, &mut DiffPath::new(), &mut found);
+
+    let mut diff = Diff::new();
+    for entry in found {
+        let excluded = matches!(
+            entry.path().segments().first(),
+            Some(Segment::Field(name)) if except.contains(&name.as_str())
+        );
+        if !excluded {
+            diff.push(entry);
+        }
+    }
+    diff
+}
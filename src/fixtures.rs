@@ -0,0 +1,295 @@
+//! Comparison against expected values held by another process, enabled by
+//! the `fixtures` feature, for parity tests between implementations in
+//! different languages.
+//!
+//! A [`FixtureServer`] holds named expected values, such as JSON files
+//! written by the reference implementation, and a [`FixtureClient`] sends it
+//! serialized actual values and receives the [`Diff`] back. The protocol is
+//! newline-delimited JSON over TCP, so either side is easy to write in
+//! another language: each request is a line
+//! `{"fixture": "user", "actual": {...}}`, answered by a line holding
+//! `{"diff": {...}}` with the serialized [`Diff`], or `{"error": "..."}`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Diff, DiffPath};
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    fixture: String,
+    actual: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Diff(Diff),
+    Error(String),
+}
+
+/// A server comparing the values sent by [`FixtureClient`]s with the
+/// expected values it holds.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "fixtures")]
+/// # {
+/// use cmp::fixtures::FixtureServer;
+///
+/// let server = FixtureServer::new().load_dir("golden/python").unwrap();
+/// let address = server.spawn("127.0.0.1:0").unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FixtureServer {
+    fixtures: HashMap<String, Value>,
+}
+
+impl FixtureServer {
+    /// Creates a server holding no fixtures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds `expected` as the fixture named `name`, replacing any fixture of
+    /// that name.
+    pub fn fixture(mut self, name: impl Into<String>, expected: Value) -> Self {
+        self.fixtures.insert(name.into(), expected);
+        self
+    }
+
+    /// Holds every `.json` file of `dir` as a fixture named after the file
+    /// without its extension, e.g. `user` for `user.json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be read, or a file can't be
+    /// read or isn't valid JSON.
+    pub fn load_dir(mut self, dir: impl AsRef<Path>) -> io::Result<Self> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let expected = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid fixture {}: {err}", path.display()),
+                )
+            })?;
+            self.fixtures.insert(name.to_string(), expected);
+        }
+        Ok(self)
+    }
+
+    /// Serves the fixtures on `address` from a background thread, returning
+    /// the bound address, e.g. to find the port when binding port 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address can't be bound.
+    pub fn spawn(self, address: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        thread::spawn(move || self.serve(listener));
+        Ok(address)
+    }
+
+    /// Serves the fixtures to the clients connecting to `listener`, each on
+    /// its own thread, until accepting a connection fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error which stopped the server.
+    pub fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let fixtures = Arc::new(self.fixtures);
+        loop {
+            let (stream, _) = listener.accept()?;
+            let fixtures = Arc::clone(&fixtures);
+            thread::spawn(move || handle(&fixtures, stream));
+        }
+    }
+}
+
+/// Answers the requests of one client until it disconnects.
+fn handle(fixtures: &HashMap<String, Value>, stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let response = match serde_json::from_str::<Request>(&line?) {
+            Ok(request) => match fixtures.get(&request.fixture) {
+                Some(expected) => {
+                    let mut diff = Diff::new();
+                    crate::value::diff_values(
+                        expected,
+                        &request.actual,
+                        false,
+                        &mut DiffPath::new(),
+                        &mut diff,
+                    );
+                    Response::Diff(diff)
+                }
+                None => Response::Error(format!("unknown fixture `{}`", request.fixture)),
+            },
+            Err(err) => Response::Error(format!("invalid request: {err}")),
+        };
+        let mut line = serde_json::to_vec(&response).map_err(io::Error::other)?;
+        line.push(b'\n');
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
+/// A connection to a [`FixtureServer`], or to a server speaking the same
+/// protocol in another language.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "fixtures")]
+/// # {
+/// use cmp::fixtures::FixtureClient;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User { id: u32, name: &'static str }
+///
+/// let mut client = FixtureClient::connect("127.0.0.1:7979").unwrap();
+/// client.assert_matches("user", &User { id: 1, name: "ada" });
+/// # }
+/// ```
+pub struct FixtureClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl FixtureClient {
+    /// Connects to a fixture server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established.
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let writer = TcpStream::connect(address)?;
+        writer.set_nodelay(true)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    /// Sends `actual` to the server and returns its differences from the
+    /// fixture named `fixture`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `actual` can't be serialized, the connection
+    /// fails, or the server answers with an error, e.g. for an unknown
+    /// fixture.
+    pub fn compare<T: Serialize + ?Sized>(
+        &mut self,
+        fixture: &str,
+        actual: &T,
+    ) -> io::Result<Diff> {
+        let request = Request {
+            fixture: fixture.to_string(),
+            actual: serde_json::to_value(actual).map_err(io::Error::other)?,
+        };
+        let mut line = serde_json::to_vec(&request).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "fixture server closed the connection",
+            ));
+        }
+        match serde_json::from_str(&line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        {
+            Response::Diff(diff) => Ok(diff),
+            Response::Error(message) => Err(io::Error::other(message)),
+        }
+    }
+
+    /// Compares `actual` with the fixture named `fixture` like
+    /// [`compare`](FixtureClient::compare), recording the comparison.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the differences if `actual` differs from the fixture, or
+    /// if the comparison fails.
+    #[track_caller]
+    pub fn assert_matches<T: Serialize + ?Sized>(&mut self, fixture: &str, actual: &T) {
+        let diff = self
+            .compare(fixture, actual)
+            .unwrap_or_else(|err| panic!("could not compare with fixture `{fixture}`: {err}"));
+        crate::metrics::record(&diff);
+        if !diff.is_empty() {
+            crate::__private::fail(&format!("value differs from fixture `{fixture}`\n"), &diff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        roles: Vec<&'static str>,
+    }
+
+    #[test]
+    fn compares_with_fixtures_over_tcp() {
+        let address = FixtureServer::new()
+            .fixture("user", json!({"id": 1, "roles": ["admin"]}))
+            .spawn("127.0.0.1:0")
+            .unwrap();
+        let mut client = FixtureClient::connect(address).unwrap();
+
+        let user = |id, roles| User { id, roles };
+        assert!(
+            client
+                .compare("user", &user(1, vec!["admin"]))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            client
+                .compare("user", &user(2, vec!["admin", "ops"]))
+                .unwrap()
+                .to_string(),
+            "id: 1 != 2\nroles[1]: field missing from expected: \"ops\"\n"
+        );
+        client.assert_matches("user", &user(1, vec!["admin"]));
+
+        let err = client.compare("account", &user(1, vec![])).unwrap_err();
+        assert_eq!(err.to_string(), "unknown fixture `account`");
+    }
+
+    #[test]
+    fn loads_json_files_as_fixtures() {
+        let dir = std::env::temp_dir().join(format!("cmp-fixtures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("user.json"), r#"{"id": 1, "roles": []}"#).unwrap();
+        fs::write(dir.join("notes.txt"), "not a fixture").unwrap();
+        let server = FixtureServer::new().load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let fixtures = server.unwrap().fixtures;
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures["user"], json!({"id": 1, "roles": []}));
+    }
+}
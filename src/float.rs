@@ -0,0 +1,92 @@
+//! Rendering of floats and tolerances in one fixed format, so golden diff
+//! outputs don't change between targets or toolchains.
+//!
+//! The digits are the shortest ones which parse back to the same value, as
+//! computed by the `LowerExp` formatting of `core`, which is implemented in
+//! software on every target. Their layout is fixed here rather than left to
+//! `Debug`: positional for magnitudes from `1e-4` up to `1e16`, with at least
+//! one fractional digit, and `1.5e-7` style scientific notation outside that.
+//! Serialized values are rendered by `serde_json`, whose `ryu` formatting is
+//! fixed too.
+
+use std::fmt::LowerExp;
+
+/// Renders a float in the fixed format, e.g. `0.1`, `2.0`, `1e-7` or
+/// `-inf`.
+pub(crate) fn render(value: impl LowerExp) -> String {
+    let scientific = format!("{value:e}");
+    // `NaN`, `inf` and `-inf` have no exponent.
+    let Some((mantissa, exponent)) = scientific.split_once('e') else {
+        return scientific;
+    };
+    let exponent: i32 = exponent.parse().expect("float exponent is an integer");
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    if mantissa == "0" {
+        return format!("{sign}0.0");
+    }
+    if !(-4..16).contains(&exponent) {
+        return format!("{sign}{mantissa}e{exponent}");
+    }
+    let digits = mantissa.replace('.', "");
+    if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        return format!("{sign}0.{zeros}{digits}");
+    }
+    let integer_len = exponent as usize + 1;
+    if digits.len() <= integer_len {
+        let zeros = "0".repeat(integer_len - digits.len());
+        format!("{sign}{digits}{zeros}.0")
+    } else {
+        let (integer, fraction) = digits.split_at(integer_len);
+        format!("{sign}{integer}.{fraction}")
+    }
+}
+
+/// Renders a tolerance in the fixed format, without the fractional digit of
+/// whole numbers, e.g. `0.01`, `1` or `1e-9`.
+pub(crate) fn tolerance(value: f64) -> String {
+    let rendered = render(value);
+    match rendered.strip_suffix(".0") {
+        Some(whole) => whole.to_string(),
+        None => rendered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_positional_and_scientific_notation() {
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+            (1.0, "1.0"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (-1234.5, "-1234.5"),
+            (0.0001, "0.0001"),
+            (0.000015, "1.5e-5"),
+            (1e15, "1000000000000000.0"),
+            (1e16, "1e16"),
+            (f64::MAX, "1.7976931348623157e308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "NaN"),
+        ];
+        for &(value, rendered) in cases {
+            assert_eq!(render(value), rendered);
+        }
+        assert_eq!(render(0.1f32), "0.1");
+        assert_eq!(render(16_777_216f32), "16777216.0");
+    }
+
+    #[test]
+    fn renders_tolerances_without_trailing_zero() {
+        assert_eq!(tolerance(1.0), "1");
+        assert_eq!(tolerance(0.01), "0.01");
+        assert_eq!(tolerance(1e-9), "1e-9");
+    }
+}
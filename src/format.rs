@@ -0,0 +1,254 @@
+//! Output formats of macro failure messages, chosen with `CMP_FORMAT`.
+//!
+//! This is used by the macros through `cmp::__private` and is not part of
+//! the public API.
+
+use std::borrow::Cow;
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+
+use crate::{Diff, DiffRenderer, FieldDiff};
+
+/// Environment variable which selects the output format.
+const FORMAT_VAR: &str = "CMP_FORMAT";
+
+/// How the differences of a failed comparison are rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One `path: expected != actual` line per difference, with equal
+    /// subtrees [elided](Diff::elided) if `CMP_ELIDE` is set.
+    Text,
+    /// Rust statements setting each differing field of the expected value,
+    /// e.g. `expected.total = 100; // actual was 103`.
+    Rust,
+    /// The versioned format of [`StableRenderer`](crate::StableRenderer),
+    /// for scripts which parse failure messages.
+    Stable,
+    /// A JSON document on one line, listing the kind, path, and expected
+    /// and actual values of each difference, for CI dashboards.
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+impl Format {
+    /// Returns the format set with `CMP_FORMAT`, or [`Format::Text`].
+    pub fn from_env() -> Self {
+        match env::var(FORMAT_VAR).as_deref() {
+            Ok("rust") => Format::Rust,
+            Ok("stable") => Format::Stable,
+            #[cfg(feature = "serde")]
+            Ok("json") => Format::Json,
+            _ => Format::Text,
+        }
+    }
+
+    /// Renders `diff` for a panic message, with `expected` as the source
+    /// text of the expected expression.
+    pub fn render(self, diff: &Diff, expected: &str) -> String {
+        self.render_to(diff, expected, &io::stderr())
+    }
+
+    /// Renders `diff` to be written to `stream`, with the current
+    /// [`DiffRenderer`](crate::DiffRenderer) if one is set, or with colored
+    /// values if the `color` feature is enabled and `stream` is a terminal.
+    #[cfg_attr(not(feature = "color"), allow(unused_variables))]
+    pub(crate) fn render_to(self, diff: &Diff, expected: &str, stream: &impl IsTerminal) -> String {
+        match self {
+            Format::Text => {
+                let diff = if crate::elide::enabled() {
+                    Cow::Owned(diff.elided())
+                } else {
+                    Cow::Borrowed(diff)
+                };
+                if let Some(renderer) = crate::render::renderer() {
+                    return renderer.render(&diff);
+                }
+                #[cfg(feature = "color")]
+                if crate::color::enabled(stream) {
+                    return crate::color::paint(&diff);
+                }
+                diff.to_string()
+            }
+            Format::Rust => render_rust(diff, expected),
+            Format::Stable => crate::StableRenderer.render(diff),
+            #[cfg(feature = "serde")]
+            Format::Json => render_json(diff),
+        }
+    }
+}
+
+fn render_rust(diff: &Diff, expected: &str) -> String {
+    // Expressions other than paths can't be assigned to.
+    let is_path = expected
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ':'));
+    let target = if is_path { expected } else { "expected" };
+
+    let mut out = String::new();
+    for entry in diff {
+        let lhs = format!("{target}.{}", entry.path());
+        let _ = match entry {
+            FieldDiff::Changed {
+                expected, actual, ..
+            } => writeln!(
+                out,
+                "{lhs} = {}; // actual was {}",
+                literal(expected),
+                one_line(actual)
+            ),
+            FieldDiff::MissingFromActual { expected, .. } => {
+                writeln!(out, "{lhs} = {}; // missing from actual", literal(expected))
+            }
+            FieldDiff::MissingFromExpected { actual, .. } => writeln!(
+                out,
+                "// {lhs} missing from expected, actual was {}",
+                one_line(actual)
+            ),
+        };
+    }
+    out
+}
+
+/// Renders `diff` as a JSON document of the form
+/// `{"differences":[{"actual":"103","expected":"100","kind":"changed","path":"total","pointer":"/total"}]}`,
+/// with a `capped` count of the differences past the memory limit, if any.
+#[cfg(feature = "serde")]
+fn render_json(diff: &Diff) -> String {
+    let mut document = serde_json::json!({ "differences": json_entries(diff) });
+    if diff.capped() > 0 {
+        document["capped"] = diff.capped().into();
+    }
+    format!("{document}\n")
+}
+
+/// Returns the differences of `diff` as JSON objects with their `kind`,
+/// `path`, `pointer`, and `expected` and `actual` values, `null` for the
+/// missing side, and `"compared_by_debug": true` for differences found by
+/// comparing `Debug` output.
+#[cfg(feature = "serde")]
+pub(crate) fn json_entries(diff: &Diff) -> Vec<serde_json::Value> {
+    diff.iter()
+        .map(|entry| {
+            let (kind, expected, actual) = match entry {
+                FieldDiff::Changed {
+                    expected, actual, ..
+                } => ("changed", Some(expected), Some(actual)),
+                FieldDiff::MissingFromExpected { actual, .. } => {
+                    ("missing_from_expected", None, Some(actual))
+                }
+                FieldDiff::MissingFromActual { expected, .. } => {
+                    ("missing_from_actual", Some(expected), None)
+                }
+            };
+            let mut object = serde_json::json!({
+                "kind": kind,
+                "path": entry.path().to_string(),
+                "pointer": entry.path().to_json_pointer(),
+                "expected": expected,
+                "actual": actual,
+            });
+            if diff.is_debug_compared(entry) {
+                object["compared_by_debug"] = true.into();
+            }
+            object
+        })
+        .collect()
+}
+
+/// Renders a `Debug` value as a Rust expression, converting string literals
+/// with `.into()` so they fit `String` fields.
+fn literal(value: &str) -> String {
+    if value.starts_with('"') {
+        format!("{value}.into()")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Collapses a pretty-printed value to one line, to fit in a comment.
+fn one_line(value: &str) -> String {
+    value
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(", ]", " ]")
+        .replace(", )", " )")
+        .replace(", }", " }")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffPath;
+
+    #[test]
+    fn renders_rust_statements() {
+        let mut diff = Diff::new();
+        let mut path = DiffPath::new();
+        path.push_field("total");
+        diff.push(FieldDiff::Changed {
+            path: path.clone(),
+            expected: "100".into(),
+            actual: "103".into(),
+        });
+        path.pop();
+        path.push_field("tags");
+        path.push_index(1);
+        diff.push(FieldDiff::Changed {
+            path: path.clone(),
+            expected: "\"new\"".into(),
+            actual: "[\n    1,\n    2,\n]".into(),
+        });
+        path.pop();
+        diff.push(FieldDiff::MissingFromExpected {
+            path,
+            actual: "3".into(),
+        });
+
+        assert_eq!(
+            Format::Rust.render(&diff, "order"),
+            "order.total = 100; // actual was 103\n\
+             order.tags[1] = \"new\".into(); // actual was [ 1, 2 ]\n\
+             // order.tags missing from expected, actual was 3\n"
+        );
+        assert!(
+            Format::Rust
+                .render(&diff, "load(1)")
+                .starts_with("expected.total")
+        );
+        assert_eq!(Format::Text.render(&diff, "order"), diff.to_string());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn renders_a_json_document() {
+        let mut path = DiffPath::new();
+        path.push_field("items");
+        path.push_index(0);
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::Changed {
+            path: path.clone(),
+            expected: "\"a\"".into(),
+            actual: "\"b\"".into(),
+        });
+        diff.push(FieldDiff::MissingFromExpected {
+            path: path.clone(),
+            actual: "1".into(),
+        });
+        path.pop();
+        path.push_field("handle");
+        crate::diff_debug(&1, &2, &path, &mut diff);
+
+        let rendered = Format::Json.render(&diff, "order");
+        assert_eq!(rendered.lines().count(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&rendered).unwrap(),
+            serde_json::json!({"differences": [
+                {"kind": "changed", "path": "items[0]", "pointer": "/items/0", "expected": "\"a\"", "actual": "\"b\""},
+                {"kind": "missing_from_expected", "path": "items[0]", "pointer": "/items/0", "expected": null, "actual": "1"},
+                {"kind": "changed", "path": "items.handle", "pointer": "/items/handle", "expected": "1", "actual": "2", "compared_by_debug": true},
+            ]})
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Custom rendering of compared values of given types, such as durations as
+//! `1.5s` or large byte buffers as their length and checksum, separate from
+//! how they are compared.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, PoisonError, RwLock};
+
+static DEFAULT: RwLock<Option<Arc<ValueFormatters>>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<ValueFormatters>>> = const { RefCell::new(None) };
+}
+
+/// Renders compared values of type `T` in differences, instead of their
+/// pretty-printed `Debug` output.
+///
+/// Closures taking a `&T` and returning a `String` are formatters.
+pub trait ValueFormatter<T>: Send + Sync {
+    /// Renders `value`.
+    fn format(&self, value: &T) -> String;
+}
+
+impl<T, F: Fn(&T) -> String + Send + Sync> ValueFormatter<T> for F {
+    fn format(&self, value: &T) -> String {
+        self(value)
+    }
+}
+
+/// A formatter for one type, taking a pointer to a value of that type.
+type ErasedFormatter = dyn Fn(*const ()) -> String + Send + Sync;
+
+/// A registry of [`ValueFormatter`]s by the type they render.
+///
+/// Values of the registered types are rendered by their formatter wherever
+/// differences render a value whose type is known to be `'static`: leaf
+/// values, and fields compared with `PartialEq`. Types without a formatter
+/// still render with `Debug`, as do the elements and entries of collections,
+/// whose types may borrow, fields of borrowed types, comparisons by `Debug`
+/// output and serialized values, which render as JSON. Set the formatters for a call
+/// with [`with_value_formatters`], or for the whole process with
+/// [`set_value_formatters`]:
+///
+/// ```edition2024
+/// use std::time::Duration;
+///
+/// use cmp::{ValueFormatters, diff_structs, with_value_formatters};
+///
+/// struct Retry { backoff: Duration, payload: Vec<u8> }
+///
+/// let formatters = ValueFormatters::new()
+///     .with(|backoff: &Duration| format!("{}s", backoff.as_secs_f64()))
+///     .with(|bytes: &Vec<u8>| format!("<{} bytes>", bytes.len()));
+///
+/// let diff = with_value_formatters(formatters, || {
+///     diff_structs!(
+///         Retry { backoff: Duration::from_millis(1500), payload: vec![0; 4096] },
+///         Retry { backoff: Duration::from_secs(2), payload: vec![1; 4096] },
+///         backoff,
+///         payload
+///     )
+/// });
+/// assert_eq!(diff.to_string(), "backoff: 1.5s != 2s\npayload: <4096 bytes> != <4096 bytes>\n");
+/// ```
+#[derive(Clone, Default)]
+pub struct ValueFormatters {
+    formatters: HashMap<TypeId, Arc<ErasedFormatter>>,
+}
+
+impl ValueFormatters {
+    /// Creates a registry without formatters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders values of type `T` with `formatter`, replacing any formatter
+    /// of that type.
+    ///
+    /// A formatter for a type with lifetimes, such as `Cow<'static, str>`,
+    /// only renders values of exactly that type, not `Cow<'a, str>`.
+    pub fn with<T: 'static>(mut self, formatter: impl ValueFormatter<T> + 'static) -> Self {
+        let format = move |value: *const ()| {
+            // SAFETY: `format_value` only passes pointers to values whose
+            // `TypeId` is that of `T`.
+            formatter.format(unsafe { &*value.cast::<T>() })
+        };
+        self.formatters.insert(TypeId::of::<T>(), Arc::new(format));
+        self
+    }
+}
+
+impl Debug for ValueFormatters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueFormatters")
+            .field("formatters", &self.formatters.len())
+            .finish()
+    }
+}
+
+/// Sets the process-wide value formatters, used when none are set for a
+/// comparison.
+pub fn set_value_formatters(formatters: ValueFormatters) {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(formatters));
+}
+
+/// Resets the process-wide value formatters, rendering every value with
+/// `Debug`.
+pub fn clear_value_formatters() {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `compare` with `formatters` as the value formatters of the current
+/// thread, overriding the process-wide ones.
+pub fn with_value_formatters<R>(formatters: ValueFormatters, compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous formatters, even if `compare` panics.
+    struct Restore(Option<Arc<ValueFormatters>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let formatters = Arc::new(formatters);
+    let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(formatters)));
+    compare()
+}
+
+/// Renders a compared value with the [`ValueFormatter`] of its type, or
+/// pretty-printed with `Debug` if there is none.
+///
+/// This is how values are rendered in differences, for custom [`Diffable`]
+/// implementations which build their own [`FieldDiff`]s. Values of types
+/// which may borrow render with `Debug`.
+///
+/// [`Diffable`]: crate::Diffable
+/// [`FieldDiff`]: crate::FieldDiff
+pub fn format_value<T: Debug + ?Sized + 'static>(value: &T) -> String {
+    let formatters = SCOPED.with(|scoped| scoped.borrow().clone()).or_else(|| {
+        DEFAULT
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    });
+    let formatter =
+        formatters.and_then(|formatters| formatters.formatters.get(&TypeId::of::<T>()).cloned());
+    match formatter {
+        Some(format) => format((value as *const T).cast::<()>()),
+        None => render_debug(value),
+    }
+}
+
+/// Renders a compared value whose type may borrow pretty-printed with
+/// `Debug`, as formatters are only looked up for `'static` types.
+pub fn render_debug<T: Debug + ?Sized>(value: &T) -> String {
+    format!("{value:#?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn formats_registered_types_wherever_values_render() {
+        let formatters = ValueFormatters::new().with(|millis: &u64| format!("{millis}ms"));
+
+        let diff = with_value_formatters(formatters, || {
+            vec![Some(1000u64), None].compare(&vec![Some(2000), Some(0)])
+        });
+        assert_eq!(
+            diff.to_string(),
+            "[0]: 1000ms != 2000ms\n[1]: None != Some(\n    0,\n)\n"
+        );
+        assert_eq!(format_value(&1000u64), "1000");
+    }
+
+    #[test]
+    fn formats_owned_fields_of_borrowing_structs() {
+        #[derive(Debug, PartialEq)]
+        struct Token(String);
+
+        struct Login<'a> {
+            user: &'a str,
+            token: Token,
+        }
+
+        let user = String::from("ada");
+        let formatters = ValueFormatters::new().with(|_: &Token| "<token>".to_string());
+        let diff = with_value_formatters(formatters, || {
+            crate::diff_structs!(
+                Login {
+                    user: &user,
+                    token: Token("a".into())
+                },
+                Login {
+                    user: "bob",
+                    token: Token("b".into())
+                },
+                user,
+                token
+            )
+        });
+        assert_eq!(
+            diff.to_string(),
+            "user: \"ada\" != \"bob\"\ntoken: <token> != <token>\n"
+        );
+    }
+
+    #[test]
+    fn formats_only_values_of_the_exact_registered_type() {
+        use std::borrow::Cow;
+
+        let formatters = ValueFormatters::new()
+            .with(|value: &Cow<'static, str>| format!("<{} chars>", value.len()));
+        let (formatted, borrowed) = with_value_formatters(formatters, || {
+            let owned = String::from("borrowed");
+            (
+                format_value(&Cow::<'static, str>::Borrowed("static")),
+                render_debug(&Cow::Borrowed(owned.as_str())),
+            )
+        });
+        assert_eq!(formatted, "<6 chars>");
+        assert_eq!(borrowed, "\"borrowed\"");
+    }
+}
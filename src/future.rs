@@ -0,0 +1,358 @@
+//! Runtime-agnostic support for [`compare_async!`](crate::compare_async):
+//! polling two futures concurrently, with an optional timeout, while
+//! catching their panics.
+
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How one of the compared futures finished.
+#[derive(Debug)]
+pub enum Outcome<T> {
+    /// The future resolved to a value.
+    Ready(T),
+    /// The future panicked, with the panic message if it had one.
+    Panicked(String),
+    /// The timeout elapsed before the future resolved.
+    TimedOut(Duration),
+}
+
+impl<T> Outcome<T> {
+    /// Returns the describing line of a future which did not resolve, e.g.
+    /// `actual future timed out after 5s`.
+    pub fn failure(&self, side: &str) -> Option<String> {
+        match self {
+            Outcome::Ready(_) => None,
+            Outcome::Panicked(message) => Some(format!("{side} future panicked: {message}")),
+            Outcome::TimedOut(after) => Some(format!("{side} future timed out after {after:?}")),
+        }
+    }
+}
+
+enum Slot<F: Future> {
+    Pending(Pin<Box<F>>),
+    Done(Outcome<F::Output>),
+    Taken,
+}
+
+impl<F: Future> Slot<F> {
+    fn poll(&mut self, cx: &mut Context<'_>) {
+        if let Slot::Pending(future) = self {
+            match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                Ok(Poll::Ready(value)) => *self = Slot::Done(Outcome::Ready(value)),
+                Ok(Poll::Pending) => {}
+                Err(payload) => *self = Slot::Done(Outcome::Panicked(panic_message(&*payload))),
+            }
+        }
+    }
+
+    fn time_out(&mut self, after: Duration) {
+        if let Slot::Pending(_) = self {
+            *self = Slot::Done(Outcome::TimedOut(after));
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self, Slot::Done(_))
+    }
+
+    fn take(&mut self) -> Outcome<F::Output> {
+        match std::mem::replace(self, Slot::Taken) {
+            Slot::Done(outcome) => outcome,
+            _ => unreachable!("slot taken before completion"),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".to_string())
+}
+
+/// State shared with the timer thread.
+#[derive(Default)]
+struct Timer {
+    waker: Mutex<Option<Waker>>,
+    cancelled: AtomicBool,
+}
+
+/// The timeout of a [`Join`], whose timer thread exits early when it is
+/// dropped.
+struct Deadline {
+    at: Instant,
+    timer: Arc<Timer>,
+    thread: thread::Thread,
+}
+
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        self.timer.cancelled.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+/// Future returned by [`join`].
+pub struct Join<A: Future, B: Future> {
+    expected: Slot<A>,
+    actual: Slot<B>,
+    timeout: Option<Duration>,
+    deadline: Option<Deadline>,
+}
+
+// The futures are boxed and their outputs are never pinned, so `Join` can be
+// moved freely.
+impl<A: Future, B: Future> Unpin for Join<A, B> {}
+
+impl<A: Future, B: Future> fmt::Debug for Join<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Join")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Polls `expected` and `actual` concurrently until both finish, or until
+/// `timeout` elapses.
+///
+/// The timeout is measured from the first poll, using a helper thread to wake
+/// the task, so it works with any executor. The thread exits once the
+/// returned future completes or is dropped.
+pub fn join<A: Future, B: Future>(expected: A, actual: B, timeout: Option<Duration>) -> Join<A, B> {
+    Join {
+        expected: Slot::Pending(Box::pin(expected)),
+        actual: Slot::Pending(Box::pin(actual)),
+        timeout,
+        deadline: None,
+    }
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (Outcome<A::Output>, Outcome<B::Output>);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.expected.poll(cx);
+        this.actual.poll(cx);
+
+        if !(this.expected.is_done() && this.actual.is_done()) {
+            let Some(after) = this.timeout else {
+                return Poll::Pending;
+            };
+            let deadline = this.deadline.get_or_insert_with(|| start_timer(after));
+            if Instant::now() < deadline.at {
+                *deadline
+                    .timer
+                    .waker
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            this.expected.time_out(after);
+            this.actual.time_out(after);
+        }
+
+        this.deadline = None;
+        Poll::Ready((this.expected.take(), this.actual.take()))
+    }
+}
+
+fn start_timer(after: Duration) -> Deadline {
+    let at = Instant::now() + after;
+    let timer = Arc::new(Timer::default());
+    let thread_timer = Arc::clone(&timer);
+    let thread = thread::spawn(move || {
+        loop {
+            if thread_timer.cancelled.load(Ordering::Acquire) {
+                return;
+            }
+            // Parking may wake spuriously, so recheck the time.
+            let now = Instant::now();
+            if now >= at {
+                break;
+            }
+            thread::park_timeout(at - now);
+        }
+        if let Some(waker) = thread_timer
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            waker.wake();
+        }
+    })
+    .thread()
+    .clone();
+    Deadline { at, timer, thread }
+}
+
+/// Macro which awaits two futures concurrently and compares the structs they
+/// resolve to, like [`compare_structs!`](crate::compare_structs).
+///
+/// It must be used inside an `async` block or function, and works with any
+/// executor. Add `; timeout = duration` to stop waiting after `duration`. A
+/// future which panics or times out is reported as such, rather than as
+/// differing fields.
+///
+/// ```edition2024
+/// # use std::future::Future;
+/// # use std::pin::pin;
+/// # use std::task::{Context, Poll, Waker};
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     let mut future = pin!(future);
+/// #     let mut cx = Context::from_waker(Waker::noop());
+/// #     loop {
+/// #         if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+/// #             return value;
+/// #         }
+/// #     }
+/// # }
+/// use std::time::Duration;
+///
+/// use cmp::compare_async;
+///
+/// struct User { id: u32, name: &'static str }
+///
+/// async fn from_primary() -> User { User { id: 1, name: "ada" } }
+/// async fn from_replica() -> User { User { id: 1, name: "ada" } }
+///
+/// block_on(async {
+///     compare_async!(from_primary(), from_replica(), id, name; timeout = Duration::from_secs(5));
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics if either future panics or times out, or if any of the compared
+/// fields differ.
+#[macro_export]
+macro_rules! compare_async {
+    ($expected:expr, $actual:expr $(, $field:ident)* $(,)? $(; timeout = $timeout:expr)?) => {{
+        #[allow(unused_mut, unused_assignments)]
+        let mut timeout = ::core::option::Option::None;
+        $(timeout = ::core::option::Option::Some($timeout);)?
+
+        match $crate::__private::join($expected, $actual, timeout).await {
+            (
+                $crate::__private::Outcome::Ready(expected),
+                $crate::__private::Outcome::Ready(actual),
+            ) => $crate::compare_structs!(expected, actual $(, $field)*),
+            (expected, actual) => {
+                let failures: ::std::vec::Vec<::std::string::String> = expected
+                    .failure("expected")
+                    .into_iter()
+                    .chain(actual.failure("actual"))
+                    .collect();
+                panic!("{}", failures.join("\n"));
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::*;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                return value;
+            }
+            thread::park();
+        }
+    }
+
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    async fn point(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn compares_resolved_values() {
+        block_on(async {
+            compare_async!(point(1, 2), point(1, 3), x);
+            compare_async!(point(1, 2), point(1, 2), x, y; timeout = Duration::from_secs(5));
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "y: 2 != 3")]
+    fn reports_field_diffs() {
+        block_on(async {
+            compare_async!(point(1, 2), point(1, 3), x, y);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "actual future timed out after 20ms")]
+    fn reports_timeouts() {
+        block_on(async {
+            compare_async!(
+                point(1, 2),
+                std::future::pending::<Point>(),
+                x;
+                timeout = Duration::from_millis(20)
+            );
+        });
+    }
+
+    #[test]
+    fn reports_panics_distinctly() {
+        let (expected, actual) = block_on(join(
+            async { panic!("connection reset") },
+            point(1, 2),
+            None,
+        ));
+
+        assert!(matches!(actual, Outcome::Ready(Point { x: 1, y: 2 })));
+        assert_eq!(
+            expected.failure("expected").as_deref(),
+            Some("expected future panicked: connection reset")
+        );
+    }
+
+    #[test]
+    fn dropping_the_deadline_stops_the_timer_thread() {
+        let deadline = start_timer(Duration::from_secs(60));
+        let timer = Arc::downgrade(&deadline.timer);
+        drop(deadline);
+
+        let start = Instant::now();
+        while timer.strong_count() > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "timer still running"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! [`compare_getters!`](crate::compare_getters): comparing the getters of a
+//! builder or view type with the fields of a struct.
+
+use std::borrow::Borrow;
+use std::fmt::Debug;
+
+use crate::{Diff, FieldDiff};
+
+/// Compares the value returned by the getter `name`, either a `T` or a
+/// `&T`, with the `actual` field of the same name.
+pub fn diff_getter<T, G>(name: &str, expected: G, actual: &T, out: &mut Diff)
+where
+    T: PartialEq + Debug + ?Sized + 'static,
+    G: Borrow<T>,
+{
+    let expected = expected.borrow();
+    if expected != actual {
+        out.push(FieldDiff::Changed {
+            path: crate::__private::field_path(name),
+            expected: crate::format_value(expected),
+            actual: crate::format_value(actual),
+        });
+    }
+}
+
+/// Macro which compares the named fields of `actual` with the getters of the
+/// same name on `expected`, so expected values can come from test data
+/// builders or other types without materializing the same struct type.
+///
+/// Each getter must take `&self` and return either the field's type or a
+/// reference to it. The failure output is the same as
+/// [`compare_structs!`](crate::compare_structs)'s.
+///
+/// ```edition2024
+/// use cmp::compare_getters;
+///
+/// struct User { id: u32, name: String }
+///
+/// struct UserBuilder { name: String }
+///
+/// impl UserBuilder {
+///     fn id(&self) -> u32 { 1 }
+///     fn name(&self) -> &String { &self.name }
+/// }
+///
+/// let expected = UserBuilder { name: "ada".to_string() };
+/// let actual = User { id: 1, name: "ada".to_string() };
+///
+/// compare_getters!(expected, actual, id, name);
+/// ```
+///
+/// # Panics
+///
+/// Panics if any of the getters differ from the fields.
+#[macro_export]
+macro_rules! compare_getters {
+    ($expected:expr, $actual:expr, $($field:ident),+ $(,)?) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => {
+                let mut diff = $crate::Diff::new();
+                $(
+                    $crate::__private::diff_getter(
+                        stringify!($field),
+                        expected.$field(),
+                        &actual.$field,
+                        &mut diff,
+                    );
+                )+
+                $crate::__finish!(diff, $expected, $actual, expected, actual)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    struct Order {
+        id: u64,
+        items: Vec<&'static str>,
+    }
+
+    #[derive(Default)]
+    struct OrderBuilder {
+        items: Vec<&'static str>,
+    }
+
+    impl OrderBuilder {
+        fn item(mut self, item: &'static str) -> Self {
+            self.items.push(item);
+            self
+        }
+
+        fn id(&self) -> u64 {
+            42
+        }
+
+        fn items(&self) -> &Vec<&'static str> {
+            &self.items
+        }
+    }
+
+    #[test]
+    fn compares_owned_and_borrowed_getters() {
+        let actual = Order {
+            id: 42,
+            items: vec!["tea"],
+        };
+
+        compare_getters!(OrderBuilder::default().item("tea"), actual, id, items);
+    }
+
+    #[test]
+    #[should_panic(expected = "items: [\n    \"tea\",\n    \"cake\",\n] != [\n    \"tea\",\n]")]
+    fn reports_differing_getters() {
+        let actual = Order {
+            id: 42,
+            items: vec!["tea"],
+        };
+
+        compare_getters!(
+            OrderBuilder::default().item("tea").item("cake"),
+            actual,
+            id,
+            items
+        );
+    }
+}
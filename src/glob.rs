@@ -0,0 +1,60 @@
+//! Minimal glob matching used for key and path patterns.
+
+/// Returns whether `text` matches `pattern`, where `*` matches any run of
+/// characters (including none) and `?` matches exactly one character.
+/// Matching is ASCII case-insensitive.
+pub(crate) fn matches(pattern: &str, text: &str) -> bool {
+    matches_with(pattern, text, |p, t| p.eq_ignore_ascii_case(&t))
+}
+
+/// Like [`matches`], but characters must match exactly.
+pub(crate) fn matches_case_sensitive(pattern: &str, text: &str) -> bool {
+    matches_with(pattern, text, |p, t| p == t)
+}
+
+fn matches_with(pattern: &str, text: &str, eq: fn(char, char) -> bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || eq(c, text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*_TOKEN", "GITHUB_TOKEN"));
+        assert!(matches("*_token", "GITHUB_TOKEN"));
+        assert!(matches("DB_*_URL", "DB_PRIMARY_URL"));
+        assert!(matches("a?c", "abc"));
+        assert!(matches("*", ""));
+        assert!(!matches("*_TOKEN", "TOKEN_PATH"));
+        assert!(!matches("a?c", "ac"));
+        assert!(!matches_case_sensitive("*_token", "GITHUB_TOKEN"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Persistence of [`Diff`]s, and comparison of the diffs of two runs,
+//! enabled by the `serde` feature.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{Diff, FieldDiff};
+
+impl Diff {
+    /// Writes the diff to `path` as pretty-printed JSON, creating parent
+    /// directories as needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        json.push('\n');
+        fs::write(path, json)
+    }
+
+    /// Reads a diff written by [`save`](Diff::save).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error if the file is not a
+    /// valid diff.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// How the differences of one run changed since a previous run, as returned
+/// by [`compare_diffs`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffChanges {
+    /// Differences at paths which did not differ previously.
+    pub new: Vec<FieldDiff>,
+    /// Previous differences at paths which no longer differ.
+    pub resolved: Vec<FieldDiff>,
+    /// Differences at the same path with other values, as
+    /// `(previous, current)`.
+    pub changed: Vec<(FieldDiff, FieldDiff)>,
+    /// Number of differences identical in both runs.
+    pub unchanged: usize,
+}
+
+impl DiffChanges {
+    /// Returns whether any difference is new or changed, i.e. whether the
+    /// current run regressed compared with the previous one.
+    pub fn has_regressions(&self) -> bool {
+        !self.new.is_empty() || !self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for DiffChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.new.is_empty() {
+            writeln!(f, "new:")?;
+            for entry in &self.new {
+                writeln!(f, "  {entry}")?;
+            }
+        }
+        if !self.changed.is_empty() {
+            writeln!(f, "changed:")?;
+            for (previous, current) in &self.changed {
+                writeln!(f, "  {current} (was {previous})")?;
+            }
+        }
+        if !self.resolved.is_empty() {
+            writeln!(f, "resolved:")?;
+            for entry in &self.resolved {
+                writeln!(f, "  {entry}")?;
+            }
+        }
+        writeln!(f, "{} unchanged", self.unchanged)
+    }
+}
+
+/// Compares the differences of a previous run with those of the current one,
+/// matching them by path, so a nightly parity job can tell known differences
+/// from regressions.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::Diffable;
+///
+/// let yesterday = vec![1, 2, 3].compare(&vec![1, 0, 3]);
+/// let today = vec![1, 2, 3].compare(&vec![1, 0, 4]);
+///
+/// let changes = cmp::compare_diffs(&yesterday, &today);
+/// assert!(changes.has_regressions());
+/// assert_eq!(changes.new.len(), 1);
+/// assert_eq!(changes.unchanged, 1);
+/// # }
+/// ```
+pub fn compare_diffs(previous: &Diff, current: &Diff) -> DiffChanges {
+    let mut changes = DiffChanges::default();
+    for entry in current {
+        match previous.iter().find(|old| old.path() == entry.path()) {
+            Some(old) if old == entry => changes.unchanged += 1,
+            Some(old) => changes.changed.push((old.clone(), entry.clone())),
+            None => changes.new.push(entry.clone()),
+        }
+    }
+    changes.resolved = previous
+        .iter()
+        .filter(|old| !current.iter().any(|entry| entry.path() == old.path()))
+        .cloned()
+        .collect();
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffPath;
+
+    fn diff(entries: &[(&str, &str)]) -> Diff {
+        let mut diff = Diff::new();
+        for (field, actual) in entries {
+            let mut path = DiffPath::new();
+            path.push_field("items");
+            path.push_index(0);
+            path.push_key(field);
+            diff.push(FieldDiff::Changed {
+                path,
+                expected: "1".into(),
+                actual: actual.to_string(),
+            });
+        }
+        diff
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = std::env::temp_dir()
+            .join(format!("cmp-history-{}", std::process::id()))
+            .join("diff.json");
+        let saved = diff(&[("price", "2"), ("sku", "\"b\"")]);
+
+        saved.save(&path).unwrap();
+        let loaded = Diff::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded, saved);
+    }
+
+    #[test]
+    fn classifies_changes_by_path() {
+        let previous = diff(&[("price", "2"), ("sku", "3"), ("tax", "4")]);
+        let current = diff(&[("price", "2"), ("sku", "5"), ("qty", "6")]);
+
+        let changes = compare_diffs(&previous, &current);
+
+        assert_eq!(
+            changes.to_string(),
+            "new:\n  \
+             items[0][\"qty\"]: 1 != 6\n\
+             changed:\n  \
+             items[0][\"sku\"]: 1 != 5 (was items[0][\"sku\"]: 1 != 3)\n\
+             resolved:\n  \
+             items[0][\"tax\"]: 1 != 4\n\
+             1 unchanged\n"
+        );
+        assert!(!compare_diffs(&previous, &previous).has_regressions());
+    }
+}
@@ -0,0 +1,376 @@
+//! Comparison of HTML fragments as parsed DOM trees, enabled by the `html`
+//! feature.
+//!
+//! Fragments are parsed leniently: tag and attribute names are
+//! case-insensitive, attribute order is ignored, runs of whitespace in text
+//! collapse to a single space (except inside `<pre>`, `<textarea>`,
+//! `<script>` and `<style>`), and comments are dropped. Only the first
+//! difference, in document order, is reported.
+
+use std::collections::BTreeMap;
+
+use crate::markup::{self, AttributeName, Element, Node};
+
+pub use crate::markup::ParseError;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Compares two HTML fragments, returning the first difference if any.
+///
+/// ```edition2024
+/// let diff = cmp::html::diff(
+///     r#"<ul class="menu"><li>Home</li><li><a href="/a">About</a></li></ul>"#,
+///     r#"<UL   class="menu">
+///         <li>Home</li>
+///         <li><a href="/b">About</a></li>
+///     </UL>"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(diff.as_deref(), Some(r#"/ul/li[2]/a/@href: "/a" != "/b""#));
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`ParseError`] if either side contains an unterminated tag or
+/// comment.
+pub fn diff(expected: &str, actual: &str) -> Result<Option<String>, ParseError> {
+    let expected = parse(expected).map_err(|err| ParseError::new("expected", err))?;
+    let actual = parse(actual).map_err(|err| ParseError::new("actual", err))?;
+
+    let mut diffs = Vec::new();
+    markup::diff_nodes(&expected, &actual, "", &mut diffs);
+    Ok(diffs.into_iter().next())
+}
+
+fn parse(input: &str) -> Result<Vec<Node>, String> {
+    let mut parser = Parser {
+        input,
+        position: 0,
+        open: Vec::new(),
+        roots: Vec::new(),
+    };
+    parser.run()?;
+    while parser.close_innermost() {}
+    Ok(parser.roots)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    open: Vec<Element>,
+    roots: Vec<Node>,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        while !self.rest().is_empty() {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                let end = rest
+                    .find("-->")
+                    .ok_or_else(|| format!("unterminated comment at byte {}", self.position))?;
+                self.position += end + 3;
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                let end = rest
+                    .find('>')
+                    .ok_or_else(|| format!("unterminated declaration at byte {}", self.position))?;
+                self.position += end + 1;
+            } else if let Some(tag) = rest.strip_prefix("</") {
+                let end = tag
+                    .find('>')
+                    .ok_or_else(|| format!("unterminated end tag at byte {}", self.position))?;
+                let name = tag[..end].trim().to_ascii_lowercase();
+                self.position += end + 3;
+                self.end_tag(&name);
+            } else if rest.starts_with('<')
+                && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+            {
+                self.start_tag()?;
+            } else {
+                let skip = rest.chars().next().map_or(0, char::len_utf8);
+                let end = rest[skip..].find('<').map_or(rest.len(), |end| end + skip);
+                let text = decode_entities(&rest[..end]);
+                self.position += end;
+                self.text(text);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_tag(&mut self) -> Result<(), String> {
+        let start = self.position;
+        let tag = &self.rest()[1..];
+        let name_len = tag
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_len].to_ascii_lowercase();
+        let mut attributes = BTreeMap::new();
+        let mut cursor = &tag[name_len..];
+
+        let self_closing = loop {
+            cursor = cursor.trim_start();
+            if let Some(after) = cursor.strip_prefix("/>") {
+                cursor = after;
+                break true;
+            }
+            if let Some(after) = cursor.strip_prefix('>') {
+                cursor = after;
+                break false;
+            }
+            if let Some(after) = cursor.strip_prefix('/') {
+                cursor = after;
+                continue;
+            }
+            if cursor.is_empty() {
+                return Err(format!("unterminated start tag at byte {start}"));
+            }
+
+            let attr_len = cursor
+                .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+                .unwrap_or(cursor.len());
+            let attr_name = cursor[..attr_len].to_ascii_lowercase();
+            cursor = cursor[attr_len..].trim_start();
+            let value = if let Some(after) = cursor.strip_prefix('=') {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..]
+                            .find(quote)
+                            .ok_or_else(|| format!("unterminated attribute at byte {start}"))?;
+                        (&after[1..=end], &after[end + 2..])
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(after.len());
+                        after.split_at(end)
+                    }
+                };
+                cursor = remaining;
+                decode_entities(value)
+            } else {
+                String::new()
+            };
+            attributes.insert(AttributeName::local(attr_name), value);
+        };
+
+        self.position = self.input.len() - cursor.len();
+        let element = Element {
+            name,
+            namespace: None,
+            attributes,
+            children: Vec::new(),
+        };
+
+        if self_closing || VOID_ELEMENTS.contains(&element.name.as_str()) {
+            self.push(Node::Element(element));
+        } else if RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) {
+            let close = format!("</{}", element.name);
+            let rest = self.rest();
+            let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            let text = rest[..end].to_string();
+            self.position += end;
+            self.open.push(element);
+            self.text(text);
+        } else {
+            self.open.push(element);
+        }
+        Ok(())
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        if self.open.iter().any(|element| element.name == name) {
+            while let Some(element) = self.open.last() {
+                let matched = element.name == name;
+                self.close_innermost();
+                if matched {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn close_innermost(&mut self) -> bool {
+        match self.open.pop() {
+            Some(element) => {
+                self.push(Node::Element(element));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn text(&mut self, text: String) {
+        let preformatted = self
+            .open
+            .iter()
+            .any(|element| PREFORMATTED_ELEMENTS.contains(&element.name.as_str()));
+        let text = if preformatted {
+            text
+        } else {
+            text.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+        if !text.is_empty() {
+            self.push(Node::Text(text));
+        }
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.roots.push(node),
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let replacement = entity.and_then(|(name, end)| {
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                _ => {
+                    let code = match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)?
+                }
+            };
+            Some((c, end))
+        });
+        match replacement {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Macro which compares HTML-containing string fields as DOM fragments.
+///
+/// With two arguments, the expressions themselves are compared. With field
+/// identifiers, each listed field of the two structs is compared and the
+/// first differing element or attribute path of each field is reported.
+///
+/// ```edition2024
+/// use cmp::compare_html;
+/// # struct Page { body: String }
+/// let expected = Page { body: r#"<p class="a" id="x">Hello <b>world</b></p>"#.to_string() };
+/// let actual = Page { body: "<p id=x class=a>\n  Hello\n  <b>world</b>\n</p>".to_string() };
+///
+/// compare_html!(expected, actual, body);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the fragments differ, or if either side cannot be parsed.
+#[macro_export]
+macro_rules! compare_html {
+    ($expected:expr, $actual:expr) => {{
+        let diff = $crate::html::diff(&$expected, &$actual).unwrap_or_else(|err| panic!("{err}"));
+
+        assert!(diff.is_none(), "{}\n", diff.unwrap_or_default());
+    }};
+    ($expected:expr, $actual:expr, $($field:ident),+) => {{
+        let mut diffs = String::new();
+        $(
+            let field_diff = $crate::html::diff(&$expected.$field, &$actual.$field)
+                .unwrap_or_else(|err| panic!("{}: {err}", stringify!($field)));
+            if let Some(diff) = field_diff {
+                diffs.push_str(&format!("{}{}\n", stringify!($field), diff));
+            }
+        )+
+
+        assert!(diffs.is_empty(), "{diffs}");
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_formatting_and_attribute_order() {
+        let expected = r#"<div id="a" class="b"><br><p>one &amp; two</p></div>"#;
+        let actual =
+            "<DIV class='b' id=a>\n  <br/>\n  <!-- note -->\n  <p>one &#38;   two</p>\n</div>";
+
+        assert_eq!(diff(expected, actual).unwrap(), None);
+    }
+
+    #[test]
+    fn reports_first_difference_only() {
+        let expected = "<p>a</p><p title='x'>b</p><p>c</p>";
+        let actual = "<p>a</p><p title='y'>b</p><p>d</p>";
+
+        assert_eq!(
+            diff(expected, actual).unwrap().as_deref(),
+            Some(r#"/p[2]/@title: "x" != "y""#)
+        );
+    }
+
+    #[test]
+    fn preserves_whitespace_in_pre() {
+        assert!(diff("<pre>a  b</pre>", "<pre>a b</pre>").unwrap().is_some());
+        assert!(
+            diff("<span>a  b</span>", "<span>a b</span>")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn reports_unterminated_tags() {
+        let err = diff("<p>", "<p class='a").unwrap_err();
+
+        assert!(err.to_string().starts_with("Could not parse actual value"));
+    }
+
+    #[test]
+    #[should_panic(expected = "body/ul/li[2]")]
+    fn compare_html_fields_panic() {
+        struct Page {
+            body: &'static str,
+        }
+
+        let expected = Page {
+            body: "<ul><li>a</li><li>b</li></ul>",
+        };
+        let actual = Page {
+            body: "<ul><li>a</li><li><b>b</b></li></ul>",
+        };
+
+        compare_html!(expected, actual, body);
+    }
+}
@@ -0,0 +1,873 @@
+//! [`Diffable`] implementations for standard library types.
+//!
+//! Leaf values are compared with `PartialEq`. Containers recurse into their
+//! elements, so a difference deep inside a `Vec<Option<User>>` is reported at
+//! its own path (`users[3].name`) rather than as the whole collection.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, LowerExp};
+use std::hash::{BuildHasher, Hash};
+use std::marker::{PhantomData, PhantomPinned};
+use std::num::Wrapping;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::{Diff, DiffPath, Diffable, FieldDiff, diff_eq};
+
+macro_rules! impl_diffable_eq {
+    ($($ty:ty),+) => {
+        $(
+            impl Diffable for $ty {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    diff_eq(self, other, path, out);
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_eq!(str, String, ());
+
+/// Implements [`Diffable`] for primitives, comparing slices of them at once.
+macro_rules! impl_diffable_primitive {
+    ($($ty:ty),+) => {
+        $(
+            impl Diffable for $ty {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    diff_eq(self, other, path, out);
+                }
+
+                fn slices_equal(expected: &[Self], actual: &[Self]) -> bool {
+                    expected == actual
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_primitive!(bool, char);
+impl_diffable_primitive!(i8, i16, i32, i64, i128, isize);
+impl_diffable_primitive!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_diffable_float {
+    ($($ty:ty),+) => {
+        $(
+            impl Diffable for $ty {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    diff_float(*self, *other, path, out);
+                }
+
+                fn diff_with_epsilon(
+                    &self,
+                    other: &Self,
+                    epsilon: f64,
+                    path: &mut DiffPath,
+                    out: &mut Diff,
+                ) {
+                    let within =
+                        self == other || (f64::from(*self) - f64::from(*other)).abs() <= epsilon;
+                    if !within {
+                        diff_float(*self, *other, path, out);
+                    }
+                }
+
+                fn slices_equal(expected: &[Self], actual: &[Self]) -> bool {
+                    expected == actual
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_float!(f32, f64);
+
+/// Reports differing floats, rendered in the fixed float format.
+fn diff_float<T: PartialEq + LowerExp>(expected: T, actual: T, path: &DiffPath, out: &mut Diff) {
+    if expected != actual {
+        out.push_with(|| FieldDiff::Changed {
+            path: path.clone(),
+            expected: crate::float::render(expected),
+            actual: crate::float::render(actual),
+        });
+    }
+}
+
+/// Compares `expected` and `actual`, forwarding `epsilon` when one is set.
+pub(crate) fn diff_value<T: Diffable + ?Sized>(
+    expected: &T,
+    actual: &T,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    match epsilon {
+        Some(epsilon) => expected.diff_with_epsilon(actual, epsilon, path, out),
+        None => expected.diff(actual, path, out),
+    }
+}
+
+impl<T: Diffable + ?Sized> Diffable for &T {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        (**self).diff(*other, path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        (**self).diff_with_epsilon(*other, epsilon, path, out);
+    }
+}
+
+macro_rules! impl_diffable_deref {
+    ($($ty:ident),+) => {
+        $(
+            impl<T: Diffable + ?Sized> Diffable for $ty<T> {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    (**self).diff(other, path, out);
+                }
+
+                fn diff_with_epsilon(
+                    &self,
+                    other: &Self,
+                    epsilon: f64,
+                    path: &mut DiffPath,
+                    out: &mut Diff,
+                ) {
+                    (**self).diff_with_epsilon(other, epsilon, path, out);
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_deref!(Box, Rc, Arc);
+
+/// Locks both sides before comparing. Comparing a lock with itself is a
+/// no-op rather than a deadlock, and poisoned locks are compared anyway.
+impl<T: Diffable + ?Sized> Diffable for Mutex<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        if !ptr::eq(self, other) {
+            let expected = self.lock().unwrap_or_else(PoisonError::into_inner);
+            let actual = other.lock().unwrap_or_else(PoisonError::into_inner);
+            expected.diff(&actual, path, out);
+        }
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        if !ptr::eq(self, other) {
+            let expected = self.lock().unwrap_or_else(PoisonError::into_inner);
+            let actual = other.lock().unwrap_or_else(PoisonError::into_inner);
+            expected.diff_with_epsilon(&actual, epsilon, path, out);
+        }
+    }
+}
+
+/// Takes a read lock on both sides before comparing, with the same
+/// self-comparison and poisoning behaviour as the `Mutex` implementation.
+impl<T: Diffable + ?Sized> Diffable for RwLock<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        if !ptr::eq(self, other) {
+            let expected = self.read().unwrap_or_else(PoisonError::into_inner);
+            let actual = other.read().unwrap_or_else(PoisonError::into_inner);
+            expected.diff(&actual, path, out);
+        }
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        if !ptr::eq(self, other) {
+            let expected = self.read().unwrap_or_else(PoisonError::into_inner);
+            let actual = other.read().unwrap_or_else(PoisonError::into_inner);
+            expected.diff_with_epsilon(&actual, epsilon, path, out);
+        }
+    }
+}
+
+impl<T: Diffable + Copy> Diffable for Cell<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        self.get().diff(&other.get(), path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        self.get()
+            .diff_with_epsilon(&other.get(), epsilon, path, out);
+    }
+}
+
+/// Borrows both sides before comparing.
+///
+/// # Panics
+///
+/// Panics if either side is currently mutably borrowed.
+impl<T: Diffable + ?Sized> Diffable for RefCell<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        self.borrow().diff(&other.borrow(), path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        self.borrow()
+            .diff_with_epsilon(&other.borrow(), epsilon, path, out);
+    }
+}
+
+impl<T: Diffable> Diffable for Wrapping<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        self.0.diff(&other.0, path, out);
+    }
+}
+
+/// Marker types never differ, whatever their type parameter.
+impl<T: ?Sized> Diffable for PhantomData<T> {
+    fn diff(&self, _other: &Self, _path: &mut DiffPath, _out: &mut Diff) {}
+}
+
+impl Diffable for PhantomPinned {
+    fn diff(&self, _other: &Self, _path: &mut DiffPath, _out: &mut Diff) {}
+}
+
+impl<T: Diffable + Debug> Diffable for Option<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_option(self, other, None, path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        diff_option(self, other, Some(epsilon), path, out);
+    }
+}
+
+fn diff_option<T: Diffable + Debug>(
+    expected: &Option<T>,
+    actual: &Option<T>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    match (expected, actual) {
+        (Some(expected), Some(actual)) => diff_value(expected, actual, epsilon, path, out),
+        (None, None) => {}
+        _ => push_changed(expected, actual, path, out),
+    }
+}
+
+impl<T: Diffable + Debug, E: Diffable + Debug> Diffable for Result<T, E> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_result(self, other, None, path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        diff_result(self, other, Some(epsilon), path, out);
+    }
+}
+
+fn diff_result<T: Diffable + Debug, E: Diffable + Debug>(
+    expected: &Result<T, E>,
+    actual: &Result<T, E>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    match (expected, actual) {
+        (Ok(expected), Ok(actual)) => diff_value(expected, actual, epsilon, path, out),
+        (Err(expected), Err(actual)) => diff_value(expected, actual, epsilon, path, out),
+        _ => push_changed(expected, actual, path, out),
+    }
+}
+
+fn push_changed<T: Debug + ?Sized>(expected: &T, actual: &T, path: &DiffPath, out: &mut Diff) {
+    out.push_with(|| FieldDiff::Changed {
+        path: path.clone(),
+        expected: crate::formatters::render_debug(expected),
+        actual: crate::formatters::render_debug(actual),
+    });
+}
+
+/// Compares sequences index by index, reporting trailing elements on either
+/// side as missing.
+///
+/// With [sequence context](crate::set_sequence_context), equal elements next
+/// to differing ones are recorded as context lines. [Unordered
+/// sequences](crate::with_unordered_sequences) are compared as multisets
+/// instead.
+fn diff_seq<'a, T: Diffable + Debug + 'a>(
+    expected: impl Iterator<Item = &'a T>,
+    actual: impl Iterator<Item = &'a T>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    if crate::unordered::unordered() {
+        let (expected, actual): (Vec<_>, Vec<_>) = (expected.collect(), actual.collect());
+        diff_unordered(&expected, &actual, epsilon, path, out);
+        return;
+    }
+    let context = crate::context::sequence_context();
+    if context > 0 {
+        let (expected, actual): (Vec<_>, Vec<_>) = (expected.collect(), actual.collect());
+        diff_seq_with_context(&expected, &actual, epsilon, context, path, out);
+        return;
+    }
+
+    let mut expected = expected.fuse();
+    let mut actual = actual.fuse();
+    for index in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => break,
+            (expected, actual) => {
+                path.push_index(index);
+                diff_element(expected, actual, epsilon, path, out);
+                path.pop();
+            }
+        }
+    }
+}
+
+fn diff_seq_with_context<T: Diffable + Debug>(
+    expected: &[&T],
+    actual: &[&T],
+    epsilon: Option<f64>,
+    context: usize,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    let found: Vec<Diff> = (0..expected.len().max(actual.len()))
+        .map(|index| {
+            let mut found = Diff::new();
+            path.push_index(index);
+            diff_element(
+                expected.get(index).copied(),
+                actual.get(index).copied(),
+                epsilon,
+                path,
+                &mut found,
+            );
+            path.pop();
+            found
+        })
+        .collect();
+    let differs: Vec<bool> = found.iter().map(|found| !found.is_empty()).collect();
+    let shown = crate::context::context_mask(&differs, context);
+
+    for (index, found) in found.into_iter().enumerate() {
+        if shown[index] {
+            path.push_index(index);
+            out.push_context(
+                path.clone(),
+                crate::formatters::render_debug(&expected[index]),
+            );
+            path.pop();
+        } else {
+            out.append(found);
+        }
+    }
+}
+
+/// Compares sequences regardless of order, pairing each expected element with
+/// an equal actual one, or else with the closest remaining one.
+fn diff_unordered<T: Diffable + Debug>(
+    expected: &[&T],
+    actual: &[&T],
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    let (pairs, extra) = crate::unordered::pair(expected, actual, |expected, actual| {
+        let mut found = Diff::counting();
+        diff_value(
+            *expected,
+            *actual,
+            epsilon,
+            &mut DiffPath::new(),
+            &mut found,
+        );
+        found.capped()
+    });
+    for (index, paired) in pairs {
+        path.push_index(index);
+        diff_element(
+            Some(expected[index]),
+            paired.map(|at| actual[at]),
+            epsilon,
+            path,
+            out,
+        );
+        path.pop();
+    }
+    for index in extra {
+        path.push_index(index);
+        diff_element(None, Some(actual[index]), epsilon, path, out);
+        path.pop();
+    }
+}
+
+/// Compares the elements at one index of two sequences, either of which may
+/// be past the end of its sequence.
+fn diff_element<T: Diffable + Debug>(
+    expected: Option<&T>,
+    actual: Option<&T>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    match (expected, actual) {
+        (Some(expected), Some(actual)) => diff_value(expected, actual, epsilon, path, out),
+        (Some(expected), None) => out.push_with(|| FieldDiff::MissingFromActual {
+            path: path.clone(),
+            expected: crate::formatters::render_debug(expected),
+        }),
+        (None, Some(actual)) => out.push_with(|| FieldDiff::MissingFromExpected {
+            path: path.clone(),
+            actual: crate::formatters::render_debug(actual),
+        }),
+        (None, None) => {}
+    }
+}
+
+/// Implements [`Diffable`] for sequences, checking
+/// [`slices_equal`](Diffable::slices_equal) first for contiguous ones.
+macro_rules! impl_diffable_seq {
+    ($([$($generics:tt)*] $ty:ty $(, $as_slice:ident)?);+ $(;)?) => {
+        $(
+            impl<$($generics)*> Diffable for $ty {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    $(if T::slices_equal(self.$as_slice(), other.$as_slice()) {
+                        return;
+                    })?
+                    diff_seq(self.iter(), other.iter(), None, path, out);
+                }
+
+                fn diff_with_epsilon(
+                    &self,
+                    other: &Self,
+                    epsilon: f64,
+                    path: &mut DiffPath,
+                    out: &mut Diff,
+                ) {
+                    $(if T::slices_equal(self.$as_slice(), other.$as_slice()) {
+                        return;
+                    })?
+                    diff_seq(self.iter(), other.iter(), Some(epsilon), path, out);
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_seq!(
+    [T: Diffable + Debug] [T], as_ref;
+    [T: Diffable + Debug, const N: usize] [T; N], as_slice;
+    [T: Diffable + Debug] Vec<T>, as_slice;
+    [T: Diffable + Debug] VecDeque<T>;
+);
+
+/// Sorts hash-ordered items by the `Debug` rendering of `key`, so the output
+/// is stable between runs.
+fn sorted_by_debug<T, K: Debug + ?Sized>(
+    items: impl Iterator<Item = T>,
+    key: impl Fn(&T) -> &K,
+) -> Vec<T> {
+    let mut items: Vec<T> = items.collect();
+    items.sort_by_cached_key(|item| format!("{:?}", key(item)));
+    items
+}
+
+/// Compares map entries key by key, then reports keys only present in
+/// `actual`.
+///
+/// Keys missing from `actual` are paired with keys only present in `actual`
+/// by the current [`KeyMatcher`](crate::KeyMatcher), comparing their `Debug`
+/// renderings without quotes.
+fn diff_map<'a, K: Debug + 'a, V: Diffable + Debug + 'a>(
+    expected: Vec<(&'a K, &'a V)>,
+    actual: impl Fn(&K) -> Option<&'a V>,
+    mut missing_from_expected: Vec<(&'a K, &'a V)>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    let matcher = crate::keys::key_matcher();
+    let unquoted = |key: &K| {
+        let key = format!("{key:?}");
+        match key.strip_prefix('"').and_then(|key| key.strip_suffix('"')) {
+            Some(inner) => inner.to_string(),
+            None => key,
+        }
+    };
+    for (key, expected) in expected {
+        path.push_key(key);
+        let actual = actual(key).or_else(|| {
+            if matches!(matcher, crate::KeyMatcher::Exact) {
+                return None;
+            }
+            let name = unquoted(key);
+            let at = missing_from_expected
+                .iter()
+                .position(|(other, _)| matcher.matches(&name, &unquoted(other)))?;
+            Some(missing_from_expected.remove(at).1)
+        });
+        match actual {
+            Some(actual) => diff_value(expected, actual, epsilon, path, out),
+            None => out.push_with(|| FieldDiff::MissingFromActual {
+                path: path.clone(),
+                expected: crate::formatters::render_debug(expected),
+            }),
+        }
+        path.pop();
+    }
+    for (key, actual) in missing_from_expected {
+        path.push_key(key);
+        out.push_with(|| FieldDiff::MissingFromExpected {
+            path: path.clone(),
+            actual: crate::formatters::render_debug(actual),
+        });
+        path.pop();
+    }
+}
+
+fn diff_hash_map<K: Debug + Eq + Hash, V: Diffable + Debug, S: BuildHasher>(
+    expected: &HashMap<K, V, S>,
+    actual: &HashMap<K, V, S>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    let extra = actual
+        .iter()
+        .filter(|(key, _)| !expected.contains_key(*key));
+    diff_map(
+        sorted_by_debug(expected.iter(), |(key, _)| *key),
+        |key| actual.get(key),
+        sorted_by_debug(extra, |(key, _)| *key),
+        epsilon,
+        path,
+        out,
+    );
+}
+
+fn diff_btree_map<K: Debug + Ord, V: Diffable + Debug>(
+    expected: &BTreeMap<K, V>,
+    actual: &BTreeMap<K, V>,
+    epsilon: Option<f64>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    let extra = actual
+        .iter()
+        .filter(|(key, _)| !expected.contains_key(*key));
+    diff_map(
+        expected.iter().collect(),
+        |key| actual.get(key),
+        extra.collect(),
+        epsilon,
+        path,
+        out,
+    );
+}
+
+impl<K: Debug + Eq + Hash, V: Diffable + Debug, S: BuildHasher> Diffable for HashMap<K, V, S> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_hash_map(self, other, None, path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        diff_hash_map(self, other, Some(epsilon), path, out);
+    }
+}
+
+impl<K: Debug + Ord, V: Diffable + Debug> Diffable for BTreeMap<K, V> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_btree_map(self, other, None, path, out);
+    }
+
+    fn diff_with_epsilon(&self, other: &Self, epsilon: f64, path: &mut DiffPath, out: &mut Diff) {
+        diff_btree_map(self, other, Some(epsilon), path, out);
+    }
+}
+
+/// Reports set elements only present on one side, keyed by the element.
+fn diff_set<T: Debug>(
+    missing_from_actual: Vec<&T>,
+    missing_from_expected: Vec<&T>,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    for item in missing_from_actual {
+        path.push_key(item);
+        out.push_with(|| FieldDiff::MissingFromActual {
+            path: path.clone(),
+            expected: crate::formatters::render_debug(item),
+        });
+        path.pop();
+    }
+    for item in missing_from_expected {
+        path.push_key(item);
+        out.push_with(|| FieldDiff::MissingFromExpected {
+            path: path.clone(),
+            actual: crate::formatters::render_debug(item),
+        });
+        path.pop();
+    }
+}
+
+impl<T: Debug + Eq + Hash, S: BuildHasher> Diffable for HashSet<T, S> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_set(
+            sorted_by_debug(self.difference(other), |item| *item),
+            sorted_by_debug(other.difference(self), |item| *item),
+            path,
+            out,
+        );
+    }
+}
+
+impl<T: Debug + Ord> Diffable for BTreeSet<T> {
+    fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+        diff_set(
+            self.difference(other).collect(),
+            other.difference(self).collect(),
+            path,
+            out,
+        );
+    }
+}
+
+macro_rules! impl_diffable_tuple {
+    ($(($($name:ident $index:tt),+))+) => {
+        $(
+            impl<$($name: Diffable),+> Diffable for ($($name,)+) {
+                fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                    $(
+                        path.push_field(stringify!($index));
+                        self.$index.diff(&other.$index, path, out);
+                        path.pop();
+                    )+
+                }
+
+                fn diff_with_epsilon(
+                    &self,
+                    other: &Self,
+                    epsilon: f64,
+                    path: &mut DiffPath,
+                    out: &mut Diff,
+                ) {
+                    $(
+                        path.push_field(stringify!($index));
+                        self.$index.diff_with_epsilon(&other.$index, epsilon, path, out);
+                        path.pop();
+                    )+
+                }
+            }
+        )+
+    };
+}
+
+impl_diffable_tuple! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, cmp_derive::Diffable)]
+    struct Server {
+        host: &'static str,
+        port: u16,
+    }
+
+    #[derive(Debug, cmp_derive::Diffable)]
+    struct Config {
+        servers: Vec<Server>,
+        limits: HashMap<&'static str, Option<f64>>,
+        tags: BTreeSet<&'static str>,
+        origin: (f32, f32),
+    }
+
+    fn config() -> Config {
+        Config {
+            servers: vec![
+                Server {
+                    host: "a",
+                    port: 80,
+                },
+                Server {
+                    host: "b",
+                    port: 81,
+                },
+            ],
+            limits: HashMap::from([("cpu", Some(0.5)), ("mem", None)]),
+            tags: BTreeSet::from(["x", "y"]),
+            origin: (0.0, 0.0),
+        }
+    }
+
+    fn paths(diff: &Diff) -> Vec<String> {
+        diff.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn nested_types_compose_recursively() {
+        let mut actual = config();
+        actual.servers[1].port = 8081;
+        actual.limits.insert("cpu", Some(0.75));
+        actual.origin.1 = 1.5;
+
+        assert_eq!(
+            paths(&config().compare(&actual)),
+            [
+                "servers[1].port: 81 != 8081",
+                r#"limits["cpu"]: 0.5 != 0.75"#,
+                "origin.1: 0.0 != 1.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_elements_and_keys() {
+        let mut actual = config();
+        actual.servers.pop();
+        actual.limits.remove("mem");
+        actual.limits.insert("disk", None);
+        actual.tags.insert("z");
+
+        assert_eq!(
+            paths(&config().compare(&actual)),
+            [
+                "servers[1]: field missing from actual: Server {\n    host: \"b\",\n    port: 81,\n}",
+                r#"limits["mem"]: field missing from actual: None"#,
+                r#"limits["disk"]: field missing from expected: None"#,
+                r#"tags["z"]: field missing from expected: "z""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn option_mismatch_reports_whole_values() {
+        let mut actual = config();
+        actual.limits.insert("mem", Some(1.0));
+
+        assert_eq!(
+            paths(&config().compare(&actual)),
+            [r#"limits["mem"]: None != Some(
+    1.0,
+)"#]
+        );
+    }
+
+    #[test]
+    fn compares_through_pointers_and_locks() {
+        #[derive(cmp_derive::Diffable)]
+        struct Shared {
+            config: Arc<Mutex<Vec<u8>>>,
+            cache: Rc<RefCell<Option<String>>>,
+            hits: Cell<u32>,
+            seq: Wrapping<u8>,
+            name: Box<str>,
+            gauges: RwLock<HashMap<&'static str, f64>>,
+        }
+
+        let shared = || Shared {
+            config: Arc::new(Mutex::new(vec![1, 2])),
+            cache: Rc::new(RefCell::new(None)),
+            hits: Cell::new(3),
+            seq: Wrapping(255),
+            name: "a".into(),
+            gauges: RwLock::new(HashMap::from([("load", 0.5)])),
+        };
+        let actual = shared();
+        actual.config.lock().unwrap()[1] = 3;
+        *actual.cache.borrow_mut() = Some("hit".into());
+        actual.hits.set(4);
+        actual.gauges.write().unwrap().insert("load", 0.7);
+
+        assert_eq!(
+            paths(&shared().compare(&actual)),
+            [
+                "config[1]: 2 != 3",
+                "cache: None != Some(\n    \"hit\",\n)",
+                "hits: 3 != 4",
+                r#"gauges["load"]: 0.5 != 0.7"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn comparing_a_lock_with_itself_does_not_deadlock() {
+        let lock = Mutex::new(1);
+
+        assert!(lock.compare(&lock).is_empty());
+    }
+
+    #[test]
+    fn epsilon_is_forwarded_to_elements() {
+        let expected = vec![Some(1.0), None];
+        let actual = vec![Some(1.0 + 1e-12), None];
+        let mut out = Diff::new();
+
+        expected.diff_with_epsilon(&actual, 1e-9, &mut DiffPath::new(), &mut out);
+
+        assert!(out.is_empty());
+        assert_eq!(expected.compare(&actual).len(), 1);
+    }
+
+    #[test]
+    fn equal_primitive_slices_skip_elementwise_diffing() {
+        /// Claims every slice is equal, to show elements aren't compared.
+        #[derive(Debug, PartialEq)]
+        struct Opaque(u8);
+
+        impl Diffable for Opaque {
+            fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+                diff_eq(self, other, path, out);
+            }
+
+            fn slices_equal(_: &[Self], _: &[Self]) -> bool {
+                true
+            }
+        }
+
+        assert!(vec![Opaque(1)].compare(&vec![Opaque(2)]).is_empty());
+        assert_eq!(
+            VecDeque::from([Opaque(1)])
+                .compare(&VecDeque::from([Opaque(2)]))
+                .len(),
+            1
+        );
+
+        let buffer = vec![7u8; 1 << 20];
+        let mut changed = buffer.clone();
+        changed[1000] = 8;
+        assert!(buffer.compare(&buffer.clone()).is_empty());
+        assert_eq!(paths(&buffer.compare(&changed)), ["[1000]: 7 != 8"]);
+        assert_eq!([f64::NAN].compare(&[f64::NAN]).len(), 1);
+    }
+
+    #[test]
+    fn records_equal_neighbours_as_context() {
+        let expected = [vec![1, 2, 3], vec![4], vec![5], vec![6], vec![7]];
+        let actual = [vec![1, 0, 3], vec![4], vec![5], vec![6]];
+        let [expected, actual] =
+            [&expected[..], &actual[..]].map(|seq| seq.iter().collect::<Vec<_>>());
+        let mut out = Diff::new();
+
+        diff_seq_with_context(&expected, &actual, None, 1, &mut DiffPath::new(), &mut out);
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            out.to_string(),
+            "[0][1]: 2 != 0\n\
+             [1]: [\n    4,\n] (equal)\n\
+             [3]: [\n    6,\n] (equal)\n\
+             [4]: field missing from actual: [\n    7,\n]\n"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Visible rendering of whitespace in differing string values.
+//!
+//! `Debug` already escapes tabs, line endings and zero-width characters,
+//! but not spaces, so `"foo "` and `"foo"` can look the same at a glance.
+//! Spaces at either end of a string are shown as `␠`, and so are all spaces
+//! when the strings differ only in whitespace. Byte-order marks and the
+//! replacement characters left by lossy UTF-8 decoding are called out in a
+//! [note], as they are common causes of strings read from files looking
+//! identical.
+
+use std::borrow::Cow;
+
+/// Stands in for a space.
+const SPACE: &str = "␠";
+
+/// `Debug` escape of a byte-order mark.
+const BOM: &str = "\\u{feff}";
+
+/// Left in place of invalid UTF-8 by lossy decoding, and not escaped by
+/// `Debug`.
+const REPLACEMENT: char = '\u{fffd}';
+
+/// Escapes of invisible characters in `Debug` output, besides spaces.
+const INVISIBLE: &[&str] = &[
+    "\\t",
+    "\\r",
+    "\\n",
+    "\\u{a0}",
+    "\\u{200b}",
+    "\\u{200c}",
+    "\\u{200d}",
+    "\\u{2060}",
+    "\\u{feff}",
+];
+
+/// Marks the spaces of two `Debug`-rendered string literals, or returns
+/// other values unchanged.
+pub(crate) fn mark<'a>(expected: &'a str, actual: &'a str) -> (Cow<'a, str>, Cow<'a, str>) {
+    let (Some(expected_inner), Some(actual_inner)) = (inner(expected), inner(actual)) else {
+        return (expected.into(), actual.into());
+    };
+    let all = without_whitespace(expected_inner) == without_whitespace(actual_inner);
+    (
+        mark_one(expected, expected_inner, all),
+        mark_one(actual, actual_inner, all),
+    )
+}
+
+/// Returns whether two `Debug`-rendered string literals differ only in
+/// whitespace, so all their spaces should be marked.
+pub(crate) fn only_whitespace_differs(expected: &str, actual: &str) -> bool {
+    match (inner(expected), inner(actual)) {
+        (Some(expected), Some(actual)) => {
+            without_whitespace(expected) == without_whitespace(actual)
+        }
+        _ => false,
+    }
+}
+
+/// Describes encoding differences between two `Debug`-rendered string
+/// literals, or returns `None` if there are none or the values aren't
+/// strings.
+pub(crate) fn note(expected: &str, actual: &str) -> Option<String> {
+    let sides = [("expected", inner(expected)?), ("actual", inner(actual)?)];
+    let mut notes = Vec::new();
+
+    let boms = sides.map(|(_, inner)| (inner.starts_with(BOM), inner.matches(BOM).count()));
+    if boms[0] != boms[1] {
+        for ((side, _), (leading, count)) in sides.iter().zip(boms) {
+            match (leading, count) {
+                (true, _) => notes.push(format!("{side} starts with a byte-order mark")),
+                (false, 0) => {}
+                (false, _) => notes.push(format!("{side} contains a byte-order mark")),
+            }
+        }
+    }
+
+    let replacements = sides.map(|(_, inner)| inner.matches(REPLACEMENT).count());
+    if replacements[0] != replacements[1] {
+        for ((side, _), count) in sides.iter().zip(replacements) {
+            let plural = if count == 1 { "" } else { "s" };
+            if count > 0 {
+                notes.push(format!(
+                    "{side} contains {count} replacement character{plural} (U+FFFD) from invalid UTF-8"
+                ));
+            }
+        }
+    }
+
+    (!notes.is_empty()).then(|| notes.join("; "))
+}
+
+/// Returns the contents of a string literal.
+fn inner(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn without_whitespace(inner: &str) -> String {
+    INVISIBLE
+        .iter()
+        .fold(inner.replace(' ', ""), |text, escape| {
+            text.replace(escape, "")
+        })
+}
+
+fn mark_one<'a>(value: &'a str, inner: &str, all: bool) -> Cow<'a, str> {
+    match mark_line(inner, all) {
+        Cow::Borrowed(_) => value.into(),
+        Cow::Owned(marked) => format!("\"{marked}\"").into(),
+    }
+}
+
+/// Marks the spaces at either end of the contents of a string literal, or a
+/// line of one, or all of them with `all`.
+pub(crate) fn mark_line(inner: &str, all: bool) -> Cow<'_, str> {
+    if !(all && inner.contains(' ') || inner.starts_with(' ') || inner.ends_with(' ')) {
+        return inner.into();
+    }
+    if all {
+        inner.replace(' ', SPACE).into()
+    } else {
+        let start = inner.len() - inner.trim_start_matches(' ').len();
+        let end = inner.trim_end_matches(' ').len().max(start);
+        format!(
+            "{}{}{}",
+            SPACE.repeat(start),
+            &inner[start..end],
+            SPACE.repeat(inner.len() - end)
+        )
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(expected: &str, actual: &str) -> String {
+        let (expected, actual) = mark(expected, actual);
+        format!("{expected} != {actual}")
+    }
+
+    #[test]
+    fn marks_spaces_at_either_end() {
+        assert_eq!(marked(r#""foo ""#, r#""foo""#), r#""foo␠" != "foo""#);
+        assert_eq!(marked(r#""  a b ""#, r#""x y""#), r#""␠␠a b␠" != "x y""#);
+        assert_eq!(marked(r#""   ""#, r#""a""#), r#""␠␠␠" != "a""#);
+    }
+
+    #[test]
+    fn marks_all_spaces_when_only_whitespace_differs() {
+        assert_eq!(
+            marked(r#""a b\tc""#, r#""a\tb c""#),
+            r#""a␠b\tc" != "a\tb␠c""#
+        );
+        assert_eq!(
+            marked(r#""line one\r\n""#, r#""line one\n""#),
+            r#""line␠one\r\n" != "line␠one\n""#
+        );
+        assert_eq!(
+            marked(r#""a b""#, r#""a\u{a0}b""#),
+            r#""a␠b" != "a\u{a0}b""#
+        );
+    }
+
+    #[test]
+    fn notes_byte_order_marks_and_replacement_characters() {
+        assert_eq!(
+            note(r#""\u{feff}id""#, r#""id""#).as_deref(),
+            Some("expected starts with a byte-order mark")
+        );
+        assert_eq!(
+            note(r#""a\u{feff}b""#, r#""\u{feff}ab""#).as_deref(),
+            Some("expected contains a byte-order mark; actual starts with a byte-order mark")
+        );
+        assert_eq!(
+            note(r#""caf�""#, r#""café""#).as_deref(),
+            Some("expected contains 1 replacement character (U+FFFD) from invalid UTF-8")
+        );
+        assert_eq!(note(r#""\u{feff}a""#, r#""\u{feff}b""#), None);
+        assert_eq!(note(r#""a""#, "None"), None);
+    }
+
+    #[test]
+    fn leaves_other_values_unchanged() {
+        assert_eq!(marked("1 ", "2"), "1  != 2");
+        assert_eq!(marked(r#""a ""#, "None"), r#""a " != None"#);
+        assert_eq!(marked("\"", "\""), "\" != \"");
+    }
+}
@@ -0,0 +1,206 @@
+//! Strategies for matching up the keys of compared maps and serialized
+//! structs, chosen per call, per derived type or process-wide.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::similarity;
+
+/// A function telling whether an expected and an actual key match.
+type MatchFn = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+static DEFAULT: RwLock<Option<KeyMatcher>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<KeyMatcher>> = const { RefCell::new(None) };
+}
+
+/// How the keys of an expected object are matched with those of an actual
+/// one, so values named after different conventions can still be compared.
+///
+/// It is used by `compare_structs!` in serde mode, by
+/// [`assert_struct_snapshot!`](crate::assert_struct_snapshot), and by the
+/// [`Diffable`](crate::Diffable) implementations of maps, whose keys are
+/// matched by their `Debug` rendering without quotes. Exact matches are
+/// always preferred, and differences are reported at the expected key.
+///
+/// Set the matcher for one comparison with [`with_key_matcher`], for a
+/// derived type with `#[cmp(key_matcher = ...)]`, or for the whole process
+/// with [`set_key_matcher`]:
+///
+/// ```edition2024
+/// use std::collections::HashMap;
+///
+/// use cmp::{Diffable, KeyMatcher};
+///
+/// let expected = HashMap::from([("Content-Type", "text/html")]);
+/// let actual = HashMap::from([("content-type", "text/html")]);
+///
+/// assert_eq!(expected.compare(&actual).len(), 2);
+/// assert!(cmp::with_key_matcher(KeyMatcher::CaseInsensitive, || expected.compare(&actual)).is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub enum KeyMatcher {
+    /// Keys must be equal. This is the default.
+    #[default]
+    Exact,
+    /// Keys are equal ignoring ASCII and Unicode case.
+    CaseInsensitive,
+    /// Keys are the same words in any naming convention, so `user_name`,
+    /// `userName`, `UserName` and `user-name` match.
+    SameWords,
+    /// Keys match if the function, called with the expected and actual keys,
+    /// returns `true`.
+    Custom(Arc<MatchFn>),
+}
+
+impl KeyMatcher {
+    /// Creates a matcher from a function called with the expected and actual
+    /// keys.
+    pub fn custom(matches: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        KeyMatcher::Custom(Arc::new(matches))
+    }
+
+    /// Returns whether `expected` and `actual` are the same key.
+    pub fn matches(&self, expected: &str, actual: &str) -> bool {
+        match self {
+            KeyMatcher::Exact => expected == actual,
+            KeyMatcher::CaseInsensitive => expected.to_lowercase() == actual.to_lowercase(),
+            KeyMatcher::SameWords => similarity::same_words(expected, actual),
+            KeyMatcher::Custom(matches) => matches(expected, actual),
+        }
+    }
+
+    /// Pairs each expected key with the actual key it matches, preferring
+    /// exact matches, and each actual key with at most one expected key.
+    ///
+    /// Returns the pairs in the order of `expected`, and the actual keys left
+    /// over in the order of `actual`.
+    pub fn pair<'a>(
+        &self,
+        expected: impl IntoIterator<Item = &'a str>,
+        actual: impl IntoIterator<Item = &'a str>,
+    ) -> (Vec<(&'a str, Option<&'a str>)>, Vec<&'a str>) {
+        let actual: Vec<&str> = actual.into_iter().collect();
+        let positions: HashMap<&str, usize> = actual
+            .iter()
+            .enumerate()
+            .map(|(index, &key)| (key, index))
+            .collect();
+        let mut unmatched: Vec<Option<&str>> = actual.into_iter().map(Some).collect();
+
+        let mut pairs: Vec<(&str, Option<&str>)> = expected
+            .into_iter()
+            .map(|key| (key, positions.get(key).and_then(|&at| unmatched[at].take())))
+            .collect();
+        if !matches!(self, KeyMatcher::Exact) {
+            for (key, paired) in pairs.iter_mut().filter(|(_, paired)| paired.is_none()) {
+                *paired = unmatched
+                    .iter_mut()
+                    .find(|candidate| {
+                        candidate.is_some_and(|candidate| self.matches(key, candidate))
+                    })
+                    .and_then(Option::take);
+            }
+        }
+        (pairs, unmatched.into_iter().flatten().collect())
+    }
+}
+
+impl fmt::Debug for KeyMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMatcher::Exact => f.write_str("Exact"),
+            KeyMatcher::CaseInsensitive => f.write_str("CaseInsensitive"),
+            KeyMatcher::SameWords => f.write_str("SameWords"),
+            KeyMatcher::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Sets the process-wide key matcher, used when no other is set for a
+/// comparison.
+pub fn set_key_matcher(matcher: KeyMatcher) {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = Some(matcher);
+}
+
+/// Resets the process-wide key matcher to [`KeyMatcher::Exact`].
+pub fn clear_key_matcher() {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `compare` with `matcher` as the key matcher of the current thread,
+/// overriding the process-wide one.
+pub fn with_key_matcher<R>(matcher: KeyMatcher, compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous matcher, even if `compare` panics.
+    struct Restore(Option<KeyMatcher>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(matcher)));
+    compare()
+}
+
+/// Returns the key matcher of the current comparison.
+pub fn key_matcher() -> KeyMatcher {
+    SCOPED
+        .with(|scoped| scoped.borrow().clone())
+        .or_else(|| {
+            DEFAULT
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_keys_by_strategy() {
+        assert!(KeyMatcher::CaseInsensitive.matches("ETag", "etag"));
+        assert!(!KeyMatcher::CaseInsensitive.matches("user_name", "userName"));
+        assert!(KeyMatcher::SameWords.matches("user_name", "userName"));
+        assert!(KeyMatcher::custom(|e, a| e.trim_start_matches('@') == a).matches("@id", "id"));
+        assert!(!KeyMatcher::Exact.matches("id", "ID"));
+    }
+
+    #[test]
+    fn pairs_exact_matches_first() {
+        let (pairs, extra) =
+            KeyMatcher::CaseInsensitive.pair(["id", "Name", "ID"], ["ID", "name", "id", "age"]);
+
+        assert_eq!(
+            pairs,
+            [
+                ("id", Some("id")),
+                ("Name", Some("name")),
+                ("ID", Some("ID"))
+            ]
+        );
+        assert_eq!(extra, ["age"]);
+
+        let (pairs, extra) = KeyMatcher::Exact.pair(["id", "Name"], ["name", "id"]);
+        assert_eq!(pairs, [("id", Some("id")), ("Name", None)]);
+        assert_eq!(extra, ["name"]);
+    }
+
+    #[test]
+    fn scopes_matchers_to_a_call() {
+        let inner = with_key_matcher(KeyMatcher::SameWords, || {
+            with_key_matcher(KeyMatcher::CaseInsensitive, key_matcher);
+            key_matcher()
+        });
+
+        assert!(matches!(inner, KeyMatcher::SameWords));
+        assert!(SCOPED.with(|scoped| scoped.borrow().is_none()));
+    }
+}
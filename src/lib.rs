@@ -20,6 +20,403 @@
 //! compare_structs!(struct_a, struct_b, a);
 //! ```
 //!
+//! # Environment and config maps
+//!
+//! The [`compare_env!`] macro compares string maps key by key, masking the
+//! values of keys that look like secrets (`*_TOKEN`, `*_SECRET`,
+//! `*_PASSWORD`) in the output while still comparing them.
+//!
+//! ```edition2024
+//! use cmp::compare_env;
+//! use std::collections::HashMap;
+//!
+//! let expected = HashMap::from([("PORT", "8080"), ("API_TOKEN", "secret")]);
+//! let actual = HashMap::from([("PORT", "8080"), ("API_TOKEN", "secret")]);
+//!
+//! compare_env!(&expected, &actual);
+//! ```
+//!
+//! # Expression capture
+//!
+//! When the compared expressions are method chains rather than simple
+//! bindings, set `CMP_CAPTURE=1` to print, like the `assert2` crate, the
+//! source text of both expressions above the field diffs, followed by their
+//! values if they implement `Debug`:
+//!
+//! ```text
+//! expected: fixtures::user().with_name("ada")
+//!   User {
+//!       name: "ada",
+//!   }
+//! actual: client.fetch_user(1).unwrap()
+//!   User {
+//!       name: "bob",
+//!   }
+//!
+//! name: "ada" != "bob"
+//! ```
+//!
+//! Each expression is evaluated once, whether or not capture is enabled.
+//!
+//! # Suggested expected values
+//!
+//! Set `CMP_SUGGEST=1` to print, below the differences, a ready-to-paste
+//! struct literal with the actual values of the differing fields:
+//!
+//! ```text
+//! suggested expected value:
+//! Order {
+//!     total: 103,
+//!     status: "shipped".into(),
+//!     // other fields unchanged
+//! }
+//! ```
+//!
+//! # Output formats
+//!
+//! Set `CMP_FORMAT=rust` to render the differences as Rust statements
+//! updating the expected value, which is handy when fixtures are built in
+//! Rust source:
+//!
+//! ```text
+//! expected.total = 100; // actual was 103
+//! expected.status = "paid".into(); // actual was "pending"
+//! ```
+//!
+//! Set `CMP_FORMAT=stable`, or use [`StableRenderer`], for a versioned,
+//! line-based format whose layout only changes in major releases, for
+//! scripts which parse failure messages.
+//!
+//! With the `serde` feature, set `CMP_FORMAT=json` to render the differences
+//! as a JSON document on one line, which CI dashboards can pick out of the
+//! test output and aggregate:
+//!
+//! ```text
+//! {"differences":[{"actual":"103","expected":"100","kind":"changed","path":"total","pointer":"/total"}]}
+//! ```
+//!
+//! The text output can be replaced by a [`DiffRenderer`], such as
+//! [`TextRenderer`] or, with the `serde` feature, `JsonRenderer`, set for one
+//! call with [`with_renderer`] or for the process with [`set_renderer`].
+//!
+//! # Invisible characters
+//!
+//! Differing strings are rendered with `Debug`, which escapes tabs, line
+//! endings and zero-width characters. Spaces at either end of a string, and
+//! all spaces when the strings differ only in whitespace, are also shown as
+//! `␠`, e.g. `name: "ada␠" != "ada"`. Byte-order marks and the U+FFFD
+//! replacement characters left by lossy UTF-8 decoding are called out after
+//! the values, e.g. `(expected starts with a byte-order mark)`.
+//!
+//! # Value formatting
+//!
+//! [`ValueFormatters`] render the values of chosen types in differences
+//! instead of `Debug`, e.g. durations as `1.5s` or large byte buffers as
+//! `<4096 bytes>`, without changing how they are compared. Set them for a
+//! call with [`with_value_formatters`] or for the process with
+//! [`set_value_formatters`]; custom [`Diffable`] implementations render
+//! values with [`format_value`] to honor them.
+//!
+//! # Elision
+//!
+//! When a large nested value differs in one leaf, run the tests with
+//! `CMP_ELIDE=1` to show both sides with the subtrees which are equal
+//! collapsed to `…`, keeping field names and the path to each difference.
+//! [`Diff::elided`] does the same for a [`Diff`].
+//!
+//! # Sequence context
+//!
+//! Run the tests with `CMP_CONTEXT=N`, or call [`set_sequence_context`], to
+//! show up to `N` equal elements before and after each differing element of
+//! a sequence compared with [`Diffable`], like the context lines of a text
+//! diff. [`Diff::push_context`] records such lines in custom implementations;
+//! they don't count as differences.
+//!
+//! # Line diffs
+//!
+//! Differing multiline strings longer than [`DEFAULT_LINE_DIFF_THRESHOLD`]
+//! bytes are rendered as a line diff, with `-` before expected lines, `+`
+//! before actual lines and up to three equal lines of context, instead of in
+//! full. Run the tests with `CMP_LINE_DIFF=N`, or call
+//! [`set_line_diff_threshold`], to change the length, or with `0` to always
+//! print strings in full.
+//!
+//! # Output budgets
+//!
+//! So one huge field doesn't crowd out the differences of the others,
+//! [`with_max_render_len`], [`set_max_render_len`],
+//! `#[cmp(max_render_len = 200)]` on a derived field or
+//! `field max_render_len = 200` in [`compare_structs!`] cut each rendered
+//! value to fewer than that many bytes, ending with the number of bytes left
+//! out.
+//!
+//! # Non-panicking comparisons
+//!
+//! [`diff_structs!`] takes the same arguments as [`compare_structs!`] but
+//! returns the [`Diff`], whose [`FieldDiff`] entries give each differing
+//! field's path and rendered values, for custom reporting. With the `serde`
+//! feature, the function [`diff()`] does the same as
+//! `compare_structs!(expected, actual)` without a macro, for library code.
+//! [`try_compare_structs!`] returns `Result<(), StructDiff>` instead, so a
+//! harness can run many comparisons and aggregate the failures. A
+//! [`Comparer`] does that for soft assertions: it records each comparison
+//! passed to [`Comparer::check`] and panics once with every failure when it
+//! finishes, so a table-driven test reports all mismatched rows.
+//!
+//! # Negated comparisons
+//!
+//! [`compare_structs_ne!`] takes the same arguments as [`compare_structs!`]
+//! and asserts that the structs differ in at least one compared field, for
+//! mutation and cache-busting tests.
+//!
+//! # Summaries
+//!
+//! [`Diff::summarize`] describes the differences in one line of prose, e.g.
+//! `` `status` changed from Pending to Failed; 3 items added to `errors`;
+//! `retries` increased by 2 ``, for alerts and reports where field-level
+//! entries are too low-level.
+//!
+//! # Key matching
+//!
+//! A [`KeyMatcher`] pairs up the keys of compared maps and serialized
+//! structs which follow different conventions, e.g. case-insensitive HTTP
+//! header names or `user_name` against `userName`. Set it for one comparison
+//! with [`with_key_matcher`] or `compare_structs!(a, b; key_matcher = ...)`,
+//! for a derived type with `#[cmp(key_matcher = ...)]`, or for the process
+//! with [`set_key_matcher`].
+//!
+//! # Quiet comparisons
+//!
+//! [`compare_quiet`] returns whether two [`Diffable`] values are equal
+//! without rendering any difference, recording metrics or panicking, for
+//! benchmarks and property-test generators.
+//!
+//! # Memory limit
+//!
+//! A [`Diff`] stops recording differences once its entries use about
+//! [`DEFAULT_DIFF_MEMORY_LIMIT`] bytes, and only [counts](Diff::capped) the
+//! rest, so a badly wrong comparison can't exhaust memory. Set the limit
+//! with `CMP_MAX_DIFF_BYTES` or [`set_diff_memory_limit`].
+//!
+//! # Compact encoding
+//!
+//! [`Diff::to_compact_bytes`] encodes a diff in a compact binary form, with
+//! each repeated string stored once, for services shipping diffs over the
+//! network, and [`Diff::from_compact_bytes`] decodes it, within the memory
+//! limit. With the `compression` feature, `Diff::to_compressed_bytes` and
+//! `Diff::from_compressed_bytes` deflate and inflate the encoding too.
+//!
+//! # Messages
+//!
+//! The fixed text of rendered differences, such as `!=` and `field missing
+//! from actual`, comes from [`Messages`], whose methods default to English.
+//! Override them to localize the output or match a house report style, and
+//! set them for the process with [`set_messages`] or for one call with
+//! [`with_messages`].
+//!
+//! # Provenance
+//!
+//! Wrap either side with [`sourced`] to name where it came from, such as a
+//! fixture file or an API response. The value is used as-is, and failure
+//! messages start with a line per sourced side, e.g.
+//! `actual (api) from POST /users response`.
+//!
+//! # Partial expected values
+//!
+//! [`compare_partial!`] compares only the fields named in a struct literal,
+//! without needing `Default` for the others:
+//!
+//! ```edition2024,ignore
+//! compare_partial!(response, Response { status: Status::Ok, count: 3 });
+//! ```
+//!
+//! [`compare_getters!`] compares the fields of a struct with the getters of
+//! the same name on any other type, such as a test data builder:
+//!
+//! ```edition2024,ignore
+//! compare_getters!(UserBuilder::new().name("ada"), user, id, name);
+//! ```
+//!
+//! # Async comparisons
+//!
+//! [`compare_async!`] awaits two futures concurrently, with an optional
+//! timeout, and compares the structs they resolve to. Futures which panic or
+//! time out are reported as such, rather than as field differences. It works
+//! with any executor.
+//!
+//! # Eventually consistent systems
+//!
+//! [`assert_eventually_eq!`] re-evaluates an expression until its fields
+//! match the expected struct, or a timeout elapses. A timeout reports which
+//! fields converged over the attempts and which never matched:
+//!
+//! ```edition2024,ignore
+//! assert_eventually_eq!(fetch_state(), expected, status, replicas; timeout = 5s, interval = 100ms);
+//! ```
+//!
+//! # Plugins
+//!
+//! [`Comparison`] compares two [`Diffable`] values like
+//! [`Diffable::compare`], running every registered [`ComparePlugin`] on the
+//! differences found. Plugins can drop paths, rewrite rendered values (for
+//! redaction or normalization) and observe each recorded difference:
+//!
+//! ```edition2024,ignore
+//! let diff = Comparison::new(&expected, &actual).plugin(Redact).plugin(Lowercase).run();
+//! ```
+//!
+//! [`Comparison::override_path`] adds a table of glob patterns to
+//! [`PathRule`]s, which override the [default epsilon](Comparison::epsilon)
+//! for the matching paths and everything below them:
+//!
+//! ```edition2024,ignore
+//! let diff = Comparison::new(&expected, &actual)
+//!     .epsilon(1e-9)
+//!     .override_path("items[*].price", epsilon(0.01))
+//!     .override_path("*.id", ignore())
+//!     .run();
+//! ```
+//!
+//! With the `serde` feature, [`Comparison::plan`] lists the paths which
+//! would be compared and the rule for each without comparing anything, like
+//! `compare_structs!(a, b; dry_run)` does for serde mode, to help author
+//! ignore lists for large payloads.
+//!
+//! Run with `CMP_EXPLAIN=1` to print which rule or plugin decided the
+//! outcome of each difference a [`Comparison`] finds, and which rules it
+//! shadowed.
+//!
+//! # Aggregate comparisons
+//!
+//! [`compare_collections!`] compares two collections element by element and
+//! passes if at most `tolerate_mismatch_rate` of the elements differ, still
+//! printing a summary of the mismatches, which suits noisy outputs. Fields
+//! can be weighted, to rank them by importance and decide on a weighted score:
+//!
+//! ```edition2024,ignore
+//! compare_collections!(expected, actual; tolerate_mismatch_rate = 0.1%);
+//! compare_collections!(expected, actual; weight("[*].label") = 5.0, max_weighted_score = 0.2);
+//! ```
+//!
+//! Set `CMP_SAMPLE_SEED` (or `sample_seed = n`) to show a reproducible
+//! sample of the differing elements rather than the first ones.
+//!
+//! # Chunked comparisons
+//!
+//! A [`ChunkedComparer`] compares sequences too large to load at once, fed
+//! corresponding chunks of both sides as they are read, and returns the
+//! combined [`Diff`] from `finish`, reporting elements at their index in the
+//! whole sequence:
+//!
+//! ```edition2024,ignore
+//! let mut comparer = cmp::ChunkedComparer::new();
+//! while let (Some(expected), Some(actual)) = (expected_rows.next_chunk()?, actual_rows.next_chunk()?) {
+//!     comparer.push(expected, actual);
+//! }
+//! let diff = comparer.finish();
+//! ```
+//!
+//! # Comparison coverage
+//!
+//! [`assert_comparison_covers!`] checks that a type's [`Diffable`] comparison
+//! detects a change to each listed field, so an over-aggressive `ignore`
+//! list can't silently mask regressions:
+//!
+//! ```edition2024,ignore
+//! assert_comparison_covers!(Order, [id, total, items]);
+//! ```
+//!
+//! [`assert_compares_all_fields!`] fails to compile unless the listed
+//! fields, compared and ignored, are all the fields of a struct, so adding
+//! a field forces the tests to decide about it:
+//!
+//! ```edition2024,ignore
+//! assert_compares_all_fields!(Order, [id, total], ignore: [updated_at]);
+//! ```
+//!
+//! # Self-checks
+//!
+//! Run the tests with `CMP_SELF_CHECK=1` to also check, after every
+//! [`Diffable::compare`], that each value compares equal to itself and that
+//! comparing the values the other way round finds the inverted differences.
+//! A violation, e.g. from a custom comparator or a `NaN` field, panics with a
+//! [`self_check::SelfCheckFailure`] describing it.
+//!
+//! # Differential testing
+//!
+//! [`differential_check()`] runs two implementations on the same input and
+//! compares their outputs. A [`Divergence`] has a compact signature of where
+//! the outputs differ, without values, which suits a cargo-fuzz oracle:
+//!
+//! ```edition2024,ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     if let Err(divergence) = cmp::differential_check(data, parse_v1, parse_v2) {
+//!         panic!("{divergence}");
+//!     }
+//! });
+//! ```
+//!
+//! # Reporting
+//!
+//! A [`Reporter`] registered with [`set_reporter`] receives the [`Diff`] of
+//! every failed `compare_structs!` before it panics, and of anything passed
+//! to [`report`]. Closures taking `&Diff` are reporters too.
+//!
+//! Each failure message is rendered completely before panicking, and notes
+//! printed to standard error, such as the summary of a tolerated
+//! [`Aggregate`] mismatch, are written in one locked write, so the reports of
+//! tests failing concurrently stay intact.
+//!
+//! Standalone verification binaries can return
+//! [`report_and_exit(diff)`](report_and_exit) from `main`, which reports and
+//! prints the differences in the `CMP_FORMAT` output format and exits with
+//! status `1` if there are any, or use [`Diff::into_exit_code`] directly.
+//!
+//! # Metrics
+//!
+//! A [`MetricsRecorder`] registered with [`set_metrics_recorder`] counts the
+//! comparisons run and failed, and the failures per field path, for every
+//! [`Diffable::compare`] and `compare_structs!`. [`CounterRecorder`] keeps
+//! them in memory and renders them in the Prometheus text format.
+//!
+//! # `capi` feature
+//!
+//! The `capi` feature (which enables `serde`) adds the [`capi`] module, whose
+//! `extern "C"` functions `cmp_diff_json` and `cmp_string_free` let C and C++
+//! test suites compare JSON documents with the same semantics and output as
+//! `compare_structs!` in serde mode, and `cmp_diff_json_entries` returns the
+//! differences as JSON. Their declarations are in `include/cmp.h`.
+//!
+//! # `pyo3` feature
+//!
+//! The `pyo3` feature (which enables `serde`) builds the crate as a Python
+//! extension module, `cmp`, whose `cmp.diff(expected, actual, options)`
+//! compares dicts, lists and other JSON-like values like
+//! `compare_structs!` in serde mode, and returns the differences as a list
+//! of dicts with the `kind`, `path`, `pointer`, `expected` and `actual` of
+//! each, so Python tooling reports the same differences as Rust tests.
+//!
+//! # `color` feature
+//!
+//! The `color` feature renders expected values in green and actual values in
+//! red in text failure messages and [`report_and_exit`] output. Colors are
+//! left out when the output isn't a terminal, or when `NO_COLOR` is set to a
+//! non-empty value.
+//!
+//! # `stats` feature
+//!
+//! The `stats` feature also keeps process-wide counters of every comparison,
+//! whether or not a recorder is set. [`stats()`](stats) returns a snapshot of
+//! them as [`Stats`], for long soak tests to assert on at the end of the run,
+//! and [`reset_stats`] sets them back to zero.
+//!
+//! # `otel` feature
+//!
+//! The `otel` feature adds [`OtelReporter`], which attaches each reported
+//! difference as a span event (with path, kind, and truncated expected and
+//! actual attributes) to the current OpenTelemetry span, so production shadow
+//! comparisons show up in traces.
+//!
 //! # `serde` feature
 //!
 //! This crate has an optional `serde` feature that allows comparing all fields
@@ -54,9 +451,567 @@
 //! # }
 //! ```
 //!
+//! Fields of tuple and newtype structs are named by their index, e.g.
+//! `compare_structs!(a, b, 0, 2)`. Fields of nested structs are named by
+//! their path, and may end in an
+//! accessor call without arguments, e.g.
+//! `compare_structs!(a, b, header.version, body.payload.len())`, which reports
+//! differences at paths like `header.version`.
+//!
+//! Fields followed by `~ epsilon` are compared with an absolute tolerance,
+//! using [`Diffable::diff_with_epsilon`], while the others stay exact, e.g.
+//! `compare_structs!(a, b, x ~ 1e-9, y)`. Floats and tolerances are
+//! rendered in a fixed format, the shortest round-tripping digits laid out
+//! positionally from `1e-4` up to `1e16` and as `1e-9` outside, so outputs
+//! are identical on every target and toolchain. Fields followed by
+//! `=> comparator` are compared with a closure or function taking references
+//! to both values, for domain-specific equality such as
+//! `compare_structs!(a, b, name, created_at => |e, a| e.date() == a.date())`.
+//! `id => any()` accepts any value, with [`any`], for generated IDs and
+//! timestamps. With the `regex` feature, `id => matches(r"^usr_[0-9a-f]{8}$")`
+//! accepts strings matching a pattern, and reports the pattern rather than
+//! the expected value when one doesn't. Fields followed by `via debug` are
+//! compared by their `Debug` output with [`diff_debug`], for third-party
+//! types with neither `PartialEq` nor `Serialize`, and their differences
+//! are labeled `(compared by Debug output)`.
+//!
+//! Named `HashMap` and `BTreeMap` fields are compared key by key, so only
+//! the added, removed and changed keys are reported, e.g.
+//! `env["HOME"]: "/root" != "/home"`, rather than both entire maps. With the
+//! `serde` feature, maps nested in serialized values are compared key by key
+//! too.
+//!
+//! Fields followed by `~ unordered` are compared with [`Diffable`]
+//! regardless of the order of their sequences, as multisets, so
+//! `compare_structs!(a, b, id, roles ~ unordered)` only reports roles which
+//! were added, removed or changed. Use `#[cmp(unordered)]` on a derived
+//! field, [`with_unordered_sequences`] for one comparison, or
+//! [`set_unordered_sequences`] for the whole process.
+//!
+//! Derived struct comparisons loop over a table of per-field functions
+//! rather than inlining every field, which keeps the generated code small
+//! for structs with hundreds of fields. `#[cmp(unrolled)]` opts a struct
+//! back into inline comparisons.
+//!
+//! To compare every field but a few, such as timestamps and IDs, list those
+//! after `except:`, e.g. `compare_structs!(a, b, except: id, updated_at)`.
+//! With the `serde` feature, they are removed from the serialized values;
+//! without it, the structs must implement [`Diffable`], and differences
+//! inside the listed fields are dropped.
+//!
+//! Like `assert_eq!`, the arguments may end with `;` and a format string
+//! and its arguments, printed above the diff if the comparison fails, e.g.
+//! `compare_structs!(a, b, x, y; "while processing request {}", id)`.
+//!
+//! With the `serde` feature, nested values are compared recursively, so
+//! only the differing leaves are reported, with their full paths, e.g.
+//! `config.server.port: 8080 != 8081`.
+//!
 //! If there are missing fields in one of the expressions when using the `serde`
 //! feature, the macro will panic with a clear error message indicating which
-//! field is missing from which struct.
+//! field is missing from which struct. Fields which serialize to `null` on
+//! the side where they exist, such as `PhantomData` or `()` markers, are not
+//! reported as missing; use `compare_structs!(a, b; include_zst)` to report
+//! them too. When a field missing from actual looks like one missing from
+//! expected under another name, differing by case or separators or by one
+//! or two characters, the message asks `(did you mean "userName"?)`.
+//!
+//! The `serde` feature also adds [`schema::drift`], which compares the
+//! *shape* of two values (usually two versions of a struct) and reports
+//! fields that were added, removed, renamed or changed type.
+//!
+//! The `serde` feature also adds [`Diff::save`] and [`Diff::load`] to persist
+//! differences as JSON, and [`compare_diffs`] to tell which differences of a
+//! run are new, changed or resolved since a previous one, e.g. in a nightly
+//! parity job.
+//!
+//! [`assert_struct_snapshot!`] compares a value with a JSON snapshot in the
+//! crate's `snapshots` directory, named after the enclosing test by default,
+//! creating it on first use. [`diff_report_path!`] returns where to save
+//! diff reports alongside. Set `CMP_ARTIFACT_DIR` to store both for every
+//! crate of a workspace in per-crate subdirectories of one directory.
+//!
+//! [`compare_with_snapshot!`] compares a value with a hand-written JSON
+//! fixture instead, such as `tests/fixtures/user.json`, reporting the
+//! differing fields without ever writing the file, for golden-file tests
+//! without another snapshot library. Run the tests with
+//! `CMP_UPDATE_SNAPSHOTS=1` to overwrite differing fixtures and snapshots
+//! with the actual values instead of failing.
+//!
+//! `compare_structs!(a, b; bytes = Encoding::SortedKeyJson)` also asserts
+//! that values which compare equal serialize to identical bytes with an
+//! [`Encoding`], reporting the first differing byte with the bytes around it,
+//! for values which are signed or hashed. [`compare_bytes`] returns the
+//! [`ByteMismatch`] instead of panicking.
+//!
+//! Arrays are compared element by element. `compare_structs!(a, b; key =
+//! "id")`, or [`with_element_key`], pairs elements of arrays of objects by
+//! their `id` field instead, so reordered elements aren't reported.
+//!
+//! `compare_structs!(a, b; ignore_paths = ["**/created_at", "items[*].etag"])`,
+//! or [`with_ignored_paths`], skips the values at paths matching any of the
+//! [glob patterns](DiffPath::matches_glob), for volatile fields anywhere in
+//! nested values.
+//!
+//! [`Redactions`] replace the serialized values at matching paths before
+//! they are compared, like the redactions of the `insta` crate:
+//! `Redactions::new().redact("user.token", |_| json!("[redacted]"))` ignores
+//! the token and keeps it out of failure messages, and a redaction returning
+//! a normalized value compares values up to the normalization. Set them for
+//! a call with [`with_redactions`] or for the process with
+//! [`set_redactions`].
+//!
+//! [`diff_as_json_patch`] returns the differences of two serialized values
+//! as JSON Patch (RFC 6902) operations instead, for tooling which applies or
+//! displays patches.
+//!
+//! To serialize each value only once when helpers compare the same large
+//! values several times, run them inside [`memoize`], which caches the
+//! serialized forms by address until it returns.
+//!
+//! # `mmap` feature
+//!
+//! The `mmap` feature adds [`mmap::compare_files`], which compares two large
+//! JSON or NDJSON golden files record by record, memory-mapping them and
+//! parsing one record at a time instead of loading either file into the
+//! heap. Each difference is reported at its path, such as `[41].user.name`,
+//! and with the byte offsets of its records in both files. It is `unsafe`,
+//! as the files must not be modified while they are mapped.
+//!
+//! # `review` feature
+//!
+//! The `review` feature builds the `cargo-cmp` binary (install it with
+//! `cargo install cmp --features review`). `cargo cmp review` walks the
+//! snapshot updates left by failed [`assert_struct_snapshot!`]s, shows the
+//! field-level diff of each and lets you accept or reject it. The
+//! [`review`] module offers the same operations as a library.
+//!
+//! # `derive` feature
+//!
+//! The `derive` feature adds `#[derive(Diffable)]`, which implements
+//! [`Diffable`] by comparing every field of a struct without `serde`. The type
+//! owner can set comparison defaults once with container attributes, and
+//! override them per field:
+//!
+//! ```edition2024
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use cmp::Diffable;
+//!
+//! #[derive(Diffable)]
+//! #[cmp(default_epsilon = 1e-9, ignore = "updated_at")]
+//! struct Measurement {
+//!     value: f64,
+//!     #[cmp(epsilon = 0.01)]
+//!     rounded: f64,
+//!     updated_at: u64,
+//! }
+//!
+//! let expected = Measurement { value: 0.3, rounded: 1.0, updated_at: 1 };
+//! let actual = Measurement { value: 0.1 + 0.2, rounded: 1.005, updated_at: 2 };
+//!
+//! let diff = expected.compare(&actual);
+//! assert!(diff.is_empty(), "{diff}");
+//! # }
+//! ```
+//!
+//! `#[derive(CompareFields)]` instead implements [`CompareFields`], which
+//! compares each field as a whole with `PartialEq`, so `compare_structs!(a,
+//! b)` can compare all fields of types whose fields don't implement
+//! [`Diffable`], without `serde`.
+//!
+//! # `xml` feature
+//!
+//! The `xml` feature adds the [`compare_xml!`] macro and the [`xml`] module,
+//! which compare XML-containing strings structurally: attribute order and
+//! insignificant whitespace are ignored, and differences are reported with
+//! XPath-like paths.
+//!
+//! ```edition2024
+//! # #[cfg(feature = "xml")]
+//! # {
+//! use cmp::compare_xml;
+//!
+//! struct Request {
+//!     body: &'static str,
+//! }
+//!
+//! let expected = Request { body: r#"<a x="1" y="2"><b>text</b></a>"# };
+//! let actual = Request { body: "<a y='2' x='1'>\n  <b>text</b>\n</a>" };
+//!
+//! compare_xml!(expected, actual, body);
+//! # }
+//! ```
+//!
+//! # `fixtures` feature
+//!
+//! The `fixtures` feature (which enables `serde`) adds the [`fixtures`]
+//! module, for parity tests against another language's implementation. A
+//! [`fixtures::FixtureServer`] holds expected values, such as JSON files
+//! generated by the reference implementation, and a Rust test sends its
+//! actual value through a [`fixtures::FixtureClient`] and gets the [`Diff`]
+//! back. The protocol is one line of JSON per request and response, so
+//! either side can be written in any language.
+//!
+//! # `html` feature
+//!
+//! The `html` feature adds the [`compare_html!`] macro and the [`html`] module,
+//! which compare HTML fragments as parsed DOM trees, ignoring formatting and
+//! attribute order, and report the first differing element or attribute path.
+//!
+//! ```edition2024
+//! # #[cfg(feature = "html")]
+//! # {
+//! use cmp::compare_html;
+//!
+//! struct Page {
+//!     body: &'static str,
+//! }
+//!
+//! let expected = Page { body: r#"<p class="lead" id="intro">Hello <b>world</b></p>"# };
+//! let actual = Page { body: "<P id=intro class=lead>\n  Hello\n  <b>world</b>\n</P>" };
+//!
+//! compare_html!(expected, actual, body);
+//! # }
+//! ```
+//!
+//! # `socket` feature
+//!
+//! The `socket` feature (which enables `serde`) adds [`SocketReporter`],
+//! which streams each reported [`Diff`] as one line of JSON to a TCP endpoint
+//! or Unix socket, so a live dashboard can show comparison failures during
+//! long integration runs. It also makes [`Diff`] serializable.
+//!
+//! # `sql` feature
+//!
+//! The `sql` feature adds the [`compare_sql!`] macro and the [`sql`] module,
+//! which normalize generated SQL (collapsing whitespace, dropping comments,
+//! uppercasing keywords and, optionally, canonicalizing placeholders) before
+//! comparing, and show a token-level diff when the statements still differ.
+//!
+//! ```edition2024
+//! # #[cfg(feature = "sql")]
+//! # {
+//! use cmp::compare_sql;
+//!
+//! struct Query {
+//!     text: &'static str,
+//! }
+//!
+//! let expected = Query { text: "SELECT id, name FROM users WHERE id = 1" };
+//! let actual = Query { text: "select id,\n       name\n  from users\n where id = 1" };
+//!
+//! compare_sql!(expected, actual, text);
+//! # }
+//! ```
+
+extern crate self as cmp;
+
+mod aggregate;
+mod atomic;
+#[cfg(any(feature = "capi", feature = "pyo3"))]
+mod bindings;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod capture;
+mod chunked;
+#[cfg(feature = "color")]
+mod color;
+mod compact;
+mod compare_fields;
+mod comparer;
+mod comparison;
+mod context;
+mod coverage;
+mod diff;
+mod diffable;
+mod differential;
+mod elide;
+#[cfg(feature = "serde")]
+mod encoding;
+pub mod env;
+mod eventually;
+mod except;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+mod float;
+mod format;
+mod formatters;
+mod future;
+mod getters;
+mod glob;
+#[cfg(feature = "serde")]
+mod history;
+#[cfg(feature = "html")]
+pub mod html;
+mod impls;
+mod invisible;
+mod keys;
+mod limit;
+mod lines;
+mod map_fields;
+#[cfg(any(feature = "xml", feature = "html"))]
+mod markup;
+mod matchers;
+#[cfg(feature = "serde")]
+mod memo;
+mod messages;
+mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+mod negated;
+#[cfg(feature = "otel")]
+mod otel;
+mod output;
+mod partial;
+#[cfg(feature = "serde")]
+mod patch;
+mod path;
+#[cfg(feature = "serde")]
+mod plan;
+mod provenance;
+#[cfg(feature = "pyo3")]
+mod python;
+#[cfg(feature = "serde")]
+mod redact;
+mod render;
+mod report;
+#[cfg(feature = "review")]
+pub mod review;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod self_check;
+#[cfg(feature = "serde")]
+mod serialize;
+mod similarity;
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "socket")]
+mod socket;
+#[cfg(feature = "sql")]
+pub mod sql;
+mod stable;
+mod struct_diff;
+mod suggest;
+mod summary;
+mod truncate;
+mod unordered;
+#[cfg(feature = "serde")]
+mod value;
+#[cfg(feature = "xml")]
+pub mod xml;
+
+pub use aggregate::{Aggregate, AggregateReport, FieldCount};
+pub use atomic::{atomic_load_ordering, set_atomic_load_ordering};
+pub use chunked::ChunkedComparer;
+pub use compare_fields::CompareFields;
+pub use comparer::Comparer;
+pub use comparison::{ComparePlugin, Comparison, PathRule, epsilon, exact, ignore};
+pub use context::set_sequence_context;
+pub use coverage::Mutate;
+pub use diff::{Diff, FieldDiff};
+pub use diffable::{Diffable, compare_quiet, diff_debug, diff_eq};
+pub use differential::{Divergence, differential_check};
+#[cfg(feature = "serde")]
+pub use encoding::{ByteMismatch, Encoding, compare_bytes};
+pub use formatters::{
+    ValueFormatter, ValueFormatters, clear_value_formatters, format_value, set_value_formatters,
+    with_value_formatters,
+};
+#[cfg(feature = "serde")]
+pub use history::{DiffChanges, compare_diffs};
+pub use keys::{KeyMatcher, clear_key_matcher, set_key_matcher, with_key_matcher};
+pub use limit::{DEFAULT_DIFF_MEMORY_LIMIT, set_diff_memory_limit};
+pub use lines::{DEFAULT_LINE_DIFF_THRESHOLD, set_line_diff_threshold};
+pub use matchers::any;
+#[cfg(feature = "regex")]
+pub use matchers::matches;
+#[cfg(feature = "serde")]
+pub use memo::memoize;
+pub use messages::{Messages, clear_messages, set_messages, with_messages};
+pub use metrics::{CounterRecorder, MetricsRecorder, clear_metrics_recorder, set_metrics_recorder};
+#[cfg(feature = "stats")]
+pub use metrics::{Stats, reset_stats, stats};
+#[cfg(feature = "otel")]
+pub use otel::{EVENT_NAME as OTEL_EVENT_NAME, OtelReporter};
+#[cfg(feature = "serde")]
+pub use patch::{PatchOp, diff_as_json_patch};
+pub use path::{DiffPath, Segment};
+#[cfg(feature = "serde")]
+pub use plan::{Plan, PlannedPath};
+pub use provenance::{Sourced, sourced};
+#[cfg(feature = "serde")]
+pub use redact::{Redactions, clear_redactions, set_redactions, with_redactions};
+#[cfg(feature = "color")]
+pub use render::ColorRenderer;
+#[cfg(feature = "serde")]
+pub use render::JsonRenderer;
+pub use render::{DiffRenderer, TextRenderer, clear_renderer, set_renderer, with_renderer};
+pub use report::{
+    RateLimited, Reporter, Sampled, clear_reporter, report, report_and_exit, set_reporter,
+};
+#[cfg(feature = "serde")]
+pub use serialize::{
+    SerializationFailure, SerializeError, set_serialization_failure, with_serialization_failure,
+};
+#[cfg(feature = "socket")]
+pub use socket::SocketReporter;
+pub use stable::{STABLE_FORMAT_VERSION, StableRenderer};
+pub use struct_diff::StructDiff;
+pub use truncate::{set_max_render_len, with_max_render_len};
+pub use unordered::{set_unordered_sequences, with_unordered_sequences};
+#[cfg(feature = "serde")]
+pub use value::{diff, try_diff, with_element_key, with_ignored_paths};
+
+/// Runtime support for the macros, not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::capture::{Capture, CaptureDebug, CaptureFallback};
+    pub use crate::capture::{enabled as capture_enabled, header as capture_header};
+    pub use crate::compare_fields::compare_all;
+    #[cfg(feature = "serde")]
+    pub use crate::encoding::assert_identical_bytes;
+    pub use crate::eventually::{Eventually, parse_duration};
+    pub use crate::except::diff_except;
+    pub use crate::format::Format;
+    pub use crate::formatters::render_debug;
+    pub use crate::future::{Outcome, join};
+    pub use crate::getters::diff_getter;
+    pub use crate::keys::key_matcher;
+    pub use crate::map_fields::{DiffEqField, DiffMapField, Field};
+    pub use crate::matchers::take_expectation;
+    pub use crate::negated::fail_equal;
+    pub use crate::partial::Partial;
+    #[cfg(feature = "serde")]
+    pub use crate::plan::dry_run;
+    pub use crate::provenance::header as provenance_header;
+    pub use crate::provenance::{Origin, OriginFallback, OriginSourced};
+    #[cfg(feature = "serde")]
+    pub use crate::snapshot::{
+        Location as SnapshotLocation, assert_snapshot, compare_with_fixture,
+    };
+    pub use crate::suggest::{enabled as suggest_enabled, suggestion};
+    #[cfg(feature = "serde")]
+    pub use crate::value::diff_all;
+
+    use crate::{Diff, DiffPath};
+
+    /// Path of a field named in a macro invocation, such as `id` or
+    /// `header.version`, from its `stringify!`ed tokens.
+    pub fn field_path(name: &str) -> DiffPath {
+        let mut path = DiffPath::new();
+        for field in name.split('.') {
+            // Tokens such as `len ()` may be stringified with spaces.
+            path.push_field(&field.split_whitespace().collect::<String>());
+        }
+        path
+    }
+
+    /// Runs `diff` with the path of the field named `name`.
+    ///
+    /// The field is first compared at an empty path into a scratch diff, so
+    /// the path is only built, and the comparison repeated, if it differs:
+    /// matching fields allocate nothing.
+    pub fn diff_at(name: &str, out: &mut Diff, mut diff: impl FnMut(&mut DiffPath, &mut Diff)) {
+        let mut found = Diff::new();
+        diff(&mut DiffPath::new(), &mut found);
+        if !found.is_empty() {
+            diff(&mut field_path(name), out);
+        }
+    }
+
+    /// Records the outcome of a macro comparison with the metrics recorder.
+    pub fn record(diff: &Diff) {
+        crate::metrics::record(diff);
+    }
+
+    /// Calls a `field => comparator` closure, so its parameter types are
+    /// inferred from the field.
+    pub fn compare_with<T: ?Sized>(
+        expected: &T,
+        actual: &T,
+        compare: impl FnOnce(&T, &T) -> bool,
+    ) -> bool {
+        compare(expected, actual)
+    }
+
+    /// Returns the name of the type of `value`.
+    pub fn type_name_of<T: ?Sized>(value: &T) -> &'static str {
+        std::any::type_name_of_val(value)
+    }
+
+    /// Reports a failed comparison and panics with `header` followed by the
+    /// differences.
+    #[track_caller]
+    pub fn fail(header: &str, diff: &Diff) -> ! {
+        fail_with(diff, &format!("{header}{diff}"))
+    }
+
+    /// Reports a failed comparison and panics with `message`, which renders
+    /// the differences.
+    #[track_caller]
+    pub fn fail_with(diff: &Diff, message: &str) -> ! {
+        crate::report(diff);
+        panic!("{message}");
+    }
+}
+
+/// Renders the `CMP_CAPTURE` header for a failed comparison, or nothing when
+/// capture is disabled.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __capture_header {
+    ($expected:expr, $actual:expr, $expected_value:expr, $actual_value:expr) => {{
+        // Only one of the traits is used for each value, depending on whether it
+        // implements `Debug`.
+        #[allow(unused_imports)]
+        use $crate::__private::{CaptureDebug as _, CaptureFallback as _};
+
+        if $crate::__private::capture_enabled() {
+            #[allow(clippy::needless_borrow)]
+            let expected = (&$crate::__private::Capture($expected_value)).render();
+            #[allow(clippy::needless_borrow)]
+            let actual = (&$crate::__private::Capture($actual_value)).render();
+            $crate::__private::capture_header(
+                stringify!($expected),
+                expected,
+                stringify!($actual),
+                actual,
+            )
+        } else {
+            String::new()
+        }
+    }};
+}
+
+/// Renders a line naming the source of each [`Sourced`] side of a failed
+/// comparison.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __provenance_header {
+    ($expected_value:expr, $actual_value:expr) => {{
+        // Only one of the traits is used for each value, depending on whether it
+        // is `Sourced`.
+        #[allow(unused_imports)]
+        use $crate::__private::{OriginFallback as _, OriginSourced as _};
+
+        #[allow(clippy::needless_borrow)]
+        let expected = (&$crate::__private::Origin($expected_value)).describe("expected");
+        #[allow(clippy::needless_borrow)]
+        let actual = (&$crate::__private::Origin($actual_value)).describe("actual");
+        $crate::__private::provenance_header(expected, actual)
+    }};
+}
+
+/// Derives [`Diffable`] for a struct, see its documentation for the supported
+/// `#[cmp(...)]` attributes.
+#[cfg(feature = "derive")]
+pub use cmp_derive::Diffable;
+
+/// Derives [`CompareFields`] for a struct, comparing every field with
+/// `PartialEq`, so `compare_structs!(a, b)` works without `serde`.
+#[cfg(feature = "derive")]
+pub use cmp_derive::CompareFields;
 
 /// Macro which is mostly useful when writing `assert!` tests on structs.
 ///
@@ -120,89 +1075,394 @@
 #[cfg(not(feature = "serde"))]
 #[macro_export]
 macro_rules! compare_structs {
-    ($expected:expr, $actual:expr, $($field:ident),+) => {{
-        let mut diffs = String::new();
-        $(
-            if $expected.$field != $actual.$field {
-                diffs.push_str(&format!(
-                    "{}: {:#?} != {:#?}\n",
-                    stringify!($field),
-                    $expected.$field,
-                    $actual.$field
-                ));
-            }
-        )+
-
-        assert!(diffs.is_empty(), "{diffs}");
-    }};
+    ($expected:expr, $actual:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_all!(expected, actual, true),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; $format:literal $($args:tt)*) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_all!(expected, actual, true),
+                $expected,
+                $actual,
+                expected,
+                actual;
+                $format $($args)*
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ $(,)?) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__private::diff_except(expected, actual, &[$(stringify!($field)),+]),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ ; $format:literal $($args:tt)*) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__private::diff_except(expected, actual, &[$(stringify!($field)),+]),
+                $expected,
+                $actual,
+                expected,
+                actual;
+                $format $($args)*
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [] $($fields)+)
+    };
 }
 
 #[cfg(feature = "serde")]
 #[macro_export]
 macro_rules! compare_structs {
-    ($expected:expr, $actual:expr) => {{
-        let expected_val =
-            serde_json::to_value(&$expected).expect("Could not serialize expected value");
-        let actual_val = serde_json::to_value(&$actual).expect("Could not serialize actual value");
-
-        if expected_val != actual_val {
-            let expected_map = expected_val
-                .as_object()
-                .expect("Expected value is not an object");
-            let actual_map = actual_val
-                .as_object()
-                .expect("Actual value is not an object");
-            let mut diffs = String::new();
-
-            for (key, expected_field_val) in expected_map {
-                match actual_map.get(key) {
-                    Some(actual_field_val) => {
-                        if expected_field_val != actual_field_val {
-                            diffs.push_str(&format!(
-                                "{}: {:#?} != {:#?}\n",
-                                key, expected_field_val, actual_field_val
-                            ));
-                        }
-                    }
-                    None => {
-                        diffs.push_str(&format!(
-                            "{}: field missing from actual: {:#?}\n",
-                            key, expected_field_val
-                        ));
-                    }
-                }
-            }
-
-            for (key, actual_field_val) in actual_map {
-                if !expected_map.contains_key(key) {
-                    diffs.push_str(&format!(
-                        "{}: field missing from expected: {:#?}\n",
-                        key, actual_field_val
-                    ));
-                }
+    ($expected:expr, $actual:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_all!(expected, actual, true),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; $format:literal $($args:tt)*) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_all!(expected, actual, true),
+                $expected,
+                $actual,
+                expected,
+                actual;
+                $format $($args)*
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; include_zst) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_all!(expected, actual, false),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; dry_run) => {
+        $crate::__private::dry_run(&$expected, &$actual)
+    };
+    ($expected:expr, $actual:expr; bytes = $encoding:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => {
+                $crate::__finish!(
+                    $crate::__diff_all!(expected, actual, true),
+                    $expected,
+                    $actual,
+                    expected,
+                    actual
+                );
+                $crate::__private::assert_identical_bytes(
+                    expected,
+                    actual,
+                    $encoding,
+                    stringify!($expected),
+                    stringify!($actual),
+                );
             }
+        }
+    };
+    ($expected:expr, $actual:expr; key_matcher = $matcher:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::with_key_matcher($matcher, || $crate::__diff_all!(expected, actual, true)),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; key = $key:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::with_element_key($key, || $crate::__diff_all!(expected, actual, true)),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; ignore_paths = $patterns:expr) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::with_ignored_paths(&$patterns, || {
+                    $crate::__diff_all!(expected, actual, true)
+                }),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr; unordered) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::with_unordered_sequences(|| $crate::__diff_all!(expected, actual, true)),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ $(,)?) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__private::diff_except(expected, actual, &[$(stringify!($field)),+]),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ ; $format:literal $($args:tt)*) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__private::diff_except(expected, actual, &[$(stringify!($field)),+]),
+                $expected,
+                $actual,
+                expected,
+                actual;
+                $format $($args)*
+            ),
+        }
+    };
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [] $($fields)+)
+    };
+}
 
-            assert!(diffs.is_empty(), "{diffs}");
+/// Compares the named fields of two structs for `compare_structs!`,
+/// splitting a failure message after a `;` off the fields.
+///
+/// Tokens are moved four at a time, after checking that none of them is the
+/// `;`, so long field lists stay within the recursion limit.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compare_fields {
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] ; $($message:tt)+) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_fields!(expected, actual, $($fields)*),
+                $expected,
+                $actual,
+                expected,
+                actual;
+                $($message)+
+            ),
+        }
+    };
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] $a:tt ; $($message:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [$($fields)* $a] ; $($message)+)
+    };
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] $a:tt $b:tt ; $($message:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [$($fields)* $a $b] ; $($message)+)
+    };
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] $a:tt $b:tt $c:tt ; $($message:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [$($fields)* $a $b $c] ; $($message)+)
+    };
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] $a:tt $b:tt $c:tt $d:tt $($rest:tt)+) => {
+        $crate::__compare_fields!([$expected, $actual] [$($fields)* $a $b $c $d] $($rest)+)
+    };
+    ([$expected:expr, $actual:expr] [$($fields:tt)*] $($rest:tt)*) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => $crate::__finish!(
+                $crate::__diff_fields!(expected, actual, $($fields)* $($rest)*),
+                $expected,
+                $actual,
+                expected,
+                actual
+            ),
         }
+    };
+}
+
+/// Compares the named fields of two borrowed structs with `PartialEq`,
+/// fields followed by `~ epsilon` with [`Diffable::diff_with_epsilon`],
+/// fields followed by `~ unordered` with [`Diffable::diff`] regardless of
+/// order, or fields followed by `=> comparator` with the comparator,
+/// returning the [`Diff`]. Fields followed by `max_render_len = N` have
+/// their rendered values cut with [`with_max_render_len`].
+///
+/// Fields may be tuple indices, like `0`, nested, like `header.version` or
+/// `pair.0.1`, and end in accessor calls without arguments, like
+/// `body.payload.len()`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __diff_fields {
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {{
+        let (expected, actual) = ($expected, $actual);
+        let mut diff = $crate::Diff::new();
+        $crate::__diff_field!(diff, expected, actual, $($fields)+);
+        diff
     }};
-    ($expected:expr, $actual:expr, $($field:ident),+) => {{
-        let mut diffs = String::new();
-        $(
-            if $expected.$field != $actual.$field {
-                diffs.push_str(&format!(
-                    "{}: {:#?} != {:#?}\n",
-                    stringify!($field),
-                    $expected.$field,
-                    $actual.$field
-                ));
-            }
-        )+
+}
+
+/// Compares each field of a comma-separated list for [`__diff_fields!`],
+/// collecting the tokens of each field's access path before its comparison.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __diff_field {
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] . $name:ident () $($rest:tt)*) => {
+        $crate::__diff_field!(@access $diff, $expected, $actual, [$($access)+ . $name ()] $($rest)*)
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] . $name:ident $($rest:tt)*) => {
+        $crate::__diff_field!(@access $diff, $expected, $actual, [$($access)+ . $name] $($rest)*)
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] . $index:tt $($rest:tt)*) => {
+        $crate::__diff_field!(@access $diff, $expected, $actual, [$($access)+ . $index] $($rest)*)
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] ~ unordered $(, $($rest:tt)*)?) => {
+        $crate::__private::diff_at(stringify!($($access)+), &mut $diff, |path, out| {
+            $crate::with_unordered_sequences(|| {
+                $crate::Diffable::diff(&$expected.$($access)+, &$actual.$($access)+, path, out)
+            })
+        });
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] ~ $epsilon:expr => $compare:expr $(, $($rest:tt)*)?) => {
+        compile_error!(concat!(
+            "`",
+            stringify!($($access)+),
+            "` can't have both an epsilon and a comparator"
+        ))
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] ~ $epsilon:expr $(, $($rest:tt)*)?) => {
+        let epsilon: f64 = $epsilon;
+        $crate::__private::diff_at(stringify!($($access)+), &mut $diff, |path, out| {
+            $crate::Diffable::diff_with_epsilon(
+                &$expected.$($access)+,
+                &$actual.$($access)+,
+                epsilon,
+                path,
+                out,
+            )
+        });
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] max_render_len = $len:expr $(, $($rest:tt)*)?) => {
+        $crate::with_max_render_len($len, || {
+            $crate::__diff_field!(@access $diff, $expected, $actual, [$($access)+]);
+        });
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] via debug $(, $($rest:tt)*)?) => {
+        $crate::__private::diff_at(stringify!($($access)+), &mut $diff, |path, out| {
+            $crate::diff_debug(&$expected.$($access)+, &$actual.$($access)+, path, out)
+        });
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] => $compare:expr $(, $($rest:tt)*)?) => {
+        if !$crate::__private::compare_with(&$expected.$($access)+, &$actual.$($access)+, $compare) {
+            $diff.push($crate::FieldDiff::Changed {
+                path: $crate::__private::field_path(stringify!($($access)+)),
+                expected: $crate::__private::take_expectation()
+                    .unwrap_or_else(|| $crate::format_value(&$expected.$($access)+)),
+                actual: $crate::format_value(&$actual.$($access)+),
+            });
+        }
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    (@access $diff:ident, $expected:ident, $actual:ident, [$($access:tt)+] $(, $($rest:tt)*)?) => {
+        {
+            // Only one of the traits is used for each field, depending on
+            // whether it is a map.
+            #[allow(unused_imports)]
+            use $crate::__private::{DiffEqField as _, DiffMapField as _};
+            #[allow(clippy::needless_borrow)]
+            (&$crate::__private::Field(&$expected.$($access)+, &$actual.$($access)+))
+                .diff_field(stringify!($($access)+), &mut $diff);
+        }
+        $($crate::__diff_field!($diff, $expected, $actual, $($rest)*);)?
+    };
+    ($diff:ident, $expected:ident, $actual:ident, $field:ident $($rest:tt)*) => {
+        $crate::__diff_field!(@access $diff, $expected, $actual, [$field] $($rest)*)
+    };
+    // A tuple index, as `literal` fragments can't be used in field access.
+    ($diff:ident, $expected:ident, $actual:ident, $index:tt $($rest:tt)*) => {
+        $crate::__diff_field!(@access $diff, $expected, $actual, [$index] $($rest)*)
+    };
+    ($diff:ident, $expected:ident, $actual:ident $(,)?) => {};
+}
+
+/// Compares all serialized fields of two borrowed structs, returning the
+/// [`Diff`]. `null` fields missing from one side are skipped if `$skip_zst`.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __diff_all {
+    ($expected:expr, $actual:expr, $skip_zst:expr) => {
+        $crate::__private::diff_all($expected, $actual, $skip_zst)
+    };
+}
+
+/// Compares all fields of two borrowed structs with [`CompareFields`],
+/// returning the [`Diff`].
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __diff_all {
+    ($expected:expr, $actual:expr, $skip_zst:expr) => {
+        $crate::__private::compare_all($expected, $actual)
+    };
+}
 
-        assert!(diffs.is_empty(), "{diffs}");
+/// Records a macro comparison's [`Diff`], and reports it and panics if it is
+/// not empty.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __finish {
+    ($diff:expr, $expected:expr, $actual:expr, $expected_value:expr, $actual_value:expr $(; $($message:tt)+)?) => {{
+        let diff = $diff;
+        $crate::__private::record(&diff);
+        if !diff.is_empty() {
+            let mut header = $crate::__provenance_header!($expected_value, $actual_value);
+            $(header.insert_str(0, &format!("{}\n", format_args!($($message)+)));)?
+            header +=
+                &$crate::__capture_header!($expected, $actual, $expected_value, $actual_value);
+            header += &$crate::__private::Format::from_env().render(&diff, stringify!($expected));
+            if $crate::__private::suggest_enabled() {
+                header += &$crate::__private::suggestion(
+                    $crate::__private::type_name_of($actual_value),
+                    &diff,
+                );
+            }
+            $crate::__private::fail_with(&diff, &header);
+        }
     }};
 }
 
 #[cfg(test)]
+// The baseline tests glob-import the crate root, which they don't use.
+#[allow(unused_imports)]
 mod tests {
     use super::*;
     #[cfg(feature = "serde")]
@@ -234,6 +1494,139 @@ mod tests {
         c: [(1.0, 1.0), (2.0, 2.0)],
     };
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn compare_all_fields_skips_unit_fields_missing_from_one_side() {
+        #[derive(Serialize)]
+        struct Tagged {
+            a: i32,
+            marker: std::marker::PhantomData<u8>,
+        }
+
+        #[derive(Serialize)]
+        struct Untagged {
+            a: i32,
+        }
+
+        compare_structs!(
+            Tagged {
+                a: 1,
+                marker: std::marker::PhantomData,
+            },
+            Untagged { a: 1 }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn compare_all_fields_except() {
+        #[derive(Serialize)]
+        struct Event {
+            id: u32,
+            name: &'static str,
+            created_at: u64,
+        }
+
+        compare_structs!(
+            Event { id: 1, name: "deploy", created_at: 10 },
+            Event { id: 2, name: "deploy", created_at: 20 },
+            except: id, created_at
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "name: \"deploy\" != \"build\"")]
+    #[cfg(not(feature = "serde"))]
+    fn compare_diffable_fields_except() {
+        #[derive(Debug, cmp_derive::Diffable)]
+        struct Event {
+            id: u32,
+            name: &'static str,
+        }
+
+        compare_structs!(Event { id: 1, name: "deploy" }, Event { id: 2, name: "deploy" }, except: id);
+        compare_structs!(Event { id: 1, name: "deploy" }, Event { id: 2, name: "build" }, except: id);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn dry_run_does_not_compare() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i32,
+        }
+
+        compare_structs!(Point { x: 1 }, Point { x: 2 }; dry_run);
+    }
+
+    #[test]
+    #[should_panic(expected = "marker: field missing from actual: null")]
+    #[cfg(feature = "serde")]
+    fn compare_all_fields_include_zst() {
+        #[derive(Serialize)]
+        struct Tagged {
+            a: i32,
+            marker: (),
+        }
+
+        #[derive(Serialize)]
+        struct Untagged {
+            a: i32,
+        }
+
+        compare_structs!(Tagged { a: 1, marker: () }, Untagged { a: 2 }; include_zst);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "user_name: field missing from actual: \"ada\" (did you mean \"userName\"?)\n\
+                    userName: field missing from expected: \"ada\"\n"
+    )]
+    #[cfg(feature = "serde")]
+    fn compare_all_fields_hints_at_renamed_fields() {
+        #[derive(Serialize)]
+        struct Snake {
+            user_name: &'static str,
+        }
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Camel {
+            user_name: &'static str,
+        }
+
+        compare_structs!(Snake { user_name: "ada" }, Camel { user_name: "ada" });
+    }
+
+    #[test]
+    #[should_panic(expected = "user_name: \"ada\" != \"bob\"")]
+    #[cfg(feature = "serde")]
+    fn compare_all_fields_with_key_matcher() {
+        #[derive(Serialize)]
+        struct Snake {
+            user_name: &'static str,
+            id: u32,
+        }
+
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Camel {
+            user_name: &'static str,
+            id: u32,
+        }
+
+        compare_structs!(
+            Snake { user_name: "ada", id: 1 },
+            Camel { user_name: "ada", id: 1 };
+            key_matcher = crate::KeyMatcher::SameWords
+        );
+        compare_structs!(
+            Snake { user_name: "ada", id: 1 },
+            Camel { user_name: "bob", id: 1 };
+            key_matcher = crate::KeyMatcher::SameWords
+        );
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn compare_all_fields_no_args() {
@@ -289,6 +1682,170 @@ mod tests {
         compare_structs!(struct_a, struct_b, a, b, c);
     }
 
+    #[test]
+    #[should_panic(expected = "y: 0.5 != 0.6")]
+    fn compare_fields_with_tolerances() {
+        #[derive(Debug)]
+        struct Sample {
+            x: f64,
+            y: f64,
+            steps: u32,
+            trace: Vec<f64>,
+        }
+
+        let expected = Sample {
+            x: 0.1 + 0.2,
+            y: 0.5,
+            steps: 3,
+            trace: vec![1.0, 2.0],
+        };
+        let actual = Sample {
+            x: 0.3,
+            y: 0.5,
+            steps: 3,
+            trace: vec![1.0, 2.0 + 1e-12],
+        };
+        compare_structs!(expected, actual, x ~ 1e-9, y, steps, trace ~ 1e-9);
+
+        let actual = Sample { y: 0.6, ..actual };
+        compare_structs!(expected, actual, x ~ 1e-9, y ~ 0.01, steps);
+    }
+
+    #[test]
+    #[should_panic(expected = "2: \"b\" != \"c\"")]
+    fn compare_tuple_struct_fields() {
+        #[derive(Debug)]
+        struct Row(u32, (f64, f64), &'static str);
+
+        let expected = Row(1, (0.3, 1.0), "b");
+        let actual = Row(1, (0.1 + 0.2, 2.0), "c");
+
+        let diff = crate::diff_structs!(expected, actual, 0, 1.0 ~ 1e-9, 1.1);
+        assert_eq!(diff.to_string(), "1.1: 1.0 != 2.0\n");
+        assert_eq!(diff.entries()[0].path().segments().len(), 2);
+        compare_structs!(expected, actual, 0, 2);
+    }
+
+    #[test]
+    fn compare_nested_fields() {
+        #[derive(Debug)]
+        struct Header {
+            version: u8,
+        }
+
+        #[derive(Debug)]
+        struct Message {
+            header: Header,
+            body: Vec<f64>,
+        }
+
+        let expected = Message {
+            header: Header { version: 1 },
+            body: vec![0.3],
+        };
+        let actual = Message {
+            header: Header { version: 2 },
+            body: vec![0.1 + 0.2],
+        };
+
+        let diff = crate::diff_structs!(expected, actual, header.version, body.len(), body ~ 1e-9);
+        assert_eq!(diff.to_string(), "header.version: 1 != 2\n");
+        assert_eq!(diff.entries()[0].path().segments().len(), 2);
+        let diff = crate::diff_structs!(
+            expected,
+            Message {
+                body: vec![],
+                ..actual
+            },
+            body.len()
+        );
+        assert_eq!(diff.to_string(), "body.len(): 1 != 0\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "created_at: 1700003600 != 1700090000")]
+    fn compare_fields_with_closures() {
+        #[derive(Debug)]
+        struct Event {
+            name: String,
+            created_at: u64,
+        }
+
+        let expected = Event {
+            name: "Deploy".into(),
+            created_at: 1_700_000_000,
+        };
+        let actual = Event {
+            name: "deploy".into(),
+            created_at: 1_700_003_600,
+        };
+        let same_day = |e: &u64, a: &u64| e / 86_400 == a / 86_400;
+        compare_structs!(
+            expected,
+            actual,
+            name => |e, a| e.eq_ignore_ascii_case(a),
+            created_at => same_day
+        );
+
+        let expected = Event {
+            created_at: 1_700_003_600,
+            ..expected
+        };
+        let actual = Event {
+            created_at: 1_700_090_000,
+            ..actual
+        };
+        compare_structs!(expected, actual, name => |e, a| e.eq_ignore_ascii_case(a), created_at => same_day);
+    }
+
+    #[test]
+    fn compares_fields_via_debug_output() {
+        #[allow(dead_code)]
+        #[derive(Debug)]
+        struct Opaque(&'static str);
+
+        struct Job {
+            id: u32,
+            state: Opaque,
+        }
+
+        let expected = Job {
+            id: 1,
+            state: Opaque("queued"),
+        };
+        compare_structs!(expected, Job { id: 1, state: Opaque("queued") }, id, state via debug);
+
+        let diff = crate::diff_structs!(expected, Job { id: 2, state: Opaque("done") }, state via debug, id);
+        assert_eq!(
+            diff.to_string(),
+            "state: Opaque(\n    \"queued\",\n) != Opaque(\n    \"done\",\n) (compared by Debug output)\n\
+             id: 1 != 2\n"
+        );
+    }
+
+    #[test]
+    fn cuts_the_values_of_fields_with_a_render_budget() {
+        struct Upload {
+            body: String,
+            name: &'static str,
+        }
+
+        let expected = Upload {
+            body: "a".repeat(40),
+            name: "x",
+        };
+        let actual = Upload {
+            body: "b".repeat(40),
+            name: "y",
+        };
+        let diff = crate::diff_structs!(expected, actual, body max_render_len = 24, name);
+        assert_eq!(
+            diff.to_string(),
+            "body: \"aaaa…(37 more bytes) != \"bbbb…(37 more bytes)\n\
+             name: \"x\" != \"y\"\n"
+        );
+    }
+
     #[test]
     fn compare_some_fields() {
         let struct_a = A {
@@ -339,4 +1896,71 @@ mod tests {
 
         compare_structs!(struct_a, struct_b, a, b, c);
     }
+
+    #[test]
+    fn prints_the_failure_message_above_the_diff() {
+        let id = 7;
+        let message = |compare: fn(i32)| {
+            let panic = std::panic::catch_unwind(move || compare(id)).unwrap_err();
+            panic.downcast_ref::<String>().unwrap().clone()
+        };
+
+        assert_eq!(
+            message(
+                |id| compare_structs!(STRUCT_A, B { a: 11, ..STRUCT_B }, a, b; "while processing request {}", id)
+            ),
+            "while processing request 7\na: 10 != 11\n"
+        );
+        assert!(
+            message(|id| compare_structs!(STRUCT_A, B { b: "x", ..STRUCT_B }, a, c.len(), c => |e, a| e == a, b; "request {id}"))
+                .starts_with("request 7\n")
+        );
+        compare_structs!(STRUCT_A, STRUCT_B, a, b; "unused {}", id);
+    }
+
+    /// Counts the allocations of each thread, to check that matching
+    /// comparisons allocate nothing.
+    struct CountingAllocator;
+
+    std::thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { std::alloc::System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            unsafe { std::alloc::System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Nested `~ epsilon` and `~ unordered` fields still allocate the path
+    /// segments of their elements while they are walked.
+    #[test]
+    fn matching_fields_allocate_nothing() {
+        struct Account {
+            roles: Vec<u32>,
+            limits: std::collections::HashMap<&'static str, u32>,
+        }
+
+        let account = Account {
+            roles: vec![1, 2],
+            limits: std::collections::HashMap::from([("daily", 10)]),
+        };
+        let compare = || {
+            compare_structs!(STRUCT_A, STRUCT_B, a ~ 1e-9, b, c, c.len(), c => |e, a| e == a);
+            compare_structs!(account, account, roles ~ unordered, limits);
+        };
+        compare();
+
+        let before = ALLOCATIONS.with(std::cell::Cell::get);
+        compare();
+        assert_eq!(ALLOCATIONS.with(std::cell::Cell::get), before);
+    }
 }
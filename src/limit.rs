@@ -0,0 +1,128 @@
+//! A cap on the memory a [`Diff`](crate::Diff) uses for its entries, so a
+//! badly wrong comparison of large values can't exhaust the test process.
+//!
+//! Once an entry would take a diff past the limit, further differences are
+//! only counted, and rendered as one `output capped at 1MB; 42,113
+//! additional differences counted` line.
+
+use std::env;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable which sets the limit in bytes when
+/// [`set_diff_memory_limit`] hasn't been called.
+const LIMIT_VAR: &str = "CMP_MAX_DIFF_BYTES";
+
+/// The limit when neither [`set_diff_memory_limit`] nor `CMP_MAX_DIFF_BYTES`
+/// set one.
+pub const DEFAULT_DIFF_MEMORY_LIMIT: usize = 1 << 20;
+
+/// Stored when [`set_diff_memory_limit`] hasn't been called.
+const UNSET: usize = 0;
+
+/// Stored for no limit.
+const UNLIMITED: usize = usize::MAX;
+
+static LIMIT: AtomicUsize = AtomicUsize::new(UNSET);
+
+/// Sets the approximate number of bytes the entries of a [`Diff`](crate::Diff)
+/// may use before further differences are only counted, or `None` for no
+/// limit, overriding `CMP_MAX_DIFF_BYTES`.
+///
+/// The first difference is always recorded. The default is
+/// [`DEFAULT_DIFF_MEMORY_LIMIT`], 1MB. Use
+/// [`Diff::with_memory_limit`](crate::Diff::with_memory_limit) to set the
+/// limit of one diff.
+///
+/// ```edition2024,no_run
+/// // In a soak test comparing very large payloads.
+/// cmp::set_diff_memory_limit(Some(16 << 20));
+/// ```
+pub fn set_diff_memory_limit(bytes: Option<usize>) {
+    let stored = match bytes {
+        None => UNLIMITED,
+        Some(bytes) => bytes.clamp(1, UNLIMITED - 1),
+    };
+    LIMIT.store(stored, Ordering::Relaxed);
+}
+
+/// Returns the limit in bytes of the entries of a diff.
+pub(crate) fn diff_memory_limit() -> usize {
+    match LIMIT.load(Ordering::Relaxed) {
+        UNSET => from_env(),
+        limit => limit,
+    }
+}
+
+fn from_env() -> usize {
+    static FROM_ENV: OnceLock<usize> = OnceLock::new();
+    *FROM_ENV.get_or_init(|| {
+        env::var(LIMIT_VAR)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DIFF_MEMORY_LIMIT)
+    })
+}
+
+/// Renders a limit as `1MB`, `64KB` or `1000 bytes`.
+pub(crate) fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        UNLIMITED => "no limit".to_string(),
+        bytes if bytes >= 1 << 20 && bytes.is_multiple_of(1 << 20) => {
+            format!("{}MB", bytes >> 20)
+        }
+        bytes if bytes >= 1 << 10 && bytes.is_multiple_of(1 << 10) => {
+            format!("{}KB", bytes >> 10)
+        }
+        1 => "1 byte".to_string(),
+        bytes => format!("{bytes} bytes"),
+    }
+}
+
+/// Renders a count with thousands separators, like `42,113`.
+pub(crate) fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Diff, DiffPath, Diffable};
+
+    #[test]
+    fn counts_differences_past_the_limit() {
+        let mut diff = Diff::with_memory_limit(1000);
+        let expected: Vec<String> = (0..100).map(|n| n.to_string()).collect();
+        expected.diff(&vec![String::new(); 100], &mut DiffPath::new(), &mut diff);
+
+        assert!(diff.len() > 1 && diff.len() < 100);
+        assert_eq!(diff.len() + diff.capped(), 100);
+        assert!(diff.to_string().ends_with(&format!(
+            "output capped at 1000 bytes; {} additional differences counted\n",
+            diff.capped()
+        )));
+
+        let mut merged = Diff::with_memory_limit(0);
+        merged.append(diff.clone());
+        assert_eq!((merged.len(), merged.capped()), (1, 99));
+    }
+
+    #[test]
+    fn formats_limits_and_counts() {
+        assert_eq!(format_bytes(1 << 20), "1MB");
+        assert_eq!(format_bytes(64 << 10), "64KB");
+        assert_eq!(format_bytes(1000), "1000 bytes");
+        assert_eq!(format_count(42_113), "42,113");
+        assert_eq!(format_count(1_000_000), "1,000,000");
+        assert_eq!(format_count(7), "7");
+    }
+}
@@ -0,0 +1,302 @@
+//! Line diffs of long multiline strings.
+//!
+//! When two long strings with several lines differ, printing both in full
+//! buries the change. Instead, the differing lines are shown like a unified
+//! diff, `-` for expected and `+` for actual lines, with up to three equal
+//! lines of context around each hunk.
+
+use std::env;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable which sets the threshold when
+/// [`set_line_diff_threshold`] hasn't been called.
+const THRESHOLD_VAR: &str = "CMP_LINE_DIFF";
+
+/// Length, in bytes of the rendered strings, above which multiline strings
+/// are diffed by line unless configured otherwise.
+pub const DEFAULT_LINE_DIFF_THRESHOLD: usize = 400;
+
+/// Threshold set with [`set_line_diff_threshold`], or `usize::MAX` if unset.
+static THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Equal lines shown before and after each change.
+const CONTEXT: usize = 3;
+
+/// Most pairs of lines compared to find the changes, past which the strings
+/// are printed in full instead.
+const MAX_COMPARED: usize = 1 << 20;
+
+/// Sets the length above which differing multiline strings are rendered as a
+/// line diff, overriding `CMP_LINE_DIFF`, or disables line diffs if `0`.
+///
+/// The length is that of the longer rendered string, escapes included, and
+/// defaults to [`DEFAULT_LINE_DIFF_THRESHOLD`].
+///
+/// ```edition2024
+/// use cmp::Diffable;
+///
+/// cmp::set_line_diff_threshold(10);
+///
+/// let diff = "a\nb\nc\nd".to_string().compare(&"a\nb\nC\nd".to_string());
+/// assert_eq!(
+///     diff.to_string(),
+///     ": lines differ:\n@@ -1,4 +1,4 @@\n a\n b\n-c\n+C\n d\n"
+/// );
+/// # cmp::set_line_diff_threshold(cmp::DEFAULT_LINE_DIFF_THRESHOLD);
+/// ```
+pub fn set_line_diff_threshold(len: usize) {
+    THRESHOLD.store(len, Ordering::Relaxed);
+}
+
+/// Returns the length above which multiline strings are diffed by line, or
+/// `0` if they never are.
+fn threshold() -> usize {
+    match THRESHOLD.load(Ordering::Relaxed) {
+        usize::MAX => from_env(),
+        len => len,
+    }
+}
+
+fn from_env() -> usize {
+    static FROM_ENV: OnceLock<usize> = OnceLock::new();
+    *FROM_ENV.get_or_init(|| {
+        env::var(THRESHOLD_VAR)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_LINE_DIFF_THRESHOLD)
+    })
+}
+
+/// A line of a line diff.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Line<'a> {
+    /// Heads a hunk, e.g. `@@ -3,7 +3,6 @@`.
+    Hunk(String),
+    /// A line of both strings.
+    Equal(&'a str),
+    /// A line only in the expected string.
+    Removed(&'a str),
+    /// A line only in the actual string.
+    Added(&'a str),
+}
+
+/// Returns the line diff of two `Debug`-rendered string literals, or `None`
+/// if they aren't long multiline strings.
+///
+/// Lines keep their escapes, so tabs and other invisible characters stay
+/// visible.
+pub(crate) fn diff<'a>(expected: &'a str, actual: &'a str) -> Option<Vec<Line<'a>>> {
+    let threshold = threshold();
+    if threshold == 0 || expected.len().max(actual.len()) <= threshold {
+        return None;
+    }
+    let expected = split(expected)?;
+    let actual = split(actual)?;
+    if expected.len() < 2 && actual.len() < 2
+        || expected.len().saturating_mul(actual.len()) > MAX_COMPARED
+    {
+        return None;
+    }
+    Some(hunks(&expected, &actual))
+}
+
+/// Splits a `Debug`-rendered string literal at its `\n` escapes.
+fn split(literal: &str) -> Option<Vec<&str>> {
+    let inner = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut chars = inner.char_indices();
+    while let Some((index, c)) = chars.next() {
+        if c == '\\' && chars.next() == Some((index + 1, 'n')) {
+            lines.push(&inner[start..index]);
+            start = index + 2;
+        }
+    }
+    lines.push(&inner[start..]);
+    Some(lines)
+}
+
+/// An edit turning the expected lines into the actual ones.
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    Equal,
+    Removed,
+    Added,
+}
+
+/// Groups the changes between two lists of lines into hunks with context.
+fn hunks<'a>(expected: &[&'a str], actual: &[&'a str]) -> Vec<Line<'a>> {
+    // Longest common subsequence of the lines after each position.
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Each edit, with the positions in both lists before it.
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        let edit = if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            Edit::Equal
+        } else if j == actual.len() || (i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            Edit::Removed
+        } else {
+            Edit::Added
+        };
+        edits.push((edit, i, j));
+        match edit {
+            Edit::Equal => (i, j) = (i + 1, j + 1),
+            Edit::Removed => i += 1,
+            Edit::Added => j += 1,
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut next = 0;
+    while let Some(offset) = edits[next..]
+        .iter()
+        .position(|(edit, ..)| *edit != Edit::Equal)
+    {
+        let start = (next + offset).saturating_sub(CONTEXT).max(next);
+        // Extend the hunk until more than twice the context of equal lines
+        // separates it from the next change.
+        let mut end = next + offset;
+        let mut equal = 0;
+        for (index, (edit, ..)) in edits.iter().enumerate().skip(end) {
+            if *edit == Edit::Equal {
+                equal += 1;
+                if equal > 2 * CONTEXT {
+                    break;
+                }
+            } else {
+                equal = 0;
+                end = index;
+            }
+        }
+        let end = (end + CONTEXT + 1).min(edits.len());
+
+        let hunk = &edits[start..end];
+        let (_, first_expected, first_actual) = hunk[0];
+        let removed = hunk
+            .iter()
+            .filter(|(edit, ..)| *edit != Edit::Added)
+            .count();
+        let added = hunk
+            .iter()
+            .filter(|(edit, ..)| *edit != Edit::Removed)
+            .count();
+        lines.push(Line::Hunk(format!(
+            "@@ -{} +{} @@",
+            range(first_expected, removed),
+            range(first_actual, added)
+        )));
+        lines.extend(hunk.iter().map(|&(edit, i, j)| match edit {
+            Edit::Equal => Line::Equal(expected[i]),
+            Edit::Removed => Line::Removed(expected[i]),
+            Edit::Added => Line::Added(actual[j]),
+        }));
+        next = end;
+    }
+    lines
+}
+
+/// Renders the range of a hunk on one side, as in unified diffs.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        len => format!("{},{len}", start + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(lines: &[&str]) -> String {
+        format!("{:?}", lines.join("\n"))
+    }
+
+    #[test]
+    fn splits_literals_at_escaped_line_feeds_only() {
+        assert_eq!(split(r#""a\nb\\nc\n""#).unwrap(), ["a", r"b\\nc", ""]);
+        assert_eq!(split("1"), None);
+    }
+
+    #[test]
+    fn groups_changes_into_hunks_with_context() {
+        let expected: Vec<String> = (1..=20).map(|n| format!("line {n}")).collect();
+        let mut actual = expected.clone();
+        actual[1] = "changed".to_string();
+        actual.remove(15);
+        let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+        let actual: Vec<&str> = actual.iter().map(String::as_str).collect();
+
+        let rendered: Vec<String> = hunks(&expected, &actual)
+            .into_iter()
+            .map(|line| match line {
+                Line::Hunk(header) => header,
+                Line::Equal(line) => format!(" {line}"),
+                Line::Removed(line) => format!("-{line}"),
+                Line::Added(line) => format!("+{line}"),
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "@@ -1,5 +1,5 @@",
+                " line 1",
+                "-line 2",
+                "+changed",
+                " line 3",
+                " line 4",
+                " line 5",
+                "@@ -13,7 +13,6 @@",
+                " line 13",
+                " line 14",
+                " line 15",
+                "-line 16",
+                " line 17",
+                " line 18",
+                " line 19",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_diffs_long_multiline_strings() {
+        let long = |last: &str| literal(&[&"x".repeat(DEFAULT_LINE_DIFF_THRESHOLD), last]);
+        assert!(diff(&long("a"), &long("b")).is_some());
+        assert!(diff(&literal(&["a", "b"]), &literal(&["a", "c"])).is_none());
+        let single = format!("{:?}", "x".repeat(2 * DEFAULT_LINE_DIFF_THRESHOLD));
+        assert!(diff(&single, "\"y\"").is_none());
+    }
+
+    #[test]
+    fn marks_whitespace_changes_in_changed_lines() {
+        let long = |last: &str| literal(&[&"x".repeat(DEFAULT_LINE_DIFF_THRESHOLD), last]);
+        let changed = |expected: &str, actual: &str| crate::FieldDiff::Changed {
+            path: crate::DiffPath::new(),
+            expected: long(expected),
+            actual: long(actual),
+        };
+
+        let rendered = changed("total: 1 ", "total: 1").to_string();
+        assert!(rendered.ends_with("\n-total:␠1␠\n+total:␠1"), "{rendered}");
+
+        let rendered = changed("caf\u{fffd}", "café").to_string();
+        assert!(
+            rendered.ends_with(
+                "\n-caf\u{fffd}\n+café\n(expected contains 1 replacement character (U+FFFD) from invalid UTF-8)"
+            ),
+            "{rendered}"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Key-by-key comparison of `HashMap` and `BTreeMap` fields named in
+//! `compare_structs!`, so only the added, removed and changed keys are
+//! reported rather than both entire maps.
+//!
+//! Unlike the [`Diffable`](crate::Diffable) implementations of maps, the
+//! values only need `PartialEq` and `Debug`, like any other named field.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+
+use crate::{Diff, FieldDiff};
+
+/// The expected and actual values of a field named in a macro invocation.
+///
+/// Call `(&Field(expected, actual)).diff_field(name, out)` with both
+/// [`DiffMapField`] and [`DiffEqField`] in scope: method resolution prefers
+/// the map and float implementations and falls back to `PartialEq` for other
+/// types.
+/// The path of the field is only built from its `name` if it differs.
+pub struct Field<'a, T: ?Sized>(pub &'a T, pub &'a T);
+
+pub trait DiffMapField {
+    fn diff_field(&self, name: &str, out: &mut Diff);
+}
+
+impl<K: Debug + Eq + Hash, V: PartialEq + Debug, S: BuildHasher> DiffMapField
+    for Field<'_, HashMap<K, V, S>>
+{
+    fn diff_field(&self, name: &str, out: &mut Diff) {
+        let Field(expected, actual) = *self;
+        if expected == actual {
+            return;
+        }
+        let mut entries: Vec<(&K, &V)> = expected.iter().collect();
+        entries.sort_by_cached_key(|(key, _)| format!("{key:?}"));
+        let mut added: Vec<(&K, &V)> = actual
+            .iter()
+            .filter(|(key, _)| !expected.contains_key(*key))
+            .collect();
+        added.sort_by_cached_key(|(key, _)| format!("{key:?}"));
+        diff_entries(entries, |key| actual.get(key), added, name, out);
+    }
+}
+
+impl<K: Debug + Ord, V: PartialEq + Debug> DiffMapField for Field<'_, BTreeMap<K, V>> {
+    fn diff_field(&self, name: &str, out: &mut Diff) {
+        let Field(expected, actual) = *self;
+        if expected == actual {
+            return;
+        }
+        let added = actual
+            .iter()
+            .filter(|(key, _)| !expected.contains_key(*key))
+            .collect();
+        diff_entries(
+            expected.iter().collect(),
+            |key| actual.get(key),
+            added,
+            name,
+            out,
+        );
+    }
+}
+
+/// Floats are rendered in the fixed float format rather than with `Debug`,
+/// like in [`Diffable`](crate::Diffable) comparisons.
+macro_rules! impl_diff_float_field {
+    ($($ty:ty),+) => {
+        $(
+            impl DiffMapField for Field<'_, $ty> {
+                fn diff_field(&self, name: &str, out: &mut Diff) {
+                    let Field(expected, actual) = *self;
+                    if expected != actual {
+                        out.push(FieldDiff::Changed {
+                            path: crate::__private::field_path(name),
+                            expected: crate::float::render(*expected),
+                            actual: crate::float::render(*actual),
+                        });
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_diff_float_field!(f32, f64);
+
+/// References may borrow for less than `'static`, so their values are
+/// rendered with `Debug` rather than a [`ValueFormatter`].
+///
+/// [`ValueFormatter`]: crate::ValueFormatter
+impl<T: PartialEq + Debug + ?Sized> DiffMapField for Field<'_, &T> {
+    fn diff_field(&self, name: &str, out: &mut Diff) {
+        let Field(expected, actual) = *self;
+        if expected != actual {
+            out.push(FieldDiff::Changed {
+                path: crate::__private::field_path(name),
+                expected: crate::formatters::render_debug(expected),
+                actual: crate::formatters::render_debug(actual),
+            });
+        }
+    }
+}
+
+pub trait DiffEqField {
+    fn diff_field(&self, name: &str, out: &mut Diff);
+}
+
+impl<T: PartialEq + Debug + ?Sized + 'static> DiffEqField for &Field<'_, T> {
+    fn diff_field(&self, name: &str, out: &mut Diff) {
+        let Field(expected, actual) = **self;
+        if expected != actual {
+            out.push(FieldDiff::Changed {
+                path: crate::__private::field_path(name),
+                expected: crate::format_value(expected),
+                actual: crate::format_value(actual),
+            });
+        }
+    }
+}
+
+/// Reports the expected entries whose key is missing from `actual` or whose
+/// value differs, then the `added` entries.
+fn diff_entries<'a, K: Debug + 'a, V: PartialEq + Debug + 'a>(
+    expected: Vec<(&'a K, &'a V)>,
+    actual: impl Fn(&K) -> Option<&'a V>,
+    added: Vec<(&'a K, &'a V)>,
+    name: &str,
+    out: &mut Diff,
+) {
+    let mut path = crate::__private::field_path(name);
+    for (key, expected) in expected {
+        path.push_key(key);
+        match actual(key) {
+            Some(actual) if actual == expected => {}
+            Some(actual) => out.push(FieldDiff::Changed {
+                path: path.clone(),
+                expected: crate::formatters::render_debug(expected),
+                actual: crate::formatters::render_debug(actual),
+            }),
+            None => out.push(FieldDiff::MissingFromActual {
+                path: path.clone(),
+                expected: crate::formatters::render_debug(expected),
+            }),
+        }
+        path.pop();
+    }
+    for (key, actual) in added {
+        path.push_key(key);
+        out.push(FieldDiff::MissingFromExpected {
+            path: path.clone(),
+            actual: crate::formatters::render_debug(actual),
+        });
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        env: HashMap<&'static str, &'static str>,
+        limits: BTreeMap<u32, f64>,
+        name: &'static str,
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_keys() {
+        let expected = Config {
+            env: HashMap::from([("HOME", "/root"), ("PATH", "/bin"), ("TERM", "xterm")]),
+            limits: BTreeMap::from([(1, 0.5), (2, 1.0)]),
+            name: "a",
+        };
+        let actual = Config {
+            env: HashMap::from([("HOME", "/home"), ("LANG", "C"), ("TERM", "xterm")]),
+            limits: BTreeMap::from([(1, 0.5), (3, 2.0)]),
+            name: "b",
+        };
+
+        assert_eq!(
+            crate::diff_structs!(expected, actual, env, limits, name).to_string(),
+            "env[\"HOME\"]: \"/root\" != \"/home\"\n\
+             env[\"PATH\"]: field missing from actual: \"/bin\"\n\
+             env[\"LANG\"]: field missing from expected: \"C\"\n\
+             limits[2]: field missing from actual: 1.0\n\
+             limits[3]: field missing from expected: 2.0\n\
+             name: \"a\" != \"b\"\n"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Document model shared by the `xml` and `html` features.
+//!
+//! Both parsers lower their input into [`Node`]s, dropping comments and
+//! insignificant whitespace and storing attributes in a sorted map, so the
+//! structural walk below does not care about attribute order or formatting.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) namespace: Option<String>,
+    pub(crate) attributes: BTreeMap<AttributeName, String>,
+    pub(crate) children: Vec<Node>,
+}
+
+/// An attribute name with its namespace, so attributes of the same local name
+/// in different namespaces are told apart.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct AttributeName {
+    pub(crate) namespace: Option<String>,
+    pub(crate) name: String,
+}
+
+impl AttributeName {
+    /// Names an attribute without a namespace.
+    #[cfg(feature = "html")]
+    pub(crate) fn local(name: String) -> Self {
+        Self {
+            namespace: None,
+            name,
+        }
+    }
+}
+
+/// Renders the name in Clark notation, `{namespace}name`, if it has a
+/// namespace.
+impl fmt::Display for AttributeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{{{namespace}}}{}", self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+impl Node {
+    fn describe(&self) -> String {
+        match self {
+            Node::Element(element) => format!("<{}>", element.name),
+            Node::Text(text) => format!("{text:?}"),
+        }
+    }
+
+    fn segment_name(&self) -> &str {
+        match self {
+            Node::Element(element) => &element.name,
+            Node::Text(_) => "text()",
+        }
+    }
+}
+
+/// Error returned when one side of a markup comparison cannot be parsed.
+#[derive(Debug)]
+pub struct ParseError {
+    side: &'static str,
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(side: &'static str, message: impl fmt::Display) -> Self {
+        Self {
+            side,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not parse {} value: {}", self.side, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Walks both node lists position by position, pushing one line per
+/// difference in the form `path: expected != actual`.
+pub(crate) fn diff_nodes(expected: &[Node], actual: &[Node], path: &str, diffs: &mut Vec<String>) {
+    let len = expected.len().max(actual.len());
+    for index in 0..len {
+        match (expected.get(index), actual.get(index)) {
+            (Some(expected_node), Some(actual_node)) => {
+                let node_path = format!("{path}/{}", segment(expected, index));
+                diff_node(expected_node, actual_node, &node_path, diffs);
+            }
+            (Some(expected_node), None) => diffs.push(format!(
+                "{path}/{}: node missing from actual: {}",
+                segment(expected, index),
+                expected_node.describe()
+            )),
+            (None, Some(actual_node)) => diffs.push(format!(
+                "{path}/{}: node missing from expected: {}",
+                segment(actual, index),
+                actual_node.describe()
+            )),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+fn diff_node(expected: &Node, actual: &Node, path: &str, diffs: &mut Vec<String>) {
+    match (expected, actual) {
+        (Node::Text(expected_text), Node::Text(actual_text)) => {
+            if expected_text != actual_text {
+                diffs.push(format!("{path}: {expected_text:?} != {actual_text:?}"));
+            }
+        }
+        (Node::Element(expected_element), Node::Element(actual_element))
+            if expected_element.name == actual_element.name =>
+        {
+            if expected_element.namespace != actual_element.namespace {
+                diffs.push(format!(
+                    "{path}/namespace-uri(): {:?} != {:?}",
+                    expected_element.namespace.as_deref().unwrap_or_default(),
+                    actual_element.namespace.as_deref().unwrap_or_default()
+                ));
+            }
+            diff_attributes(expected_element, actual_element, path, diffs);
+            diff_nodes(
+                &expected_element.children,
+                &actual_element.children,
+                path,
+                diffs,
+            );
+        }
+        _ => diffs.push(format!(
+            "{path}: {} != {}",
+            expected.describe(),
+            actual.describe()
+        )),
+    }
+}
+
+fn diff_attributes(expected: &Element, actual: &Element, path: &str, diffs: &mut Vec<String>) {
+    for (name, expected_value) in &expected.attributes {
+        match actual.attributes.get(name) {
+            Some(actual_value) if actual_value != expected_value => {
+                diffs.push(format!(
+                    "{path}/@{name}: {expected_value:?} != {actual_value:?}"
+                ));
+            }
+            Some(_) => {}
+            None => diffs.push(format!(
+                "{path}/@{name}: attribute missing from actual: {expected_value:?}"
+            )),
+        }
+    }
+    for (name, actual_value) in &actual.attributes {
+        if !expected.attributes.contains_key(name) {
+            diffs.push(format!(
+                "{path}/@{name}: attribute missing from expected: {actual_value:?}"
+            ));
+        }
+    }
+}
+
+/// Renders the XPath-like step for `siblings[index]`, adding a 1-based
+/// position only when several siblings share the same name.
+fn segment(siblings: &[Node], index: usize) -> String {
+    let name = siblings[index].segment_name();
+    let same_name = |node: &&Node| node.segment_name() == name;
+    let position = siblings[..index].iter().filter(same_name).count() + 1;
+    if siblings.iter().filter(same_name).count() > 1 {
+        format!("{name}[{position}]")
+    } else {
+        name.to_string()
+    }
+}
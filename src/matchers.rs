@@ -0,0 +1,149 @@
+//! Matchers for `field => matcher` in [`compare_structs!`](crate::compare_structs),
+//! which check a field without pinning its value, for generated IDs,
+//! timestamps and other values which differ on every run.
+//!
+//! A matcher which ignores the expected value describes what it expected
+//! when it fails, and that description is reported instead of the expected
+//! value.
+
+use std::cell::RefCell;
+
+thread_local! {
+    /// What the last failing matcher expected, taken by the `=> matcher` arm.
+    static EXPECTATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records what a failing matcher expected, to be reported instead of the
+/// expected value.
+#[cfg(feature = "regex")]
+fn expect(description: String) {
+    EXPECTATION.with(|expectation| *expectation.borrow_mut() = Some(description));
+}
+
+/// Takes the description recorded by the last failing matcher, if any.
+pub fn take_expectation() -> Option<String> {
+    EXPECTATION.with(|expectation| expectation.borrow_mut().take())
+}
+
+/// Returns a comparator accepting any pair of values, for a field which must
+/// exist with its type but whose value isn't checked:
+/// `compare_structs!(expected, actual, id => any(), name)`.
+///
+/// The field's type is still checked by the compiler, and renaming or
+/// removing the field still fails to compile, unlike leaving it out of the
+/// comparison.
+///
+/// ```edition2024
+/// use cmp::{any, compare_structs};
+///
+/// #[derive(Debug)]
+/// struct Created { id: String, name: &'static str }
+///
+/// let expected = Created { id: String::new(), name: "ada" };
+/// let actual = Created { id: "usr_3f2a91c0".into(), name: "ada" };
+///
+/// compare_structs!(expected, actual, id => any(), name);
+/// ```
+pub fn any<T: ?Sized>() -> fn(&T, &T) -> bool {
+    |_, _| true
+}
+
+/// Returns a comparator accepting string values which match the regular
+/// expression `pattern`, whatever the expected value, for IDs and tokens
+/// with a known shape: `compare_structs!(expected, actual, id =>
+/// matches(r"^usr_[0-9a-f]{8}$"))`. Requires the `regex` feature.
+///
+/// A failing field is reported with the pattern in place of the expected
+/// value, e.g. `id: matching /^usr_[0-9a-f]{8}$/ != "usr_42"`. Patterns
+/// aren't anchored, so use `^` and `$` to match whole values.
+///
+/// ```edition2024
+/// # #[cfg(feature = "regex")]
+/// # {
+/// use cmp::{diff_structs, matches};
+///
+/// struct User { id: String, name: &'static str }
+///
+/// let expected = User { id: String::new(), name: "ada" };
+/// let actual = User { id: "usr_42".into(), name: "ada" };
+///
+/// assert_eq!(
+///     diff_structs!(expected, actual, id => matches(r"^usr_[0-9a-f]{8}$"), name).to_string(),
+///     "id: matching /^usr_[0-9a-f]{8}$/ != \"usr_42\"\n"
+/// );
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `pattern` is not a valid regular expression.
+#[cfg(feature = "regex")]
+pub fn matches<T: AsRef<str> + ?Sized>(pattern: &str) -> impl Fn(&T, &T) -> bool {
+    let regex = regex::Regex::new(pattern)
+        .unwrap_or_else(|err| panic!("invalid pattern /{pattern}/: {err}"));
+    move |_, actual| {
+        let matched = regex.is_match(actual.as_ref());
+        if !matched {
+            expect(format!("matching /{}/", regex.as_str()));
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Event {
+        id: u64,
+        at: std::time::SystemTime,
+        kind: &'static str,
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn matches_reports_the_failed_pattern() {
+        struct Token {
+            id: &'static str,
+            scope: String,
+        }
+
+        let expected = Token {
+            id: "",
+            scope: "read".into(),
+        };
+        let passing = Token {
+            id: "tok_1f",
+            scope: "read".into(),
+        };
+        let failing = Token {
+            id: "tok_",
+            scope: "write".into(),
+        };
+
+        assert!(
+            crate::diff_structs!(expected, passing, id => matches("^tok_[0-9a-f]+$"), scope)
+                .is_empty()
+        );
+        assert_eq!(
+            crate::diff_structs!(expected, failing, id => matches("^tok_[0-9a-f]+$"), scope => matches("^read$"))
+                .to_string(),
+            "id: matching /^tok_[0-9a-f]+$/ != \"tok_\"\n\
+             scope: matching /^read$/ != \"write\"\n"
+        );
+        assert_eq!(take_expectation(), None);
+    }
+
+    #[test]
+    fn any_accepts_every_value() {
+        let event = |id, kind| Event {
+            id,
+            at: std::time::SystemTime::now(),
+            kind,
+        };
+        let (expected, actual) = (event(0, "login"), event(41, "logout"));
+
+        let diff = crate::diff_structs!(expected, actual, id => any(), at => any(), kind);
+        assert_eq!(diff.to_string(), "kind: \"login\" != \"logout\"\n");
+    }
+}
@@ -0,0 +1,139 @@
+//! Memoization of serialized values within a scope, enabled by the `serde`
+//! feature, so helpers comparing the same large values several times only
+//! serialize them once.
+//!
+//! Values are keyed by their address, type and size, which only identify a
+//! value while it is alive and unchanged. Memoization is therefore opt-in
+//! and limited to the closure passed to [`memoize`].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::Serialize;
+
+use crate::serialize::{SerializeError, Serialized};
+
+/// Identity of a serialized value: its address, type name and size.
+type Key = (usize, &'static str, usize);
+
+thread_local! {
+    static CACHE: RefCell<Option<HashMap<Key, Rc<Serialized>>>> = const { RefCell::new(None) };
+}
+
+/// Runs `compare` with the serialized forms of the values compared by
+/// `compare_structs!` in serde mode memoized by their address, so comparing
+/// the same values again doesn't serialize them again.
+///
+/// Only compare values which stay alive and unchanged for the whole
+/// closure: a value mutated in place, or a new value of the same type at the
+/// address of a dropped one, would be compared as the value first
+/// serialized there. The cache is dropped when the outermost `memoize`
+/// returns, or panics.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::compare_structs;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Report { rows: Vec<u64> }
+///
+/// fn check(expected: &Report, actual: &Report) {
+///     compare_structs!(expected, actual);
+/// }
+///
+/// let expected = Report { rows: (0..10_000).collect() };
+/// let actual = Report { rows: (0..10_000).collect() };
+///
+/// cmp::memoize(|| {
+///     check(&expected, &actual);
+///     check(&expected, &actual); // Serializes neither value again.
+/// });
+/// # }
+/// ```
+pub fn memoize<R>(compare: impl FnOnce() -> R) -> R {
+    /// Drops the cache of the outermost scope, even if `compare` panics.
+    struct Clear(bool);
+
+    impl Drop for Clear {
+        fn drop(&mut self) {
+            if self.0 {
+                CACHE.with(|cache| *cache.borrow_mut() = None);
+            }
+        }
+    }
+
+    let outermost = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let outermost = cache.is_none();
+        cache.get_or_insert_with(HashMap::new);
+        outermost
+    });
+    let _clear = Clear(outermost);
+    compare()
+}
+
+/// Serializes the `side` value of a comparison, or returns its memoized
+/// form inside [`memoize`].
+///
+/// Values with fields left out because they couldn't be serialized aren't
+/// memoized, as they depend on the [`SerializationFailure`] behavior.
+///
+/// [`SerializationFailure`]: crate::SerializationFailure
+pub(crate) fn serialize<T: Serialize + ?Sized>(
+    value: &T,
+    side: &'static str,
+) -> Result<Rc<Serialized>, SerializeError> {
+    let key = (
+        (value as *const T).cast::<()>() as usize,
+        std::any::type_name::<T>(),
+        size_of_val(value),
+    );
+    if let Some(value) = CACHE.with(|cache| cache.borrow().as_ref()?.get(&key).cloned()) {
+        return Ok(value);
+    }
+    let serialized = Rc::new(crate::serialize::serialize(value, side)?);
+    if serialized.skipped.is_empty() {
+        CACHE.with(|cache| {
+            if let Some(cache) = cache.borrow_mut().as_mut() {
+                cache.insert(key, Rc::clone(&serialized));
+            }
+        });
+    }
+    Ok(serialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use serde::Serializer;
+
+    use super::*;
+
+    /// Counts how many times it is serialized.
+    struct Counted<'a>(&'a Cell<u32>);
+
+    impl Serialize for Counted<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.set(self.0.get() + 1);
+            serializer.serialize_u32(self.0.get())
+        }
+    }
+
+    #[test]
+    fn serializes_each_value_once_per_scope() {
+        let count = Cell::new(0);
+        let value = Counted(&count);
+
+        memoize(|| {
+            assert_eq!(serialize(&value, "expected").unwrap().value, 1);
+            memoize(|| assert_eq!(serialize(&value, "expected").unwrap().value, 1));
+            assert_eq!(serialize(&value, "expected").unwrap().value, 1);
+        });
+        assert_eq!(serialize(&value, "expected").unwrap().value, 2);
+        assert!(CACHE.with(|cache| cache.borrow().is_none()));
+    }
+}
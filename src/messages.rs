@@ -0,0 +1,246 @@
+//! The wording of rendered differences and failure headers, which can be
+//! replaced to localize output or match a house test-report style.
+
+use std::cell::RefCell;
+use std::sync::{Arc, PoisonError, RwLock};
+
+static DEFAULT: RwLock<Option<Arc<dyn Messages>>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<dyn Messages>>> = const { RefCell::new(None) };
+}
+
+/// The fixed text of rendered differences, such as `!=` and `field missing
+/// from actual`.
+///
+/// Every method defaults to the English text, so an implementation only
+/// overrides what it rephrases. Values, paths and notes such as
+/// `(expected contains a byte-order mark)` aren't affected.
+///
+/// Set the messages for one comparison with [`with_messages`], or for the
+/// whole process with [`set_messages`]:
+///
+/// ```edition2024
+/// use cmp::{Diffable, Messages};
+///
+/// struct German;
+///
+/// impl Messages for German {
+///     fn not_equal(&self) -> &str {
+///         "≠"
+///     }
+///
+///     fn missing_from_actual(&self) -> &str {
+///         "fehlt im Ist-Wert"
+///     }
+/// }
+///
+/// let diff = vec![1, 2].compare(&vec![1]);
+///
+/// assert_eq!(cmp::with_messages(German, || diff.to_string()), "[1]: fehlt im Ist-Wert: 2\n");
+/// ```
+pub trait Messages: Send + Sync {
+    /// Separates the expected and actual values of a changed field.
+    fn not_equal(&self) -> &str {
+        "!="
+    }
+
+    /// Precedes the value of a field which only exists on the actual side.
+    fn missing_from_expected(&self) -> &str {
+        "field missing from expected"
+    }
+
+    /// Precedes the value of a field which only exists on the expected side.
+    fn missing_from_actual(&self) -> &str {
+        "field missing from actual"
+    }
+
+    /// Marks context lines showing equal values next to the differences.
+    fn equal(&self) -> &str {
+        "equal"
+    }
+
+    /// Suggests the similarly named field `name` for a missing field.
+    fn did_you_mean(&self, name: &str) -> String {
+        format!("did you mean {name:?}?")
+    }
+
+    /// Ends a diff which reached its memory limit of `limit` bytes, after
+    /// which `count` more differences were only counted.
+    fn capped(&self, limit: usize, count: usize) -> String {
+        format!(
+            "output capped at {}; {} additional differences counted",
+            crate::limit::format_bytes(limit),
+            crate::limit::format_count(count)
+        )
+    }
+
+    /// Labels the expected side in `CMP_CAPTURE` headers.
+    fn expected(&self) -> &str {
+        "expected"
+    }
+
+    /// Labels the actual side in `CMP_CAPTURE` headers.
+    fn actual(&self) -> &str {
+        "actual"
+    }
+
+    /// Heads the differences of a [`StructDiff`](crate::StructDiff), with
+    /// the source text of the compared expressions.
+    fn differ_in(&self, expected: &str, actual: &str, fields: usize) -> String {
+        let plural = if fields == 1 { "" } else { "s" };
+        format!("`{expected}` and `{actual}` differ in {fields} field{plural}")
+    }
+
+    /// Explains a failed [`compare_structs_ne!`](crate::compare_structs_ne),
+    /// with the source text of the compared expressions and of the listed
+    /// `fields`, if any.
+    fn unexpectedly_equal(&self, expected: &str, actual: &str, fields: Option<&str>) -> String {
+        let mut message = format!(
+            "`{expected}` and `{actual}` were expected to differ, but are equal in every compared field"
+        );
+        if let Some(fields) = fields {
+            message += &format!(": {fields}");
+        }
+        message
+    }
+
+    /// Explains a failed `compare_structs!(a, b; bytes = ...)` of values
+    /// which are equal but serialize to different bytes with `encoding`.
+    fn serialized_differently(&self, expected: &str, actual: &str, encoding: &str) -> String {
+        format!("`{expected}` and `{actual}` are equal, but serialize to different {encoding}")
+    }
+
+    /// Labels a difference found by comparing `Debug` output, such as for a
+    /// field marked `#[cmp(debug_eq)]`.
+    fn compared_by_debug(&self) -> &str {
+        "compared by Debug output"
+    }
+
+    /// Ends a rendered value cut to its [budget](crate::with_max_render_len),
+    /// after which `omitted` bytes were left out.
+    fn truncated(&self, omitted: usize) -> String {
+        format!("…({omitted} more bytes)")
+    }
+
+    /// Heads the line diff of two long multiline strings.
+    fn lines_differ(&self) -> &str {
+        "lines differ"
+    }
+
+    /// Renders a field [skipped](crate::SerializationFailure::Skip) because
+    /// it couldn't be serialized, with the serializer's error `message`.
+    fn not_serializable(&self, message: &str) -> String {
+        format!("<could not serialize: {message}>")
+    }
+
+    /// Heads the failures collected by a [`Comparer`](crate::Comparer).
+    fn comparisons_failed(&self, failed: usize, total: usize) -> String {
+        let plural = if total == 1 { "" } else { "s" };
+        format!("{failed} of {total} comparison{plural} failed")
+    }
+}
+
+/// The default English messages.
+struct English;
+
+impl Messages for English {}
+
+/// Sets the process-wide messages, used when none are set for a comparison.
+pub fn set_messages(messages: impl Messages + 'static) {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(messages));
+}
+
+/// Resets the process-wide messages to English.
+pub fn clear_messages() {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `render` with `messages` as the messages of the current thread,
+/// overriding the process-wide ones.
+pub fn with_messages<R>(messages: impl Messages + 'static, render: impl FnOnce() -> R) -> R {
+    /// Restores the previous messages, even if `render` panics.
+    struct Restore(Option<Arc<dyn Messages>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let messages: Arc<dyn Messages> = Arc::new(messages);
+    let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(messages)));
+    render()
+}
+
+/// Returns the messages of the current comparison.
+pub(crate) fn messages() -> Arc<dyn Messages> {
+    SCOPED
+        .with(|scoped| scoped.borrow().clone())
+        .or_else(|| {
+            DEFAULT
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+        .unwrap_or_else(|| Arc::new(English))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Diff, DiffPath, FieldDiff, StructDiff};
+
+    struct Terse;
+
+    impl Messages for Terse {
+        fn not_equal(&self) -> &str {
+            "vs"
+        }
+
+        fn missing_from_expected(&self) -> &str {
+            "unexpected"
+        }
+
+        fn did_you_mean(&self, name: &str) -> String {
+            format!("maybe {name}")
+        }
+
+        fn differ_in(&self, _: &str, _: &str, fields: usize) -> String {
+            format!("{fields} mismatches")
+        }
+    }
+
+    fn field(name: &str) -> DiffPath {
+        let mut path = DiffPath::new();
+        path.push_field(name);
+        path
+    }
+
+    #[test]
+    fn renders_differences_with_the_current_messages() {
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::Changed {
+            path: field("id"),
+            expected: "1".into(),
+            actual: "2".into(),
+        });
+        diff.push(FieldDiff::MissingFromActual {
+            path: field("user_name"),
+            expected: "\"ada\"".into(),
+        });
+        diff.push(FieldDiff::MissingFromExpected {
+            path: field("userName"),
+            actual: "\"ada\"".into(),
+        });
+        let failure = StructDiff::check(diff.clone(), "a", "b").unwrap_err();
+
+        assert_eq!(
+            with_messages(Terse, || failure.to_string()),
+            "3 mismatches\nid: 1 vs 2\n\
+             user_name: field missing from actual: \"ada\" (maybe userName)\n\
+             userName: unexpected: \"ada\"\n"
+        );
+        assert!(diff.to_string().starts_with("id: 1 != 2\n"));
+    }
+}
@@ -0,0 +1,314 @@
+//! Counters of comparison outcomes, recorded by a process-wide
+//! [`MetricsRecorder`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::{Diff, DiffPath};
+
+static RECORDER: RwLock<Option<Box<dyn MetricsRecorder>>> = RwLock::new(None);
+
+#[cfg(feature = "stats")]
+static STATS: CounterRecorder = CounterRecorder::new();
+
+/// Receives counter increments for every finished comparison.
+///
+/// Register one with [`set_metrics_recorder`]. [`Diffable::compare`] and
+/// [`compare_structs!`] record their outcome, so long-running parity jobs can
+/// be monitored without custom bookkeeping. Every method does nothing by
+/// default.
+///
+/// [`Diffable::compare`]: crate::Diffable::compare
+/// [`compare_structs!`]: crate::compare_structs
+pub trait MetricsRecorder: Send + Sync {
+    /// Called once per comparison, whatever its outcome.
+    fn comparison_run(&self) {}
+
+    /// Called once per comparison which found differences.
+    fn comparison_failed(&self) {}
+
+    /// Called once for each difference of a failed comparison.
+    fn field_failed(&self, path: &DiffPath) {
+        let _ = path;
+    }
+}
+
+impl<R: MetricsRecorder + ?Sized> MetricsRecorder for Arc<R> {
+    fn comparison_run(&self) {
+        (**self).comparison_run();
+    }
+
+    fn comparison_failed(&self) {
+        (**self).comparison_failed();
+    }
+
+    fn field_failed(&self, path: &DiffPath) {
+        (**self).field_failed(path);
+    }
+}
+
+/// Sets the process-wide metrics recorder, replacing any previous one.
+pub fn set_metrics_recorder(recorder: impl MetricsRecorder + 'static) {
+    *RECORDER.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(recorder));
+}
+
+/// Removes the process-wide metrics recorder.
+pub fn clear_metrics_recorder() {
+    *RECORDER.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Records the outcome of one comparison with the process-wide recorder, if
+/// one is set, and in the [`stats`] with the `stats` feature.
+pub(crate) fn record(diff: &Diff) {
+    #[cfg(feature = "stats")]
+    record_into(&STATS, diff);
+    if let Some(recorder) = RECORDER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        record_into(recorder.as_ref(), diff);
+    }
+}
+
+fn record_into(recorder: &dyn MetricsRecorder, diff: &Diff) {
+    recorder.comparison_run();
+    if diff.is_empty() {
+        return;
+    }
+    recorder.comparison_failed();
+    for entry in diff {
+        recorder.field_failed(entry.path());
+    }
+}
+
+/// In-memory [`MetricsRecorder`] which can render its counters in the
+/// Prometheus text format.
+///
+/// Share it with [`Arc`] to read the counters after registering it:
+///
+/// ```edition2024
+/// use std::sync::Arc;
+///
+/// use cmp::Diffable;
+///
+/// let counters = Arc::new(cmp::CounterRecorder::new());
+/// cmp::set_metrics_recorder(Arc::clone(&counters));
+///
+/// assert!(vec![1, 2].compare(&vec![1, 3]).len() == 1);
+///
+/// assert_eq!(counters.comparisons_failed(), 1);
+/// assert_eq!(counters.field_failures()["[*]"], 1);
+/// # cmp::clear_metrics_recorder();
+/// ```
+///
+/// Sequence indices are replaced with `[*]` in the per-path counters, to
+/// keep the number of label values bounded.
+#[derive(Debug, Default)]
+pub struct CounterRecorder {
+    run: AtomicU64,
+    failed: AtomicU64,
+    fields: Mutex<BTreeMap<String, u64>>,
+}
+
+impl CounterRecorder {
+    /// Creates a recorder with all counters at zero.
+    pub const fn new() -> Self {
+        Self {
+            run: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            fields: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the number of comparisons run.
+    pub fn comparisons_run(&self) -> u64 {
+        self.run.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of comparisons which found differences.
+    pub fn comparisons_failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of differences found per path.
+    pub fn field_failures(&self) -> BTreeMap<String, u64> {
+        self.fields
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Renders the counters in the Prometheus text exposition format, as
+    /// `cmp_comparisons_total`, `cmp_comparisons_failed_total` and
+    /// `cmp_field_failures_total{path="..."}`.
+    pub fn render_prometheus(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "# TYPE cmp_comparisons_total counter");
+        let _ = writeln!(text, "cmp_comparisons_total {}", self.comparisons_run());
+        let _ = writeln!(text, "# TYPE cmp_comparisons_failed_total counter");
+        let _ = writeln!(
+            text,
+            "cmp_comparisons_failed_total {}",
+            self.comparisons_failed()
+        );
+        let _ = writeln!(text, "# TYPE cmp_field_failures_total counter");
+        for (path, count) in self.field_failures() {
+            let label = path
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let _ = writeln!(text, "cmp_field_failures_total{{path=\"{label}\"}} {count}");
+        }
+        text
+    }
+}
+
+/// Snapshot of the process-wide comparison counters, returned by [`stats`].
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of comparisons run.
+    pub comparisons_run: u64,
+    /// Number of comparisons which found differences.
+    pub comparisons_failed: u64,
+    /// Number of differences found per path, with sequence indices replaced
+    /// with `[*]`.
+    pub field_failures: BTreeMap<String, u64>,
+}
+
+/// Returns the counters of every comparison recorded since the process
+/// started, or since the last [`reset_stats`], from all threads.
+///
+/// They are kept whether or not a [`MetricsRecorder`] is set, so long soak
+/// tests can check the health of their comparisons at the end of the run:
+///
+/// ```edition2024
+/// use cmp::Diffable;
+///
+/// for i in 0..100 {
+///     let _ = (i % 10).compare(&i);
+/// }
+///
+/// let stats = cmp::stats();
+/// assert!(stats.comparisons_failed * 10 <= stats.comparisons_run * 9);
+/// ```
+#[cfg(feature = "stats")]
+pub fn stats() -> Stats {
+    Stats {
+        comparisons_run: STATS.comparisons_run(),
+        comparisons_failed: STATS.comparisons_failed(),
+        field_failures: STATS.field_failures(),
+    }
+}
+
+/// Sets the counters returned by [`stats`] back to zero.
+#[cfg(feature = "stats")]
+pub fn reset_stats() {
+    STATS.run.store(0, Ordering::Relaxed);
+    STATS.failed.store(0, Ordering::Relaxed);
+    STATS
+        .fields
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+impl MetricsRecorder for CounterRecorder {
+    fn comparison_run(&self) {
+        self.run.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn comparison_failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn field_failed(&self, path: &DiffPath) {
+        *self
+            .fields
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(path.with_wildcard_indices().to_string())
+            .or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FieldDiff;
+
+    fn failed(path: impl FnOnce(&mut DiffPath)) -> Diff {
+        let mut at = DiffPath::new();
+        path(&mut at);
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::MissingFromActual {
+            path: at,
+            expected: "1".into(),
+        });
+        diff
+    }
+
+    #[test]
+    fn counts_comparisons_and_failed_paths() {
+        let counters = CounterRecorder::new();
+        record_into(&counters, &Diff::new());
+        record_into(
+            &counters,
+            &failed(|path| {
+                path.push_field("items");
+                path.push_index(3);
+                path.push_field("sku");
+            }),
+        );
+        record_into(
+            &counters,
+            &failed(|path| {
+                path.push_field("items");
+                path.push_index(7);
+                path.push_field("sku");
+            }),
+        );
+
+        assert_eq!(counters.comparisons_run(), 3);
+        assert_eq!(counters.comparisons_failed(), 2);
+        assert_eq!(
+            counters.render_prometheus(),
+            "# TYPE cmp_comparisons_total counter\n\
+             cmp_comparisons_total 3\n\
+             # TYPE cmp_comparisons_failed_total counter\n\
+             cmp_comparisons_failed_total 2\n\
+             # TYPE cmp_field_failures_total counter\n\
+             cmp_field_failures_total{path=\"items[*].sku\"} 2\n"
+        );
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn keeps_process_wide_stats() {
+        // Other tests compare concurrently, so only look at the paths and
+        // lower bounds of the counts recorded here.
+        let before = stats();
+        record(&failed(|path| path.push_field("stats_total")));
+        record(&Diff::new());
+        let after = stats();
+
+        assert!(after.comparisons_run >= before.comparisons_run + 2);
+        assert!(after.comparisons_failed > before.comparisons_failed);
+        assert_eq!(after.field_failures["stats_total"], 1);
+    }
+
+    #[test]
+    fn escapes_prometheus_labels() {
+        let counters = CounterRecorder::new();
+        record_into(&counters, &failed(|path| path.push_key("a\"b")));
+
+        assert!(
+            counters
+                .render_prometheus()
+                .contains(r#"cmp_field_failures_total{path="[\"a\\\"b\"]"} 1"#)
+        );
+    }
+}
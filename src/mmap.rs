@@ -0,0 +1,382 @@
+//! Comparison of large JSON and NDJSON files, memory-mapped rather than read
+//! into the heap, enabled by the `mmap` feature.
+//!
+//! Files are compared record by record: the elements of a top-level array,
+//! or the values of an NDJSON (or otherwise whitespace-separated) stream.
+//! Only one record of each file is parsed at a time, so memory use is bounded
+//! by the largest record and the differences found, whatever the size of the
+//! files. Each difference is reported with the byte offsets of the records it
+//! was found in, to locate it in files too large to open in an editor.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+use serde_json::Value;
+use serde_json::de::SliceRead;
+
+use crate::value::{diff_values, render};
+use crate::{Diff, DiffPath, FieldDiff};
+
+/// Byte offsets, from the start of each file, of the records a difference was
+/// found in. A side is `None` if the file has no record at that index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Offsets {
+    pub expected: Option<usize>,
+    pub actual: Option<usize>,
+}
+
+impl fmt::Display for Offsets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.expected, self.actual) {
+            (Some(expected), Some(actual)) => {
+                write!(f, "expected at byte {expected}, actual at byte {actual}")
+            }
+            (Some(expected), None) => write!(f, "expected at byte {expected}"),
+            (None, Some(actual)) => write!(f, "actual at byte {actual}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Differences between two files found by [`compare_files`], each with the
+/// [`Offsets`] of the records it was found in.
+///
+/// `Display` renders one line per difference, followed by its offsets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileDiff {
+    diff: Diff,
+    offsets: Vec<Offsets>,
+}
+
+impl FileDiff {
+    /// Returns `true` if the files are equal.
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+    }
+
+    /// Returns the differences, without their offsets.
+    pub fn diff(&self) -> &Diff {
+        &self.diff
+    }
+
+    /// Returns the offsets of each difference, in the order of
+    /// [`diff`](FileDiff::diff)'s entries.
+    pub fn offsets(&self) -> &[Offsets] {
+        &self.offsets
+    }
+
+    /// Returns the differences, dropping their offsets.
+    pub fn into_diff(self) -> Diff {
+        self.diff
+    }
+}
+
+impl fmt::Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (entry, offsets) in self.diff.iter().zip(&self.offsets) {
+            writeln!(f, "{entry} ({offsets})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two JSON or NDJSON files record by record, memory-mapping them
+/// instead of reading them into the heap.
+///
+/// A file whose first value is an array is split into its elements, and any
+/// other file into its whitespace-separated values, such as the lines of
+/// NDJSON. Records are compared by index like serialized values in
+/// [`compare_structs!`](crate::compare_structs), so the current
+/// [`KeyMatcher`](crate::KeyMatcher) and the other scoped settings apply
+/// within them, and differences are reported at paths such as
+/// `[41].user.name`. Two files of a single non-array value each are compared
+/// as those values, at the root.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "mmap")]
+/// # {
+/// // SAFETY: nothing modifies the files while the test runs.
+/// let diff = unsafe { cmp::mmap::compare_files("golden/events.ndjson", "out/events.ndjson") }
+///     .unwrap();
+/// assert!(diff.is_empty(), "{diff}");
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// Neither file may be modified or truncated, by this or any other process,
+/// until the comparison returns. The mapped bytes are read as an immutable
+/// slice, so a change while they are compared is undefined behavior, and a
+/// truncation may crash the process.
+///
+/// # Errors
+///
+/// Returns an error if either file can't be opened or mapped, or contains
+/// invalid JSON, naming the file and the byte offset of the invalid record.
+pub unsafe fn compare_files(
+    expected: impl AsRef<Path>,
+    actual: impl AsRef<Path>,
+) -> io::Result<FileDiff> {
+    let (expected, actual) = (expected.as_ref(), actual.as_ref());
+    // SAFETY: the caller guarantees that the files aren't modified while
+    // they are mapped.
+    let expected_bytes = unsafe { Mmap::map(&File::open(expected)?)? };
+    // SAFETY: as above.
+    let actual_bytes = unsafe { Mmap::map(&File::open(actual)?)? };
+    let mut expected_records = Records::new(&expected_bytes, expected).peekable();
+    let mut actual_records = Records::new(&actual_bytes, actual).peekable();
+    let streams = !(starts_array(&expected_bytes) || starts_array(&actual_bytes));
+
+    let mut out = FileDiff::default();
+    let mut path = DiffPath::new();
+    for index in 0.. {
+        let (expected, actual) = match (expected_records.next(), actual_records.next()) {
+            (None, None) => break,
+            (expected, actual) => (expected.transpose()?, actual.transpose()?),
+        };
+        let root = index == 0
+            && streams
+            && expected_records.peek().is_none()
+            && actual_records.peek().is_none();
+        if !root {
+            path.push_index(index);
+        }
+        let offsets = Offsets {
+            expected: expected.as_ref().map(|(offset, _)| *offset),
+            actual: actual.as_ref().map(|(offset, _)| *offset),
+        };
+        let before = out.diff.len();
+        match (expected, actual) {
+            (Some((_, expected)), Some((_, actual))) => {
+                diff_values(&expected, &actual, false, &mut path, &mut out.diff)
+            }
+            (Some((_, expected)), None) => out.diff.push_with(|| FieldDiff::MissingFromActual {
+                path: path.clone(),
+                expected: render(&expected),
+            }),
+            (None, Some((_, actual))) => out.diff.push_with(|| FieldDiff::MissingFromExpected {
+                path: path.clone(),
+                actual: render(&actual),
+            }),
+            (None, None) => unreachable!("both files ended"),
+        }
+        out.offsets
+            .extend(std::iter::repeat_n(offsets, out.diff.len() - before));
+        if !root {
+            path.pop();
+        }
+    }
+    Ok(out)
+}
+
+/// Returns whether the first value of `bytes` is an array.
+fn starts_array(bytes: &[u8]) -> bool {
+    bytes.get(skip_whitespace(bytes, 0)) == Some(&b'[')
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// The records of a mapped file with their byte offsets, parsed one at a
+/// time.
+enum Records<'a> {
+    /// The elements of a top-level array, from `pos`.
+    Array {
+        bytes: &'a [u8],
+        pos: usize,
+        file: &'a Path,
+    },
+    /// Whitespace-separated values.
+    Stream {
+        bytes: &'a [u8],
+        values: serde_json::StreamDeserializer<'a, SliceRead<'a>, Value>,
+        file: &'a Path,
+    },
+}
+
+impl<'a> Records<'a> {
+    fn new(bytes: &'a [u8], file: &'a Path) -> Self {
+        if starts_array(bytes) {
+            let pos = skip_whitespace(bytes, 0) + 1;
+            Records::Array { bytes, pos, file }
+        } else {
+            let values = serde_json::Deserializer::from_slice(bytes).into_iter();
+            Records::Stream {
+                bytes,
+                values,
+                file,
+            }
+        }
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = io::Result<(usize, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Records::Array { bytes, pos, file } => {
+                let start = skip_whitespace(bytes, *pos);
+                let start = if bytes.get(start) == Some(&b',') {
+                    skip_whitespace(bytes, start + 1)
+                } else {
+                    start
+                };
+                match bytes.get(start) {
+                    None => return Some(Err(invalid(file, start, "unterminated array"))),
+                    Some(b']') => {
+                        *pos = skip_whitespace(bytes, start + 1);
+                        return (*pos < bytes.len())
+                            .then(|| Err(invalid(file, *pos, "trailing data after array")));
+                    }
+                    Some(_) => {}
+                }
+                let end = element_end(bytes, start);
+                *pos = end;
+                Some(
+                    serde_json::from_slice(&bytes[start..end])
+                        .map(|value| (start, value))
+                        .map_err(|err| invalid(file, start, err)),
+                )
+            }
+            Records::Stream {
+                bytes,
+                values,
+                file,
+            } => {
+                let start = skip_whitespace(bytes, values.byte_offset());
+                let value = values.next()?;
+                Some(
+                    value
+                        .map(|value| (start, value))
+                        .map_err(|err| invalid(file, start, err)),
+                )
+            }
+        }
+    }
+}
+
+/// Returns the end of the array element starting at `start`: the first `,`
+/// or `]` outside of strings and nested values.
+fn element_end(bytes: &[u8], start: usize) -> usize {
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (pos, &byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b',' | b']' | b'}' if depth == 0 => return pos,
+            b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+    }
+    bytes.len()
+}
+
+fn invalid(file: &Path, offset: usize, err: impl fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid JSON in {} at byte {offset}: {err}", file.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn compare(expected: &str, actual: &str) -> io::Result<FileDiff> {
+        static RUN: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "cmp-mmap-{}-{}",
+            std::process::id(),
+            RUN.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("expected.json"), expected).unwrap();
+        fs::write(dir.join("actual.json"), actual).unwrap();
+        // SAFETY: the files are only written before the comparison.
+        let diff = unsafe { compare_files(dir.join("expected.json"), dir.join("actual.json")) };
+        fs::remove_dir_all(&dir).unwrap();
+        diff
+    }
+
+    #[test]
+    fn reports_ndjson_differences_with_offsets() {
+        let diff = compare(
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n",
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"c\"}\n{\"id\":3}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff.to_string(),
+            "[1].name: \"b\" != \"c\" (expected at byte 20, actual at byte 20)\n\
+             [2]: field missing from expected: {\"id\":3} (actual at byte 40)\n"
+        );
+    }
+
+    #[test]
+    fn splits_top_level_arrays_into_elements() {
+        let diff = compare(
+            "[{\"tags\": [\"a\", \"]\"]}, 2]",
+            "[\n  {\"tags\": [\"a\", \"]\"]},\n  3\n]\n",
+        )
+        .unwrap();
+
+        assert_eq!(diff.diff().to_string(), "[1]: 2 != 3\n");
+        assert_eq!(
+            diff.offsets(),
+            [Offsets {
+                expected: Some(23),
+                actual: Some(28),
+            }]
+        );
+        assert!(compare("[1, 2]", "[1, 2]").unwrap().is_empty());
+        assert!(compare("", "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unterminated_arrays_and_trailing_data() {
+        let err = compare("[1, 2", "[1, 2]").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().contains("at byte 5: unterminated array"),
+            "{err}"
+        );
+
+        let err = compare("[1]", "[1] garbage").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            err.to_string().contains("at byte 4: trailing data"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn compares_single_documents_at_the_root() {
+        let diff = compare("{\"port\": 80}", "{\"port\": 81}").unwrap();
+        assert_eq!(diff.diff().to_string(), "port: 80 != 81\n");
+
+        let err = compare("{\"port\": 80}\n{oops}", "{}").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("at byte 13"), "{err}");
+    }
+}
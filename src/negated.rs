@@ -0,0 +1,88 @@
+//! Negated struct comparisons, asserting that two structs differ in at least
+//! one compared field, for mutation and cache-busting tests.
+
+/// Macro which asserts that two structs differ in at least one of the
+/// compared fields, the negation of [`compare_structs!`](crate::compare_structs).
+///
+/// It accepts the same forms as [`diff_structs!`](crate::diff_structs):
+/// named fields, with `~ epsilon`, `~ unordered` or `=> comparator`,
+/// `except:` followed by the fields not to compare, or with the `serde`
+/// feature no fields and the `;` options. If every compared field is equal,
+/// it panics explaining so, naming the listed fields.
+///
+/// ```edition2024
+/// use cmp::compare_structs_ne;
+///
+/// struct Entry { key: &'static str, version: u32 }
+///
+/// let cached = Entry { key: "user:1", version: 1 };
+/// let refreshed = Entry { key: "user:1", version: 2 };
+///
+/// compare_structs_ne!(cached, refreshed, key, version);
+/// ```
+///
+/// ```edition2024,should_panic
+/// # use cmp::compare_structs_ne;
+/// # struct Entry { key: &'static str, version: u32 }
+/// # let cached = Entry { key: "user:1", version: 1 };
+/// # let refreshed = Entry { key: "user:1", version: 2 };
+/// // `cached` and `refreshed` were expected to differ, but are equal in
+/// // every compared field: key
+/// compare_structs_ne!(cached, refreshed, key);
+/// ```
+#[macro_export]
+macro_rules! compare_structs_ne {
+    ($expected:expr, $actual:expr $(; $option:ident $(= $value:expr)?)?) => {
+        if $crate::diff_structs!($expected, $actual $(; $option $(= $value)?)?).is_empty() {
+            $crate::__private::fail_equal(stringify!($expected), stringify!($actual), None);
+        }
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ $(,)?) => {
+        if $crate::diff_structs!($expected, $actual, except: $($field),+).is_empty() {
+            $crate::__private::fail_equal(stringify!($expected), stringify!($actual), None);
+        }
+    };
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {
+        if $crate::diff_structs!($expected, $actual, $($fields)+).is_empty() {
+            $crate::__private::fail_equal(
+                stringify!($expected),
+                stringify!($actual),
+                Some(stringify!($($fields)+)),
+            );
+        }
+    };
+}
+
+/// Panics explaining that the compared structs are equal.
+#[track_caller]
+pub fn fail_equal(expected: &str, actual: &str, fields: Option<&str>) -> ! {
+    panic!(
+        "{}",
+        crate::messages::messages().unexpectedly_equal(expected, actual, fields)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn passes_when_a_field_differs() {
+        compare_structs_ne!(Point { x: 1, y: 2 }, Point { x: 1, y: 3 }, x, y);
+        compare_structs_ne!(Point { x: 1, y: 2 }, Point { x: 1, y: 3 }, y ~ 0.5);
+    }
+
+    #[test]
+    fn explains_that_every_field_was_equal() {
+        let (a, b) = (Point { x: 1, y: 2 }, Point { x: 1, y: 3 });
+
+        let panic = std::panic::catch_unwind(|| compare_structs_ne!(a, b, x)).unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("`a` and `b` were expected to differ, but are equal in every compared field: x")
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! OpenTelemetry span events for failed comparisons, enabled by the `otel`
+//! feature.
+
+use opentelemetry::KeyValue;
+use opentelemetry::trace::get_active_span;
+
+use crate::{Diff, FieldDiff, Reporter};
+
+/// Name of the span events added by [`OtelReporter`].
+pub const EVENT_NAME: &str = "cmp.diff";
+
+/// [`Reporter`] which adds one [`EVENT_NAME`] event per difference to the
+/// current OpenTelemetry span, so shadow comparisons in production show up in
+/// traces.
+///
+/// Each event has `cmp.kind`, `cmp.path` and, where present, `cmp.expected`
+/// and `cmp.actual` attributes. Values longer than
+/// [`max_value_len`](OtelReporter::max_value_len) characters (256 by default)
+/// are truncated with a trailing `…`. Nothing is recorded when there is no
+/// recording span.
+///
+/// ```edition2024
+/// # #[cfg(feature = "otel")]
+/// # {
+/// cmp::set_reporter(cmp::OtelReporter::new().max_value_len(64));
+/// # cmp::clear_reporter();
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct OtelReporter {
+    max_value_len: usize,
+}
+
+impl Default for OtelReporter {
+    fn default() -> Self {
+        Self { max_value_len: 256 }
+    }
+}
+
+impl OtelReporter {
+    /// Creates a reporter truncating values to 256 characters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of characters after which values are truncated.
+    pub fn max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = max_value_len;
+        self
+    }
+
+    fn attributes(&self, entry: &FieldDiff) -> Vec<KeyValue> {
+        let truncate = |value: &str| -> String {
+            match value.char_indices().nth(self.max_value_len) {
+                Some((end, _)) => format!("{}…", &value[..end]),
+                None => value.to_string(),
+            }
+        };
+        let mut attributes = vec![KeyValue::new("cmp.path", entry.path().to_string())];
+        match entry {
+            FieldDiff::Changed {
+                expected, actual, ..
+            } => {
+                attributes.push(KeyValue::new("cmp.kind", "changed"));
+                attributes.push(KeyValue::new("cmp.expected", truncate(expected)));
+                attributes.push(KeyValue::new("cmp.actual", truncate(actual)));
+            }
+            FieldDiff::MissingFromExpected { actual, .. } => {
+                attributes.push(KeyValue::new("cmp.kind", "missing_from_expected"));
+                attributes.push(KeyValue::new("cmp.actual", truncate(actual)));
+            }
+            FieldDiff::MissingFromActual { expected, .. } => {
+                attributes.push(KeyValue::new("cmp.kind", "missing_from_actual"));
+                attributes.push(KeyValue::new("cmp.expected", truncate(expected)));
+            }
+        }
+        attributes
+    }
+}
+
+impl Reporter for OtelReporter {
+    fn report(&self, diff: &Diff) {
+        get_active_span(|span| {
+            if !span.is_recording() {
+                return;
+            }
+            for entry in diff {
+                span.add_event(EVENT_NAME, self.attributes(entry));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffPath;
+
+    #[test]
+    fn truncates_values_in_event_attributes() {
+        let mut path = DiffPath::new();
+        path.push_field("body");
+        let entry = FieldDiff::Changed {
+            path,
+            expected: "\"héllo\"".into(),
+            actual: "\"hi\"".into(),
+        };
+
+        assert_eq!(
+            OtelReporter::new().max_value_len(4).attributes(&entry),
+            [
+                KeyValue::new("cmp.path", "body"),
+                KeyValue::new("cmp.kind", "changed"),
+                KeyValue::new("cmp.expected", "\"hél…"),
+                KeyValue::new("cmp.actual", "\"hi\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn reporting_without_a_span_is_a_no_op() {
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::MissingFromActual {
+            path: DiffPath::new(),
+            expected: "1".into(),
+        });
+
+        OtelReporter::new().report(&diff);
+    }
+}
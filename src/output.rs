@@ -0,0 +1,52 @@
+//! Emission of comparison output in one piece, so the reports of tests
+//! failing concurrently don't interleave.
+//!
+//! Failure messages are rendered completely before panicking, so each is a
+//! single panic payload, and notes printed next to passing comparisons go
+//! through [`emit`].
+
+use std::fmt::Display;
+
+/// Writes `message` to standard error with one write of the locked stream.
+///
+/// The message is rendered before the lock is taken, so a slow `Display`
+/// implementation can't hold it. `eprint!` is used rather than
+/// `io::stderr()`, so the test harness still captures the output.
+pub(crate) fn emit(message: impl Display) {
+    let rendered = render(message);
+    eprint!("{rendered}");
+}
+
+fn render(message: impl Display) -> String {
+    let mut rendered = message.to_string();
+    if !rendered.ends_with('\n') {
+        rendered.push('\n');
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Diff, DiffPath, FieldDiff};
+
+    #[test]
+    fn renders_whole_messages_ending_in_a_newline() {
+        let mut diff = Diff::new();
+        for field in ["id", "name"] {
+            let mut path = DiffPath::new();
+            path.push_field(field);
+            diff.push(FieldDiff::Changed {
+                path,
+                expected: "1".into(),
+                actual: "2".into(),
+            });
+        }
+
+        assert_eq!(render(&diff), "id: 1 != 2\nname: 1 != 2\n");
+        assert_eq!(
+            render(format_args!("created snapshot {}", "a.json")),
+            "created snapshot a.json\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! [`compare_partial!`](crate::compare_partial): comparing a struct with a
+//! partial struct literal.
+
+/// Stands in for the expected side of a partial comparison, which has no
+/// value of its own. It doesn't implement `Debug`, so `CMP_CAPTURE` only
+/// shows the literal's source text.
+pub struct Partial;
+
+/// Macro which compares the fields named in a struct literal with those of
+/// `actual`, ignoring all other fields.
+///
+/// Unlike `MyStruct { ..Default::default() }`, the literal needs no
+/// `Default` implementation and can't accidentally compare unmentioned
+/// fields. The literal is checked against the type at compile time: `actual`
+/// must be of the named struct type, with all mentioned fields. Each value is
+/// compared with `PartialEq` and the failure output is the same as
+/// [`compare_structs!`](crate::compare_structs)'s.
+///
+/// ```edition2024
+/// use cmp::compare_partial;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Status { Ok, Failed }
+///
+/// struct Response { status: Status, count: u32, body: String }
+///
+/// let actual = Response { status: Status::Ok, count: 3, body: "...".to_string() };
+///
+/// compare_partial!(actual, Response { status: Status::Ok, count: 3 });
+/// ```
+///
+/// # Panics
+///
+/// Panics if any of the mentioned fields differ.
+#[macro_export]
+macro_rules! compare_partial {
+    ($actual:expr, $($ty:ident)::+ { $($field:ident : $value:expr),+ $(,)? }) => {
+        match &$actual {
+            actual => {
+                // Checks that `actual` is of the named type, with all the mentioned fields.
+                #[allow(unused_variables)]
+                let $($ty)::+ { $($field: _,)+ .. } = actual;
+
+                let mut diff = $crate::Diff::new();
+                $(
+                    let expected = $value;
+                    if actual.$field != expected {
+                        diff.push($crate::FieldDiff::Changed {
+                            path: $crate::__private::field_path(stringify!($field)),
+                            expected: $crate::format_value(&expected),
+                            actual: $crate::format_value(&actual.$field),
+                        });
+                    }
+                )+
+                $crate::__finish!(
+                    diff,
+                    $($ty)::+ { $($field: $value),+ },
+                    $actual,
+                    &$crate::__private::Partial,
+                    actual
+                )
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, PartialEq)]
+    enum Status {
+        Ok,
+        Failed,
+    }
+
+    #[allow(dead_code)]
+    struct Job {
+        id: u64,
+        status: Status,
+        log: Vec<String>,
+    }
+
+    fn job() -> Job {
+        Job {
+            id: 7,
+            status: Status::Failed,
+            log: vec!["boom".to_string()],
+        }
+    }
+
+    #[test]
+    fn ignores_unmentioned_fields() {
+        compare_partial!(
+            job(),
+            Job {
+                id: 7,
+                status: Status::Failed
+            }
+        );
+        compare_partial!(job(), Job { id: 7 });
+    }
+
+    #[test]
+    #[should_panic(expected = "status: Ok != Failed")]
+    fn reports_differing_mentioned_fields() {
+        compare_partial!(
+            job(),
+            Job {
+                status: Status::Ok,
+                id: 7
+            }
+        );
+    }
+}
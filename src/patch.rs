@@ -0,0 +1,167 @@
+//! Differences as JSON Patch (RFC 6902) operations, enabled by the `serde`
+//! feature, for tooling which consumes patches.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::DiffPath;
+
+/// A JSON Patch operation, serialized in the standard form, e.g.
+/// `{"op":"replace","path":"/items/0/price","value":12}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Adds `value` at `path`, a JSON pointer.
+    Add {
+        /// Where to add the value.
+        path: String,
+        /// The value of the actual side.
+        value: Value,
+    },
+    /// Removes the value at `path`.
+    Remove {
+        /// Where the value to remove is.
+        path: String,
+    },
+    /// Replaces the value at `path` with `value`.
+    Replace {
+        /// Where the value to replace is.
+        path: String,
+        /// The value of the actual side.
+        value: Value,
+    },
+}
+
+/// Returns the JSON Patch operations turning the serialized `expected` into
+/// the serialized `actual`, or none if they are equal.
+///
+/// Objects and arrays are descended into, so only differing leaves are
+/// replaced. Array elements are paired by position: trailing elements only
+/// present in `actual` are added, and those only present in `expected` are
+/// removed from the last, so the operations apply in order.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::diff_as_json_patch;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Order { id: u32, tags: Vec<&'static str> }
+///
+/// let patch = diff_as_json_patch(
+///     &Order { id: 1, tags: vec!["new"] },
+///     &Order { id: 2, tags: vec!["new", "paid"] },
+/// );
+/// assert_eq!(
+///     serde_json::to_string(&patch).unwrap(),
+///     r#"[{"op":"replace","path":"/id","value":2},{"op":"add","path":"/tags/1","value":"paid"}]"#
+/// );
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if either value can't be serialized.
+pub fn diff_as_json_patch<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+) -> Vec<PatchOp> {
+    let expected = crate::serialize::to_value(expected, "expected");
+    let actual = crate::serialize::to_value(actual, "actual");
+    let mut ops = Vec::new();
+    patch_values(&expected, &actual, &mut DiffPath::new(), &mut ops);
+    ops
+}
+
+fn patch_values(expected: &Value, actual: &Value, path: &mut DiffPath, out: &mut Vec<PatchOp>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                path.push_field(key);
+                match actual.get(key) {
+                    Some(actual) => patch_values(expected, actual, path, out),
+                    None => out.push(PatchOp::Remove {
+                        path: path.to_json_pointer(),
+                    }),
+                }
+                path.pop();
+            }
+            for (key, actual) in actual {
+                if !expected.contains_key(key) {
+                    path.push_field(key);
+                    out.push(PatchOp::Add {
+                        path: path.to_json_pointer(),
+                        value: actual.clone(),
+                    });
+                    path.pop();
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                path.push_index(index);
+                patch_values(expected, actual, path, out);
+                path.pop();
+            }
+            for (index, actual) in actual.iter().enumerate().skip(expected.len()) {
+                path.push_index(index);
+                out.push(PatchOp::Add {
+                    path: path.to_json_pointer(),
+                    value: actual.clone(),
+                });
+                path.pop();
+            }
+            for index in (actual.len()..expected.len()).rev() {
+                path.push_index(index);
+                out.push(PatchOp::Remove {
+                    path: path.to_json_pointer(),
+                });
+                path.pop();
+            }
+        }
+        _ if expected != actual => out.push(PatchOp::Replace {
+            path: path.to_json_pointer(),
+            value: actual.clone(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn patches_nested_fields_and_trailing_elements() {
+        let expected = json!({"a/b": {"x": 1, "gone": true}, "list": [1, 2, 3, 4], "same": [0]});
+        let actual = json!({"a/b": {"x": 2, "new~": null}, "list": [1, 5]});
+
+        assert_eq!(
+            serde_json::to_value(diff_as_json_patch(&expected, &actual)).unwrap(),
+            json!([
+                {"op": "remove", "path": "/a~1b/gone"},
+                {"op": "replace", "path": "/a~1b/x", "value": 2},
+                {"op": "add", "path": "/a~1b/new~0", "value": null},
+                {"op": "replace", "path": "/list/1", "value": 5},
+                {"op": "remove", "path": "/list/3"},
+                {"op": "remove", "path": "/list/2"},
+                {"op": "remove", "path": "/same"},
+            ])
+        );
+        assert!(diff_as_json_patch(&expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn replaces_the_root_when_types_differ() {
+        assert_eq!(
+            diff_as_json_patch(&[1], &"one"),
+            [PatchOp::Replace {
+                path: String::new(),
+                value: json!("one"),
+            }]
+        );
+    }
+}
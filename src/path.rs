@@ -0,0 +1,327 @@
+//! Paths to the value being compared, as reported in diff output.
+
+use std::fmt::{self, Write};
+
+use crate::glob;
+
+/// Location of a value inside the structs being compared, such as
+/// `config.servers[2].port`.
+///
+/// [`Diffable`](crate::Diffable) implementations push a segment before
+/// comparing a nested value and pop it afterwards. The segments stay
+/// structured, so tools can inspect them with [`segments`](DiffPath::segments),
+/// convert them with [`to_json_pointer`](DiffPath::to_json_pointer) or filter
+/// them with [`matches_glob`](DiffPath::matches_glob), while `Display`
+/// renders the familiar dotted form.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct DiffPath {
+    segments: Vec<Segment>,
+}
+
+/// One step of a [`DiffPath`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Segment {
+    /// A struct field, rendered as `.name`.
+    Field(String),
+    /// A sequence index, rendered as `[index]`.
+    Index(usize),
+    /// A map key, holding and rendered as the key's `Debug` representation
+    /// in brackets, e.g. `["tls"]`.
+    Key(String),
+    /// An enum variant, rendered as `::Variant`.
+    Variant(String),
+}
+
+impl DiffPath {
+    /// Creates an empty path, referring to the values being compared.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a struct field segment.
+    pub fn push_field(&mut self, name: &str) {
+        self.segments.push(Segment::Field(name.to_string()));
+    }
+
+    /// Appends an enum variant segment.
+    pub fn push_variant(&mut self, name: &str) {
+        self.segments.push(Segment::Variant(name.to_string()));
+    }
+
+    /// Appends a sequence index segment.
+    pub fn push_index(&mut self, index: usize) {
+        self.segments.push(Segment::Index(index));
+    }
+
+    /// Appends a map key segment, using the key's `Debug` representation.
+    pub fn push_key(&mut self, key: &(impl fmt::Debug + ?Sized)) {
+        self.segments.push(Segment::Key(format!("{key:?}")));
+    }
+
+    /// Removes the last pushed segment.
+    pub fn pop(&mut self) {
+        self.segments.pop();
+    }
+
+    /// Returns `true` if no segments have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the segments, outermost first.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns a copy of the path with every index replaced by `[*]`, to
+    /// group paths which only differ in their sequence positions.
+    pub(crate) fn with_wildcard_indices(&self) -> DiffPath {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Index(_) => Segment::Key("*".to_string()),
+                segment => segment.clone(),
+            })
+            .collect()
+    }
+
+    /// Renders the path as an [RFC 6901] JSON pointer, matching the layout
+    /// serde uses for the same value: `servers[2].port` becomes
+    /// `/servers/2/port`, and string map keys lose their quotes.
+    ///
+    /// [RFC 6901]: https://www.rfc-editor.org/rfc/rfc6901
+    pub fn to_json_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            let token = match segment {
+                Segment::Field(name) | Segment::Variant(name) => name.as_str(),
+                Segment::Index(index) => {
+                    let _ = write!(pointer, "{index}");
+                    continue;
+                }
+                Segment::Key(key) => key
+                    .strip_prefix('"')
+                    .and_then(|key| key.strip_suffix('"'))
+                    .unwrap_or(key),
+            };
+            pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        }
+        pointer
+    }
+
+    /// Returns whether the path matches `pattern`, written in the same form
+    /// as the rendered path.
+    ///
+    /// Within a segment, `*` matches any run of characters and `?` a single
+    /// one, so `*` alone matches any field and `[*]` any index or key. A
+    /// `**` segment matches any number of segments. `/` is accepted as a
+    /// separator as well as `.`:
+    ///
+    /// ```edition2024
+    /// let mut path = cmp::DiffPath::new();
+    /// path.push_field("items");
+    /// path.push_index(3);
+    /// path.push_field("price");
+    ///
+    /// assert!(path.matches_glob("items[*].price"));
+    /// assert!(path.matches_glob("**/price"));
+    /// assert!(!path.matches_glob("*.price"));
+    /// ```
+    pub fn matches_glob(&self, pattern: &str) -> bool {
+        PathGlob::new(pattern).matches(self)
+    }
+}
+
+/// A [glob](DiffPath::matches_glob) pattern parsed once, for matching many
+/// paths.
+#[derive(Clone, Debug)]
+pub(crate) struct PathGlob(Vec<PatternSegment>);
+
+impl PathGlob {
+    pub(crate) fn new(pattern: &str) -> Self {
+        Self(parse_pattern(pattern))
+    }
+
+    pub(crate) fn matches(&self, path: &DiffPath) -> bool {
+        matches_segments(&self.0, &path.segments)
+    }
+}
+
+impl FromIterator<Segment> for DiffPath {
+    fn from_iter<I: IntoIterator<Item = Segment>>(segments: I) -> Self {
+        Self {
+            segments: segments.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for DiffPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (position, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Field(name) if position == 0 => f.write_str(name)?,
+                Segment::Field(name) => write!(f, ".{name}")?,
+                Segment::Variant(name) if position == 0 => f.write_str(name)?,
+                Segment::Variant(name) => write!(f, "::{name}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+                Segment::Key(key) => write!(f, "[{key}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PatternSegment {
+    /// `**`, matching any number of segments.
+    AnyDepth,
+    /// A field or variant name glob.
+    Name(String),
+    /// The glob inside `[...]`, matching an index or key.
+    Bracket(String),
+}
+
+fn parse_pattern(pattern: &str) -> Vec<PatternSegment> {
+    let mut segments = Vec::new();
+    let mut name = String::new();
+    let flush = |name: &mut String, segments: &mut Vec<PatternSegment>| {
+        match name.as_str() {
+            "" => {}
+            "**" => segments.push(PatternSegment::AnyDepth),
+            _ => segments.push(PatternSegment::Name(name.clone())),
+        }
+        name.clear();
+    };
+
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' | '/' => flush(&mut name, &mut segments),
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                flush(&mut name, &mut segments);
+            }
+            '[' => {
+                flush(&mut name, &mut segments);
+                let inner: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                segments.push(PatternSegment::Bracket(inner));
+            }
+            _ => name.push(c),
+        }
+    }
+    flush(&mut name, &mut segments);
+    segments
+}
+
+fn matches_segments(pattern: &[PatternSegment], path: &[Segment]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((PatternSegment::AnyDepth, rest)) => {
+            (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => path.split_first().is_some_and(|(first, remaining)| {
+            matches_segment(segment, first) && matches_segments(rest, remaining)
+        }),
+    }
+}
+
+fn matches_segment(pattern: &PatternSegment, segment: &Segment) -> bool {
+    match (pattern, segment) {
+        (PatternSegment::Name(glob), Segment::Field(name) | Segment::Variant(name)) => {
+            glob::matches_case_sensitive(glob, name)
+        }
+        (PatternSegment::Bracket(glob), Segment::Index(index)) => {
+            glob::matches_case_sensitive(glob, &index.to_string())
+        }
+        (PatternSegment::Bracket(glob), Segment::Key(key)) => {
+            glob::matches_case_sensitive(glob, key)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(build: impl FnOnce(&mut DiffPath)) -> DiffPath {
+        let mut path = DiffPath::new();
+        build(&mut path);
+        path
+    }
+
+    #[test]
+    fn renders_and_pops_segments() {
+        let mut path = DiffPath::new();
+        path.push_field("servers");
+        path.push_index(2);
+        path.push_field("port");
+
+        assert_eq!(path.to_string(), "servers[2].port");
+
+        path.pop();
+        path.pop();
+        path.push_index(0);
+        path.push_key("tls");
+
+        assert_eq!(path.to_string(), r#"servers[0]["tls"]"#);
+
+        path.pop();
+        path.push_variant("Https");
+        path.push_field("port");
+
+        assert_eq!(path.to_string(), "servers[0]::Https.port");
+        assert_eq!(
+            path.segments(),
+            [
+                Segment::Field("servers".into()),
+                Segment::Index(0),
+                Segment::Variant("Https".into()),
+                Segment::Field("port".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_to_json_pointer() {
+        let path = path(|path| {
+            path.push_field("limits");
+            path.push_key("a/b~c");
+            path.push_index(1);
+            path.push_variant("Some");
+        });
+
+        assert_eq!(path.to_json_pointer(), "/limits/a~1b~0c/1/Some");
+        assert_eq!(DiffPath::new().to_json_pointer(), "");
+    }
+
+    #[test]
+    fn matches_glob_patterns() {
+        let path = path(|path| {
+            path.push_field("orders");
+            path.push_key("eu");
+            path.push_field("items");
+            path.push_index(12);
+            path.push_field("unit_price");
+        });
+
+        assert!(path.matches_glob(r#"orders["eu"].items[12].unit_price"#));
+        assert!(path.matches_glob("orders[*].items[*].*_price"));
+        assert!(path.matches_glob("**.unit_price"));
+        assert!(path.matches_glob("orders/**/items/[1?]/unit_price"));
+        assert!(!path.matches_glob("orders[*].items[*]"));
+        assert!(!path.matches_glob("**.Unit_price"));
+        assert!(!path.matches_glob("*.unit_price"));
+    }
+}
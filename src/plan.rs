@@ -0,0 +1,154 @@
+//! Dry runs listing the paths a comparison would compare, and with which
+//! rules, without comparing anything, enabled by the `serde` feature.
+//!
+//! Paths are those of the serialized values, so they are the paths reported
+//! by `compare_structs!` in serde mode and, for derived types without map
+//! fields, those reported by [`Diffable`](crate::Diffable).
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{DiffPath, PathRule};
+
+/// A path a comparison would compare, and the rule it would use.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedPath {
+    /// The path of a leaf value of either side.
+    pub path: DiffPath,
+    /// The rule the value would be compared with.
+    pub rule: PathRule,
+    /// The pattern of the override the rule comes from, if any.
+    pub pattern: Option<String>,
+}
+
+/// The paths a comparison would compare, returned by
+/// [`Comparison::plan`](crate::Comparison::plan).
+///
+/// `Display` renders a line per path with its rule, e.g.
+/// `items[0].id: ignore (override "items[*].id")`, to help author ignore
+/// lists for large payloads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Plan {
+    paths: Vec<PlannedPath>,
+}
+
+impl Plan {
+    pub(crate) fn new(paths: Vec<PlannedPath>) -> Self {
+        Self { paths }
+    }
+
+    /// Returns the planned paths, those of the serialized expected value
+    /// first, then those only present in the actual value.
+    pub fn paths(&self) -> &[PlannedPath] {
+        &self.paths
+    }
+
+    /// Returns the planned paths with the given rule.
+    pub fn with_rule(&self, rule: PathRule) -> impl Iterator<Item = &PlannedPath> {
+        self.paths
+            .iter()
+            .filter(move |planned| planned.rule == rule)
+    }
+}
+
+impl fmt::Display for PathRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathRule::Ignore => f.write_str("ignore"),
+            PathRule::Epsilon(epsilon) => {
+                write!(f, "epsilon {}", crate::float::tolerance(*epsilon))
+            }
+            PathRule::Exact => f.write_str("exact"),
+        }
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for planned in &self.paths {
+            write!(f, "{}: {}", planned.path, planned.rule)?;
+            if let Some(pattern) = &planned.pattern {
+                write!(f, " (override {pattern:?})")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the paths of the leaves of `expected`, then those of the leaves
+/// of `actual` which `expected` doesn't have.
+pub fn leaf_paths(expected: &Value, actual: &Value) -> Vec<DiffPath> {
+    let mut paths = Vec::new();
+    collect(expected, &mut DiffPath::new(), &mut paths);
+    let mut extra = Vec::new();
+    collect(actual, &mut DiffPath::new(), &mut extra);
+    for path in extra {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn collect(value: &Value, path: &mut DiffPath, out: &mut Vec<DiffPath>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                path.push_field(key);
+                collect(value, path, out);
+                path.pop();
+            }
+        }
+        Value::Array(elements) if !elements.is_empty() => {
+            for (index, value) in elements.iter().enumerate() {
+                path.push_index(index);
+                collect(value, path, out);
+                path.pop();
+            }
+        }
+        _ => out.push(path.clone()),
+    }
+}
+
+/// Prints the plan of `compare_structs!(expected, actual; dry_run)`.
+pub fn dry_run<E: Serialize + ?Sized, A: Serialize + ?Sized>(expected: &E, actual: &A) {
+    let expected = crate::serialize::expect_serialized(expected, "expected");
+    let actual = crate::serialize::expect_serialized(actual, "actual");
+    crate::output::emit(plan_values(&expected.value, &actual.value));
+}
+
+/// Returns the plan of `compare_structs!` in serde mode, which compares every
+/// path exactly.
+fn plan_values(expected: &Value, actual: &Value) -> Plan {
+    Plan::new(
+        leaf_paths(expected, actual)
+            .into_iter()
+            .map(|path| PlannedPath {
+                path,
+                rule: PathRule::Exact,
+                pattern: None,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lists_leaves_of_both_sides() {
+        let expected = json!({"id": 1, "tags": ["a"], "meta": {}});
+        let actual = json!({"id": 2, "tags": ["a", "b"], "extra": null});
+
+        assert_eq!(
+            plan_values(&expected, &actual).to_string(),
+            "id: exact\nmeta: exact\ntags[0]: exact\nextra: exact\ntags[1]: exact\n"
+        );
+    }
+}
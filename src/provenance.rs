@@ -0,0 +1,172 @@
+//! Provenance annotations, naming where each compared value came from in the
+//! failure message.
+
+use std::fmt;
+use std::ops::Deref;
+
+/// A compared value tagged with its source, such as `fixtures/user.json:12`
+/// or `POST /users response`.
+///
+/// It dereferences to the value, so it can be passed to
+/// [`compare_structs!`](crate::compare_structs) in place of either side,
+/// which then names the source of that side above the differing fields:
+///
+/// ```edition2024
+/// use cmp::{compare_structs, sourced};
+///
+/// struct User { id: u32, name: &'static str }
+///
+/// let fixture = User { id: 1, name: "ada" };
+/// let response = User { id: 1, name: "ada" };
+///
+/// compare_structs!(
+///     sourced(fixture, "fixtures/user.json:12"),
+///     sourced(response, "POST /users response").label("api"),
+///     id,
+///     name
+/// );
+/// ```
+///
+/// On failure, the message starts with:
+///
+/// ```text
+/// expected from fixtures/user.json:12
+/// actual (api) from POST /users response
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Sourced<T> {
+    value: T,
+    source: String,
+    label: Option<String>,
+}
+
+/// Tags `value` with the `source` it was loaded from.
+pub fn sourced<T>(value: T, source: impl Into<String>) -> Sourced<T> {
+    Sourced {
+        value,
+        source: source.into(),
+        label: None,
+    }
+}
+
+impl<T> Sourced<T> {
+    /// Adds a short label, shown next to the side it was compared as.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Returns the source of the value.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the value, discarding its provenance.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Renders the header line naming the source of `side`, e.g.
+    /// `actual (api) from POST /users response`.
+    fn describe(&self, side: &str) -> String {
+        match &self.label {
+            Some(label) => format!("{side} ({label}) from {}", self.source),
+            None => format!("{side} from {}", self.source),
+        }
+    }
+}
+
+impl<T> Deref for Sourced<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Formats as the value, so provenance doesn't clutter captured values.
+impl<T: fmt::Debug> fmt::Debug for Sourced<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// Serializes as the value, so sourced values can be compared by all fields.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Sourced<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+/// A compared value, described with its source if it is [`Sourced`].
+///
+/// Call `(&Origin(value)).describe(side)` with both [`OriginSourced`] and
+/// [`OriginFallback`] in scope: method resolution prefers the `Sourced`
+/// implementation and falls back to `None` for other types.
+pub struct Origin<'a, T: ?Sized>(pub &'a T);
+
+pub trait OriginSourced {
+    fn describe(&self, side: &str) -> Option<String>;
+}
+
+impl<T> OriginSourced for Origin<'_, Sourced<T>> {
+    fn describe(&self, side: &str) -> Option<String> {
+        Some(self.0.describe(side))
+    }
+}
+
+pub trait OriginFallback {
+    fn describe(&self, side: &str) -> Option<String> {
+        let _ = side;
+        None
+    }
+}
+
+impl<T: ?Sized> OriginFallback for &Origin<'_, T> {}
+
+/// Renders one line per sourced side, printed above the field diffs.
+pub fn header(expected: Option<String>, actual: Option<String>) -> String {
+    expected
+        .into_iter()
+        .chain(actual)
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct User {
+        name: &'static str,
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn describes_only_sourced_values() {
+        let fixture = sourced(User { name: "ada" }, "fixtures/user.json:12");
+        let response = User { name: "grace" };
+
+        assert_eq!(fixture.name, "ada");
+        assert_eq!(
+            header(
+                (&Origin(&fixture)).describe("expected"),
+                (&Origin(&response)).describe("actual")
+            ),
+            "expected from fixtures/user.json:12\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected from fixtures/user.json:12\nactual (api) from POST /users response\nname: \"ada\" != \"grace\""
+    )]
+    fn names_sources_in_the_failure_message() {
+        crate::compare_structs!(
+            sourced(User { name: "ada" }, "fixtures/user.json:12"),
+            sourced(User { name: "grace" }, "POST /users response").label("api"),
+            name
+        );
+    }
+}
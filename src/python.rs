@@ -0,0 +1,183 @@
+//! Python bindings for the diff engine, enabled by the `pyo3` feature, so
+//! Python tooling gets the same differences as Rust tests.
+//!
+//! Build the extension module with `cargo rustc --release --features pyo3
+//! --crate-type cdylib` and copy the library to `cmp.so` (`cmp.pyd` on
+//! Windows) on the Python path. Then
+//!
+//! ```python
+//! >>> import cmp
+//! >>> cmp.diff({"port": 8080}, {"port": 8081})
+//! [{'actual': '8081', 'expected': '8080', 'kind': 'changed', 'path': 'port', 'pointer': '/port'}]
+//! ```
+
+use pyo3::IntoPyObjectExt;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use serde_json::{Map, Number, Value};
+
+use crate::bindings::Options;
+
+#[pymodule]
+fn cmp(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(diff, module)?)
+}
+
+/// Returns the differences between two JSON-like values, as
+/// `compare_structs!` in serde mode finds them.
+///
+/// Each difference is a dict with its `kind` ("changed",
+/// "missing_from_expected" or "missing_from_actual"), `path`, JSON `pointer`,
+/// and rendered `expected` and `actual` values, None for the missing side.
+///
+/// `options` may hold `key`, the field pairing list elements such as "id",
+/// and `unordered`, to compare lists regardless of order.
+///
+/// Raises `TypeError` if a value holds anything but dicts with string keys,
+/// lists, tuples, strings, numbers, booleans and None, and `ValueError` if
+/// it holds NaN, an infinity or an integer out of JSON's range, or if the
+/// options are invalid.
+#[pyfunction]
+#[pyo3(signature = (expected, actual, options = None))]
+fn diff<'py>(
+    py: Python<'py>,
+    expected: &Bound<'py, PyAny>,
+    actual: &Bound<'py, PyAny>,
+    options: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let (expected, actual) = (to_json(expected, "expected")?, to_json(actual, "actual")?);
+    let options = match options {
+        None => Options::default(),
+        Some(options) => to_json(options, "options")
+            .ok()
+            .and_then(|json| Options::from_json(&json))
+            .ok_or_else(|| {
+                PyValueError::new_err(format!("invalid comparison options: {options}"))
+            })?,
+    };
+    let entries = crate::format::json_entries(&options.diff(&expected, &actual));
+    from_json(py, &Value::Array(entries))
+}
+
+/// Converts a Python value to JSON, naming the path to a value which can't
+/// be converted in the error, starting from `path`.
+fn to_json(value: &Bound<'_, PyAny>, path: &str) -> PyResult<Value> {
+    if value.is_none() {
+        Ok(Value::Null)
+    } else if let Ok(value) = value.cast::<PyBool>() {
+        Ok(Value::Bool(value.is_true()))
+    } else if value.is_instance_of::<PyInt>() {
+        match (value.extract::<i64>(), value.extract::<u64>()) {
+            (Ok(int), _) => Ok(int.into()),
+            (_, Ok(int)) => Ok(int.into()),
+            _ => Err(PyValueError::new_err(format!(
+                "{path} is an integer out of JSON's range: {value}"
+            ))),
+        }
+    } else if value.is_instance_of::<PyFloat>() {
+        let float = value.extract::<f64>()?;
+        Number::from_f64(float).map(Value::Number).ok_or_else(|| {
+            PyValueError::new_err(format!("{path} is {float}, which JSON can't represent"))
+        })
+    } else if let Ok(value) = value.cast::<PyString>() {
+        Ok(Value::String(value.to_str()?.to_string()))
+    } else if let Ok(dict) = value.cast::<PyDict>() {
+        let mut map = Map::new();
+        for (key, value) in dict {
+            let Ok(key) = key.cast::<PyString>() else {
+                return Err(PyTypeError::new_err(format!(
+                    "{path} has a key which isn't a string: {key}"
+                )));
+            };
+            let key = key.to_str()?;
+            map.insert(
+                key.to_string(),
+                to_json(&value, &format!("{path}[{key:?}]"))?,
+            );
+        }
+        Ok(Value::Object(map))
+    } else if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        value
+            .try_iter()?
+            .enumerate()
+            .map(|(index, element)| to_json(&element?, &format!("{path}[{index}]")))
+            .collect()
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "{path} is a {}, which isn't JSON-like",
+            value.get_type().name()?
+        )))
+    }
+}
+
+/// Converts JSON to the equivalent Python value.
+fn from_json<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Null => Ok(py.None().into_bound(py)),
+        Value::Bool(value) => value.into_bound_py_any(py),
+        Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(int), _) => int.into_bound_py_any(py),
+            (_, Some(int)) => int.into_bound_py_any(py),
+            _ => number.as_f64().into_bound_py_any(py),
+        },
+        Value::String(string) => string.into_bound_py_any(py),
+        Value::Array(values) => {
+            let list = PyList::empty(py);
+            for value in values {
+                list.append(from_json(py, value)?)?;
+            }
+            list.into_bound_py_any(py)
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, value) in map {
+                dict.set_item(key, from_json(py, value)?)?;
+            }
+            dict.into_bound_py_any(py)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_python_values_like_rust_tests() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "cmp")?;
+            cmp(&module)?;
+            let locals = PyDict::new(py);
+            locals.set_item("cmp", module)?;
+            py.run(
+                cr#"
+assert cmp.diff({"port": 8080}, {"port": 8081}) == [
+    {"actual": "8081", "expected": "8080", "kind": "changed", "path": "port", "pointer": "/port"}
+]
+assert cmp.diff([{"id": 1, "n": "a"}, {"id": 2}], [{"id": 2}, {"id": 1, "n": "b"}], {"key": "id"}) == [
+    {"actual": '"b"', "expected": '"a"', "kind": "changed", "path": "[0].n", "pointer": "/0/n"}
+]
+assert cmp.diff((1, 2), [2, 1], {"unordered": True}) == []
+
+for args, error, message in [
+    (([1], [float("nan")]), ValueError, "actual[0] is NaN, which JSON can't represent"),
+    (({1: 2}, {}), TypeError, "expected has a key which isn't a string: 1"),
+    (({"a": {1}}, {}), TypeError, "expected[\"a\"] is a set, which isn't JSON-like"),
+    (([], [], {"sort": True}), ValueError, "invalid comparison options: {'sort': True}"),
+]:
+    try:
+        cmp.diff(*args)
+    except error as err:
+        assert str(err) == message, str(err)
+    else:
+        raise AssertionError(args)
+"#,
+                None,
+                Some(&locals),
+            )
+        })
+        .unwrap();
+    }
+}
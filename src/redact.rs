@@ -0,0 +1,221 @@
+//! Redaction and normalization of serialized values before comparison,
+//! enabled by the `serde` feature, to ignore secrets and volatile values and
+//! keep secrets out of failure messages.
+
+use std::cell::RefCell;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use serde_json::Value;
+
+use crate::DiffPath;
+use crate::path::PathGlob;
+
+static DEFAULT: RwLock<Option<Arc<Redactions>>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<Redactions>>> = const { RefCell::new(None) };
+}
+
+/// Replaces a serialized value before it is compared.
+type Redaction = dyn Fn(&Value) -> Value + Send + Sync;
+
+/// Redactions of serialized values by path, like the redactions of the
+/// `insta` crate.
+///
+/// In serialized comparisons, both sides' values at paths matching a
+/// redaction's [glob pattern](DiffPath::matches_glob) are replaced by what
+/// it returns before they are compared, so redacting secrets to a constant
+/// ignores them and keeps them out of the failure message, and normalizing
+/// values compares them up to the normalization. Only the first matching
+/// redaction applies. Set the redactions for a call with [`with_redactions`],
+/// or for the whole process with [`set_redactions`]:
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::{Redactions, diff_structs, with_redactions};
+/// use serde::Serialize;
+/// use serde_json::json;
+///
+/// #[derive(Serialize)]
+/// struct User { token: &'static str, email: &'static str }
+///
+/// #[derive(Serialize)]
+/// struct Session { user: User }
+///
+/// let redactions = Redactions::new()
+///     .redact("user.token", |_| json!("[redacted]"))
+///     .redact("**/email", |email| json!(email.as_str().map(str::to_lowercase)));
+///
+/// let diff = with_redactions(redactions, || {
+///     diff_structs!(
+///         Session { user: User { token: "s3cr3t", email: "Ada@example.com" } },
+///         Session { user: User { token: "t0k3n", email: "ada@example.org" } }
+///     )
+/// });
+/// assert_eq!(diff.to_string(), "user.email: \"ada@example.com\" != \"ada@example.org\"\n");
+/// # }
+/// ```
+///
+/// Values captured with `CMP_CAPTURE=1` render with `Debug`, before
+/// serialization, so they are left out of failure messages while redactions
+/// are set.
+#[derive(Clone, Default)]
+pub struct Redactions {
+    redactions: Vec<(PathGlob, Arc<Redaction>)>,
+}
+
+impl Redactions {
+    /// Creates an empty set of redactions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the values at paths matching `pattern` with what `redaction`
+    /// returns for them, e.g. `|_| json!("[redacted]")`.
+    pub fn redact(
+        mut self,
+        pattern: &str,
+        redaction: impl Fn(&Value) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        self.redactions
+            .push((PathGlob::new(pattern), Arc::new(redaction)));
+        self
+    }
+
+    /// Returns the redacted value at `path`, or `None` if no redaction
+    /// matches it.
+    pub(crate) fn apply(&self, path: &DiffPath, value: &Value) -> Option<Value> {
+        self.redactions
+            .iter()
+            .find(|(glob, _)| glob.matches(path))
+            .map(|(_, redaction)| redaction(value))
+    }
+
+    /// Returns `value`, at `path`, with the redactions applied to it and
+    /// every value it contains.
+    pub(crate) fn apply_within(&self, path: &mut DiffPath, value: &Value) -> Value {
+        self.apply(path, value)
+            .unwrap_or_else(|| self.apply_below(path, value))
+    }
+
+    /// Returns `value`, at `path`, with the redactions applied to every value
+    /// it contains but not to itself.
+    pub(crate) fn apply_below(&self, path: &mut DiffPath, value: &Value) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| {
+                        path.push_field(key);
+                        let value = self.apply_within(path, value);
+                        path.pop();
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(array) => Value::Array(
+                array
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| {
+                        path.push_index(index);
+                        let value = self.apply_within(path, value);
+                        path.pop();
+                        value
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+impl Debug for Redactions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactions")
+            .field("redactions", &self.redactions.len())
+            .finish()
+    }
+}
+
+/// Sets the process-wide redactions, used when none are set for a
+/// comparison.
+pub fn set_redactions(redactions: Redactions) {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(redactions));
+}
+
+/// Removes the process-wide redactions.
+pub fn clear_redactions() {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `compare` with `redactions` as the redactions of the current thread,
+/// overriding the process-wide ones.
+pub fn with_redactions<R>(redactions: Redactions, compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous redactions, even if `compare` panics.
+    struct Restore(Option<Arc<Redactions>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let redactions = Arc::new(redactions);
+    let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(redactions)));
+    compare()
+}
+
+/// Returns the redactions set for the current comparison, if any.
+pub(crate) fn redactions() -> Option<Arc<Redactions>> {
+    SCOPED
+        .with(|scoped| scoped.borrow().clone())
+        .or_else(|| {
+            DEFAULT
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+        .filter(|redactions| !redactions.redactions.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn redacts_matching_values_within_a_value() {
+        let redactions = Redactions::new()
+            .redact("users[*].token", |_| json!("[redacted]"))
+            .redact("**/token", |_| json!(null));
+        let value = json!({"users": [{"name": "ada", "token": "a"}], "token": "b"});
+
+        assert_eq!(
+            redactions.apply_within(&mut DiffPath::new(), &value),
+            json!({"users": [{"name": "ada", "token": "[redacted]"}], "token": null})
+        );
+        assert_eq!(redactions.apply(&DiffPath::new(), &value), None);
+    }
+
+    #[test]
+    fn keeps_redacted_values_out_of_differences() {
+        let redactions = Redactions::new().redact("**/token", |_| json!("[redacted]"));
+        let diff = with_redactions(redactions, || {
+            crate::diff(
+                &json!({"user": {"token": "a", "id": 1}}),
+                &json!({"user": {"id": 1}, "admin": {"token": "b"}}),
+            )
+        });
+
+        assert_eq!(
+            diff.to_string(),
+            "user.token: field missing from actual: \"[redacted]\"\n\
+             admin: field missing from expected: {\"token\":\"[redacted]\"}\n"
+        );
+        assert!(super::redactions().is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! Pluggable rendering of the differences in failure messages, selected per
+//! call or process-wide.
+
+use std::cell::RefCell;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::Diff;
+
+static DEFAULT: RwLock<Option<Arc<dyn DiffRenderer>>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Arc<dyn DiffRenderer>>> = const { RefCell::new(None) };
+}
+
+/// Renders the differences of a failed comparison for its failure message.
+///
+/// When no renderer is set, the text output format renders differences like
+/// [`TextRenderer`], with values colored if the `color` feature is enabled
+/// and the output is a terminal. A renderer replaces that, after any
+/// `CMP_ELIDE` elision; `CMP_FORMAT=rust` still takes precedence. Set one for
+/// a call with [`with_renderer`], or for the whole process with
+/// [`set_renderer`]:
+///
+/// ```edition2024
+/// use cmp::{Diff, DiffRenderer, Diffable};
+///
+/// /// Renders only the differing paths.
+/// struct Paths;
+///
+/// impl DiffRenderer for Paths {
+///     fn render(&self, diff: &Diff) -> String {
+///         let paths: Vec<_> = diff.iter().map(|entry| entry.path().to_string()).collect();
+///         format!("differing: {}\n", paths.join(", "))
+///     }
+/// }
+///
+/// let diff = vec![1, 2, 3].compare(&vec![1, 5, 6]);
+///
+/// assert_eq!(Paths.render(&diff), "differing: [1], [2]\n");
+/// ```
+pub trait DiffRenderer: Send + Sync {
+    /// Renders `diff`, which is not empty.
+    fn render(&self, diff: &Diff) -> String;
+}
+
+/// Renders one `path: expected != actual` line per difference, the
+/// `Display` implementation of [`Diff`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextRenderer;
+
+impl DiffRenderer for TextRenderer {
+    fn render(&self, diff: &Diff) -> String {
+        diff.to_string()
+    }
+}
+
+/// Renders like [`TextRenderer`], with expected values in green and actual
+/// values in red, whether or not the output is a terminal.
+#[cfg(feature = "color")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorRenderer;
+
+#[cfg(feature = "color")]
+impl DiffRenderer for ColorRenderer {
+    fn render(&self, diff: &Diff) -> String {
+        crate::color::paint(diff)
+    }
+}
+
+/// Renders the differences as pretty-printed JSON, in the format of
+/// [`Diff::save`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonRenderer;
+
+#[cfg(feature = "serde")]
+impl DiffRenderer for JsonRenderer {
+    fn render(&self, diff: &Diff) -> String {
+        let mut json = serde_json::to_string_pretty(diff).expect("Could not serialize diff");
+        json.push('\n');
+        json
+    }
+}
+
+/// Sets the process-wide renderer, used when none is set for a comparison.
+pub fn set_renderer(renderer: impl DiffRenderer + 'static) {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(renderer));
+}
+
+/// Resets the process-wide renderer to the default text output.
+pub fn clear_renderer() {
+    *DEFAULT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Runs `compare` with `renderer` as the renderer of the current thread,
+/// overriding the process-wide one.
+pub fn with_renderer<R>(renderer: impl DiffRenderer + 'static, compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous renderer, even if `compare` panics.
+    struct Restore(Option<Arc<dyn DiffRenderer>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let renderer: Arc<dyn DiffRenderer> = Arc::new(renderer);
+    let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(renderer)));
+    compare()
+}
+
+/// Returns the renderer set for the current comparison, if any.
+pub(crate) fn renderer() -> Option<Arc<dyn DiffRenderer>> {
+    SCOPED.with(|scoped| scoped.borrow().clone()).or_else(|| {
+        DEFAULT
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    struct Count;
+
+    impl DiffRenderer for Count {
+        fn render(&self, diff: &Diff) -> String {
+            format!("{} differences\n", diff.len())
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "2 differences")]
+    fn renders_failures_with_the_scoped_renderer() {
+        #[derive(Debug)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        assert!(renderer().is_none());
+        assert_eq!(
+            TextRenderer.render(&1.compare(&2)),
+            (1.compare(&2)).to_string()
+        );
+        with_renderer(Count, || {
+            crate::compare_structs!(Point { x: 1, y: 2 }, Point { x: 0, y: 3 }, x, y)
+        });
+    }
+}
@@ -0,0 +1,302 @@
+//! Reporting of failed comparisons to a process-wide [`Reporter`].
+
+use std::collections::HashMap;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::Diff;
+use crate::format::Format;
+
+static REPORTER: RwLock<Option<Arc<dyn Reporter>>> = RwLock::new(None);
+
+/// Receives every failed comparison, in addition to the usual panic message.
+///
+/// Register one with [`set_reporter`]. [`compare_structs!`](crate::compare_structs)
+/// reports its differences before panicking, and code using the
+/// non-panicking API can call [`report`] itself.
+///
+/// In hot paths, wrap a reporter with [`sample`](Reporter::sample) and
+/// [`rate_limit_per_path`](Reporter::rate_limit_per_path) so reporting can't
+/// overwhelm the log pipeline:
+///
+/// ```edition2024
+/// use std::time::Duration;
+///
+/// use cmp::Reporter;
+///
+/// let reporter = |diff: &cmp::Diff| eprint!("{diff}");
+/// cmp::set_reporter(reporter.sample(100).rate_limit_per_path(10, Duration::from_secs(60)));
+/// # cmp::clear_reporter();
+/// ```
+pub trait Reporter: Send + Sync {
+    /// Called with the differences of one failed comparison.
+    fn report(&self, diff: &Diff);
+
+    /// Only passes on one in every `n` failed comparisons, starting with the
+    /// first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    fn sample(self, n: u64) -> Sampled<Self>
+    where
+        Self: Sized,
+    {
+        assert!(n > 0, "sampling rate must be at least 1");
+        Sampled {
+            inner: self,
+            n,
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Passes on at most `max` differences per path in each `window`,
+    /// dropping the rest. Sequence indices are ignored when grouping paths,
+    /// so `items[1].price` and `items[2].price` share a limit.
+    fn rate_limit_per_path(self, max: u32, window: Duration) -> RateLimited<Self>
+    where
+        Self: Sized,
+    {
+        RateLimited {
+            inner: self,
+            max,
+            window,
+            paths: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<F: Fn(&Diff) + Send + Sync> Reporter for F {
+    fn report(&self, diff: &Diff) {
+        self(diff);
+    }
+}
+
+/// Reporter returned by [`Reporter::sample`].
+#[derive(Debug)]
+pub struct Sampled<R> {
+    inner: R,
+    n: u64,
+    seen: AtomicU64,
+}
+
+impl<R: Reporter> Reporter for Sampled<R> {
+    fn report(&self, diff: &Diff) {
+        if self
+            .seen
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.n)
+        {
+            self.inner.report(diff);
+        }
+    }
+}
+
+/// Reporter returned by [`Reporter::rate_limit_per_path`].
+#[derive(Debug)]
+pub struct RateLimited<R> {
+    inner: R,
+    max: u32,
+    window: Duration,
+    paths: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl<R: Reporter> Reporter for RateLimited<R> {
+    fn report(&self, diff: &Diff) {
+        let now = Instant::now();
+        let mut allowed = Diff::new();
+        {
+            let mut paths = self.paths.lock().unwrap_or_else(PoisonError::into_inner);
+            for entry in diff {
+                let key = entry.path().with_wildcard_indices().to_string();
+                let (start, count) = paths.entry(key).or_insert((now, 0));
+                if now.duration_since(*start) >= self.window {
+                    (*start, *count) = (now, 0);
+                }
+                if *count < self.max {
+                    *count += 1;
+                    allowed.push(entry.clone());
+                }
+            }
+        }
+        if !allowed.is_empty() {
+            self.inner.report(&allowed);
+        }
+    }
+}
+
+/// Sets the process-wide reporter, replacing any previous one.
+pub fn set_reporter(reporter: impl Reporter + 'static) {
+    *REPORTER.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(reporter));
+}
+
+/// Removes the process-wide reporter.
+pub fn clear_reporter() {
+    *REPORTER.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Passes `diff` to the process-wide reporter, if one is set and `diff` is
+/// not empty.
+///
+/// The reporter runs without holding the lock on it, so it may itself call
+/// [`set_reporter`] or [`clear_reporter`].
+pub fn report(diff: &Diff) {
+    if diff.is_empty() {
+        return;
+    }
+    let reporter = REPORTER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    if let Some(reporter) = reporter {
+        reporter.report(diff);
+    }
+}
+
+/// Reports and prints `diff` in the format set with `CMP_FORMAT`, returning
+/// its [exit code](Diff::into_exit_code), for verification binaries using the
+/// non-panicking API.
+///
+/// The differences go to standard output and are passed to the
+/// process-wide reporter, if one is set. Nothing is printed if there are
+/// none.
+///
+/// ```edition2024,no_run
+/// use std::process::ExitCode;
+///
+/// use cmp::Diffable;
+///
+/// fn main() -> ExitCode {
+///     let expected = vec![1, 2, 3];
+///     let actual = vec![1, 2, 3];
+///     cmp::report_and_exit(expected.compare(&actual))
+/// }
+/// ```
+pub fn report_and_exit(diff: Diff) -> ExitCode {
+    if !diff.is_empty() {
+        report(&diff);
+        print!(
+            "{}",
+            Format::from_env().render_to(&diff, "expected", &std::io::stdout())
+        );
+    }
+    diff.into_exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{DiffPath, FieldDiff};
+
+    static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    #[test]
+    fn reports_non_empty_diffs_to_the_reporter() {
+        // Other tests may fail comparisons concurrently, so only look at the
+        // diffs made here.
+        set_reporter(|diff: &Diff| {
+            for entry in diff {
+                if entry.path().to_string().starts_with("reported_") {
+                    REPORTED.lock().unwrap().push(entry.to_string());
+                }
+            }
+        });
+
+        let mut path = DiffPath::new();
+        path.push_field("reported_port");
+        let mut diff = Diff::new();
+        report(&diff);
+        diff.push(FieldDiff::Changed {
+            path,
+            expected: "1".into(),
+            actual: "2".into(),
+        });
+        report(&diff);
+        clear_reporter();
+        report(&diff);
+
+        assert_eq!(*REPORTED.lock().unwrap(), ["reported_port: 1 != 2"]);
+        // A reporter may replace itself, e.g. to report only once.
+        set_reporter(|_: &Diff| clear_reporter());
+        report(&diff);
+        assert!(REPORTER.read().unwrap().is_none());
+    }
+
+    #[test]
+    fn exit_codes_reflect_differences() {
+        assert_eq!(report_and_exit(Diff::new()), ExitCode::SUCCESS);
+        assert_eq!(diff_at(0, "exit_code").into_exit_code(), ExitCode::FAILURE);
+    }
+
+    fn diff_at(index: usize, field: &str) -> Diff {
+        let mut path = DiffPath::new();
+        path.push_field("items");
+        path.push_index(index);
+        path.push_field(field);
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::MissingFromActual {
+            path,
+            expected: "1".into(),
+        });
+        diff
+    }
+
+    #[test]
+    fn samples_one_in_n_failures() {
+        let count = AtomicU64::new(0);
+        let reporter = (|_: &Diff| {
+            count.fetch_add(1, Ordering::Relaxed);
+        })
+        .sample(3);
+
+        for index in 0..7 {
+            reporter.report(&diff_at(index, "price"));
+        }
+
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn rate_limits_each_path_separately() {
+        let reported = Mutex::new(Vec::new());
+        let reporter = (|diff: &Diff| {
+            reported
+                .lock()
+                .unwrap()
+                .extend(diff.iter().map(ToString::to_string));
+        })
+        .rate_limit_per_path(2, Duration::from_secs(3600));
+
+        for index in 0..4 {
+            reporter.report(&diff_at(index, "price"));
+        }
+        reporter.report(&diff_at(9, "sku"));
+
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [
+                "items[0].price: field missing from actual: 1",
+                "items[1].price: field missing from actual: 1",
+                "items[9].sku: field missing from actual: 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn rate_limits_reset_after_the_window() {
+        let count = AtomicU64::new(0);
+        let reporter = (|_: &Diff| {
+            count.fetch_add(1, Ordering::Relaxed);
+        })
+        .rate_limit_per_path(1, Duration::ZERO);
+
+        reporter.report(&diff_at(0, "price"));
+        reporter.report(&diff_at(0, "price"));
+
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,143 @@
+//! Review of pending snapshot updates, enabled by the `review` feature.
+//!
+//! When a value differs from its snapshot,
+//! [`assert_struct_snapshot!`](crate::assert_struct_snapshot) writes it next
+//! to the snapshot with a `.new` extension. [`pending`] finds these files,
+//! and each [`PendingSnapshot`] can be diffed, accepted or rejected. The
+//! `cargo-cmp` binary uses this for `cargo cmp review`, which walks the
+//! pending updates interactively.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::snapshot::pending_path;
+use crate::value::diff_values;
+use crate::{Diff, DiffPath};
+
+/// A value written next to its snapshot because it differed from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingSnapshot {
+    /// The snapshot file, which may not exist yet.
+    pub snapshot: PathBuf,
+    /// The file with the new value.
+    pub pending: PathBuf,
+}
+
+impl PendingSnapshot {
+    /// Returns the pending update of `snapshot`, if there is one.
+    pub fn of(snapshot: impl Into<PathBuf>) -> Option<Self> {
+        let snapshot = snapshot.into();
+        let pending = pending_path(&snapshot);
+        pending.is_file().then_some(Self { snapshot, pending })
+    }
+
+    /// Returns whether there is no snapshot yet.
+    pub fn is_new(&self) -> bool {
+        !self.snapshot.exists()
+    }
+
+    /// Compares the snapshot with the new value. A missing snapshot compares
+    /// as `null`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file can't be read, or an
+    /// [`io::ErrorKind::InvalidData`] error if it isn't valid JSON.
+    pub fn diff(&self) -> io::Result<Diff> {
+        let expected = match fs::read_to_string(&self.snapshot) {
+            Ok(json) => parse(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Value::Null,
+            Err(err) => return Err(err),
+        };
+        let actual = parse(&fs::read_to_string(&self.pending)?)?;
+        let mut diff = Diff::new();
+        diff_values(&expected, &actual, false, &mut DiffPath::new(), &mut diff);
+        Ok(diff)
+    }
+
+    /// Replaces the snapshot with the new value.
+    pub fn accept(&self) -> io::Result<()> {
+        fs::rename(&self.pending, &self.snapshot)
+    }
+
+    /// Discards the new value, keeping the snapshot.
+    pub fn reject(&self) -> io::Result<()> {
+        fs::remove_file(&self.pending)
+    }
+}
+
+fn parse(json: &str) -> io::Result<Value> {
+    serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Finds the pending snapshot updates under `root`, sorted by path, skipping
+/// `target` and hidden directories.
+pub fn pending(root: impl AsRef<Path>) -> io::Result<Vec<PendingSnapshot>> {
+    let mut found = Vec::new();
+    walk(root.as_ref(), &mut found)?;
+    found.sort_by(|a, b| a.pending.cmp(&b.pending));
+    Ok(found)
+}
+
+fn walk(dir: &Path, found: &mut Vec<PendingSnapshot>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if entry.file_type()?.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                walk(&path, found)?;
+            }
+        } else if let Some(snapshot) = name.strip_suffix(".json.new") {
+            found.push(PendingSnapshot {
+                snapshot: path.with_file_name(format!("{snapshot}.json")),
+                pending: path,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_diffs_and_resolves_pending_updates() {
+        let root = std::env::temp_dir().join(format!("cmp-review-{}", std::process::id()));
+        let snapshots = root.join("crate/snapshots");
+        fs::create_dir_all(root.join("target/snapshots")).unwrap();
+        fs::create_dir_all(&snapshots).unwrap();
+        fs::write(root.join("target/snapshots/skipped.json.new"), "1").unwrap();
+        fs::write(snapshots.join("a.json"), r#"{"id": 1, "name": "ada"}"#).unwrap();
+        fs::write(
+            snapshots.join("a.json.new"),
+            r#"{"id": 1, "name": "grace"}"#,
+        )
+        .unwrap();
+        fs::write(snapshots.join("b.json.new"), "[1]").unwrap();
+
+        let pending = pending(&root).unwrap();
+        let [a, b] = pending.as_slice() else {
+            panic!("unexpected pending snapshots: {pending:?}");
+        };
+        let a_diff = a.diff().unwrap().to_string();
+        let new = [a.is_new(), b.is_new()];
+        a.accept().unwrap();
+        b.reject().unwrap();
+        let accepted = fs::read_to_string(snapshots.join("a.json")).unwrap();
+        let remaining = super::pending(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(PendingSnapshot::of(snapshots.join("c.json")), None);
+        assert_eq!(a.snapshot, snapshots.join("a.json"));
+        assert_eq!(a_diff, "name: \"ada\" != \"grace\"\n");
+        assert_eq!(new, [false, true]);
+        assert!(accepted.contains("grace"));
+        assert!(remaining.is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+//! Shape comparison between two versions of a serializable type, enabled by
+//! the `serde` feature.
+//!
+//! Rather than comparing values, [`drift`] compares the *shape* of two
+//! serialized values: which fields exist and what JSON type they hold. This
+//! is useful for reviewing API evolution in tests, e.g. checking that a new
+//! version of a response struct only adds fields.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::similarity;
+
+/// Report of the fields added, removed, renamed and retyped between two
+/// versions of a struct.
+///
+/// Nested fields use dotted paths (`address.city`), and the shape of array
+/// elements is taken from the first element (`items[].sku`). Only the
+/// outermost added or removed field is reported, so removing `address` does
+/// not also list `address.city`.
+#[derive(Debug, Default, PartialEq)]
+pub struct SchemaDrift {
+    pub added: Vec<Field>,
+    pub removed: Vec<Field>,
+    pub renamed: Vec<Rename>,
+    pub type_changed: Vec<TypeChange>,
+}
+
+/// A field path and the JSON type it holds.
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    pub path: String,
+    pub kind: &'static str,
+}
+
+/// A removed field paired with an added one which is likely its new name.
+#[derive(Debug, PartialEq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    pub kind: &'static str,
+}
+
+/// A field present in both versions whose JSON type changed.
+#[derive(Debug, PartialEq)]
+pub struct TypeChange {
+    pub path: String,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+impl SchemaDrift {
+    /// Returns `true` if both versions have the same shape.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.type_changed.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no schema drift");
+        }
+        for field in &self.added {
+            writeln!(f, "added: {} ({})", field.path, field.kind)?;
+        }
+        for field in &self.removed {
+            writeln!(f, "removed: {} ({})", field.path, field.kind)?;
+        }
+        for rename in &self.renamed {
+            writeln!(
+                f,
+                "renamed: {} -> {} ({})",
+                rename.from, rename.to, rename.kind
+            )?;
+        }
+        for change in &self.type_changed {
+            writeln!(
+                f,
+                "type changed: {} ({} -> {})",
+                change.path, change.from, change.to
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the shapes of `old` and `new`, which are usually two different
+/// versions of the same struct.
+///
+/// Renames are detected heuristically: a removed and an added field under
+/// the same parent, holding the same type, are paired when their names only
+/// differ in case or separators (`user_name` and `userName`), when they hold
+/// the same value, or when their names are within an edit distance of two.
+/// `null` values (such as `None`) are compatible with every type.
+///
+/// ```edition2024
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct UserV1 { id: u32, user_name: String, age: String }
+///
+/// #[derive(Serialize)]
+/// struct UserV2 {
+///     id: u32,
+///     #[serde(rename = "userName")]
+///     user_name: String,
+///     age: u8,
+///     email: String,
+/// }
+///
+/// let old = UserV1 { id: 1, user_name: "ada".into(), age: "36".into() };
+/// let new = UserV2 { id: 1, user_name: "ada".into(), age: 36, email: "ada@example.com".into() };
+///
+/// let drift = cmp::schema::drift(&old, &new).unwrap();
+///
+/// assert_eq!(
+///     drift.to_string(),
+///     "added: email (string)\n\
+///      renamed: user_name -> userName (string)\n\
+///      type changed: age (string -> integer)\n"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if either value cannot be serialized.
+pub fn drift<O, N>(old: &O, new: &N) -> Result<SchemaDrift, serde_json::Error>
+where
+    O: Serialize + ?Sized,
+    N: Serialize + ?Sized,
+{
+    let old = serde_json::to_value(old)?;
+    let new = serde_json::to_value(new)?;
+    let mut old_shape = BTreeMap::new();
+    let mut new_shape = BTreeMap::new();
+    collect_shape(&old, "", &mut old_shape);
+    collect_shape(&new, "", &mut new_shape);
+
+    let mut drift = SchemaDrift::default();
+    for (path, (old_kind, _)) in &old_shape {
+        if let Some((new_kind, _)) = new_shape.get(path)
+            && old_kind != new_kind
+            && *old_kind != "null"
+            && *new_kind != "null"
+        {
+            drift.type_changed.push(TypeChange {
+                path: path.clone(),
+                from: old_kind,
+                to: new_kind,
+            });
+        }
+    }
+
+    let mut removed = outermost_missing(&old_shape, &new_shape);
+    let mut added = outermost_missing(&new_shape, &old_shape);
+
+    removed.retain(|from| {
+        let (kind, old_value) = &old_shape[from];
+        let candidate = added
+            .iter()
+            .enumerate()
+            .filter(|(_, to)| parent(to) == parent(from) && new_shape[*to].0 == *kind)
+            .filter_map(|(index, to)| {
+                let score = rename_score(leaf(from), leaf(to), old_value, new_shape[to].1)?;
+                Some((score, index))
+            })
+            .min();
+        match candidate {
+            Some((_, index)) => {
+                drift.renamed.push(Rename {
+                    from: from.clone(),
+                    to: added.remove(index),
+                    kind,
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
+    drift.removed = to_fields(removed, &old_shape);
+    drift.added = to_fields(added, &new_shape);
+    Ok(drift)
+}
+
+type Shape<'a> = BTreeMap<String, (&'static str, &'a Value)>;
+
+fn collect_shape<'a>(value: &'a Value, path: &str, shape: &mut Shape<'a>) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                shape.insert(field_path.clone(), (kind(field), field));
+                collect_shape(field, &field_path, shape);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(first) = items.first() {
+                collect_shape(first, &format!("{path}[]"), shape);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(number) if number.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Paths in `shape` missing from `other`, excluding those nested under
+/// another missing path.
+fn outermost_missing(shape: &Shape<'_>, other: &Shape<'_>) -> Vec<String> {
+    let missing: Vec<&String> = shape
+        .keys()
+        .filter(|path| !other.contains_key(*path))
+        .collect();
+    missing
+        .iter()
+        .filter(|path| {
+            !missing.iter().any(|ancestor| {
+                path.strip_prefix(ancestor.as_str())
+                    .is_some_and(|rest| rest.starts_with('.') || rest.starts_with("[]"))
+            })
+        })
+        .map(|path| path.to_string())
+        .collect()
+}
+
+fn to_fields(paths: Vec<String>, shape: &Shape<'_>) -> Vec<Field> {
+    paths
+        .into_iter()
+        .map(|path| Field {
+            kind: shape[&path].0,
+            path,
+        })
+        .collect()
+}
+
+fn parent(path: &str) -> &str {
+    path.rfind('.').map_or("", |index| &path[..index])
+}
+
+fn leaf(path: &str) -> &str {
+    path.rfind('.').map_or(path, |index| &path[index + 1..])
+}
+
+/// Lower scores are better matches; `None` means the names are unrelated.
+fn rename_score(from: &str, to: &str, old_value: &Value, new_value: &Value) -> Option<usize> {
+    if similarity::same_words(from, to) {
+        return Some(0);
+    }
+    if !old_value.is_null() && old_value == new_value {
+        return Some(1);
+    }
+    let distance = similarity::levenshtein(from, to);
+    (distance <= 2).then_some(distance + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn same_shape_has_no_drift() {
+        let drift = drift(&json!({"a": 1, "b": "x"}), &json!({"a": 2, "b": "y"})).unwrap();
+
+        assert!(drift.is_empty());
+        assert_eq!(drift.to_string(), "no schema drift\n");
+    }
+
+    #[test]
+    fn reports_only_outermost_nested_changes() {
+        let old = json!({"id": 1, "address": {"city": "x", "zip": "1"}});
+        let new = json!({"id": 1, "location": {"lat": 1.5}, "tags": [{"name": "a"}]});
+
+        let drift = drift(&old, &new).unwrap();
+
+        assert_eq!(
+            drift.removed,
+            [Field {
+                path: "address".into(),
+                kind: "object"
+            }]
+        );
+        assert_eq!(
+            drift
+                .added
+                .iter()
+                .map(|f| f.path.as_str())
+                .collect::<Vec<_>>(),
+            ["location", "tags"]
+        );
+    }
+
+    #[test]
+    fn pairs_renames_by_value_and_nested_parent() {
+        let old = json!({"user": {"mail": "a@b.c", "count": 3}});
+        let new = json!({"user": {"email_address": "a@b.c", "count": 3.5}});
+
+        let drift = drift(&old, &new).unwrap();
+
+        assert_eq!(
+            drift.renamed,
+            [Rename {
+                from: "user.mail".into(),
+                to: "user.email_address".into(),
+                kind: "string"
+            }]
+        );
+        assert_eq!(
+            drift.type_changed,
+            [TypeChange {
+                path: "user.count".into(),
+                from: "integer",
+                to: "float"
+            }]
+        );
+    }
+
+    #[test]
+    fn null_is_compatible_with_any_type() {
+        let drift = drift(&json!({"a": null}), &json!({"a": "now set"})).unwrap();
+
+        assert!(drift.is_empty());
+    }
+}
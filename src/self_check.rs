@@ -0,0 +1,184 @@
+//! Reflexivity and symmetry checks of [`Diffable`] comparisons, enabled with
+//! `CMP_SELF_CHECK=1`.
+//!
+//! With the variable set, every [`Diffable::compare`] also checks that each
+//! value compares equal to itself and that comparing the values the other
+//! way round finds the [inverted](crate::Diff::inverted) differences. This
+//! catches buggy custom comparators and non-reflexive `PartialEq`
+//! implementations, such as fields holding `NaN`, before they make a test
+//! pass or fail for the wrong reason.
+
+use std::env;
+use std::fmt;
+
+use crate::{Diff, DiffPath, Diffable};
+
+/// Environment variable which enables the self-checks.
+const SELF_CHECK_VAR: &str = "CMP_SELF_CHECK";
+
+/// Returns whether `CMP_SELF_CHECK` is set to anything other than `0` or an
+/// empty string.
+pub fn enabled() -> bool {
+    env::var_os(SELF_CHECK_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// A violated property of a comparison, returned by [`verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Comparing a value with itself found differences.
+    NotReflexive {
+        /// Which side of the original comparison the value was on.
+        side: &'static str,
+        diff: Diff,
+    },
+    /// Comparing the actual value with the expected one didn't find the
+    /// inverse of the original differences.
+    NotSymmetric { forward: Diff, backward: Diff },
+}
+
+/// A failed self-check of a comparison of values of type `type_name`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfCheckFailure {
+    pub type_name: &'static str,
+    pub violation: Violation,
+}
+
+impl fmt::Display for SelfCheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "comparison self-check failed for `{}`: ", self.type_name)?;
+        match &self.violation {
+            Violation::NotReflexive { side, diff } => {
+                writeln!(f, "the {side} value differs from itself")?;
+                write!(f, "{diff}")
+            }
+            Violation::NotSymmetric { forward, backward } => {
+                writeln!(f, "comparing actual with expected isn't the inverse")?;
+                writeln!(f, "expected vs actual:")?;
+                write!(f, "{forward}")?;
+                writeln!(f, "actual vs expected:")?;
+                write!(f, "{backward}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelfCheckFailure {}
+
+/// Checks that `expected` and `actual` each compare equal to themselves, and
+/// that comparing `actual` with `expected` finds the inverse of `diff`, the
+/// result of comparing `expected` with `actual`.
+///
+/// Differences may be found in any order the other way round.
+///
+/// ```edition2024
+/// let diff = cmp::Diffable::compare(&1.0, &2.0);
+/// assert!(cmp::self_check::verify(&1.0, &2.0, &diff).is_ok());
+///
+/// let failure = cmp::self_check::verify(&f64::NAN, &1.0, &diff).unwrap_err();
+/// assert!(failure.to_string().contains("the expected value differs from itself"));
+/// ```
+#[allow(clippy::result_large_err)]
+pub fn verify<T: Diffable + ?Sized>(
+    expected: &T,
+    actual: &T,
+    diff: &Diff,
+) -> Result<(), SelfCheckFailure> {
+    let fail = |violation| SelfCheckFailure {
+        type_name: std::any::type_name::<T>(),
+        violation,
+    };
+    for (side, value) in [("expected", expected), ("actual", actual)] {
+        let reflexive = diff_of(value, value);
+        if !reflexive.is_empty() {
+            return Err(fail(Violation::NotReflexive {
+                side,
+                diff: reflexive,
+            }));
+        }
+    }
+
+    let backward = diff_of(actual, expected);
+    if !same_entries(&diff.inverted(), &backward) {
+        return Err(fail(Violation::NotSymmetric {
+            forward: diff.clone(),
+            backward,
+        }));
+    }
+    Ok(())
+}
+
+/// Compares without recording metrics or recursing into the self-checks.
+fn diff_of<T: Diffable + ?Sized>(expected: &T, actual: &T) -> Diff {
+    let mut out = Diff::new();
+    expected.diff(actual, &mut DiffPath::new(), &mut out);
+    out
+}
+
+fn same_entries(a: &Diff, b: &Diff) -> bool {
+    let sorted = |diff: &Diff| {
+        let mut lines: Vec<String> = diff.iter().map(ToString::to_string).collect();
+        lines.sort();
+        lines
+    };
+    sorted(a) == sorted(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldDiff, diff_eq};
+
+    /// A comparator which only notices when the actual value is larger.
+    struct Lopsided(u32);
+
+    impl Diffable for Lopsided {
+        fn diff(&self, other: &Self, path: &mut DiffPath, out: &mut Diff) {
+            if other.0 > self.0 {
+                diff_eq(&self.0, &other.0, path, out);
+            }
+        }
+    }
+
+    #[test]
+    fn passes_consistent_comparisons() {
+        let expected = vec![1, 2, 3];
+        let actual = vec![1, 5];
+        let diff = diff_of(&expected, &actual);
+
+        assert_eq!(diff.len(), 2);
+        assert_eq!(verify(&expected, &actual, &diff), Ok(()));
+    }
+
+    #[test]
+    fn reports_values_which_differ_from_themselves() {
+        let actual = (1.0, f64::NAN);
+        let failure = verify(&(1.0, 2.0), &actual, &diff_of(&(1.0, 2.0), &actual)).unwrap_err();
+
+        assert_eq!(
+            failure.to_string(),
+            "comparison self-check failed for `(f64, f64)`: \
+             the actual value differs from itself\n\
+             1: NaN != NaN\n"
+        );
+    }
+
+    #[test]
+    fn reports_asymmetric_comparators() {
+        let (expected, actual) = (Lopsided(1), Lopsided(2));
+        let diff = diff_of(&expected, &actual);
+        let failure = verify(&expected, &actual, &diff).unwrap_err();
+
+        assert!(matches!(
+            &failure.violation,
+            Violation::NotSymmetric { backward, .. } if backward.is_empty()
+        ));
+        assert_eq!(
+            diff.inverted().entries(),
+            [FieldDiff::Changed {
+                path: DiffPath::new(),
+                expected: "2".into(),
+                actual: "1".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,634 @@
+//! Serialization of compared values in serde mode, locating failures at the
+//! path of the field which couldn't be serialized.
+//!
+//! Values are serialized with `serde_json` first. Only when that fails are
+//! they serialized again, field by field, to find where, and either fail
+//! with that path or, with [`SerializationFailure::Skip`], leave out the
+//! fields which fail and report them instead.
+
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use serde::ser;
+use serde_json::{Map, Value};
+
+use crate::DiffPath;
+
+static SKIP: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static SCOPED: Cell<Option<SerializationFailure>> = const { Cell::new(None) };
+}
+
+/// What serde mode comparisons do when a compared value can't be serialized.
+///
+/// Snapshots, fixtures and [JSON Patches](crate::diff_as_json_patch) always
+/// panic, as a field left out would be written or patched as `null`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SerializationFailure {
+    /// Panic with the path of the field which couldn't be serialized, or
+    /// return it as a [`SerializeError`] from [`try_diff`](crate::try_diff).
+    #[default]
+    Panic,
+    /// Compare the other fields, and report each field which couldn't be
+    /// serialized as a difference, e.g. `avatar: <could not serialize: …> !=
+    /// {"url":"a.png"}`.
+    Skip,
+}
+
+/// Sets what serde mode comparisons do when a value can't be serialized, for
+/// comparisons with no behavior set for them.
+pub fn set_serialization_failure(failure: SerializationFailure) {
+    SKIP.store(failure == SerializationFailure::Skip, Ordering::Relaxed);
+}
+
+/// Runs `compare` with `failure` as what serde mode comparisons on the
+/// current thread do when a value can't be serialized.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use std::collections::HashMap;
+///
+/// use cmp::{SerializationFailure, with_serialization_failure};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Page { title: &'static str, cells: HashMap<(u8, u8), u32> }
+///
+/// let page = |title| Page { title, cells: HashMap::from([((0, 0), 1)]) };
+/// let diff = with_serialization_failure(SerializationFailure::Skip, || {
+///     cmp::diff(&page("a"), &page("b"))
+/// });
+/// assert_eq!(
+///     diff.to_string(),
+///     "title: \"a\" != \"b\"\n\
+///      cells: <could not serialize: key must be a string> != <could not serialize: key must be a string>\n"
+/// );
+/// # }
+/// ```
+pub fn with_serialization_failure<R>(
+    failure: SerializationFailure,
+    compare: impl FnOnce() -> R,
+) -> R {
+    /// Restores the previous behavior, even if `compare` panics.
+    struct Restore(Option<SerializationFailure>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| scoped.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SCOPED.with(|scoped| scoped.replace(Some(failure))));
+    compare()
+}
+
+fn skip() -> bool {
+    match SCOPED.with(Cell::get) {
+        Some(failure) => failure == SerializationFailure::Skip,
+        None => SKIP.load(Ordering::Relaxed),
+    }
+}
+
+/// A compared value which couldn't be serialized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerializeError {
+    side: &'static str,
+    path: DiffPath,
+    message: String,
+}
+
+impl SerializeError {
+    /// Returns which side of the comparison failed, `expected` or `actual`.
+    pub fn side(&self) -> &str {
+        self.side
+    }
+
+    /// Returns the path of the field which couldn't be serialized, empty if
+    /// the value itself couldn't be.
+    pub fn path(&self) -> &DiffPath {
+        &self.path
+    }
+
+    /// Returns the error of the serializer.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not serialize {} value", self.side)?;
+        if !self.path.segments().is_empty() {
+            write!(f, " at `{}`", self.path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for SerializeError {}
+
+/// A serialized value, with the fields left out because they couldn't be
+/// serialized.
+#[derive(Debug, Default)]
+pub(crate) struct Serialized {
+    pub(crate) value: Value,
+    /// The path and error of each field left out, set to `null` in `value`.
+    pub(crate) skipped: Vec<(DiffPath, String)>,
+}
+
+/// Serializes the `side` value of a comparison, locating any failure, and
+/// leaving out the fields which fail with [`SerializationFailure::Skip`].
+pub(crate) fn serialize<T: Serialize + ?Sized>(
+    value: &T,
+    side: &'static str,
+) -> Result<Serialized, SerializeError> {
+    if let Ok(value) = serde_json::to_value(value) {
+        return Ok(Serialized {
+            value,
+            skipped: Vec::new(),
+        });
+    }
+    let skip = skip();
+    let mut path = DiffPath::new();
+    let mut failures = Vec::new();
+    let serializer = ValueSerializer {
+        path: &mut path,
+        failures: &mut failures,
+        skip,
+    };
+    match value.serialize(serializer) {
+        Ok(value) => Ok(Serialized {
+            value,
+            skipped: failures,
+        }),
+        Err(err) if skip => Ok(Serialized {
+            value: Value::Null,
+            skipped: vec![(DiffPath::new(), err.to_string())],
+        }),
+        Err(err) => {
+            let (path, message) = failures
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| (DiffPath::new(), err.to_string()));
+            Err(SerializeError {
+                side,
+                path,
+                message,
+            })
+        }
+    }
+}
+
+/// Serializes the `side` value of a comparison like [`serialize`], panicking
+/// with the path of the field which couldn't be serialized unless it is
+/// skipped.
+pub(crate) fn expect_serialized<T: Serialize + ?Sized>(
+    value: &T,
+    side: &'static str,
+) -> Serialized {
+    serialize(value, side).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Serializes a value which is written or patched rather than compared,
+/// panicking with the path of any field which couldn't be serialized, even
+/// with [`SerializationFailure::Skip`].
+pub(crate) fn to_value<T: Serialize + ?Sized>(value: &T, side: &'static str) -> Value {
+    with_serialization_failure(SerializationFailure::Panic, || {
+        expect_serialized(value, side).value
+    })
+}
+
+/// Serializes into a `Value` like `serde_json::value::Serializer`, tracking
+/// the path of the value being serialized to locate failures.
+struct ValueSerializer<'a> {
+    path: &'a mut DiffPath,
+    failures: &'a mut Vec<(DiffPath, String)>,
+    skip: bool,
+}
+
+impl ValueSerializer<'_> {
+    /// Serializes the value at the current path, recording its failure if no
+    /// value inside it failed, and replacing it with `null` when skipping.
+    fn child<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<Value, serde_json::Error> {
+        let failed = self.failures.len();
+        let serializer = ValueSerializer {
+            path: self.path,
+            failures: self.failures,
+            skip: self.skip,
+        };
+        match value.serialize(serializer) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                if self.failures.len() == failed {
+                    self.failures.push((self.path.clone(), err.to_string()));
+                }
+                if self.skip { Ok(Value::Null) } else { Err(err) }
+            }
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(
+        &mut self,
+        index: usize,
+        value: &T,
+    ) -> Result<Value, serde_json::Error> {
+        self.path.push_index(index);
+        let value = self.child(value);
+        self.path.pop();
+        value
+    }
+
+    fn field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<Value, serde_json::Error> {
+        self.path.push_field(name);
+        let value = self.child(value);
+        self.path.pop();
+        value
+    }
+}
+
+/// Delegates serializing a scalar to `serde_json`.
+macro_rules! scalars {
+    ($($method:ident($ty:ty)),+ $(,)?) => {
+        $(
+            fn $method(self, value: $ty) -> Result<Value, serde_json::Error> {
+                ser::Serializer::$method(serde_json::value::Serializer, value)
+            }
+        )+
+    };
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+    type SerializeSeq = Seq<'a>;
+    type SerializeTuple = Seq<'a>;
+    type SerializeTupleStruct = Seq<'a>;
+    type SerializeTupleVariant = Seq<'a>;
+    type SerializeMap = Object<'a>;
+    type SerializeStruct = Object<'a>;
+    type SerializeStructVariant = Object<'a>;
+
+    scalars!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_none(self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, serde_json::Error> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, serde_json::Error> {
+        let value = self.field(variant, value)?;
+        Ok(Value::Object(Map::from_iter([(
+            variant.to_string(),
+            value,
+        )])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Seq<'a>, serde_json::Error> {
+        Ok(Seq {
+            serializer: self,
+            variant: None,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'a>, serde_json::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Seq<'a>, serde_json::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Seq<'a>, serde_json::Error> {
+        self.path.push_field(variant);
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = Some(variant);
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Object<'a>, serde_json::Error> {
+        Ok(Object {
+            serializer: self,
+            variant: None,
+            fields: Map::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Object<'a>, serde_json::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Object<'a>, serde_json::Error> {
+        self.path.push_field(variant);
+        let mut object = self.serialize_map(Some(len))?;
+        object.variant = Some(variant);
+        Ok(object)
+    }
+}
+
+/// Wraps the value of an enum variant in an object keyed by the variant,
+/// leaving its path segment.
+fn in_variant(path: &mut DiffPath, variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => {
+            path.pop();
+            Value::Object(Map::from_iter([(variant.to_string(), value)]))
+        }
+        None => value,
+    }
+}
+
+/// A sequence, tuple or tuple variant being serialized.
+struct Seq<'a> {
+    serializer: ValueSerializer<'a>,
+    variant: Option<&'static str>,
+    elements: Vec<Value>,
+}
+
+impl Seq<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        let value = self.serializer.element(self.elements.len(), value)?;
+        self.elements.push(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, serde_json::Error> {
+        Ok(in_variant(
+            self.serializer.path,
+            self.variant,
+            Value::Array(self.elements),
+        ))
+    }
+}
+
+/// Implements the serde traits of a sequence kind for [`Seq`].
+macro_rules! seq_traits {
+    ($($trait:ident::$method:ident),+) => {
+        $(
+            impl ser::$trait for Seq<'_> {
+                type Ok = Value;
+                type Error = serde_json::Error;
+
+                fn $method<T: Serialize + ?Sized>(
+                    &mut self,
+                    value: &T,
+                ) -> Result<(), serde_json::Error> {
+                    self.push(value)
+                }
+
+                fn end(self) -> Result<Value, serde_json::Error> {
+                    self.finish()
+                }
+            }
+        )+
+    };
+}
+
+seq_traits!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+/// A map, struct or struct variant being serialized.
+struct Object<'a> {
+    serializer: ValueSerializer<'a>,
+    variant: Option<&'static str>,
+    fields: Map<String, Value>,
+    /// The key of the map entry whose value is serialized next.
+    key: Option<String>,
+}
+
+impl Object<'_> {
+    fn insert<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        let value = self.serializer.field(key, value)?;
+        self.fields.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, serde_json::Error> {
+        Ok(in_variant(
+            self.serializer.path,
+            self.variant,
+            Value::Object(self.fields),
+        ))
+    }
+}
+
+impl ser::SerializeMap for Object<'_> {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), serde_json::Error> {
+        // Keys are serialized like `serde_json` does, so they fail alike.
+        let key = match serde_json::to_value(key)? {
+            Value::String(key) => key,
+            Value::Number(number) => number.to_string(),
+            Value::Bool(bool) => bool.to_string(),
+            _ => return Err(ser::Error::custom("key must be a string")),
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ser::Error::custom("map value serialized before its key"))?;
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<Value, serde_json::Error> {
+        self.finish()
+    }
+}
+
+/// Implements the serde traits of a struct kind for [`Object`].
+macro_rules! struct_traits {
+    ($($trait:ident),+) => {
+        $(
+            impl ser::$trait for Object<'_> {
+                type Ok = Value;
+                type Error = serde_json::Error;
+
+                fn serialize_field<T: Serialize + ?Sized>(
+                    &mut self,
+                    key: &'static str,
+                    value: &T,
+                ) -> Result<(), serde_json::Error> {
+                    self.insert(key, value)
+                }
+
+                fn end(self) -> Result<Value, serde_json::Error> {
+                    self.finish()
+                }
+            }
+        )+
+    };
+}
+
+struct_traits!(SerializeStruct, SerializeStructVariant);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Grid { cells: HashMap<(u8, u8), u8> },
+    }
+
+    #[derive(Serialize)]
+    struct Drawing {
+        name: &'static str,
+        shapes: Vec<Shape>,
+    }
+
+    fn drawing() -> Drawing {
+        Drawing {
+            name: "a",
+            shapes: vec![Shape::Grid {
+                cells: HashMap::from([((0, 0), 1)]),
+            }],
+        }
+    }
+
+    #[test]
+    fn locates_the_field_which_failed() {
+        let err = serialize(&drawing(), "actual").unwrap_err();
+        assert_eq!(err.path().to_string(), "shapes[0].Grid.cells");
+        assert_eq!(
+            err.to_string(),
+            "Could not serialize actual value at `shapes[0].Grid.cells`: key must be a string"
+        );
+        assert!(serialize(&json!({"a": [1]}), "expected").is_ok());
+    }
+
+    #[test]
+    fn leaves_out_failing_fields_when_skipping() {
+        let serialized = with_serialization_failure(SerializationFailure::Skip, || {
+            serialize(&drawing(), "expected").unwrap()
+        });
+        assert_eq!(
+            serialized.value,
+            json!({"name": "a", "shapes": [{"Grid": {"cells": null}}]})
+        );
+        assert_eq!(serialized.skipped.len(), 1);
+        assert_eq!(serialized.skipped[0].0.to_string(), "shapes[0].Grid.cells");
+    }
+
+    #[test]
+    #[should_panic(expected = "Could not serialize expected value at `shapes[0].Grid.cells`")]
+    fn panics_with_the_path_of_the_failing_field() {
+        with_serialization_failure(SerializationFailure::Skip, || {
+            to_value(&drawing(), "expected")
+        });
+    }
+
+    #[test]
+    fn comparisons_except_fields_skip_failing_fields() {
+        let other = Drawing {
+            name: "b",
+            shapes: Vec::new(),
+        };
+        let diff = with_serialization_failure(SerializationFailure::Skip, || {
+            crate::except::diff_except(&drawing(), &other, &["name"])
+        });
+        assert_eq!(
+            diff.to_string(),
+            "shapes[0]: field missing from actual: {\"Grid\":{}}\n\
+             shapes[0].Grid.cells: field missing from actual: <could not serialize: key must be a string>\n"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! String similarity helpers used to pair up likely renamed fields.
+
+/// Edit distance between `a` and `b` counting single-character insertions,
+/// deletions and substitutions.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Returns whether two names only differ by case or by `_`/`-` separators,
+/// e.g. `user_name`, `userName` and `UserName`.
+pub(crate) fn same_words(a: &str, b: &str) -> bool {
+    let words = |name: &str| -> String {
+        name.chars()
+            .filter(|c| !matches!(c, '_' | '-'))
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    words(a) == words(b)
+}
+
+/// Returns the candidate most likely to be `name` under another name: one
+/// differing only by case or separators, or else the closest within an edit
+/// distance of 2 which is small for the names' length, so `id` and `ip`
+/// aren't taken for each other.
+pub(crate) fn near_miss<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            if same_words(name, candidate) {
+                return Some((0, candidate));
+            }
+            let distance = levenshtein(name, candidate);
+            let longest = name.chars().count().max(candidate.chars().count());
+            (distance <= 2 && distance * 2 < longest).then_some((distance, candidate))
+        })
+        .min_by_key(|&(score, _)| score)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn finds_near_misses() {
+        assert_eq!(near_miss("user_name", ["id", "userName"]), Some("userName"));
+        assert_eq!(near_miss("emial", ["mail", "email"]), Some("email"));
+        assert_eq!(near_miss("adress", ["address", "adresse"]), Some("address"));
+        assert_eq!(near_miss("id", ["ip"]), None);
+        assert_eq!(near_miss("total", ["count"]), None);
+    }
+
+    #[test]
+    fn compares_words_across_naming_conventions() {
+        assert!(same_words("user_name", "userName"));
+        assert!(same_words("UserName", "user-name"));
+        assert!(!same_words("user_name", "username2"));
+    }
+}
@@ -0,0 +1,506 @@
+//! Snapshot assertions, comparing a value with its serialized form stored
+//! in a file, enabled by the `serde` feature.
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::value::diff_values;
+use crate::{Diff, DiffPath};
+
+/// Environment variable setting a directory, relative to the crate's
+/// manifest directory, storing snapshots and diff reports of every crate in
+/// per-crate subdirectories.
+const ARTIFACT_DIR_VAR: &str = "CMP_ARTIFACT_DIR";
+
+/// Environment variable which makes differing snapshots and fixtures be
+/// overwritten with the actual value instead of failing.
+const UPDATE_VAR: &str = "CMP_UPDATE_SNAPSHOTS";
+
+/// Number of snapshots taken so far by each test, to number the snapshots of
+/// a test taking several.
+static TAKEN: Mutex<Option<HashMap<String, usize>>> = Mutex::new(None);
+
+/// Where an [`assert_struct_snapshot!`](crate::assert_struct_snapshot) or
+/// [`diff_report_path!`](crate::diff_report_path) was invoked, captured by
+/// the macro.
+#[derive(Clone, Copy, Debug)]
+pub struct Location {
+    pub manifest_dir: &'static str,
+    pub crate_name: &'static str,
+    /// Path of the enclosing function, e.g. `my_crate::tests::renders_user`.
+    pub function: &'static str,
+}
+
+impl Location {
+    /// Returns the snapshot's name: `name` if given, or else the enclosing
+    /// function's path without the crate name, with `::` replaced by `__`.
+    /// Unnamed snapshots after a test's first get a `-2`, `-3`, ... suffix.
+    pub fn snapshot_name(&self, name: Option<&str>) -> String {
+        if let Some(name) = name {
+            return name.to_string();
+        }
+        let function = self
+            .function
+            .split("::")
+            .filter(|segment| *segment != "{{closure}}")
+            .collect::<Vec<_>>();
+        let base = function
+            .get(1..)
+            .filter(|rest| !rest.is_empty())
+            .unwrap_or(&function)
+            .join("__");
+
+        let mut taken = TAKEN.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = taken
+            .get_or_insert_with(HashMap::new)
+            .entry(self.function.to_string())
+            .or_default();
+        *count += 1;
+        match *count {
+            1 => base,
+            n => format!("{base}-{n}"),
+        }
+    }
+
+    /// Returns the file storing the snapshot named `name`.
+    pub fn snapshot_path(&self, name: &str) -> PathBuf {
+        self.artifact_path("snapshots", &format!("{name}.json"))
+    }
+
+    /// Returns the path of the diff report `file`.
+    pub fn report_path(&self, file: &str) -> PathBuf {
+        self.artifact_path("diffs", file)
+    }
+
+    /// Returns the path of `file` in the `kind` directory: under the crate's
+    /// manifest directory, or under `CMP_ARTIFACT_DIR` and a subdirectory
+    /// named after the crate if it is set.
+    fn artifact_path(&self, kind: &str, file: &str) -> PathBuf {
+        self.artifact_path_in(env::var_os(ARTIFACT_DIR_VAR).as_deref(), kind, file)
+    }
+
+    fn artifact_path_in(&self, artifact_dir: Option<&OsStr>, kind: &str, file: &str) -> PathBuf {
+        let manifest_dir = Path::new(self.manifest_dir);
+        let base = match artifact_dir.filter(|dir| !dir.is_empty()) {
+            Some(dir) => manifest_dir.join(dir).join(self.crate_name),
+            None => manifest_dir.to_path_buf(),
+        };
+        base.join(kind).join(file)
+    }
+}
+
+/// Returns whether `CMP_UPDATE_SNAPSHOTS` is set to anything other than `0`
+/// or an empty string.
+fn updating() -> bool {
+    env::var_os(UPDATE_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Compares `value` with the snapshot stored at `path`, creating it if it
+/// doesn't exist yet.
+///
+/// With `CMP_UPDATE_SNAPSHOTS=1`, a differing snapshot is overwritten with
+/// the value instead.
+///
+/// # Panics
+///
+/// Panics with the differences if the snapshot differs, after writing the
+/// new value next to it with a `.new` extension, or if the value can't be
+/// serialized or the snapshot can't be read or written.
+#[track_caller]
+pub fn assert_snapshot<T: Serialize + ?Sized>(path: &Path, value: &T) {
+    check_snapshot(path, value, updating());
+}
+
+#[track_caller]
+fn check_snapshot<T: Serialize + ?Sized>(path: &Path, value: &T, update: bool) {
+    let actual = crate::serialize::to_value(value, "snapshot");
+    let rendered = render(&actual);
+
+    let Ok(stored) = fs::read_to_string(path) else {
+        write(path, &rendered);
+        crate::output::emit(format_args!("created snapshot {}", path.display()));
+        return;
+    };
+    let expected: Value = serde_json::from_str(&stored)
+        .unwrap_or_else(|err| panic!("invalid snapshot {}: {err}", path.display()));
+
+    let mut diff = Diff::new();
+    diff_values(&expected, &actual, false, &mut DiffPath::new(), &mut diff);
+    crate::metrics::record(&diff);
+    if diff.is_empty() {
+        return;
+    }
+    if update {
+        write(path, &rendered);
+        let _ = fs::remove_file(pending_path(path));
+        crate::output::emit(format_args!("updated snapshot {}", path.display()));
+        return;
+    }
+    let pending = pending_path(path);
+    write(&pending, &rendered);
+    crate::__private::fail(
+        &format!(
+            "snapshot {} differs, new value written to {}\n",
+            path.display(),
+            pending.display()
+        ),
+        &diff,
+    );
+}
+
+/// Compares `actual` with the hand-written JSON fixture at `path`, reporting
+/// the differing fields like a serialized
+/// [`compare_structs!`](crate::compare_structs).
+///
+/// Unlike [`assert_snapshot`], a missing fixture is an error and nothing is
+/// written: the fixture is the source of truth. With
+/// `CMP_UPDATE_SNAPSHOTS=1`, a missing or differing fixture is written with
+/// the serialized value instead, e.g. after a deliberate schema change.
+///
+/// # Panics
+///
+/// Panics with the differences if `actual` differs from the fixture, or if
+/// the value can't be serialized or the fixture can't be read or parsed.
+#[track_caller]
+pub fn compare_with_fixture<T: Serialize + ?Sized>(path: &Path, actual: &T, expression: &str) {
+    check_fixture(path, actual, expression, updating());
+}
+
+#[track_caller]
+fn check_fixture<T: Serialize + ?Sized>(path: &Path, actual: &T, expression: &str, update: bool) {
+    let actual = crate::serialize::to_value(actual, "actual");
+    let stored = match fs::read_to_string(path) {
+        Ok(stored) => stored,
+        Err(_) if update => {
+            write(path, &render(&actual));
+            crate::output::emit(format_args!("created fixture {}", path.display()));
+            return;
+        }
+        Err(err) => panic!("could not read fixture {}: {err}", path.display()),
+    };
+    let expected: Value = serde_json::from_str(&stored)
+        .unwrap_or_else(|err| panic!("invalid fixture {}: {err}", path.display()));
+
+    let mut diff = Diff::new();
+    diff_values(&expected, &actual, false, &mut DiffPath::new(), &mut diff);
+    crate::metrics::record(&diff);
+    if diff.is_empty() {
+        return;
+    }
+    if update {
+        write(path, &render(&actual));
+        crate::output::emit(format_args!("updated fixture {}", path.display()));
+        return;
+    }
+    crate::__private::fail(
+        &format!("`{expression}` differs from fixture {}\n", path.display()),
+        &diff,
+    );
+}
+
+/// Returns the file a differing value is written to, next to the snapshot.
+pub fn pending_path(path: &Path) -> PathBuf {
+    let mut pending = path.as_os_str().to_owned();
+    pending.push(".new");
+    PathBuf::from(pending)
+}
+
+fn render(value: &Value) -> String {
+    let mut rendered =
+        serde_json::to_string_pretty(value).expect("Could not serialize snapshot value");
+    rendered.push('\n');
+    rendered
+}
+
+#[track_caller]
+fn write(path: &Path, contents: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .unwrap_or_else(|err| panic!("could not create {}: {err}", parent.display()));
+    }
+    fs::write(path, contents)
+        .unwrap_or_else(|err| panic!("could not write snapshot {}: {err}", path.display()));
+}
+
+/// Returns the path of the enclosing function, e.g.
+/// `my_crate::tests::renders_user`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __function_name {
+    () => {{
+        fn f() {}
+        let name = ::core::any::type_name_of_val(&f);
+        name.strip_suffix("::f").unwrap_or(name)
+    }};
+}
+
+/// Captures the [`Location`] of the macro invocation.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location {
+    () => {
+        $crate::__private::SnapshotLocation {
+            manifest_dir: env!("CARGO_MANIFEST_DIR"),
+            crate_name: env!("CARGO_PKG_NAME"),
+            function: $crate::__function_name!(),
+        }
+    };
+}
+
+/// Macro which returns the path to save a diff report `file` to, e.g. with
+/// [`Diff::save`], laid out like the snapshots of
+/// [`assert_struct_snapshot!`](crate::assert_struct_snapshot): in the
+/// crate's `diffs` directory, or in `$CMP_ARTIFACT_DIR/<crate>/diffs` if
+/// `CMP_ARTIFACT_DIR` is set.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::Diffable;
+///
+/// let diff = vec![1, 2].compare(&vec![1, 3]);
+/// diff.save(cmp::diff_report_path!("nightly.json")).unwrap();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! diff_report_path {
+    ($file:expr) => {
+        $crate::__location!().report_path($file)
+    };
+}
+
+/// Macro which compares a serializable value with a snapshot of it stored
+/// as JSON in the crate's `snapshots` directory.
+///
+/// With a single argument, the snapshot is named after the enclosing test,
+/// e.g. `snapshots/tests__renders_user.json` for the `tests::renders_user`
+/// test; later unnamed snapshots in the same test get a `-2`, `-3`, ...
+/// suffix. Give a name as the first argument to choose it instead.
+///
+/// Snapshots are stored in the `snapshots` directory of the crate being
+/// tested. In workspaces, set `CMP_ARTIFACT_DIR` to a directory (relative
+/// to each crate's manifest directory) to gather them in per-crate
+/// subdirectories instead, e.g. `CMP_ARTIFACT_DIR=../artifacts` stores them
+/// in `artifacts/<crate>/snapshots`.
+///
+/// A missing snapshot is created from the value. When the value differs from
+/// the snapshot, it is written next to it with a `.new` extension, for
+/// review.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::assert_struct_snapshot;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User { id: u32, name: &'static str }
+///
+/// assert_struct_snapshot!(User { id: 1, name: "ada" });
+/// assert_struct_snapshot!("admin_user", User { id: 0, name: "root" });
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics with the differing fields if the value differs from the snapshot.
+#[macro_export]
+macro_rules! assert_struct_snapshot {
+    ($value:expr $(,)?) => {
+        $crate::__snapshot!(::core::option::Option::None, $value)
+    };
+    ($name:expr, $value:expr $(,)?) => {
+        $crate::__snapshot!(::core::option::Option::Some($name), $value)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __snapshot {
+    ($name:expr, $value:expr) => {{
+        let location = $crate::__location!();
+        let name: ::core::option::Option<&str> = $name;
+        let path = location.snapshot_path(&location.snapshot_name(name));
+        $crate::__private::assert_snapshot(&path, &$value);
+    }};
+}
+
+/// Macro which compares a serializable value with a JSON fixture file,
+/// reporting the differing fields like
+/// [`compare_structs!`](crate::compare_structs) in serde mode.
+///
+/// The path is relative to the crate's manifest directory. The fixture is
+/// deserialized to a `serde_json::Value` and compared with the serialized
+/// value, so nested differences are reported at their paths, and
+/// [`with_element_key`](crate::with_element_key) and the other scoped
+/// settings apply. Unlike
+/// [`assert_struct_snapshot!`](crate::assert_struct_snapshot), the fixture
+/// is only ever written with `CMP_UPDATE_SNAPSHOTS=1`, which overwrites a
+/// missing or differing fixture with the value instead of failing.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::compare_with_snapshot;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User { id: u32, name: &'static str }
+///
+/// compare_with_snapshot!(User { id: 1, name: "ada" }, "tests/fixtures/user.json");
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics with the differing fields if the value differs from the fixture,
+/// or if the fixture can't be read or parsed.
+#[macro_export]
+macro_rules! compare_with_snapshot {
+    ($actual:expr, $path:expr $(,)?) => {
+        $crate::__private::compare_with_fixture(
+            &::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path),
+            &$actual,
+            stringify!($actual),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        tags: Vec<&'static str>,
+    }
+
+    fn here() -> Location {
+        crate::__location!()
+    }
+
+    #[test]
+    fn names_snapshots_after_the_test() {
+        let location = here();
+
+        assert_eq!(location.function, "cmp::snapshot::tests::here");
+        assert_eq!(location.snapshot_name(None), "snapshot__tests__here");
+        assert_eq!(location.snapshot_name(None), "snapshot__tests__here-2");
+        assert_eq!(location.snapshot_name(Some("named")), "named");
+        assert!(
+            location
+                .snapshot_path("named")
+                .ends_with("snapshots/named.json")
+        );
+    }
+
+    #[test]
+    fn lays_out_artifacts_per_crate() {
+        let location = Location {
+            manifest_dir: "/work/crates/api",
+            crate_name: "api",
+            function: "api::tests::lists_users",
+        };
+
+        assert_eq!(
+            location.artifact_path_in(None, "snapshots", "a.json"),
+            Path::new("/work/crates/api/snapshots/a.json")
+        );
+        assert_eq!(
+            location.artifact_path_in(Some(OsStr::new("../../artifacts")), "diffs", "b.json"),
+            Path::new("/work/crates/api/../../artifacts/api/diffs/b.json")
+        );
+        assert_eq!(
+            location.artifact_path_in(Some(OsStr::new("/tmp/out")), "diffs", "b.json"),
+            Path::new("/tmp/out/api/diffs/b.json")
+        );
+    }
+
+    #[test]
+    fn creates_then_compares_snapshots() {
+        let dir = std::env::temp_dir().join(format!("cmp-snapshot-{}", std::process::id()));
+        let path = dir.join("user.json");
+        let user = User {
+            id: 1,
+            tags: vec!["admin"],
+        };
+
+        assert_snapshot(&path, &user);
+        assert_snapshot(&path, &user);
+        let changed = User {
+            id: 1,
+            tags: vec!["admin", "ops"],
+        };
+        let failure = std::panic::catch_unwind(|| assert_snapshot(&path, &changed))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        let pending = fs::read_to_string(pending_path(&path)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(failure.starts_with(&format!("snapshot {} differs", path.display())));
+        assert!(failure.ends_with("\ntags[1]: field missing from expected: \"ops\"\n"));
+        assert!(pending.contains("\"ops\""));
+    }
+
+    #[test]
+    fn compares_with_fixture_files() {
+        let dir = std::env::temp_dir().join(format!("cmp-fixture-{}", std::process::id()));
+        let path = dir.join("user.json");
+        write(&path, "{\"id\": 1, \"tags\": [\"admin\", \"ops\"]}");
+        let user = User {
+            id: 1,
+            tags: vec!["admin", "ops"],
+        };
+
+        crate::compare_with_snapshot!(user, &path);
+        let changed = User {
+            id: 2,
+            tags: vec!["admin"],
+        };
+        let failure = std::panic::catch_unwind(|| crate::compare_with_snapshot!(changed, &path))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        let missing = std::panic::catch_unwind(|| {
+            crate::compare_with_snapshot!(user, dir.join("missing.json"))
+        });
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(failure.starts_with(&format!(
+            "`changed` differs from fixture {}",
+            path.display()
+        )));
+        assert!(failure.ends_with("\nid: 1 != 2\ntags[1]: field missing from actual: \"ops\"\n"));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn updates_differing_snapshots_and_fixtures() {
+        let dir = std::env::temp_dir().join(format!("cmp-update-{}", std::process::id()));
+        let (snapshot, fixture) = (dir.join("snapshot.json"), dir.join("fixture.json"));
+        let user = |id| User {
+            id,
+            tags: vec!["admin"],
+        };
+
+        check_fixture(&fixture, &user(1), "user", true);
+        check_snapshot(&snapshot, &user(1), false);
+        check_fixture(&fixture, &user(2), "user", true);
+        check_snapshot(&snapshot, &user(2), true);
+        check_fixture(&fixture, &user(2), "user", false);
+        check_snapshot(&snapshot, &user(2), false);
+        let pending = pending_path(&snapshot).exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!pending);
+    }
+}
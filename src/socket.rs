@@ -0,0 +1,133 @@
+//! Streaming of failed comparisons to a socket, enabled by the `socket`
+//! feature.
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{Diff, Reporter};
+
+/// [`Reporter`] which writes each [`Diff`] to a socket as one line of JSON,
+/// so a live dashboard can follow comparison failures during long test runs.
+///
+/// Each line holds the serialized [`Diff`]:
+///
+/// ```json
+/// {"entries":[{"kind":"changed","path":[{"field":"port"}],"expected":"8080","actual":"8081"}]}
+/// ```
+///
+/// Write errors are ignored, so a dashboard going away never fails the
+/// comparisons being reported.
+///
+/// ```edition2024,no_run
+/// # #[cfg(feature = "socket")]
+/// # {
+/// let reporter = cmp::SocketReporter::tcp("127.0.0.1:7878").unwrap();
+/// cmp::set_reporter(reporter);
+/// # }
+/// ```
+pub struct SocketReporter {
+    stream: Mutex<Box<dyn Write + Send>>,
+}
+
+impl SocketReporter {
+    /// Connects to a TCP endpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established.
+    pub fn tcp(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::from_writer(stream))
+    }
+
+    /// Connects to a Unix domain socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established.
+    #[cfg(unix)]
+    pub fn unix(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Ok(Self::from_writer(stream))
+    }
+
+    /// Streams to any writer, such as an already connected socket or a file.
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            stream: Mutex::new(Box::new(writer)),
+        }
+    }
+}
+
+impl Reporter for SocketReporter {
+    fn report(&self, diff: &Diff) {
+        let Ok(mut line) = serde_json::to_vec(diff) else {
+            return;
+        };
+        line.push(b'\n');
+        let mut stream = self.stream.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = stream.write_all(&line).and_then(|()| stream.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{DiffPath, FieldDiff};
+
+    fn diff() -> Diff {
+        let mut path = DiffPath::new();
+        path.push_field("servers");
+        path.push_index(1);
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::MissingFromActual {
+            path,
+            expected: "\"b\"".into(),
+        });
+        diff
+    }
+
+    #[test]
+    fn streams_json_lines_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let reporter = SocketReporter::tcp(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        reporter.report(&diff());
+        reporter.report(&diff());
+
+        let lines: Vec<String> = BufReader::new(stream)
+            .lines()
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            lines[0],
+            r#"{"entries":[{"kind":"missing_from_actual","path":[{"field":"servers"},{"index":1}],"expected":"\"b\""}]}"#
+        );
+        assert_eq!(lines[0], lines[1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streams_to_unix_sockets() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("cmp-socket-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let reporter = SocketReporter::unix(&path).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        reporter.report(&diff());
+
+        let line = BufReader::new(stream).lines().next().unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(line.starts_with(r#"{"entries":[{"kind":"missing_from_actual""#));
+    }
+}
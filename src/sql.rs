@@ -0,0 +1,340 @@
+//! Normalized comparison of SQL statements, enabled by the `sql` feature.
+//!
+//! Statements are tokenized before comparing, so whitespace, comments and
+//! keyword casing do not matter. When two statements still differ, the diff
+//! is rendered token by token, marking removed tokens with `[-…-]` and added
+//! tokens with `{+…+}`.
+
+/// Keywords which are uppercased during normalization.
+const KEYWORDS: &str = "ALL ALTER AND ANY AS ASC BETWEEN BY CASE CAST CONFLICT CREATE CROSS \
+    DEFAULT DELETE DESC DISTINCT DO DROP ELSE END EXCEPT EXISTS FALSE FETCH FOR FROM FULL GROUP \
+    HAVING IN INDEX INNER INSERT INTERSECT INTO IS JOIN LEFT LIKE LIMIT NOT NOTHING NULL OFFSET \
+    ON OR ORDER OUTER OVER PARTITION RETURNING RIGHT SELECT SET TABLE THEN TRUE UNION UPDATE \
+    USING VALUES WHEN WHERE WITH";
+
+/// Normalizes SQL statements before they are compared.
+///
+/// Whitespace is always collapsed and keywords are always uppercased.
+/// Placeholder canonicalization is opt-in, and rewrites `$1`, `:name`,
+/// `@name` and `?` placeholders to `?`, so statements generated for
+/// different database drivers compare equal.
+///
+/// ```edition2024
+/// use cmp::sql::Normalizer;
+///
+/// let normalizer = Normalizer::new().canonicalize_placeholders(true);
+///
+/// assert_eq!(
+///     normalizer.normalize("select *\n  from users\n  where id = $1"),
+///     "SELECT * FROM users WHERE id = ?"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Normalizer {
+    canonicalize_placeholders: bool,
+}
+
+impl Normalizer {
+    /// Creates a normalizer which collapses whitespace and uppercases keywords.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether placeholders are rewritten to `?` before comparing.
+    pub fn canonicalize_placeholders(mut self, canonicalize: bool) -> Self {
+        self.canonicalize_placeholders = canonicalize;
+        self
+    }
+
+    /// Returns the normalized form of `sql`.
+    pub fn normalize(&self, sql: &str) -> String {
+        render(&self.tokens(sql))
+    }
+
+    /// Compares two statements after normalization, returning a token-level
+    /// diff if they still differ.
+    ///
+    /// ```edition2024
+    /// use cmp::sql::Normalizer;
+    ///
+    /// let diff = Normalizer::new().diff(
+    ///     "SELECT id, name FROM users WHERE id = 1",
+    ///     "select id,\n  email from users where id = 1",
+    /// );
+    ///
+    /// assert_eq!(
+    ///     diff.as_deref(),
+    ///     Some("SELECT id, [-name-] {+email+} FROM users WHERE id = 1")
+    /// );
+    /// ```
+    pub fn diff(&self, expected: &str, actual: &str) -> Option<String> {
+        let expected = self.tokens(expected);
+        let actual = self.tokens(actual);
+        (expected != actual).then(|| render_diff(&expected, &actual))
+    }
+
+    fn tokens(&self, sql: &str) -> Vec<String> {
+        tokenize(sql)
+            .into_iter()
+            .map(|token| match token {
+                Token::Word(word) => {
+                    let upper = word.to_ascii_uppercase();
+                    if KEYWORDS.split_whitespace().any(|keyword| keyword == upper) {
+                        upper
+                    } else {
+                        word.to_string()
+                    }
+                }
+                Token::Placeholder(_) if self.canonicalize_placeholders => "?".to_string(),
+                Token::Placeholder(text) | Token::Other(text) => text.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Normalizes `sql` with the default [`Normalizer`].
+pub fn normalize(sql: &str) -> String {
+    Normalizer::new().normalize(sql)
+}
+
+/// Compares two statements with the default [`Normalizer`], returning a
+/// token-level diff if they differ.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    Normalizer::new().diff(expected, actual)
+}
+
+enum Token<'a> {
+    Word(&'a str),
+    Placeholder(&'a str),
+    Other(&'a str),
+}
+
+fn tokenize<'a>(sql: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut rest = sql;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+            continue;
+        }
+        if rest.starts_with("--") {
+            rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+            continue;
+        }
+        if rest.starts_with("/*") {
+            rest = &rest[rest.find("*/").map_or(rest.len(), |end| end + 2)..];
+            continue;
+        }
+
+        let (token, len): (fn(&'a str) -> Token<'a>, usize) = if matches!(c, '\'' | '"' | '`') {
+            (Token::Other, quoted_len(rest, c))
+        } else if c.is_alphanumeric() || c == '_' {
+            (Token::Word, word_len(rest))
+        } else if c == '?' || (matches!(c, '$' | ':' | '@') && word_len(&rest[1..]) > 0) {
+            (Token::Placeholder, 1 + word_len(&rest[1..]))
+        } else {
+            let len = ["<=", ">=", "<>", "!=", "::", "||"]
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .map_or(c.len_utf8(), |op| op.len());
+            (Token::Other, len)
+        };
+        tokens.push(token(&rest[..len]));
+        rest = &rest[len..];
+    }
+
+    tokens
+}
+
+fn word_len(text: &str) -> usize {
+    text.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(text.len())
+}
+
+/// Length of a quoted literal or identifier, treating a doubled quote as an
+/// escaped one.
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((index, c)) = chars.next() {
+        if c == quote {
+            if chars.peek().is_some_and(|&(_, next)| next == quote) {
+                chars.next();
+            } else {
+                return index + c.len_utf8();
+            }
+        }
+    }
+    text.len()
+}
+
+fn render(tokens: &[String]) -> String {
+    let mut rendered = String::new();
+    for token in tokens {
+        push_token(&mut rendered, token);
+    }
+    rendered
+}
+
+fn push_token(rendered: &mut String, token: &str) {
+    let joins_previous = matches!(token, "," | ")" | ";" | ".");
+    let follows_opener = rendered.ends_with('(') || rendered.ends_with('.');
+    if !rendered.is_empty() && !joins_previous && !follows_opener {
+        rendered.push(' ');
+    }
+    rendered.push_str(token);
+}
+
+/// Renders both token streams as a single statement, using the longest common
+/// subsequence to mark removed (`[-…-]`) and added (`{+…+}`) runs.
+fn render_diff(expected: &[String], actual: &[String]) -> String {
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut rendered = String::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            flush_changes(&mut rendered, &mut removed, &mut added);
+            push_token(&mut rendered, &expected[i]);
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            removed.push(expected[i].as_str());
+            i += 1;
+        } else {
+            added.push(actual[j].as_str());
+            j += 1;
+        }
+    }
+    flush_changes(&mut rendered, &mut removed, &mut added);
+    rendered
+}
+
+fn flush_changes(rendered: &mut String, removed: &mut Vec<&str>, added: &mut Vec<&str>) {
+    if !removed.is_empty() {
+        push_token(rendered, &format!("[-{}-]", render_run(removed)));
+        removed.clear();
+    }
+    if !added.is_empty() {
+        push_token(rendered, &format!("{{+{}+}}", render_run(added)));
+        added.clear();
+    }
+}
+
+fn render_run(tokens: &[&str]) -> String {
+    let tokens: Vec<String> = tokens.iter().map(|token| token.to_string()).collect();
+    render(&tokens)
+}
+
+/// Macro which compares SQL-containing string fields after normalization.
+///
+/// With two arguments, the expressions themselves are compared. With field
+/// identifiers, each listed field of the two structs is compared. Whitespace,
+/// comments and keyword casing are ignored, and remaining differences are
+/// shown as a token-level diff. Use [`sql::Normalizer`](crate::sql::Normalizer)
+/// directly to canonicalize placeholders.
+///
+/// ```edition2024
+/// use cmp::compare_sql;
+/// # struct Query { text: String }
+/// let expected = Query { text: "SELECT id FROM users WHERE active = TRUE".to_string() };
+/// let actual = Query { text: "select id\n  from users\n  where active = true".to_string() };
+///
+/// compare_sql!(expected, actual, text);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the normalized statements differ.
+#[macro_export]
+macro_rules! compare_sql {
+    ($expected:expr, $actual:expr) => {{
+        let diff = $crate::sql::diff(&$expected, &$actual);
+
+        assert!(diff.is_none(), "{}\n", diff.unwrap_or_default());
+    }};
+    ($expected:expr, $actual:expr, $($field:ident),+) => {{
+        let mut diffs = String::new();
+        $(
+            if let Some(diff) = $crate::sql::diff(&$expected.$field, &$actual.$field) {
+                diffs.push_str(&format!("{}: {}\n", stringify!($field), diff));
+            }
+        )+
+
+        assert!(diffs.is_empty(), "{diffs}");
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_whitespace_comments_and_keywords() {
+        assert_eq!(
+            normalize("select a , b\n-- pick columns\nfrom t /* main */ where x<=1"),
+            "SELECT a, b FROM t WHERE x <= 1"
+        );
+    }
+
+    #[test]
+    fn keeps_literals_and_identifiers_verbatim() {
+        assert_eq!(
+            normalize(r#"select "Select", 'it''s  here' from t"#),
+            r#"SELECT "Select", 'it''s  here' FROM t"#
+        );
+    }
+
+    #[test]
+    fn canonicalizes_placeholders_on_request() {
+        let expected = "SELECT * FROM t WHERE a = ? AND b = ?";
+        let actual = "SELECT * FROM t WHERE a = $1 AND b = :b";
+
+        assert!(diff(expected, actual).is_some());
+        assert_eq!(
+            Normalizer::new()
+                .canonicalize_placeholders(true)
+                .diff(expected, actual),
+            None
+        );
+    }
+
+    #[test]
+    fn renders_token_level_diff() {
+        assert_eq!(
+            diff(
+                "UPDATE t SET a = 1 WHERE id = 2",
+                "UPDATE t SET a = 1, b = 3 WHERE id = 2"
+            )
+            .as_deref(),
+            Some("UPDATE t SET a = 1 {+, b = 3+} WHERE id = 2")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "text: SELECT [-a-] {+b+} FROM t")]
+    fn compare_sql_fields_panic() {
+        struct Query {
+            text: &'static str,
+        }
+
+        let expected = Query {
+            text: "SELECT a FROM t",
+        };
+        let actual = Query {
+            text: "select b from t",
+        };
+
+        compare_sql!(expected, actual, text);
+    }
+}
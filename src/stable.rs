@@ -0,0 +1,141 @@
+//! A versioned, line-based rendering of differences for scripts which parse
+//! failure messages, selected with [`StableRenderer`] or
+//! `CMP_FORMAT=stable`.
+//!
+//! Unlike the default text output, whose wording, hints and notes may
+//! improve in any release, the format of a version only changes in a major
+//! release of this crate. A new version gets a new header line.
+
+use std::fmt::Write;
+
+use crate::{Diff, DiffPath, DiffRenderer, FieldDiff, Segment};
+
+/// The version of the stable format rendered by [`StableRenderer`].
+pub const STABLE_FORMAT_VERSION: u32 = 1;
+
+/// Renders differences in the stable format, version
+/// [`STABLE_FORMAT_VERSION`].
+///
+/// Version 1 starts with a `cmp-diff 1` header line, followed by one line per
+/// difference of four tab-separated columns: the kind (`changed`,
+/// `missing_from_expected` or `missing_from_actual`), the path, and the
+/// expected and actual values, empty for the missing side. Differences found
+/// by comparing `Debug` output have a fifth column, `debug`. Backslashes,
+/// tabs, line feeds and carriage returns in paths and values are escaped as
+/// `\\`, `\t`, `\n` and `\r`.
+///
+/// Paths are rendered like `config.servers[2].port`, with map keys as
+/// `["key"]` and enum variants as `::Variant`. [`Messages`](crate::Messages),
+/// colors, elision, context lines and hints aren't applied.
+///
+/// ```edition2024
+/// use cmp::{DiffRenderer, Diffable, StableRenderer};
+///
+/// let diff = vec!["a\tb", "c"].compare(&vec!["a\tb"]);
+///
+/// assert_eq!(
+///     StableRenderer.render(&diff),
+///     "cmp-diff 1\nmissing_from_actual\t[1]\t\"c\"\t\n"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StableRenderer;
+
+impl DiffRenderer for StableRenderer {
+    fn render(&self, diff: &Diff) -> String {
+        let mut out = format!("cmp-diff {STABLE_FORMAT_VERSION}\n");
+        for entry in diff {
+            let (kind, expected, actual) = match entry {
+                FieldDiff::Changed {
+                    expected, actual, ..
+                } => ("changed", expected.as_str(), actual.as_str()),
+                FieldDiff::MissingFromExpected { actual, .. } => {
+                    ("missing_from_expected", "", actual.as_str())
+                }
+                FieldDiff::MissingFromActual { expected, .. } => {
+                    ("missing_from_actual", expected.as_str(), "")
+                }
+            };
+            let _ = write!(
+                out,
+                "{kind}\t{}\t{}\t{}",
+                escape(&path(entry.path())),
+                escape(expected),
+                escape(actual)
+            );
+            let _ = if diff.is_debug_compared(entry) {
+                writeln!(out, "\tdebug")
+            } else {
+                writeln!(out)
+            };
+        }
+        out
+    }
+}
+
+/// Renders a path in the format of version 1, independently of the
+/// `Display` implementation of [`DiffPath`], which may change.
+fn path(path: &DiffPath) -> String {
+    let mut out = String::new();
+    for (position, segment) in path.segments().iter().enumerate() {
+        let _ = match segment {
+            Segment::Field(name) | Segment::Variant(name) if position == 0 => {
+                write!(out, "{name}")
+            }
+            Segment::Field(name) => write!(out, ".{name}"),
+            Segment::Variant(name) => write!(out, "::{name}"),
+            Segment::Index(index) => write!(out, "[{index}]"),
+            Segment::Key(key) => write!(out, "[{key}]"),
+        };
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_one_escaped_line_per_difference() {
+        let mut config = DiffPath::new();
+        config.push_field("config");
+        config.push_key("tls");
+        let mut mode = DiffPath::new();
+        mode.push_variant("Mode");
+        mode.push_index(0);
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::Changed {
+            path: config,
+            expected: "Tls {\n    on: true,\n}".into(),
+            actual: "\"C:\\\\\"".into(),
+        });
+        diff.push(FieldDiff::MissingFromExpected {
+            path: mode.clone(),
+            actual: "1".into(),
+        });
+        mode.pop();
+        crate::diff_debug(&1, &2, &mode, &mut diff);
+
+        assert_eq!(
+            StableRenderer.render(&diff),
+            "cmp-diff 1\n\
+             changed\tconfig[\"tls\"]\tTls {\\n    on: true,\\n}\t\"C:\\\\\\\\\"\n\
+             missing_from_expected\tMode[0]\t\t1\n\
+             changed\tMode\t1\t2\tdebug\n"
+        );
+    }
+}
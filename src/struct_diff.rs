@@ -0,0 +1,246 @@
+//! Struct comparisons returning their differences rather than panicking,
+//! for custom reporting and harnesses which collect failures across many
+//! comparisons.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{Diff, FieldDiff};
+
+/// Differences found by [`try_compare_structs!`](crate::try_compare_structs),
+/// with the source text of the compared expressions.
+///
+/// The differences are kept as [`FieldDiff`]s, so a harness can inspect each
+/// field's path and rendered values rather than parse a message. `Display`
+/// renders a header line naming the expressions, followed by the
+/// differences.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructDiff {
+    expected: &'static str,
+    actual: &'static str,
+    diff: Diff,
+}
+
+impl StructDiff {
+    /// Returns `Err` with the differences if `diff` is not empty.
+    ///
+    /// `expected` and `actual` are the source text of the compared
+    /// expressions.
+    pub fn check(diff: Diff, expected: &'static str, actual: &'static str) -> Result<(), Self> {
+        if diff.is_empty() {
+            return Ok(());
+        }
+        Err(Self {
+            expected,
+            actual,
+            diff,
+        })
+    }
+
+    /// Returns the source text of the expected expression.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Returns the source text of the actual expression.
+    pub fn actual(&self) -> &'static str {
+        self.actual
+    }
+
+    /// Returns the differing fields, in the order they were found.
+    pub fn fields(&self) -> &[FieldDiff] {
+        self.diff.entries()
+    }
+
+    /// Returns the differences.
+    pub fn diff(&self) -> &Diff {
+        &self.diff
+    }
+
+    /// Returns the differences, dropping the expressions.
+    pub fn into_diff(self) -> Diff {
+        self.diff
+    }
+}
+
+impl fmt::Display for StructDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages = crate::messages::messages();
+        writeln!(
+            f,
+            "{}",
+            messages.differ_in(self.expected, self.actual, self.diff.len())
+        )?;
+        write!(f, "{}", self.diff)
+    }
+}
+
+impl Error for StructDiff {}
+
+/// Macro which compares two structs like
+/// [`compare_structs!`](crate::compare_structs), returning the [`Diff`], for
+/// custom reporting.
+///
+/// It accepts the same forms: named fields, compared with `PartialEq`, with
+/// `~ epsilon`, `~ unordered` or with `=> comparator`, `except:` followed by the fields not to compare, or with the `serde`
+/// feature no fields, `; include_zst`, `; key_matcher = ...`, `; key = "id"`,
+/// `; ignore_paths = [...]` or `; unordered` to compare all serialized fields. Nothing is recorded or reported.
+///
+/// ```edition2024
+/// use cmp::{FieldDiff, diff_structs};
+///
+/// struct Order { id: u32, status: &'static str }
+///
+/// let diff = diff_structs!(Order { id: 1, status: "paid" }, Order { id: 1, status: "pending" }, id, status);
+///
+/// for entry in &diff {
+///     if let FieldDiff::Changed { path, expected, actual } = entry {
+///         assert_eq!((path.to_string(), expected.as_str(), actual.as_str()), ("status".into(), "\"paid\"", "\"pending\""));
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! diff_structs {
+    ($expected:expr, $actual:expr) => {
+        $crate::__diff_all!(&$expected, &$actual, true)
+    };
+    ($expected:expr, $actual:expr; include_zst) => {
+        $crate::__diff_all!(&$expected, &$actual, false)
+    };
+    ($expected:expr, $actual:expr; key_matcher = $matcher:expr) => {
+        $crate::with_key_matcher($matcher, || $crate::__diff_all!(&$expected, &$actual, true))
+    };
+    ($expected:expr, $actual:expr; key = $key:expr) => {
+        $crate::with_element_key($key, || $crate::__diff_all!(&$expected, &$actual, true))
+    };
+    ($expected:expr, $actual:expr; ignore_paths = $patterns:expr) => {
+        $crate::with_ignored_paths(&$patterns, || {
+            $crate::__diff_all!(&$expected, &$actual, true)
+        })
+    };
+    ($expected:expr, $actual:expr; unordered) => {
+        $crate::with_unordered_sequences(|| $crate::__diff_all!(&$expected, &$actual, true))
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ $(,)?) => {
+        $crate::__private::diff_except(&$expected, &$actual, &[$(stringify!($field)),+])
+    };
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {
+        $crate::__diff_fields!(&$expected, &$actual, $($fields)+)
+    };
+}
+
+/// Macro which compares two structs like
+/// [`compare_structs!`](crate::compare_structs), returning
+/// `Result<(), StructDiff>` instead of panicking.
+///
+/// It accepts the same forms as [`diff_structs!`](crate::diff_structs).
+/// Comparisons are recorded by the metrics recorder, but not reported.
+///
+/// ```edition2024
+/// use cmp::try_compare_structs;
+///
+/// #[derive(Debug)]
+/// struct Order { id: u32, total: f64, status: &'static str }
+///
+/// let expected = Order { id: 1, total: 9.5, status: "paid" };
+/// let actual = Order { id: 1, total: 9.5, status: "pending" };
+///
+/// let failure = try_compare_structs!(expected, actual, id, total, status).unwrap_err();
+/// assert_eq!(failure.fields().len(), 1);
+/// assert_eq!(failure.fields()[0].path().to_string(), "status");
+/// assert!(try_compare_structs!(expected, actual, id, total).is_ok());
+/// ```
+#[macro_export]
+macro_rules! try_compare_structs {
+    ($expected:expr, $actual:expr $(; $option:ident $(= $value:expr)?)?) => {
+        $crate::__try_finish!(
+            $crate::diff_structs!($expected, $actual $(; $option $(= $value)?)?),
+            $expected,
+            $actual
+        )
+    };
+    ($expected:expr, $actual:expr, except: $($field:ident),+ $(,)?) => {
+        $crate::__try_finish!(
+            $crate::diff_structs!($expected, $actual, except: $($field),+),
+            $expected,
+            $actual
+        )
+    };
+    ($expected:expr, $actual:expr, $($fields:tt)+) => {
+        $crate::__try_finish!(
+            $crate::diff_structs!($expected, $actual, $($fields)+),
+            $expected,
+            $actual
+        )
+    };
+}
+
+/// Records a macro comparison's [`Diff`] and returns it as a [`StructDiff`]
+/// if it is not empty.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __try_finish {
+    ($diff:expr, $expected:expr, $actual:expr) => {{
+        let diff = $diff;
+        $crate::__private::record(&diff);
+        $crate::StructDiff::check(diff, stringify!($expected), stringify!($actual))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DiffPath, FieldDiff};
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn returns_field_differences_without_panicking() {
+        let failure =
+            try_compare_structs!(Point { x: 1, y: 2 }, Point { x: 1, y: 3 }, x, y).unwrap_err();
+
+        let mut path = DiffPath::new();
+        path.push_field("y");
+        assert_eq!(
+            failure.fields(),
+            [FieldDiff::Changed {
+                path,
+                expected: "2".into(),
+                actual: "3".into(),
+            }]
+        );
+        assert_eq!(
+            failure.to_string(),
+            "`Point { x: 1, y: 2 }` and `Point { x: 1, y: 3 }` differ in 1 field\ny: 2 != 3\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn compares_all_serialized_fields() {
+        #[derive(serde::Serialize)]
+        struct User {
+            id: u32,
+            name: &'static str,
+        }
+
+        let expected = User { id: 1, name: "ada" };
+
+        assert!(try_compare_structs!(expected, User { id: 1, name: "ada" }).is_ok());
+        let failure = try_compare_structs!(expected, User { id: 2, name: "bob" }).unwrap_err();
+        assert_eq!(failure.diff().len(), 2);
+        assert_eq!(failure.expected(), "expected");
+    }
+
+    #[test]
+    fn diffs_without_recording() {
+        let diff = diff_structs!(Point { x: 1, y: 2 }, Point { x: 0, y: 3 }, x, y);
+
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff.entries()[0].path().to_string(), "x");
+        assert!(diff_structs!(Point { x: 1, y: 2 }, Point { x: 1, y: 3 }, x).is_empty());
+    }
+}
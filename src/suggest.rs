@@ -0,0 +1,108 @@
+//! Suggested expected values for failure messages, enabled with
+//! `CMP_SUGGEST=1`.
+//!
+//! The suggestion is a ready-to-paste Rust struct literal of the actual
+//! values of the differing fields, so updating a hand-written expected value
+//! is a copy-paste. This is used by the macros through `cmp::__private` and
+//! is not part of the public API.
+
+use std::env;
+use std::fmt::Write;
+
+use crate::{Diff, FieldDiff, Segment};
+
+/// Environment variable which enables suggestions.
+const SUGGEST_VAR: &str = "CMP_SUGGEST";
+
+/// Returns whether `CMP_SUGGEST` is set to anything other than `0` or an
+/// empty string.
+pub fn enabled() -> bool {
+    env::var_os(SUGGEST_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Renders the suggestion printed below the field diffs: a struct literal
+/// named after `type_name`, with the actual value of every differing
+/// top-level field. Fields missing from the actual value are left out.
+pub fn suggestion(type_name: &str, diff: &Diff) -> String {
+    let mut fields = String::new();
+    for entry in diff {
+        let actual = match entry {
+            FieldDiff::Changed { actual, .. } | FieldDiff::MissingFromExpected { actual, .. } => {
+                actual
+            }
+            FieldDiff::MissingFromActual { .. } => continue,
+        };
+        let [Segment::Field(name)] = entry.path().segments() else {
+            continue;
+        };
+        let mut lines = actual.lines();
+        let _ = write!(fields, "    {name}: {}", lines.next().unwrap_or_default());
+        for line in lines {
+            let _ = write!(fields, "\n    {line}");
+        }
+        // String literals need converting when the field is a `String`.
+        if actual.starts_with('"') {
+            fields.push_str(".into()");
+        }
+        fields.push_str(",\n");
+    }
+    if fields.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\nsuggested expected value:\n{} {{\n{fields}    // other fields unchanged\n}}\n",
+        short_type_name(type_name)
+    )
+}
+
+/// Returns the last path segment of a type name, without references, the
+/// [`Sourced`](crate::Sourced) wrapper or generic arguments, e.g. `User` for
+/// `&my_crate::model::User<u32>`.
+fn short_type_name(type_name: &str) -> &str {
+    let type_name = type_name.trim_start_matches('&');
+    let type_name = type_name
+        .strip_prefix("cmp::provenance::Sourced<")
+        .map_or(type_name, |inner| inner.trim_start_matches('&'));
+    let name = type_name.split('<').next().unwrap_or(type_name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffPath;
+
+    fn field(name: &str, actual: &str) -> FieldDiff {
+        let mut path = DiffPath::new();
+        path.push_field(name);
+        FieldDiff::Changed {
+            path,
+            expected: "0".into(),
+            actual: actual.into(),
+        }
+    }
+
+    #[test]
+    fn renders_differing_fields_as_a_struct_literal() {
+        let mut diff = Diff::new();
+        diff.push(field("total", "103"));
+        diff.push(field("name", "\"ada\""));
+        diff.push(field("tags", "[\n    1,\n    2,\n]"));
+
+        assert_eq!(
+            suggestion("shop::Order<u32>", &diff),
+            "\nsuggested expected value:\n\
+             Order {\n    \
+             total: 103,\n    \
+             name: \"ada\".into(),\n    \
+             tags: [\n        1,\n        2,\n    ],\n    \
+             // other fields unchanged\n\
+             }\n"
+        );
+        assert_eq!(suggestion("Order", &Diff::new()), "");
+        assert_eq!(
+            short_type_name("cmp::provenance::Sourced<&shop::Order<u32>>"),
+            "Order"
+        );
+    }
+}
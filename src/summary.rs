@@ -0,0 +1,211 @@
+//! Changelog-style prose summaries of a [`Diff`], for alerts and reports
+//! where field-level entries are too low-level.
+
+use crate::{Diff, DiffPath, FieldDiff, Segment};
+
+/// One clause of a summary, before rendering.
+enum Clause {
+    /// Elements added to or removed from the sequence at a path.
+    Elements {
+        path: DiffPath,
+        added: bool,
+        count: usize,
+    },
+    Text(String),
+}
+
+impl Diff {
+    /// Returns a one-line prose summary of the differences, such as
+    /// `` `status` changed from Pending to Failed; 3 items added to `errors`;
+    /// `retries` increased by 2 ``.
+    ///
+    /// Numeric changes are described as increases or decreases, elements
+    /// added to or removed from the same sequence are counted together, and
+    /// changes to multi-line values are only named.
+    ///
+    /// ```edition2024
+    /// use cmp::Diffable;
+    ///
+    /// let expected = (3, vec!["timeout"]);
+    /// let actual = (5, vec!["timeout", "refused", "reset"]);
+    ///
+    /// assert_eq!(
+    ///     expected.compare(&actual).summarize(),
+    ///     "`0` increased by 2; 2 items added to `1`"
+    /// );
+    /// ```
+    pub fn summarize(&self) -> String {
+        let mut clauses: Vec<Clause> = Vec::new();
+        for entry in self {
+            let (element, added) = match entry {
+                FieldDiff::Changed {
+                    path,
+                    expected,
+                    actual,
+                } => {
+                    clauses.push(Clause::Text(changed(path, expected, actual)));
+                    continue;
+                }
+                FieldDiff::MissingFromExpected { path, .. } => (path, true),
+                FieldDiff::MissingFromActual { path, .. } => (path, false),
+            };
+            let Some((Segment::Index(_), parent)) = element.segments().split_last() else {
+                let verb = if added { "added" } else { "removed" };
+                clauses.push(Clause::Text(format!("{} {verb}", name(element))));
+                continue;
+            };
+            let parent: DiffPath = parent.iter().cloned().collect();
+            let existing = clauses.iter_mut().find_map(|clause| match clause {
+                Clause::Elements {
+                    path,
+                    added: same,
+                    count,
+                } if *path == parent && *same == added => Some(count),
+                _ => None,
+            });
+            match existing {
+                Some(count) => *count += 1,
+                None => clauses.push(Clause::Elements {
+                    path: parent,
+                    added,
+                    count: 1,
+                }),
+            }
+        }
+
+        if clauses.is_empty() {
+            return "no changes".to_string();
+        }
+        clauses
+            .into_iter()
+            .map(|clause| match clause {
+                Clause::Elements { path, added, count } => {
+                    let items = if count == 1 { "item" } else { "items" };
+                    let (verb, preposition) = if added {
+                        ("added", "to")
+                    } else {
+                        ("removed", "from")
+                    };
+                    format!("{count} {items} {verb} {preposition} {}", name(&path))
+                }
+                Clause::Text(text) => text,
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Names a path in backticks, or `the value` for the root.
+fn name(path: &DiffPath) -> String {
+    if path.is_empty() {
+        "the value".to_string()
+    } else {
+        format!("`{path}`")
+    }
+}
+
+fn changed(path: &DiffPath, expected: &str, actual: &str) -> String {
+    let name = name(path);
+    if let Some(change) = numeric_change(expected, actual) {
+        return format!("{name} {change}");
+    }
+    if expected.contains('\n') || actual.contains('\n') {
+        return format!("{name} changed");
+    }
+    format!("{name} changed from {expected} to {actual}")
+}
+
+/// Describes the change between two numbers as an increase or decrease.
+fn numeric_change(expected: &str, actual: &str) -> Option<String> {
+    let delta =
+        if let (Ok(expected), Ok(actual)) = (expected.parse::<i128>(), actual.parse::<i128>()) {
+            let verb = if actual > expected {
+                "increased"
+            } else {
+                "decreased"
+            };
+            format!("{verb} by {}", actual.abs_diff(expected))
+        } else {
+            let (from, to) = (expected.parse::<f64>().ok()?, actual.parse::<f64>().ok()?);
+            if !(from.is_finite() && to.is_finite()) {
+                return None;
+            }
+            let verb = if to > from { "increased" } else { "decreased" };
+            // Rounded to the inputs' precision, so 0.1 to 0.3 is by 0.2, not
+            // 0.19999999999999998.
+            let precision = [expected, actual]
+                .iter()
+                .map(|value| {
+                    value
+                        .split_once('.')
+                        .map_or(0, |(_, fraction)| fraction.len())
+                })
+                .max()
+                .unwrap_or(0);
+            format!("{verb} by {:.precision$}", (to - from).abs())
+        };
+    Some(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(fields: &[&str]) -> DiffPath {
+        let mut path = DiffPath::new();
+        for field in fields {
+            path.push_field(field);
+        }
+        path
+    }
+
+    #[test]
+    fn summarizes_changes_as_prose() {
+        let mut diff = Diff::new();
+        diff.push(FieldDiff::Changed {
+            path: path(&["status"]),
+            expected: "Pending".into(),
+            actual: "Failed".into(),
+        });
+        for index in 1..4 {
+            let mut errors = path(&["errors"]);
+            errors.push_index(index);
+            diff.push(FieldDiff::MissingFromExpected {
+                path: errors,
+                actual: "\"e\"".into(),
+            });
+        }
+        diff.push(FieldDiff::Changed {
+            path: path(&["retries"]),
+            expected: "1".into(),
+            actual: "3".into(),
+        });
+        diff.push(FieldDiff::MissingFromActual {
+            path: path(&["owner"]),
+            expected: "\"ada\"".into(),
+        });
+
+        assert_eq!(
+            diff.summarize(),
+            "`status` changed from Pending to Failed; 3 items added to `errors`; \
+             `retries` increased by 2; `owner` removed"
+        );
+    }
+
+    #[test]
+    fn describes_numbers_and_large_values() {
+        assert_eq!(
+            changed(&path(&["ratio"]), "0.1", "0.3"),
+            "`ratio` increased by 0.2"
+        );
+        assert_eq!(
+            changed(&DiffPath::new(), "10", "-5"),
+            "the value decreased by 15"
+        );
+        assert_eq!(
+            changed(&path(&["config"]), "Config {\n}", "Config {\n    a: 1,\n}"),
+            "`config` changed"
+        );
+        assert_eq!(Diff::new().summarize(), "no changes");
+    }
+}
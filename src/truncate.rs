@@ -0,0 +1,215 @@
+//! Output budgets for rendered values, so one huge field doesn't crowd out
+//! the differences of the others.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::FieldDiff;
+
+/// Budget set with [`set_max_render_len`], or [`UNSET`] if unset.
+static DEFAULT: AtomicUsize = AtomicUsize::new(UNSET);
+
+/// Stands in for no process-wide budget, as values can't be as long.
+const UNSET: usize = usize::MAX;
+
+/// Smallest budget, which leaves room for the `…` ending a cut value.
+const MIN_LEN: usize = '…'.len_utf8() + 1;
+
+thread_local! {
+    static SCOPED: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Sets the process-wide budget of each rendered value in a difference, or
+/// removes it if `None`.
+///
+/// Values of `len` bytes or more are cut to fewer than `len`, and end with
+/// the number of bytes left out, e.g. `"aaaa…(4000 more bytes)`, or with
+/// just `…` if the budget has no room for the number.
+///
+/// # Panics
+///
+/// Panics if `len` is less than 4 bytes, too few for the `…`.
+pub fn set_max_render_len(len: Option<usize>) {
+    DEFAULT.store(len.map_or(UNSET, checked), Ordering::Relaxed);
+}
+
+/// Runs `compare` with the values it renders in differences cut to fewer
+/// than `len` bytes each, or to a smaller budget of an enclosing call or
+/// field, as with [`set_max_render_len`].
+///
+/// `#[cmp(max_render_len = 200)]` on a derived field, or
+/// `field max_render_len = 200` in [`compare_structs!`](crate::compare_structs),
+/// does this for the field's comparison.
+///
+/// ```edition2024
+/// use cmp::{Diffable, with_max_render_len};
+///
+/// let diff = with_max_render_len(24, || "a".repeat(100).compare(&"b".repeat(100)));
+/// assert_eq!(diff.to_string(), ": \"aaa…(98 more bytes) != \"bbb…(98 more bytes)\n");
+/// ```
+///
+/// # Panics
+///
+/// Panics if `len` is less than 4 bytes, too few for the `…`.
+pub fn with_max_render_len<R>(len: usize, compare: impl FnOnce() -> R) -> R {
+    let len = checked(len);
+    /// Restores the previous budget, even if `compare` panics.
+    struct Restore(Option<usize>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| scoped.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SCOPED.with(|scoped| {
+        let previous = scoped.get();
+        scoped.set(Some(previous.map_or(len, |previous| previous.min(len))));
+        previous
+    }));
+    compare()
+}
+
+/// Returns the most bytes a rendered value may take, if limited.
+fn max_render_len() -> Option<usize> {
+    let default = match DEFAULT.load(Ordering::Relaxed) {
+        UNSET => None,
+        len => Some(len),
+    };
+    match (SCOPED.with(Cell::get), default) {
+        (Some(scoped), Some(default)) => Some(scoped.min(default)),
+        (scoped, default) => scoped.or(default),
+    }
+}
+
+fn checked(len: usize) -> usize {
+    assert!(
+        len >= MIN_LEN,
+        "max_render_len must be at least {MIN_LEN} bytes, got {len}"
+    );
+    len
+}
+
+/// Cuts the values of `entry` to the current budget.
+pub(crate) fn truncate(entry: &mut FieldDiff) {
+    let Some(len) = max_render_len() else {
+        return;
+    };
+    match entry {
+        FieldDiff::Changed {
+            expected, actual, ..
+        } => {
+            truncate_value(expected, len);
+            truncate_value(actual, len);
+        }
+        FieldDiff::MissingFromExpected { actual: value, .. }
+        | FieldDiff::MissingFromActual {
+            expected: value, ..
+        } => truncate_value(value, len),
+    }
+}
+
+/// Cuts `value` to fewer than `len` bytes, including the marker saying how
+/// many were left out, or just `…` if the marker doesn't fit.
+fn truncate_value(value: &mut String, len: usize) {
+    if value.len() < len {
+        return;
+    }
+    let messages = crate::messages::messages();
+    // The marker for all bytes left out is at least as long as the final
+    // one.
+    let longest = messages.truncated(value.len()).len();
+    let (mut kept, counted) = match (len - 1).checked_sub(longest) {
+        Some(kept) => (kept, true),
+        None => (len - MIN_LEN, false),
+    };
+    while !value.is_char_boundary(kept) {
+        kept -= 1;
+    }
+    let marker = if counted {
+        messages.truncated(value.len() - kept)
+    } else {
+        "…".to_string()
+    };
+    value.truncate(kept);
+    value.push_str(&marker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffPath;
+
+    fn changed(expected: &str, actual: &str) -> FieldDiff {
+        FieldDiff::Changed {
+            path: DiffPath::new(),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
+
+    #[test]
+    fn cuts_long_values_within_the_budget() {
+        let mut entry = changed(&"é".repeat(20), "short");
+        with_max_render_len(25, || truncate(&mut entry));
+
+        let FieldDiff::Changed {
+            expected, actual, ..
+        } = &entry
+        else {
+            unreachable!();
+        };
+        assert_eq!(expected, "ééé…(34 more bytes)");
+        assert!(expected.len() < 25);
+        assert_eq!(actual, "short");
+    }
+
+    #[test]
+    fn cuts_values_as_long_as_the_budget() {
+        let mut entry = changed(&"a".repeat(30), &"a".repeat(29));
+        with_max_render_len(30, || truncate(&mut entry));
+
+        let FieldDiff::Changed {
+            expected, actual, ..
+        } = &entry
+        else {
+            unreachable!();
+        };
+        assert_eq!(expected, &format!("{}…(19 more bytes)", "a".repeat(11)));
+        assert_eq!(actual, &"a".repeat(29));
+    }
+
+    #[test]
+    fn ends_with_an_ellipsis_when_the_count_does_not_fit() {
+        let mut entry = changed("abcdefghij", "abc");
+        with_max_render_len(6, || truncate(&mut entry));
+
+        let FieldDiff::Changed {
+            expected, actual, ..
+        } = &entry
+        else {
+            unreachable!();
+        };
+        assert_eq!(expected, "ab…");
+        assert_eq!(actual, "abc");
+
+        let mut entry = changed("abcd", "é");
+        with_max_render_len(4, || truncate(&mut entry));
+        assert_eq!(entry, changed("…", "é"));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_render_len must be at least 4 bytes, got 0")]
+    fn rejects_budgets_without_room_for_the_ellipsis() {
+        with_max_render_len(0, || {});
+    }
+
+    #[test]
+    fn keeps_the_smallest_enclosing_budget() {
+        with_max_render_len(10, || {
+            with_max_render_len(100, || assert_eq!(max_render_len(), Some(10)));
+            assert_eq!(max_render_len(), Some(10));
+        });
+        assert_eq!(max_render_len(), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Order-insensitive comparison of sequences, for collections whose order
+//! isn't meaningful.
+//!
+//! Sequences are compared as multisets: each expected element is paired
+//! with an equal actual element wherever it is, then the elements left over
+//! are paired with the closest remaining ones, so only elements which were
+//! genuinely added, removed or changed are reported.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set with [`set_unordered_sequences`].
+static UNORDERED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static SCOPED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Indices of expected elements with those of their paired actual elements,
+/// and the indices of unpaired actual elements.
+pub(crate) type Pairs = (Vec<(usize, Option<usize>)>, Vec<usize>);
+
+/// Sets whether sequences are compared regardless of their order in every
+/// comparison of the process.
+///
+/// Use [`with_unordered_sequences`] for one comparison, `field ~ unordered`
+/// in [`compare_structs!`](crate::compare_structs) or `#[cmp(unordered)]` on
+/// a derived field for one field.
+pub fn set_unordered_sequences(unordered: bool) {
+    UNORDERED.store(unordered, Ordering::Relaxed);
+}
+
+/// Runs `compare` with sequences compared regardless of their order on the
+/// current thread, including the sequences nested in their elements.
+///
+/// Differences inside paired elements are reported at the index of the
+/// expected element, added elements at their index in the actual sequence.
+/// It applies to [`Diffable`](crate::Diffable) sequences, and to serialized
+/// arrays which aren't paired [by key](crate::with_element_key).
+///
+/// ```edition2024
+/// use cmp::Diffable;
+///
+/// let expected = vec!["admin", "audit", "billing"];
+/// let actual = vec!["billing", "support", "admin"];
+///
+/// let diff = cmp::with_unordered_sequences(|| expected.compare(&actual));
+/// assert_eq!(diff.to_string(), "[1]: \"audit\" != \"support\"\n");
+/// ```
+pub fn with_unordered_sequences<R>(compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous setting, even if `compare` panics.
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| scoped.set(self.0));
+        }
+    }
+
+    let _restore = Restore(SCOPED.with(|scoped| scoped.replace(true)));
+    compare()
+}
+
+/// Returns whether sequences of the current comparison are unordered.
+pub(crate) fn unordered() -> bool {
+    SCOPED.with(Cell::get) || UNORDERED.load(Ordering::Relaxed)
+}
+
+/// Pairs each expected element with an actual one regardless of position,
+/// given the number of differences between two elements.
+///
+/// Elements without differences are paired first, then each remaining
+/// expected element, in order, with the remaining actual element it has the
+/// fewest differences with. Returns the pairs in the order of `expected`
+/// and the unpaired actual indices in the order of `actual`.
+pub(crate) fn pair<T>(
+    expected: &[T],
+    actual: &[T],
+    differences: impl Fn(&T, &T) -> usize,
+) -> Pairs {
+    let mut unpaired: Vec<usize> = (0..actual.len()).collect();
+    let mut paired: Vec<Option<usize>> = expected
+        .iter()
+        .map(|expected| {
+            let at = unpaired
+                .iter()
+                .position(|&at| differences(expected, &actual[at]) == 0)?;
+            Some(unpaired.remove(at))
+        })
+        .collect();
+    for (index, paired) in paired.iter_mut().enumerate() {
+        if paired.is_some() {
+            continue;
+        }
+        let closest = unpaired
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &at)| differences(&expected[index], &actual[at]));
+        *paired = closest.map(|(at, _)| at).map(|at| unpaired.remove(at));
+    }
+    unpaired.sort_unstable();
+    (paired.into_iter().enumerate().collect(), unpaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diffable;
+
+    #[test]
+    fn pairs_equal_elements_first() {
+        let distance = |expected: &i32, actual: &i32| expected.abs_diff(*actual) as usize;
+
+        assert_eq!(
+            pair(&[1, 2, 3], &[3, 9, 1, 20], distance),
+            (vec![(0, Some(2)), (1, Some(1)), (2, Some(0))], vec![3])
+        );
+        assert_eq!(
+            pair(&[1, 5], &[5], distance),
+            (vec![(0, None), (1, Some(0))], vec![])
+        );
+    }
+
+    #[test]
+    fn reports_only_added_removed_and_changed_elements() {
+        let expected = vec![vec![1, 2], vec![3], vec![4], vec![4]];
+        let actual = vec![vec![4], vec![2, 1], vec![5], vec![6], vec![4]];
+
+        let diff = with_unordered_sequences(|| expected.compare(&actual));
+        assert_eq!(
+            diff.to_string(),
+            "[1][0]: 3 != 5\n[3]: field missing from expected: [\n    6,\n]\n"
+        );
+        assert!(!unordered());
+        assert_eq!(expected.compare(&actual).len(), 7);
+    }
+
+    #[test]
+    fn compares_one_macro_field_regardless_of_order() {
+        struct User {
+            roles: Vec<&'static str>,
+            history: Vec<u32>,
+        }
+
+        let expected = User {
+            roles: vec!["a", "b"],
+            history: vec![1, 2],
+        };
+        let actual = User {
+            roles: vec!["b", "a"],
+            history: vec![2, 1],
+        };
+
+        let diff = crate::diff_structs!(expected, actual, roles ~ unordered, history);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff.entries()[0].path().to_string(), "history");
+    }
+}
@@ -0,0 +1,651 @@
+//! Recursive comparison of serialized values, used by `compare_structs!` in
+//! serde mode and by snapshots.
+//!
+//! Objects and arrays are descended into, so a difference deep inside a
+//! large value is reported at the path of the differing leaf, e.g.
+//! `config.server.port: 8080 != 8081`, rather than as the whole top-level
+//! field. Array elements are paired by position, by an ID field set with
+//! [`with_element_key`], or regardless of order with
+//! [`with_unordered_sequences`](crate::with_unordered_sequences).
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::path::PathGlob;
+use crate::serialize::{SerializeError, Serialized};
+use crate::unordered::Pairs;
+use crate::{Diff, DiffPath, FieldDiff, Segment};
+
+thread_local! {
+    static ELEMENT_KEY: RefCell<Option<String>> = const { RefCell::new(None) };
+    static IGNORED_PATHS: RefCell<Vec<PathGlob>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `compare` with array elements paired by their `key` field, such as
+/// `"id"`, rather than by position, in serialized comparisons on the current
+/// thread.
+///
+/// `compare_structs!(a, b; key = "id")` does this for one comparison.
+/// Differences inside paired elements are reported at the index of the
+/// expected element, elements without a match at their own index. Arrays
+/// where an element isn't an object with the field, or where two elements
+/// of a side share a value of it, are paired by position.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::diff_structs;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Order { id: u32, status: &'static str }
+///
+/// #[derive(Serialize)]
+/// struct Account { orders: Vec<Order> }
+///
+/// let expected = Account { orders: vec![Order { id: 1, status: "open" }, Order { id: 2, status: "open" }] };
+/// let actual = Account { orders: vec![Order { id: 2, status: "open" }, Order { id: 1, status: "closed" }] };
+///
+/// let diff = cmp::with_element_key("id", || diff_structs!(expected, actual));
+/// assert_eq!(diff.to_string(), "orders[0].status: \"open\" != \"closed\"\n");
+/// assert_eq!(diff_structs!(expected, actual; key = "id"), diff);
+/// # }
+/// ```
+pub fn with_element_key<R>(key: &str, compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous key, even if `compare` panics.
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ELEMENT_KEY.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(ELEMENT_KEY.with(|scoped| scoped.borrow_mut().replace(key.to_string())));
+    compare()
+}
+
+/// Runs `compare` with the values at paths matching any of the
+/// [glob](DiffPath::matches_glob) `patterns` skipped, in serialized
+/// comparisons on the current thread.
+///
+/// `compare_structs!(a, b; ignore_paths = ["**/created_at"])` does this for
+/// one comparison. `**` matches any number of segments, so
+/// `"**/created_at"` skips volatile fields at any depth, and `[*]` any
+/// index, as in `"items[*].etag"`. Patterns add to those of an enclosing
+/// call.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use cmp::diff_structs;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Item { etag: &'static str, created_at: u64, qty: u32 }
+///
+/// #[derive(Serialize)]
+/// struct Order { created_at: u64, items: Vec<Item> }
+///
+/// let expected = Order { created_at: 1, items: vec![Item { etag: "a", created_at: 1, qty: 1 }] };
+/// let actual = Order { created_at: 2, items: vec![Item { etag: "b", created_at: 2, qty: 3 }] };
+///
+/// let diff = diff_structs!(expected, actual; ignore_paths = ["**/created_at", "items[*].etag"]);
+/// assert_eq!(diff.to_string(), "items[0].qty: 1 != 3\n");
+/// # }
+/// ```
+pub fn with_ignored_paths<R>(patterns: &[&str], compare: impl FnOnce() -> R) -> R {
+    /// Restores the previous patterns, even if `compare` panics.
+    struct Restore(usize);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            IGNORED_PATHS.with(|scoped| scoped.borrow_mut().truncate(self.0));
+        }
+    }
+
+    let _restore = Restore(IGNORED_PATHS.with(|scoped| {
+        let mut scoped = scoped.borrow_mut();
+        let previous = scoped.len();
+        scoped.extend(patterns.iter().map(|pattern| PathGlob::new(pattern)));
+        previous
+    }));
+    compare()
+}
+
+/// Returns whether the value at `path` is skipped by
+/// [`with_ignored_paths`].
+fn ignored(path: &DiffPath) -> bool {
+    IGNORED_PATHS.with(|scoped| scoped.borrow().iter().any(|glob| glob.matches(path)))
+}
+
+/// Compares the serialized forms of two values like `compare_structs!(expected,
+/// actual)` in serde mode, returning the [`Diff`] instead of panicking, for
+/// library code which acts on differences.
+///
+/// The comparison follows the same settings, such as the
+/// [`KeyMatcher`](crate::KeyMatcher) and [`with_element_key`], and skips
+/// `null` fields missing from one side. Values which aren't structs are
+/// compared too, so differing top-level scalars are reported at the empty
+/// path. Nothing is recorded or reported.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Config { host: &'static str, port: u16 }
+///
+/// let diff = cmp::diff(&Config { host: "a", port: 8080 }, &Config { host: "a", port: 8081 });
+/// assert_eq!(diff.to_string(), "port: 8080 != 8081\n");
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics with the path of the field which couldn't be serialized if either
+/// value can't be, unless the
+/// [`SerializationFailure`](crate::SerializationFailure) behavior is to skip
+/// it. [`try_diff`] returns the error instead.
+pub fn diff<E: Serialize + ?Sized, A: Serialize + ?Sized>(expected: &E, actual: &A) -> Diff {
+    try_diff(expected, actual).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Compares the serialized forms of two values like [`diff`], returning a
+/// [`SerializeError`] instead of panicking if either value can't be
+/// serialized.
+///
+/// ```edition2024
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use std::collections::HashMap;
+///
+/// let grid = HashMap::from([((0, 0), 1)]);
+/// let err = cmp::try_diff(&grid, &grid).unwrap_err();
+/// assert_eq!(err.to_string(), "Could not serialize expected value: key must be a string");
+/// # }
+/// ```
+pub fn try_diff<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+) -> Result<Diff, SerializeError> {
+    let expected = crate::memo::serialize(expected, "expected")?;
+    let actual = crate::memo::serialize(actual, "actual")?;
+    let mut diff = Diff::new();
+    diff_serialized(&expected, &actual, true, &mut diff);
+    Ok(diff)
+}
+
+/// Compares all serialized fields of two structs for `compare_structs!` and
+/// `diff_structs!` like [`diff`], reporting `null` fields missing from one
+/// side unless `skip_null`.
+///
+/// # Panics
+///
+/// Panics if either value can't be serialized, like [`diff`], or if the
+/// values differ and either isn't serialized as an object.
+pub fn diff_all<E: Serialize + ?Sized, A: Serialize + ?Sized>(
+    expected: &E,
+    actual: &A,
+    skip_null: bool,
+) -> Diff {
+    let serialize =
+        |serialized: Result<_, SerializeError>| serialized.unwrap_or_else(|err| panic!("{err}"));
+    let expected = serialize(crate::memo::serialize(expected, "expected"));
+    let actual = serialize(crate::memo::serialize(actual, "actual"));
+    let mut diff = Diff::new();
+    if expected.value != actual.value {
+        assert!(
+            expected.value.is_object(),
+            "Expected value is not an object"
+        );
+        assert!(actual.value.is_object(), "Actual value is not an object");
+    }
+    diff_serialized(&expected, &actual, skip_null, &mut diff);
+    diff
+}
+
+/// Compares two serialized values, reporting each field left out of either
+/// because it couldn't be serialized.
+pub(crate) fn diff_serialized(
+    expected: &Serialized,
+    actual: &Serialized,
+    skip_null: bool,
+    out: &mut Diff,
+) {
+    if expected.skipped.is_empty() && actual.skipped.is_empty() {
+        if expected.value != actual.value {
+            diff_values(
+                &expected.value,
+                &actual.value,
+                skip_null,
+                &mut DiffPath::new(),
+                out,
+            );
+        }
+        return;
+    }
+
+    // Leave the skipped fields out of both sides, so only the report of
+    // each one remains.
+    let skipped: Vec<&DiffPath> = expected
+        .skipped
+        .iter()
+        .chain(&actual.skipped)
+        .map(|(path, _)| path)
+        .collect();
+    let mut compared = (expected.value.clone(), actual.value.clone());
+    for path in &skipped {
+        leave_out(&mut compared.0, path.segments());
+        leave_out(&mut compared.1, path.segments());
+    }
+    if compared.0 != compared.1 {
+        diff_values(
+            &compared.0,
+            &compared.1,
+            skip_null,
+            &mut DiffPath::new(),
+            out,
+        );
+    }
+
+    let messages = crate::messages::messages();
+    let render = |serialized: &Serialized, path: &DiffPath| match serialized
+        .skipped
+        .iter()
+        .find(|(skipped, _)| skipped == path)
+    {
+        Some((_, message)) => Some(messages.not_serializable(message)),
+        None => {
+            value_at(&serialized.value, path.segments()).map(|value| render_within(path, value))
+        }
+    };
+    let mut reported = Vec::new();
+    for path in skipped {
+        if reported.contains(&path) || ignored(path) {
+            continue;
+        }
+        reported.push(path);
+        let entry = match (render(expected, path), render(actual, path)) {
+            (Some(expected), Some(actual)) => FieldDiff::Changed {
+                path: path.clone(),
+                expected,
+                actual,
+            },
+            (Some(expected), None) => FieldDiff::MissingFromActual {
+                path: path.clone(),
+                expected,
+            },
+            (None, actual) => FieldDiff::MissingFromExpected {
+                path: path.clone(),
+                actual: actual.unwrap_or_default(),
+            },
+        };
+        out.push(entry);
+    }
+}
+
+/// Returns the value at the path of field and index `segments` in `value`.
+fn value_at<'a>(value: &'a Value, segments: &[Segment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Field(name) => value.get(name),
+            Segment::Index(index) => value.get(index),
+            _ => None,
+        })
+}
+
+/// Removes the field at the path `segments` from `value`, or sets the value
+/// there to `null` if it isn't a field.
+fn leave_out(value: &mut Value, segments: &[Segment]) {
+    let Some((last, parent)) = segments.split_last() else {
+        *value = Value::Null;
+        return;
+    };
+    let parent = parent
+        .iter()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Field(name) => value.get_mut(name),
+            Segment::Index(index) => value.get_mut(index),
+            _ => None,
+        });
+    match (parent, last) {
+        (Some(Value::Object(object)), Segment::Field(name)) => {
+            object.remove(name);
+        }
+        (Some(Value::Array(array)), Segment::Index(index)) => {
+            if let Some(element) = array.get_mut(*index) {
+                *element = Value::Null;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compares two JSON values recursively, descending into objects and
+/// arrays.
+///
+/// Object keys are paired by the current [`KeyMatcher`](crate::KeyMatcher).
+/// If `skip_null`, object fields which are `null` on the only side where
+/// they exist, such as `PhantomData` markers, aren't reported as missing.
+/// Values are [redacted](crate::Redactions) before they are compared.
+pub fn diff_values(
+    expected: &Value,
+    actual: &Value,
+    skip_null: bool,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    if ignored(path) {
+        return;
+    }
+    if let Some(redactions) = crate::redact::redactions() {
+        let redacted_expected = redactions.apply(path, expected);
+        let redacted_actual = redactions.apply(path, actual);
+        if redacted_expected.is_some() || redacted_actual.is_some() {
+            let expected = redacted_expected.as_ref().unwrap_or(expected);
+            let actual = redacted_actual.as_ref().unwrap_or(actual);
+            return diff_redacted(expected, actual, skip_null, path, out);
+        }
+    }
+    diff_redacted(expected, actual, skip_null, path, out);
+}
+
+/// Compares two JSON values like [`diff_values`], once they are redacted.
+fn diff_redacted(
+    expected: &Value,
+    actual: &Value,
+    skip_null: bool,
+    path: &mut DiffPath,
+    out: &mut Diff,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let (pairs, extra) = crate::keys::key_matcher().pair(
+                expected.keys().map(String::as_str),
+                actual.keys().map(String::as_str),
+            );
+            for (key, paired) in pairs {
+                path.push_field(key);
+                match paired {
+                    Some(paired) => {
+                        diff_values(&expected[key], &actual[paired], skip_null, path, out)
+                    }
+                    None if skip_null && expected[key].is_null() || ignored(path) => {}
+                    None => out.push_with(|| FieldDiff::MissingFromActual {
+                        path: path.clone(),
+                        expected: render_within(path, &expected[key]),
+                    }),
+                }
+                path.pop();
+            }
+            for key in extra {
+                if skip_null && actual[key].is_null() {
+                    continue;
+                }
+                path.push_field(key);
+                if !ignored(path) {
+                    out.push_with(|| FieldDiff::MissingFromExpected {
+                        path: path.clone(),
+                        actual: render_within(path, &actual[key]),
+                    });
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            let key = ELEMENT_KEY.with(|scoped| scoped.borrow().clone());
+            let pairs = key
+                .and_then(|key| pair_by_key(expected, actual, &key))
+                .or_else(|| {
+                    crate::unordered::unordered().then(|| {
+                        crate::unordered::pair(expected, actual, |expected, actual| {
+                            let mut found = Diff::counting();
+                            diff_values(
+                                expected,
+                                actual,
+                                skip_null,
+                                &mut DiffPath::new(),
+                                &mut found,
+                            );
+                            found.capped()
+                        })
+                    })
+                });
+            if let Some((pairs, extra)) = pairs {
+                for (index, paired) in pairs {
+                    path.push_index(index);
+                    match paired {
+                        Some(paired) => {
+                            diff_values(&expected[index], &actual[paired], skip_null, path, out)
+                        }
+                        None if ignored(path) => {}
+                        None => out.push_with(|| FieldDiff::MissingFromActual {
+                            path: path.clone(),
+                            expected: render_within(path, &expected[index]),
+                        }),
+                    }
+                    path.pop();
+                }
+                for index in extra {
+                    path.push_index(index);
+                    if !ignored(path) {
+                        out.push_with(|| FieldDiff::MissingFromExpected {
+                            path: path.clone(),
+                            actual: render_within(path, &actual[index]),
+                        });
+                    }
+                    path.pop();
+                }
+                return;
+            }
+            for index in 0..expected.len().max(actual.len()) {
+                path.push_index(index);
+                match (expected.get(index), actual.get(index)) {
+                    (Some(expected), Some(actual)) => {
+                        diff_values(expected, actual, skip_null, path, out)
+                    }
+                    _ if ignored(path) => {}
+                    (Some(expected), None) => out.push_with(|| FieldDiff::MissingFromActual {
+                        path: path.clone(),
+                        expected: render_within(path, expected),
+                    }),
+                    (None, actual) => out.push_with(|| FieldDiff::MissingFromExpected {
+                        path: path.clone(),
+                        actual: actual
+                            .map(|actual| render_within(path, actual))
+                            .unwrap_or_default(),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        _ if expected != actual => out.push_with(|| FieldDiff::Changed {
+            path: path.clone(),
+            expected: render_below(path, expected),
+            actual: render_below(path, actual),
+        }),
+        _ => {}
+    }
+}
+
+/// Pairs the indices of expected elements with those of the actual elements
+/// with the same `key` field, returning the pairs and the unpaired actual
+/// indices, or `None` if the elements can't be identified by `key`.
+fn pair_by_key(expected: &[Value], actual: &[Value], key: &str) -> Option<Pairs> {
+    let ids = |elements: &[Value]| -> Option<HashMap<String, usize>> {
+        let mut ids = HashMap::new();
+        for (index, element) in elements.iter().enumerate() {
+            let id = element.as_object()?.get(key)?.to_string();
+            if ids.insert(id, index).is_some() {
+                return None;
+            }
+        }
+        Some(ids)
+    };
+    let expected_ids = ids(expected)?;
+    let mut actual_ids = ids(actual)?;
+
+    let mut pairs: Vec<(usize, Option<usize>)> = expected_ids
+        .into_iter()
+        .map(|(id, index)| (index, actual_ids.remove(&id)))
+        .collect();
+    pairs.sort_unstable();
+    let mut extra: Vec<usize> = actual_ids.into_values().collect();
+    extra.sort_unstable();
+    Some((pairs, extra))
+}
+
+/// Renders a value at `path` which wasn't redacted yet, such as one missing
+/// from the other side.
+fn render_within(path: &DiffPath, value: &Value) -> String {
+    match crate::redact::redactions() {
+        Some(redactions) => render(&redactions.apply_within(&mut path.clone(), value)),
+        None => render(value),
+    }
+}
+
+/// Renders a redacted value at `path`, redacting the values it contains.
+fn render_below(path: &DiffPath, value: &Value) -> String {
+    match crate::redact::redactions() {
+        Some(redactions) if value.is_object() || value.is_array() => {
+            render(&redactions.apply_below(&mut path.clone(), value))
+        }
+        _ => render(value),
+    }
+}
+
+/// Renders a value as JSON, except strings, which are rendered with `Debug`
+/// so invisible characters are escaped like in other comparisons.
+pub(crate) fn render(value: &Value) -> String {
+    match value {
+        Value::String(string) => format!("{string:?}"),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn diff(expected: Value, actual: Value, skip_null: bool) -> String {
+        let mut out = Diff::new();
+        diff_values(
+            &expected,
+            &actual,
+            skip_null,
+            &mut DiffPath::new(),
+            &mut out,
+        );
+        out.to_string()
+    }
+
+    #[test]
+    fn reports_differing_leaves_at_their_paths() {
+        let expected = json!({"config": {"server": {"host": "a", "port": 8080}, "tags": ["x"]}});
+        let actual = json!({"config": {"server": {"host": "a", "port": 8081}, "tags": ["x", "y"]}});
+
+        assert_eq!(
+            diff(expected, actual, false),
+            "config.server.port: 8080 != 8081\n\
+             config.tags[1]: field missing from expected: \"y\"\n"
+        );
+    }
+
+    #[test]
+    fn pairs_elements_by_key() {
+        let expected = json!([{"id": 1, "n": 1}, {"id": 2, "n": 2}, {"id": 3, "n": 3}]);
+        let actual = json!([{"id": 4, "n": 4}, {"id": 2, "n": 5}, {"id": 1, "n": 1}]);
+
+        assert_eq!(
+            with_element_key("id", || diff(expected.clone(), actual.clone(), false)),
+            "[1].n: 2 != 5\n\
+             [2]: field missing from actual: {\"id\":3,\"n\":3}\n\
+             [0]: field missing from expected: {\"id\":4,\"n\":4}\n"
+        );
+        assert_eq!(
+            with_element_key("id", || diff(json!([1, 2]), json!([1, 3]), false)),
+            "[1]: 2 != 3\n"
+        );
+    }
+
+    #[test]
+    fn pairs_unordered_elements_by_content() {
+        let expected = json!({"tags": ["a", "b"], "items": [{"n": 1}, {"n": 2}]});
+        let actual = json!({"tags": ["b", "a"], "items": [{"n": 3}, {"n": 1}, {"n": 4}]});
+
+        assert_eq!(
+            crate::with_unordered_sequences(|| diff(expected, actual, false)),
+            "items[1].n: 2 != 3\n\
+             items[2]: field missing from expected: {\"n\":4}\n"
+        );
+    }
+
+    #[test]
+    fn skips_paths_matching_ignored_globs() {
+        let expected = json!({
+            "created_at": 1,
+            "items": [{"etag": "a", "qty": 1}, {"etag": "b", "meta": {"created_at": 1}}],
+        });
+        let actual = json!({
+            "items": [{"etag": "c", "qty": 2}, {"meta": {"created_at": 2}}, {"etag": "d"}],
+        });
+
+        let ignored = with_ignored_paths(&["**/created_at"], || {
+            with_ignored_paths(&["items[*].etag"], || {
+                diff(expected.clone(), actual.clone(), false)
+            })
+        });
+        assert_eq!(
+            ignored,
+            "items[0].qty: 1 != 2\n\
+             items[2]: field missing from expected: {\"etag\":\"d\"}\n"
+        );
+        assert!(diff(expected, actual, false).starts_with("created_at: "));
+    }
+
+    #[test]
+    fn diffs_serializable_values_of_any_shape() {
+        #[derive(Serialize)]
+        struct Marked {
+            name: &'static str,
+            marker: Option<()>,
+        }
+
+        assert!(super::diff(&[1, 2], &vec![1, 2]).is_empty());
+        assert_eq!(super::diff(&1, &2).to_string(), ": 1 != 2\n");
+        assert_eq!(
+            super::diff(
+                &json!({"name": "a"}),
+                &Marked {
+                    name: "b",
+                    marker: None
+                }
+            )
+            .to_string(),
+            "name: \"a\" != \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn skips_null_fields_missing_from_one_side() {
+        let expected = json!({"inner": {"marker": null, "name": "a\u{feff}"}});
+        let actual = json!({"inner": {"name": "a"}});
+
+        assert_eq!(
+            diff(expected.clone(), actual.clone(), true),
+            "inner.name: \"a\\u{feff}\" != \"a\" (expected contains a byte-order mark)\n"
+        );
+        assert!(
+            diff(expected, actual, false)
+                .starts_with("inner.marker: field missing from actual: null\n")
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Structural comparison of XML strings, enabled by the `xml` feature.
+//!
+//! Documents are compared as trees: attribute order, namespace prefixes,
+//! comments and whitespace between elements are ignored. Each difference is
+//! reported with an XPath-like path such as `/Envelope/Body/Item[2]/@id`,
+//! naming namespaced attributes as `@{urn:example}id`.
+
+use std::collections::BTreeMap;
+
+use crate::markup::{self, AttributeName, Element, Node};
+
+pub use crate::markup::ParseError;
+
+/// Compares two XML documents structurally, returning one line per
+/// difference.
+///
+/// ```edition2024
+/// let diffs = cmp::xml::diff(
+///     r#"<order id="1" state="open"><item>a</item></order>"#,
+///     r#"<order state="open" id="2">
+///         <item>a</item>
+///     </order>"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(diffs, [r#"/order/@id: "1" != "2""#]);
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`ParseError`] if either side is not well-formed XML.
+pub fn diff(expected: &str, actual: &str) -> Result<Vec<String>, ParseError> {
+    let expected = parse(expected).map_err(|err| ParseError::new("expected", err))?;
+    let actual = parse(actual).map_err(|err| ParseError::new("actual", err))?;
+
+    let mut diffs = Vec::new();
+    markup::diff_nodes(&[expected], &[actual], "", &mut diffs);
+    Ok(diffs)
+}
+
+fn parse(input: &str) -> Result<Node, roxmltree::Error> {
+    let document = roxmltree::Document::parse(input)?;
+    Ok(convert(document.root_element()))
+}
+
+fn convert(node: roxmltree::Node<'_, '_>) -> Node {
+    let attributes: BTreeMap<AttributeName, String> = node
+        .attributes()
+        .map(|attribute| {
+            let name = AttributeName {
+                namespace: attribute.namespace().map(str::to_string),
+                name: attribute.name().to_string(),
+            };
+            (name, attribute.value().to_string())
+        })
+        .collect();
+    let children = node
+        .children()
+        .filter_map(|child| {
+            if child.is_element() {
+                Some(convert(child))
+            } else if child.is_text() {
+                let text = child.text().unwrap_or_default().trim();
+                (!text.is_empty()).then(|| Node::Text(text.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Node::Element(Element {
+        name: node.tag_name().name().to_string(),
+        namespace: node.tag_name().namespace().map(str::to_string),
+        attributes,
+        children,
+    })
+}
+
+/// Macro which compares XML-containing string fields structurally.
+///
+/// With two arguments, the expressions themselves are compared. With field
+/// identifiers, each listed field of the two structs is compared and the
+/// field name prefixes the XPath-like path of every difference.
+///
+/// ```edition2024
+/// use cmp::compare_xml;
+/// # struct Request { body: String }
+/// let expected = Request { body: r#"<a x="1" y="2"><b/></a>"#.to_string() };
+/// let actual = Request { body: "<a y='2' x='1'>\n  <b/>\n</a>".to_string() };
+///
+/// compare_xml!(expected, actual, body);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the documents differ, or if either side is not well-formed XML.
+#[macro_export]
+macro_rules! compare_xml {
+    ($expected:expr, $actual:expr) => {{
+        let diffs = $crate::xml::diff(&$expected, &$actual).unwrap_or_else(|err| panic!("{err}"));
+
+        assert!(diffs.is_empty(), "{}\n", diffs.join("\n"));
+    }};
+    ($expected:expr, $actual:expr, $($field:ident),+) => {{
+        let mut diffs = String::new();
+        $(
+            let field_diffs = $crate::xml::diff(&$expected.$field, &$actual.$field)
+                .unwrap_or_else(|err| panic!("{}: {err}", stringify!($field)));
+            for diff in field_diffs {
+                diffs.push_str(&format!("{}{}\n", stringify!($field), diff));
+            }
+        )+
+
+        assert!(diffs.is_empty(), "{diffs}");
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_attribute_order_and_whitespace() {
+        let expected = r#"<Envelope><Body id="1" kind="x"><Item>a</Item></Body></Envelope>"#;
+        let actual = r#"
+            <Envelope>
+                <!-- comment -->
+                <Body kind="x" id="1">
+                    <Item>a</Item>
+                </Body>
+            </Envelope>
+        "#;
+
+        assert!(diff(expected, actual).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_xpath_like_paths() {
+        let expected = "<list><item>a</item><item n='1'>b</item></list>";
+        let actual = "<list><item>a</item><item n='2'>c</item><extra/></list>";
+
+        assert_eq!(
+            diff(expected, actual).unwrap(),
+            [
+                r#"/list/item[2]/@n: "1" != "2""#,
+                r#"/list/item[2]/text(): "b" != "c""#,
+                "/list/extra: node missing from expected: <extra>",
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_namespaces_not_prefixes() {
+        let expected = r#"<s:Envelope xmlns:s="urn:soap"/>"#;
+        let same = r#"<soap:Envelope xmlns:soap="urn:soap"/>"#;
+        let other = r#"<s:Envelope xmlns:s="urn:other"/>"#;
+
+        assert!(diff(expected, same).unwrap().is_empty());
+        assert_eq!(
+            diff(expected, other).unwrap(),
+            [r#"/Envelope/namespace-uri(): "urn:soap" != "urn:other""#]
+        );
+    }
+
+    #[test]
+    fn keys_attributes_by_namespace_and_name() {
+        let expected = r#"<a xmlns:x="urn:x" xmlns:y="urn:y" x:id="1" y:id="2" id="3"/>"#;
+        let same = r#"<a xmlns:p="urn:y" xmlns:q="urn:x" id="3" p:id="2" q:id="1"/>"#;
+        let swapped = r#"<a xmlns:x="urn:x" xmlns:y="urn:y" x:id="2" y:id="1" id="3"/>"#;
+
+        assert!(diff(expected, same).unwrap().is_empty());
+        assert_eq!(
+            diff(expected, swapped).unwrap(),
+            [
+                r#"/a/@{urn:x}id: "1" != "2""#,
+                r#"/a/@{urn:y}id: "2" != "1""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let err = diff("<a>", "<a/>").unwrap_err();
+
+        assert!(
+            err.to_string()
+                .starts_with("Could not parse expected value")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "body/a/@x")]
+    fn compare_xml_fields_panic() {
+        struct Request {
+            body: &'static str,
+        }
+
+        let expected = Request { body: "<a x='1'/>" };
+        let actual = Request { body: "<a x='2'/>" };
+
+        compare_xml!(expected, actual, body);
+    }
+}